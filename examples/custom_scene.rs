@@ -0,0 +1,44 @@
+//! Minimal example of using the engine as a library: a window with its own title and size,
+//! an extra font from the repository's asset root, and a system that draws a clock (and
+//! quits after ten seconds).
+//!
+//! Run from the repository root (the fonts are resolved against the asset root):
+//! `cargo run --example custom_scene`
+
+use anyhow::Result;
+
+use game::{Engine, GameState, System};
+
+struct Clock {
+    quit_after_seconds: f32,
+}
+
+impl System for Clock {
+    fn update(&mut self, game_state: &mut GameState) -> Result<()> {
+        let seconds = game_state.run_time().as_secs_f32();
+        game_state.set_text(Some(format!(
+            "{:.1}s (quitting in {:.0}s)",
+            seconds,
+            (self.quit_after_seconds - seconds).max(0.0)
+        )));
+        if seconds >= self.quit_after_seconds {
+            game_state.quit();
+        }
+        Ok(())
+    }
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    Engine::builder()
+        .title("Custom scene")
+        .inner_size(800.0, 600.0)
+        .asset_root(env!("CARGO_MANIFEST_DIR"))
+        .font("fonts/Roboto-Regular.ttf")
+        .system(Clock {
+            quit_after_seconds: 10.0,
+        })
+        .build()?
+        .run()
+}
@@ -0,0 +1,523 @@
+//! Files the game loads while it runs: fonts, textures, sounds and shaders. An
+//! [`AssetStore`] hands out typed [`Handle`]s by path. Loading a path that's loaded already
+//! gives another handle to the same asset, files are read and parsed on rayon's thread pool,
+//! and an asset is dropped with the last handle to it.
+//!
+//! With hot reloading on, the directories of the loaded files are watched and
+//! [`AssetStore::poll_changes`] reloads the files that changed in place. A file that doesn't
+//! load anymore is logged and its asset keeps the previous version. The reload hooks get the
+//! handles of the assets that changed, e.g. to rebuild what was made from them.
+//!
+//! [`Assets`] has a store for each kind of asset, and looks relative paths up in its
+//! [`AssetRoots`] (the engine's, see
+//! [`EngineBuilder::asset_root`](crate::engine::EngineBuilder::asset_root)).
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Weak};
+
+use anyhow::{Context, Result, anyhow};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+
+use crate::gfx::shaders;
+use crate::logging;
+use crate::paths::AssetRoots;
+
+/// Something an [`AssetStore`] can load.
+pub trait Asset: Sized + Send + Sync + 'static {
+    /// What it is, for the logs
+    const KIND: &'static str;
+
+    /// Parses the contents of the file at `path`.
+    fn from_bytes(path: &Path, bytes: Vec<u8>) -> Result<Self>;
+
+    /// The store of this kind of asset.
+    fn store(assets: &mut Assets) -> &mut AssetStore<Self>;
+}
+
+/// A font file, a single font or a collection (.ttc, .otc). The font cache loads its fonts,
+/// see [`FontCache::load_font_asset`](crate::gfx::text::FontCache::load_font_asset).
+#[derive(Debug)]
+pub struct FontData {
+    data: Arc<[u8]>,
+    font_count: u32,
+}
+
+impl FontData {
+    pub fn data(&self) -> &Arc<[u8]> {
+        &self.data
+    }
+
+    /// More than one for a collection
+    pub fn font_count(&self) -> u32 {
+        self.font_count
+    }
+}
+
+impl Asset for FontData {
+    const KIND: &'static str = "font";
+
+    fn from_bytes(_path: &Path, bytes: Vec<u8>) -> Result<Self> {
+        let font_count = match skrifa::raw::FileRef::new(&bytes)? {
+            skrifa::raw::FileRef::Font(_) => 1,
+            skrifa::raw::FileRef::Collection(collection) => collection.len(),
+        };
+        Ok(Self {
+            data: bytes.into(),
+            font_count,
+        })
+    }
+
+    fn store(assets: &mut Assets) -> &mut AssetStore<Self> {
+        &mut assets.fonts
+    }
+}
+
+/// An image file as it is on disk. Nothing decodes images yet, see TODO.txt.
+#[derive(Debug)]
+pub struct TextureData {
+    pub bytes: Vec<u8>,
+}
+
+impl Asset for TextureData {
+    const KIND: &'static str = "texture";
+
+    fn from_bytes(_path: &Path, bytes: Vec<u8>) -> Result<Self> {
+        Ok(Self { bytes })
+    }
+
+    fn store(assets: &mut Assets) -> &mut AssetStore<Self> {
+        &mut assets.textures
+    }
+}
+
+/// A sound file as it is on disk. There's no audio subsystem to decode and play it yet.
+#[derive(Debug)]
+pub struct SoundData {
+    pub bytes: Vec<u8>,
+}
+
+impl Asset for SoundData {
+    const KIND: &'static str = "sound";
+
+    fn from_bytes(_path: &Path, bytes: Vec<u8>) -> Result<Self> {
+        Ok(Self { bytes })
+    }
+
+    fn store(assets: &mut Assets) -> &mut AssetStore<Self> {
+        &mut assets.sounds
+    }
+}
+
+/// WGSL source, validated when it's loaded (see [`shaders::validate`]).
+#[derive(Debug)]
+pub struct ShaderSource {
+    pub source: String,
+}
+
+impl Asset for ShaderSource {
+    const KIND: &'static str = "shader";
+
+    fn from_bytes(path: &Path, bytes: Vec<u8>) -> Result<Self> {
+        let source = String::from_utf8(bytes)?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        shaders::validate(&name, &source)?;
+        Ok(Self { source })
+    }
+
+    fn store(assets: &mut Assets) -> &mut AssetStore<Self> {
+        &mut assets.shaders
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadState<T> {
+    Loading,
+    Loaded(Arc<T>),
+    /// Why the first load failed
+    Failed(String),
+}
+
+// what the handles of an asset share
+struct Slot<T> {
+    path: PathBuf,
+    state: Mutex<LoadState<T>>,
+    // bumped by every successful (re)load
+    version: AtomicU32,
+}
+
+impl<T: Asset> Slot<T> {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            state: Mutex::new(LoadState::Loading),
+            version: AtomicU32::new(0),
+        }
+    }
+
+    fn read(&self) -> Result<T> {
+        let bytes = fs::read(&self.path)
+            .with_context(|| format!("unable to read {}", self.path.display()))?;
+        T::from_bytes(&self.path, bytes)
+    }
+
+    // the first load, a failure is kept in the state
+    fn load(&self) {
+        let state = match self.read() {
+            Ok(asset) => {
+                self.version.fetch_add(1, Ordering::Release);
+                LoadState::Loaded(Arc::new(asset))
+            }
+            Err(e) => {
+                log::warn!(
+                    target: logging::ASSETS,
+                    "unable to load {} {}: {:#}",
+                    T::KIND,
+                    self.path.display(),
+                    e
+                );
+                LoadState::Failed(format!("{:#}", e))
+            }
+        };
+        *self.state.lock() = state;
+    }
+
+    // a load after the file changed, a failure keeps the previous version. True if the
+    // asset changed.
+    fn reload(&self) -> bool {
+        match self.read() {
+            Ok(asset) => {
+                *self.state.lock() = LoadState::Loaded(Arc::new(asset));
+                self.version.fetch_add(1, Ordering::Release);
+                true
+            }
+            Err(e) => {
+                log::warn!(
+                    target: logging::ASSETS,
+                    "unable to reload {} {}, keeping the previous version: {:#}",
+                    T::KIND,
+                    self.path.display(),
+                    e
+                );
+                false
+            }
+        }
+    }
+}
+
+/// A reference counted asset. Clones are handles to the same asset, which is dropped with the
+/// last of them.
+pub struct Handle<T>(Arc<Slot<T>>);
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Handle").field(&self.0.path).finish()
+    }
+}
+
+impl<T: Asset> Handle<T> {
+    /// The file, canonicalized if it existed when it was first loaded
+    pub fn path(&self) -> &Path {
+        &self.0.path
+    }
+
+    /// The asset's current version, None while it's loading or if it failed to load.
+    pub fn get(&self) -> Option<Arc<T>> {
+        match &*self.0.state.lock() {
+            LoadState::Loaded(asset) => Some(asset.clone()),
+            LoadState::Loading | LoadState::Failed(_) => None,
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        matches!(*self.0.state.lock(), LoadState::Loading)
+    }
+
+    /// Why it failed to load
+    pub fn error(&self) -> Option<String> {
+        match &*self.0.state.lock() {
+            LoadState::Failed(error) => Some(error.clone()),
+            LoadState::Loading | LoadState::Loaded(_) => None,
+        }
+    }
+
+    /// How many times it was loaded, so users can tell it was reloaded since they last
+    /// looked. 0 until the first load is done.
+    pub fn version(&self) -> u32 {
+        self.0.version.load(Ordering::Acquire)
+    }
+
+    /// How many handles there are to the asset.
+    pub fn handle_count(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+}
+
+type ReloadHook<T> = Box<dyn FnMut(&Handle<T>) + Send>;
+
+/// The loaded assets of one kind, by path.
+pub struct AssetStore<T> {
+    // weak, so the assets are dropped with their last handle
+    slots: HashMap<PathBuf, Weak<Slot<T>>>,
+    hot_reload: bool,
+    watcher: Option<(RecommendedWatcher, Receiver<notify::Result<notify::Event>>)>,
+    watched_directories: HashSet<PathBuf>,
+    reload_hooks: Vec<ReloadHook<T>>,
+    // reloads that finished on the thread pool
+    reloaded_sender: Sender<Weak<Slot<T>>>,
+    reloaded: Receiver<Weak<Slot<T>>>,
+}
+
+impl<T: Asset> Default for AssetStore<T> {
+    fn default() -> Self {
+        let (reloaded_sender, reloaded) = mpsc::channel();
+        Self {
+            slots: HashMap::new(),
+            hot_reload: false,
+            watcher: None,
+            watched_directories: HashSet::new(),
+            reload_hooks: Vec::new(),
+            reloaded_sender,
+            reloaded,
+        }
+    }
+}
+
+impl<T: Asset> AssetStore<T> {
+    /// A handle to the asset at `path`, loaded in the background if it isn't loaded yet (see
+    /// [`Handle::get`]).
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Handle<T> {
+        let (handle, new) = self.slot(path.as_ref());
+        if new {
+            let slot = Arc::downgrade(&handle.0);
+            rayon::spawn(move || {
+                // not if all the handles were dropped in the meantime
+                if let Some(slot) = slot.upgrade() {
+                    slot.load();
+                }
+            });
+        }
+        handle
+    }
+
+    /// Like [`AssetStore::load`], but loads it on this thread if it isn't loaded yet, so it's
+    /// loaded (or failed) when this returns.
+    pub fn load_blocking(&mut self, path: impl AsRef<Path>) -> Handle<T> {
+        let (handle, new) = self.slot(path.as_ref());
+        if new {
+            handle.0.load();
+        }
+        handle
+    }
+
+    // the existing slot for the path, or a new one (true) that still has to be loaded
+    fn slot(&mut self, path: &Path) -> (Handle<T>, bool) {
+        // the same file through another path is the same asset
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if let Some(slot) = self.slots.get(&path).and_then(Weak::upgrade) {
+            return (Handle(slot), false);
+        }
+        self.slots.retain(|_, slot| slot.strong_count() > 0);
+        let slot = Arc::new(Slot::new(path.clone()));
+        self.slots.insert(path.clone(), Arc::downgrade(&slot));
+        if self.hot_reload {
+            self.watch(&path);
+        }
+        (Handle(slot), true)
+    }
+
+    /// Watches the files of the assets for changes, see [`AssetStore::poll_changes`].
+    pub fn set_hot_reload(&mut self, hot_reload: bool) {
+        self.hot_reload = hot_reload;
+        if !hot_reload {
+            self.watcher = None;
+            self.watched_directories.clear();
+            return;
+        }
+        let paths: Vec<PathBuf> = self.slots.keys().cloned().collect();
+        for path in paths {
+            self.watch(&path);
+        }
+    }
+
+    // watches the directory rather than the file, editors often save by replacing the file
+    fn watch(&mut self, path: &Path) {
+        let Some(directory) = path.parent() else {
+            return;
+        };
+        if self.watched_directories.contains(directory) {
+            return;
+        }
+        let result = match &mut self.watcher {
+            Some((watcher, _)) => watcher
+                .watch(directory, RecursiveMode::NonRecursive)
+                .map_err(|e| anyhow!(e)),
+            None => Self::watcher(directory).map(|watcher| self.watcher = Some(watcher)),
+        };
+        match result {
+            Ok(()) => {
+                self.watched_directories.insert(directory.to_path_buf());
+            }
+            Err(e) => log::warn!(
+                target: logging::ASSETS,
+                "unable to watch {} for {} changes: {}",
+                directory.display(),
+                T::KIND,
+                e
+            ),
+        }
+    }
+
+    fn watcher(
+        directory: &Path,
+    ) -> Result<(RecommendedWatcher, Receiver<notify::Result<notify::Event>>)> {
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(directory, RecursiveMode::NonRecursive)?;
+        Ok((watcher, receiver))
+    }
+
+    /// Called with the handle of every asset that was reloaded, from
+    /// [`AssetStore::poll_changes`].
+    pub fn on_reload(&mut self, hook: impl FnMut(&Handle<T>) + Send + 'static) {
+        self.reload_hooks.push(Box::new(hook));
+    }
+
+    /// Starts reloading the assets whose files changed since the last call, in the
+    /// background. Returns the assets that finished reloading since the last call, after
+    /// calling the reload hooks with them.
+    pub fn poll_changes(&mut self) -> Vec<Handle<T>> {
+        if let Some((_, events)) = &self.watcher {
+            let mut changed: Vec<Arc<Slot<T>>> = Vec::new();
+            for event in events.try_iter() {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(e) => {
+                        log::warn!(target: logging::ASSETS, "{} watcher error: {}", T::KIND, e);
+                        continue;
+                    }
+                };
+                if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    continue;
+                }
+                for path in &event.paths {
+                    if let Some(slot) = self.slots.get(path).and_then(Weak::upgrade)
+                        && !changed.iter().any(|changed| Arc::ptr_eq(changed, &slot))
+                    {
+                        changed.push(slot);
+                    }
+                }
+            }
+            for slot in changed {
+                let weak = Arc::downgrade(&slot);
+                let reloaded = self.reloaded_sender.clone();
+                rayon::spawn(move || {
+                    if slot.reload() {
+                        // the receiver is dropped with the store
+                        let _ = reloaded.send(weak);
+                    }
+                });
+            }
+        }
+
+        let reloaded: Vec<Handle<T>> = self
+            .reloaded
+            .try_iter()
+            .filter_map(|slot| slot.upgrade().map(Handle))
+            .collect();
+        for handle in &reloaded {
+            log::info!(target: logging::ASSETS, "reloaded {} {}", T::KIND, handle.path().display());
+            for hook in &mut self.reload_hooks {
+                hook(handle);
+            }
+        }
+        reloaded
+    }
+
+    /// How many assets are loaded (or loading).
+    pub fn len(&self) -> usize {
+        self.slots
+            .values()
+            .filter(|slot| slot.strong_count() > 0)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A store for each kind of asset.
+#[derive(Default)]
+pub struct Assets {
+    pub fonts: AssetStore<FontData>,
+    pub textures: AssetStore<TextureData>,
+    pub sounds: AssetStore<SoundData>,
+    pub shaders: AssetStore<ShaderSource>,
+    roots: AssetRoots,
+}
+
+impl Assets {
+    /// See [`AssetStore::load`], `path` is looked up in the roots.
+    pub fn load<T: Asset>(&mut self, path: impl AsRef<Path>) -> Handle<T> {
+        let path = self.resolve(path.as_ref());
+        T::store(self).load(path)
+    }
+
+    /// See [`AssetStore::load_blocking`], `path` is looked up in the roots.
+    pub fn load_blocking<T: Asset>(&mut self, path: impl AsRef<Path>) -> Handle<T> {
+        let path = self.resolve(path.as_ref());
+        T::store(self).load_blocking(path)
+    }
+
+    /// Where relative paths are looked up.
+    pub fn set_roots(&mut self, roots: AssetRoots) {
+        self.roots = roots;
+    }
+
+    // a path that isn't found is loaded as it is, so the store logs that it can't be read
+    fn resolve(&self, path: &Path) -> PathBuf {
+        self.roots
+            .resolve(path)
+            .unwrap_or_else(|_| path.to_path_buf())
+    }
+
+    /// See [`AssetStore::on_reload`].
+    pub fn on_reload<T: Asset>(&mut self, hook: impl FnMut(&Handle<T>) + Send + 'static) {
+        T::store(self).on_reload(hook);
+    }
+
+    pub fn set_hot_reload(&mut self, hot_reload: bool) {
+        self.fonts.set_hot_reload(hot_reload);
+        self.textures.set_hot_reload(hot_reload);
+        self.sounds.set_hot_reload(hot_reload);
+        self.shaders.set_hot_reload(hot_reload);
+    }
+
+    /// Reloads the changed files of every store and calls their reload hooks, see
+    /// [`AssetStore::poll_changes`].
+    pub fn poll_changes(&mut self) {
+        self.fonts.poll_changes();
+        self.textures.poll_changes();
+        self.sounds.poll_changes();
+        self.shaders.poll_changes();
+    }
+}
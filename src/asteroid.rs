@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+
+use crate::collision::{Shape, convex_hull};
+use crate::math::{Real, Scalar, SimRng, SimVec2};
+use crate::memory::{HeapSize, vec_size};
+use crate::world::wrap_position;
+
+/// Number of corners of the outline, evenly spaced around the center
+pub const CORNERS: i32 = 11;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AsteroidSize {
+    Large,
+    Medium,
+    Small,
+}
+
+impl AsteroidSize {
+    pub fn radius(self) -> Scalar {
+        match self {
+            AsteroidSize::Large => Scalar::from_int(40),
+            AsteroidSize::Medium => Scalar::from_int(22),
+            AsteroidSize::Small => Scalar::from_int(12),
+        }
+    }
+
+    /// Points for shooting an asteroid of this size
+    pub fn points(self) -> u32 {
+        match self {
+            AsteroidSize::Large => 20,
+            AsteroidSize::Medium => 50,
+            AsteroidSize::Small => 100,
+        }
+    }
+
+    /// What an asteroid breaks into when it is shot (nothing for the smallest ones)
+    pub fn smaller(self) -> Option<AsteroidSize> {
+        match self {
+            AsteroidSize::Large => Some(AsteroidSize::Medium),
+            AsteroidSize::Medium => Some(AsteroidSize::Small),
+            AsteroidSize::Small => None,
+        }
+    }
+
+    // smaller asteroids fly faster (units/s)
+    fn speed_range(self) -> (Scalar, Scalar) {
+        match self {
+            AsteroidSize::Large => (Scalar::from_int(30), Scalar::from_int(60)),
+            AsteroidSize::Medium => (Scalar::from_int(50), Scalar::from_int(90)),
+            AsteroidSize::Small => (Scalar::from_int(70), Scalar::from_int(120)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Asteroid {
+    pub id: u32,
+    pub size: AsteroidSize,
+    pub position: SimVec2,
+    pub velocity: SimVec2,
+    pub angle: Scalar,
+    /// Rotation speed in rad/s
+    pub spin: Scalar,
+    /// The jagged outline that gets drawn, in asteroid space
+    pub outline: Vec<SimVec2>,
+    /// Convex hull of the outline
+    pub shape: Shape,
+}
+
+impl Asteroid {
+    /// A randomly shaped asteroid flying in a random direction.
+    pub fn new(id: u32, size: AsteroidSize, position: SimVec2, rng: &mut SimRng) -> Self {
+        let turn = Scalar::from_f32(std::f32::consts::TAU);
+        let radius = size.radius();
+        let outline: Vec<SimVec2> = (0..CORNERS)
+            .map(|i| {
+                let angle = turn * Scalar::from_int(i) / Scalar::from_int(CORNERS);
+                let distance = radius * rng.range(Scalar::from_f32(0.7), Scalar::from_f32(1.05));
+                SimVec2::from_angle(angle).scale(distance)
+            })
+            .collect();
+        let shape = Shape::Polygon {
+            points: convex_hull(&outline),
+        };
+
+        let (min_speed, max_speed) = size.speed_range();
+        let direction = rng.range(Scalar::ZERO, turn);
+        let speed = rng.range(min_speed, max_speed);
+        Self {
+            id,
+            size,
+            position,
+            velocity: SimVec2::from_angle(direction).scale(speed),
+            angle: rng.range(Scalar::ZERO, turn),
+            spin: rng.range(-Scalar::ONE, Scalar::ONE),
+            outline,
+            shape,
+        }
+    }
+
+    pub fn update(&mut self, dt: Scalar) {
+        self.position = wrap_position(self.position + self.velocity.scale(dt));
+        let turn = Scalar::from_f32(std::f32::consts::TAU);
+        self.angle += self.spin * dt;
+        self.angle -= (self.angle / turn).floor() * turn;
+    }
+
+    /// Line segments (pairs of points, in world units) to draw the asteroid with.
+    pub fn line_segments(&self) -> Vec<[f32; 2]> {
+        let [x, y] = self.position.to_f32();
+        let (sin, cos) = (
+            self.angle.table_sin().to_f32(),
+            self.angle.table_cos().to_f32(),
+        );
+        let transform = |point: SimVec2| {
+            let [px, py] = point.to_f32();
+            [x + px * cos - py * sin, y + px * sin + py * cos]
+        };
+
+        let mut segments = Vec::with_capacity(self.outline.len() * 2);
+        for i in 0..self.outline.len() {
+            segments.push(transform(self.outline[i]));
+            segments.push(transform(self.outline[(i + 1) % self.outline.len()]));
+        }
+        segments
+    }
+
+    /// How far each corner of the outline is from the center, e.g. to draw the asteroid by
+    /// scaling a shared mesh (the directions are the same for every asteroid).
+    pub fn corner_radii(&self) -> impl Iterator<Item = f32> + '_ {
+        self.outline.iter().map(|point| {
+            let [x, y] = point.to_f32();
+            x.hypot(y)
+        })
+    }
+}
+
+impl HeapSize for Asteroid {
+    fn heap_size(&self) -> usize {
+        vec_size(&self.outline) + self.shape.heap_size()
+    }
+}
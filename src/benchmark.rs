@@ -0,0 +1,204 @@
+//! Benchmark mode, `--benchmark [frames]` on the command line (see
+//! [`EngineBuilder::benchmark`](crate::engine::EngineBuilder::benchmark)). Instead of the
+//! menu the game plays a fixed scripted scene that's heavy on every renderer: over a hundred
+//! asteroids, a few thousand particles and screens full of text in many sizes. It's drawn as
+//! fast as the GPU allows (no vsync, no frame cap) for a number of frames, then the game
+//! quits and writes a report into `benchmarks` in the user's data directory:
+//!
+//! - `benchmark-<time>.json`: frame time percentiles and the glyph atlas stats
+//! - `benchmark-<time>.csv`: every frame's time, in milliseconds
+//!
+//! The scene is the same every run (the same seed, and nothing reacts to input), so the
+//! reports of two builds can be compared to catch performance regressions.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+
+use crate::asteroid::AsteroidSize;
+use crate::game_state::GameState;
+use crate::gfx::particles::EmitterParams;
+use crate::gfx::text::{AtlasStats, TextDraw};
+use crate::paths;
+use crate::screen::Screen;
+use crate::world::{WORLD_HEIGHT, WORLD_WIDTH};
+
+/// Frames drawn when `--benchmark` doesn't say
+pub const DEFAULT_FRAMES: u32 = 2000;
+
+// frames drawn before the timing starts, while the shaders compile and the glyph atlas fills
+const WARMUP_FRAMES: u32 = 60;
+const SEED: u64 = 0xbe_4c4a;
+const ASTEROIDS: usize = 150;
+// explosions last about 0.75 seconds, so this keeps around 4000 particles going at 60 Hz
+const PARTICLES_PER_UPDATE: usize = 90;
+const EXPLOSIONS_PER_UPDATE: usize = 3;
+const TEXT_SIZES: [f32; 6] = [11.0, 14.0, 18.0, 24.0, 36.0, 54.0];
+const TEXT_LINES: usize = 40;
+const TEXT: &str = "The quick brown fox jumps over the lazy dog. PACK MY BOX WITH FIVE DOZEN \
+    LIQUOR JUGS! 0123456789 (){}[] Sphinx of black quartz, judge my vow.";
+
+/// Runs the scene and times the frames, see the module docs.
+#[derive(Debug)]
+pub struct Benchmark {
+    frames: u32,
+    frames_drawn: u32,
+    last_frame: Option<Instant>,
+    frame_times: Vec<Duration>,
+}
+
+impl Benchmark {
+    pub fn new(frames: u32) -> Self {
+        Self {
+            frames: frames.max(1),
+            frames_drawn: 0,
+            last_frame: None,
+            frame_times: Vec::with_capacity(frames as usize),
+        }
+    }
+
+    /// Sets up the scene, in place of the main menu.
+    pub(crate) fn start(&self, game_state: &mut GameState) {
+        log::info!("benchmarking {} frames", self.frames);
+        game_state.gfx_settings.set_vsync(false);
+        game_state.fixed_seed = Some(SEED);
+        game_state.new_game();
+        game_state.switch_screen(Screen::Playing);
+        game_state
+            .world
+            .spawn_asteroids(AsteroidSize::Medium, ASTEROIDS);
+    }
+
+    /// Keeps the scene going, every update.
+    pub(crate) fn update(&self, game_state: &mut GameState) {
+        // the asteroids would end the game
+        game_state.world.lives = game_state.world.lives.max(1);
+        if game_state.screen() != Screen::Playing {
+            game_state.switch_screen(Screen::Playing);
+        }
+
+        let state_number = game_state.state_number;
+        let params = EmitterParams {
+            color: game_state.palette().explosion,
+            ..EmitterParams::explosion()
+        };
+        for i in 0..EXPLOSIONS_PER_UPDATE {
+            // walks across the world on a fixed path
+            let step = (state_number as usize * EXPLOSIONS_PER_UPDATE + i) as f32;
+            let position = [(step * 97.0) % WORLD_WIDTH, (step * 61.0) % WORLD_HEIGHT];
+            game_state.particles_mut().burst(
+                &params,
+                position,
+                0.0,
+                [0.0, 0.0],
+                PARTICLES_PER_UPDATE / EXPLOSIONS_PER_UPDATE,
+            );
+        }
+
+        let [width, height] = game_state.window_size;
+        for line in 0..TEXT_LINES {
+            let size = TEXT_SIZES[line % TEXT_SIZES.len()];
+            let y = (line as f32 * 37.0 + state_number as f32 * 3.0) % height.max(1.0);
+            // the number changes every update, so the lines are laid out again
+            let text = format!("{} {} {}", state_number, line, TEXT);
+            game_state.queue_text(
+                TextDraw::new(
+                    text,
+                    [(line as f32 * 53.0) % (width / 2.0).max(1.0), y],
+                    size,
+                )
+                .shadow([0.0, 2.0], [0.0, 0.0, 0.0, 0.8]),
+            );
+        }
+    }
+
+    /// Counts a frame drawn at `now`. True once enough frames were drawn.
+    pub(crate) fn frame_drawn(&mut self, now: Instant) -> bool {
+        self.frames_drawn += 1;
+        if self.frames_drawn > WARMUP_FRAMES
+            && let Some(last_frame) = self.last_frame
+        {
+            self.frame_times.push(now - last_frame);
+        }
+        self.last_frame = Some(now);
+        self.frame_times.len() >= self.frames as usize
+    }
+
+    /// The results so far.
+    pub(crate) fn report(&self, glyph_atlas: AtlasStats) -> BenchmarkReport {
+        let mut milliseconds: Vec<f64> = self
+            .frame_times
+            .iter()
+            .map(|time| time.as_secs_f64() * 1000.0)
+            .collect();
+        milliseconds.sort_by(f64::total_cmp);
+        let total: f64 = milliseconds.iter().sum();
+        let mean = total / milliseconds.len().max(1) as f64;
+        // nearest rank
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * milliseconds.len() as f64).ceil() as usize;
+            milliseconds
+                .get(rank.saturating_sub(1))
+                .copied()
+                .unwrap_or(0.0)
+        };
+        BenchmarkReport {
+            version: env!("CARGO_PKG_VERSION"),
+            frames: milliseconds.len(),
+            seconds: total / 1000.0,
+            fps: if mean > 0.0 { 1000.0 / mean } else { 0.0 },
+            mean_ms: mean,
+            p50_ms: percentile(50.0),
+            p90_ms: percentile(90.0),
+            p99_ms: percentile(99.0),
+            max_ms: milliseconds.last().copied().unwrap_or(0.0),
+            glyphs: glyph_atlas.glyphs,
+            undrawable_glyphs: glyph_atlas.undrawable_glyphs,
+            atlas_size: glyph_atlas.size,
+            atlas_occupancy: glyph_atlas.occupancy,
+        }
+    }
+
+    /// Writes the report and the frame times, see the module docs. Returns the report's path.
+    pub(crate) fn write(&self, report: &BenchmarkReport) -> Result<PathBuf> {
+        let directory = paths::data_dir(paths::APP_NAME)
+            .ok_or_else(|| anyhow!("no data directory for this platform"))?
+            .join("benchmarks");
+        fs::create_dir_all(&directory)?;
+        let unix_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let path = directory.join(format!("benchmark-{}.json", unix_time));
+        fs::write(&path, serde_json::to_string_pretty(report)?)?;
+
+        let mut csv = String::from("frame,ms\n");
+        for (frame, time) in self.frame_times.iter().enumerate() {
+            let _ = writeln!(csv, "{},{:.3}", frame, time.as_secs_f64() * 1000.0);
+        }
+        fs::write(path.with_extension("csv"), csv)?;
+        Ok(path)
+    }
+}
+
+/// What a benchmark run measured, see [`crate::benchmark`].
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkReport {
+    pub version: &'static str,
+    /// Frames timed, without the warmup
+    pub frames: usize,
+    pub seconds: f64,
+    pub fps: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    pub glyphs: usize,
+    pub undrawable_glyphs: usize,
+    pub atlas_size: [u32; 2],
+    pub atlas_occupancy: f32,
+}
@@ -0,0 +1,53 @@
+//! The headless multiplayer server (see game::net), without a window or a graphics device.
+//! `server [--port <port>] [--tick-rate <30|60|120>]`, the game joins it with
+//! `--connect <address>`.
+
+use std::time::Instant;
+
+use anyhow::{Result, anyhow};
+
+use game::logging::{self, LogSettings};
+use game::net::{DEFAULT_PORT, Server};
+use game::paths;
+use game::tick_rate::TickRate;
+
+// the value after `flag`, if it's there
+fn argument<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    let position = args.iter().position(|arg| arg == flag)?;
+    args.get(position + 1).map(String::as_str)
+}
+
+fn main() -> Result<()> {
+    logging::init(LogSettings {
+        app_name: format!("{}-server", paths::APP_NAME),
+        ..LogSettings::default()
+    })?;
+
+    let args: Vec<String> = std::env::args().collect();
+    let port = match argument(&args, "--port") {
+        Some(port) => port.parse()?,
+        None => DEFAULT_PORT,
+    };
+    let tick_rate = match argument(&args, "--tick-rate") {
+        Some(hz) => TickRate::from_hz(hz.parse()?)
+            .ok_or_else(|| anyhow!("the tick rate is 30, 60 or 120 Hz, not {}", hz))?,
+        None => TickRate::default(),
+    };
+
+    let mut server = Server::bind(("0.0.0.0", port), tick_rate)?;
+    // ticks are scheduled from the start, so sleeping late doesn't add up
+    let interval = tick_rate.update_interval();
+    let mut next_tick = Instant::now();
+    loop {
+        let now = Instant::now();
+        if now >= next_tick {
+            server.tick(now);
+            next_tick += interval;
+            // too far behind to catch up, e.g. after the machine slept
+            if now > next_tick + interval * 10 {
+                next_tick = now + interval;
+            }
+        }
+        std::thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+    }
+}
@@ -0,0 +1,91 @@
+//! Bug report bundles: F12 writes a zip with everything a playtester's report needs into the
+//! user's data directory.
+//!
+//! - `info.txt`: version, platform, screen, seed and the world's progress
+//! - `settings.txt`: graphics, window and input settings as the game uses them
+//! - `settings.toml`: the settings last loaded from the settings file
+//! - `log.txt`: the last lines of the session's log
+//! - `replay.txt`: the seed and every input of the game so far (enough to reproduce it, see
+//!   crate::replay), with the last 30 seconds spelled out
+//! - `screenshot.png`: the frame on screen when the key was pressed, if it could be read back
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, anyhow};
+
+use crate::file_formats::ZipWriter;
+use crate::game_state::GameState;
+use crate::gfx::Screenshot;
+use crate::{logging, paths};
+
+const REPLAY_SECONDS: f32 = 30.0;
+
+fn info(game_state: &GameState) -> String {
+    let world = &game_state.world;
+    let mut info = String::new();
+    let _ = writeln!(info, "version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(
+        info,
+        "system: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let _ = writeln!(info, "fixed point: {}", cfg!(feature = "fixed-point"));
+    let _ = writeln!(info, "run time: {:?}", game_state.run_time());
+    let _ = writeln!(info, "screen: {:?}", game_state.screen());
+    let _ = writeln!(info, "seed: {}", world.seed);
+    let _ = writeln!(info, "tick: {}", world.tick);
+    let _ = writeln!(info, "tick rate: {}", game_state.tick_rate());
+    let _ = writeln!(info, "wave: {}", world.wave);
+    let _ = writeln!(info, "lives: {}", world.lives);
+    let _ = writeln!(
+        info,
+        "time traveling: {}",
+        !game_state.time_travel.is_live()
+    );
+    if let Some(path) = logging::session_log_path() {
+        let _ = writeln!(info, "log file: {}", path.display());
+    }
+    info
+}
+
+/// Writes the bundle and returns its path.
+pub fn write(game_state: &GameState, screenshot: Option<&Screenshot>) -> Result<PathBuf> {
+    let directory = paths::data_dir(paths::APP_NAME)
+        .ok_or_else(|| anyhow!("no data directory for this platform"))?
+        .join("bug-reports");
+    fs::create_dir_all(&directory)?;
+    let unix_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let path = directory.join(format!("bug-report-{}.zip", unix_time));
+
+    let mut zip = ZipWriter::default();
+    zip.add_file("info.txt", info(game_state).as_bytes());
+    let settings = format!(
+        "{:#?}\n{:#?}\n{:#?}\n",
+        game_state.gfx_settings, game_state.window_settings, game_state.input_map
+    );
+    zip.add_file("settings.txt", settings.as_bytes());
+    zip.add_file(
+        crate::config::SETTINGS_FILE_NAME,
+        toml::to_string_pretty(&game_state.settings)?.as_bytes(),
+    );
+    let mut log = logging::recent_lines().join("\n");
+    log.push('\n');
+    zip.add_file("log.txt", log.as_bytes());
+    let replay_ticks = (REPLAY_SECONDS * game_state.tick_rate().hz() as f32) as u64;
+    let replay = game_state
+        .replay
+        .to_text(game_state.world.tick.saturating_sub(replay_ticks));
+    zip.add_file("replay.txt", replay.as_bytes());
+    if let Some(screenshot) = screenshot {
+        zip.add_file("screenshot.png", &screenshot.to_png());
+    }
+
+    fs::write(&path, zip.finish())?;
+    Ok(path)
+}
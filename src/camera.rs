@@ -0,0 +1,138 @@
+//! The view's reactions to gameplay: trauma-based screen shake, a brief zoom punch and a hit
+//! flash. Gameplay events set them off (see [`on_entity_destroyed`], subscribed to the event
+//! bus), the renderer applies them: the shake and the punch in `FrameContext::world_to_clip`,
+//! the flash as the last pass of the post-processing chain.
+//!
+//! Hits add trauma (0 to 1), which fades at a constant rate. The shake grows with its square,
+//! so a small hit barely moves the view while a few big ones in a row add up to a violent
+//! shake.
+
+use std::f32::consts::TAU;
+
+use crate::events::EntityDestroyed;
+use crate::game_state::GameState;
+use crate::world::EntityId;
+
+/// How strong the effects are and how fast they fade, see [`Camera`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraEffects {
+    /// How far the view moves at full trauma, in world units
+    pub max_shake: f32,
+    /// How often the shake changes direction, about (in Hz)
+    pub shake_frequency: f32,
+    /// Trauma lost per second
+    pub trauma_decay: f32,
+    /// How much a full punch zooms in (0.05 is 5%)
+    pub max_punch: f32,
+    /// Punch lost per second
+    pub punch_decay: f32,
+    /// What the screen washes out to during a full flash
+    pub flash_color: [f32; 3],
+    /// Flash lost per second
+    pub flash_decay: f32,
+}
+
+impl Default for CameraEffects {
+    fn default() -> Self {
+        Self {
+            max_shake: 12.0,
+            shake_frequency: 15.0,
+            trauma_decay: 1.2,
+            max_punch: 0.04,
+            punch_decay: 5.0,
+            flash_color: [1.0, 0.95, 0.9],
+            flash_decay: 4.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Camera {
+    pub effects: CameraEffects,
+    trauma: f32,
+    punch: f32,
+    flash: f32,
+    // seconds the camera has been updated for, where the shake is in its noise
+    time: f32,
+}
+
+impl Camera {
+    /// Shakes the view, `amount` is added to the trauma (up to 1).
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    /// Zooms in by `amount` of a full punch (0 to 1), springing back quickly. Punches don't
+    /// add up, the stronger one wins.
+    pub fn punch(&mut self, amount: f32) {
+        self.punch = self.punch.max(amount.clamp(0.0, 1.0));
+    }
+
+    /// Flashes the screen, `amount` of a full flash (0 to 1). The stronger one wins.
+    pub fn flash(&mut self, amount: f32) {
+        self.flash = self.flash.max(amount.clamp(0.0, 1.0));
+    }
+
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Fades the effects by `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        let effects = &self.effects;
+        self.trauma = (self.trauma - effects.trauma_decay * dt).max(0.0);
+        self.punch = (self.punch - effects.punch_decay * dt).max(0.0);
+        self.flash = (self.flash - effects.flash_decay * dt).max(0.0);
+        // wrapped, so the noise doesn't lose precision in a long session
+        self.time = (self.time + dt) % 1000.0;
+    }
+
+    /// How far the view is moved, in world units.
+    pub fn shake_offset(&self) -> [f32; 2] {
+        if self.trauma == 0.0 {
+            return [0.0, 0.0];
+        }
+        let shake = self.trauma * self.trauma * self.effects.max_shake;
+        let t = self.time * self.effects.shake_frequency;
+        [shake * noise(t, 0.0), shake * noise(t, 17.0)]
+    }
+
+    /// How much bigger the world is drawn than its fit to the window, 1 without a punch.
+    pub fn zoom(&self) -> f32 {
+        // eased out, so it snaps in and settles back
+        1.0 + self.effects.max_punch * self.punch * self.punch
+    }
+
+    /// The flash's color, its alpha how strong it is (0 when there's none).
+    pub fn flash_color(&self) -> [f32; 4] {
+        let [r, g, b] = self.effects.flash_color;
+        [r, g, b, self.flash]
+    }
+}
+
+// smooth noise between -1 and 1, a few sines at frequencies that don't line up so it doesn't
+// visibly repeat. `seed` picks another curve.
+fn noise(t: f32, seed: f32) -> f32 {
+    let a = (t * TAU / 7.0 + seed).sin();
+    let b = (t * TAU / 3.1 + seed * 1.7).sin();
+    let c = (t * TAU / 1.3 + seed * 2.9).sin();
+    (a + b * 0.5 + c * 0.25) / 1.75
+}
+
+/// Shakes the view when something is destroyed: a little for an asteroid, a lot, with a punch
+/// and a flash, for the ship.
+pub fn on_entity_destroyed(game_state: &mut GameState, event: &EntityDestroyed) {
+    let camera = &mut game_state.camera;
+    match event.entity {
+        EntityId::Ship => {
+            camera.add_trauma(0.8);
+            camera.punch(1.0);
+            camera.flash(0.6);
+        }
+        EntityId::Asteroid(_) => {
+            camera.add_trauma(0.2);
+            camera.punch(0.4);
+        }
+        EntityId::Bullet(_) => {}
+    }
+}
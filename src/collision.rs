@@ -0,0 +1,285 @@
+//! 2D collision detection for the simulation: circle and convex polygon colliders, a uniform
+//! grid broad phase, and exact (SAT) narrow phase tests. Uses the deterministic Scalar math,
+//! so collision results are part of the deterministic simulation.
+
+use serde::{Deserialize, Serialize};
+
+use crate::math::{Real, Scalar, SimVec2};
+use crate::memory::{HeapSize, vec_size};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Shape {
+    Circle {
+        radius: Scalar,
+    },
+    /// Convex polygon in local space, counter-clockwise (see `convex_hull`)
+    Polygon {
+        points: Vec<SimVec2>,
+    },
+}
+
+/// A shape placed in the world.
+#[derive(Debug, Clone)]
+pub struct Collider<'a> {
+    pub shape: &'a Shape,
+    pub position: SimVec2,
+    /// Rotation in radians (only matters for polygons)
+    pub angle: Scalar,
+}
+
+/// Axis aligned bounding box in world units
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: SimVec2,
+    pub max: SimVec2,
+}
+
+enum WorldShape {
+    Circle { center: SimVec2, radius: Scalar },
+    Polygon { points: Vec<SimVec2> },
+}
+
+impl Collider<'_> {
+    fn world_shape(&self) -> WorldShape {
+        match self.shape {
+            Shape::Circle { radius } => WorldShape::Circle {
+                center: self.position,
+                radius: *radius,
+            },
+            Shape::Polygon { points } => {
+                let (sin, cos) = (self.angle.table_sin(), self.angle.table_cos());
+                WorldShape::Polygon {
+                    points: points
+                        .iter()
+                        .map(|p| {
+                            self.position
+                                + SimVec2::new(p.x * cos - p.y * sin, p.x * sin + p.y * cos)
+                        })
+                        .collect(),
+                }
+            }
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        match self.world_shape() {
+            WorldShape::Circle { center, radius } => Aabb {
+                min: center - SimVec2::new(radius, radius),
+                max: center + SimVec2::new(radius, radius),
+            },
+            WorldShape::Polygon { points } => {
+                let mut aabb = Aabb {
+                    min: points[0],
+                    max: points[0],
+                };
+                for p in points.iter().skip(1) {
+                    aabb.min = SimVec2::new(min(aabb.min.x, p.x), min(aabb.min.y, p.y));
+                    aabb.max = SimVec2::new(max(aabb.max.x, p.x), max(aabb.max.y, p.y));
+                }
+                aabb
+            }
+        }
+    }
+
+    /// Exact overlap test.
+    pub fn intersects(&self, other: &Collider) -> bool {
+        match (self.world_shape(), other.world_shape()) {
+            (
+                WorldShape::Circle {
+                    center: a,
+                    radius: ra,
+                },
+                WorldShape::Circle {
+                    center: b,
+                    radius: rb,
+                },
+            ) => (b - a).length_squared() <= (ra + rb) * (ra + rb),
+            (WorldShape::Circle { center, radius }, WorldShape::Polygon { points })
+            | (WorldShape::Polygon { points }, WorldShape::Circle { center, radius }) => {
+                circle_polygon(center, radius, &points)
+            }
+            (WorldShape::Polygon { points: a }, WorldShape::Polygon { points: b }) => {
+                !has_separating_axis(&a, &b) && !has_separating_axis(&b, &a)
+            }
+        }
+    }
+}
+
+fn min(a: Scalar, b: Scalar) -> Scalar {
+    if b < a { b } else { a }
+}
+
+fn max(a: Scalar, b: Scalar) -> Scalar {
+    if b > a { b } else { a }
+}
+
+fn edges(points: &[SimVec2]) -> impl Iterator<Item = (SimVec2, SimVec2)> + '_ {
+    (0..points.len()).map(move |i| (points[i], points[(i + 1) % points.len()]))
+}
+
+fn project(points: &[SimVec2], axis: SimVec2) -> (Scalar, Scalar) {
+    let first = points[0].dot(axis);
+    points.iter().skip(1).fold((first, first), |(lo, hi), p| {
+        let d = p.dot(axis);
+        (min(lo, d), max(hi, d))
+    })
+}
+
+// Separating axis theorem: two convex polygons don't overlap if there's an edge normal (of
+// either polygon) along which their projections don't overlap. The normals don't have to be
+// normalized for that.
+fn has_separating_axis(a: &[SimVec2], b: &[SimVec2]) -> bool {
+    edges(a).any(|(p, q)| {
+        let edge = q - p;
+        let axis = SimVec2::new(-edge.y, edge.x);
+        let (a_min, a_max) = project(a, axis);
+        let (b_min, b_max) = project(b, axis);
+        a_max < b_min || b_max < a_min
+    })
+}
+
+fn circle_polygon(center: SimVec2, radius: Scalar, points: &[SimVec2]) -> bool {
+    // inside if the center is on the inner side of every (counter-clockwise) edge
+    let inside = edges(points).all(|(p, q)| {
+        let edge = q - p;
+        let to_center = center - p;
+        edge.x * to_center.y - edge.y * to_center.x >= Scalar::ZERO
+    });
+    if inside {
+        return true;
+    }
+    // otherwise the circle has to reach the closest point on one of the edges
+    edges(points).any(|(p, q)| {
+        let edge = q - p;
+        let length_squared = edge.length_squared();
+        let t = if length_squared > Scalar::ZERO {
+            min(
+                max((center - p).dot(edge) / length_squared, Scalar::ZERO),
+                Scalar::ONE,
+            )
+        } else {
+            Scalar::ZERO
+        };
+        let closest = p + edge.scale(t);
+        (center - closest).length_squared() <= radius * radius
+    })
+}
+
+/// Convex hull (counter-clockwise, without collinear points) of a set of points, for
+/// building polygon colliders from arbitrary outlines.
+pub fn convex_hull(points: &[SimVec2]) -> Vec<SimVec2> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    let cross =
+        |o: SimVec2, a: SimVec2, b: SimVec2| (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x);
+    // Andrew's monotone chain
+    let mut lower: Vec<SimVec2> = Vec::with_capacity(sorted.len());
+    for p in sorted.iter() {
+        while lower.len() >= 2
+            && cross(lower[lower.len() - 2], lower[lower.len() - 1], *p) <= Scalar::ZERO
+        {
+            lower.pop();
+        }
+        lower.push(*p);
+    }
+    let mut upper: Vec<SimVec2> = Vec::with_capacity(sorted.len());
+    for p in sorted.iter().rev() {
+        while upper.len() >= 2
+            && cross(upper[upper.len() - 2], upper[upper.len() - 1], *p) <= Scalar::ZERO
+        {
+            upper.pop();
+        }
+        upper.push(*p);
+    }
+    // the last point of each half is the first point of the other half
+    lower.pop();
+    upper.pop();
+    lower.extend(upper);
+    lower
+}
+
+/// Uniform grid broad phase. Colliders are inserted into every cell
+/// their bounding box touches, and only colliders that share a cell are tested exactly.
+pub struct SpatialGrid {
+    cell_size: Scalar,
+    columns: i64,
+    rows: i64,
+    cells: Vec<Vec<usize>>,
+}
+
+impl SpatialGrid {
+    pub fn new(world_width: Scalar, world_height: Scalar, cell_size: Scalar) -> Self {
+        let columns = (world_width / cell_size).to_f32().ceil().max(1.0) as i64;
+        let rows = (world_height / cell_size).to_f32().ceil().max(1.0) as i64;
+        Self {
+            cell_size,
+            columns,
+            rows,
+            cells: vec![Vec::new(); (columns * rows) as usize],
+        }
+    }
+
+    fn cell_coordinate(&self, value: Scalar) -> i64 {
+        (value / self.cell_size).floor().to_f32() as i64
+    }
+
+    fn insert(&mut self, index: usize, aabb: Aabb) {
+        // anything sticking out of the world goes into the edge cells
+        let rows = self.cell_coordinate(aabb.min.y).clamp(0, self.rows - 1)
+            ..=self.cell_coordinate(aabb.max.y).clamp(0, self.rows - 1);
+        let columns = self.cell_coordinate(aabb.min.x).clamp(0, self.columns - 1)
+            ..=self.cell_coordinate(aabb.max.x).clamp(0, self.columns - 1);
+        for row in rows {
+            for column in columns.clone() {
+                let cell = (row * self.columns + column) as usize;
+                if self.cells[cell].last() != Some(&index) {
+                    self.cells[cell].push(index);
+                }
+            }
+        }
+    }
+
+    /// Returns the (sorted, deduplicated) index pairs of all colliders that overlap.
+    /// `filter` decides which pairs are worth testing at all (e.g. asteroids don't collide
+    /// with each other).
+    pub fn find_collisions(
+        &mut self,
+        colliders: &[Collider],
+        filter: impl Fn(usize, usize) -> bool,
+    ) -> Vec<(usize, usize)> {
+        for cell in self.cells.iter_mut() {
+            cell.clear();
+        }
+        for (i, collider) in colliders.iter().enumerate() {
+            self.insert(i, collider.aabb());
+        }
+
+        let mut candidates: Vec<(usize, usize)> = Vec::new();
+        for cell in self.cells.iter() {
+            for (n, &a) in cell.iter().enumerate() {
+                for &b in &cell[n + 1..] {
+                    candidates.push((a.min(b), a.max(b)));
+                }
+            }
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        candidates
+            .into_iter()
+            .filter(|&(a, b)| filter(a, b) && colliders[a].intersects(&colliders[b]))
+            .collect()
+    }
+}
+
+impl HeapSize for Shape {
+    fn heap_size(&self) -> usize {
+        match self {
+            Shape::Circle { .. } => 0,
+            Shape::Polygon { points } => vec_size(points),
+        }
+    }
+}
@@ -0,0 +1,420 @@
+//! The settings file: a TOML file with the settings players may want to change (window size,
+//! vsync, fonts, key bindings, volumes, tick rate, the radar, the language, UI scale and
+//! colors).
+//!
+//! ```toml
+//! [window]
+//! width = 1440.0
+//! height = 900.0
+//! fullscreen = "borderless" # or "exclusive", leave out for a window
+//!
+//! [graphics]
+//! vsync = true
+//! msaa_samples = 4
+//! post_effects = ["bloom", "vignette"]
+//! text_antialiasing = "lcd" # or "grayscale" or "none"
+//! gamma_correct_text = false
+//!
+//! [graphics.lcd_filter]
+//! weights = [0.031, 0.302, 0.334, 0.302, 0.031]
+//! bgr = false
+//!
+//! [[graphics.background]] # a layer, back to front, see crate::gfx::background
+//! pattern = "nebula" # or "dust"
+//! color = [0.45, 0.35, 0.8, 0.12]
+//! scroll_factor = 0.05 # 0 stays put, 1 moves with the world
+//! drift = [1.5, 0.5] # world units per second
+//! tile_size = 1200.0 # world units
+//! seed = 1
+//!
+//! [fonts]
+//! files = ["fonts/Roboto-Regular.ttf"]
+//! urls = ["https://example.com/fonts/Orbitron-Regular.ttf"] # with the remote-fonts feature
+//!
+//! [keys]
+//! thrust = ["W", "Key8"]
+//! fire = ["Space", "Enter"]
+//!
+//! [audio]
+//! master = 0.8
+//!
+//! [simulation]
+//! tick_rate = 60 # 30, 60 or 120
+//! seed = 1234 # every game's, leave out for a new one each game (see crate::rng)
+//!
+//! [hud]
+//! radar = true
+//! radar_range = 450.0 # world units
+//! radar_friend_color = [0.4, 1.0, 0.6, 1.0] # leave out for the palette's
+//! radar_foe_color = [1.0, 0.35, 0.3, 1.0]
+//!
+//! [interface]
+//! language = "de" # a string table's name, see crate::i18n
+//! ui_scale = 1.25 # text and the UI, from 0.75 to 2.0
+//! color_vision = "deuteranopia" # or "protanopia", "tritanopia" or "normal"
+//! ```
+//!
+//! Everything is optional, missing values keep their defaults. Key names are device_query
+//! `Keycode` names. The engine loads the file on start (see
+//! [`EngineBuilder::settings_file`](crate::engine::EngineBuilder::settings_file)) and
+//! reloads it when it changes, applying what it can while the game runs. Fonts are only
+//! loaded on start.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use anyhow::{Result, anyhow};
+use device_query::Keycode;
+use serde::{Deserialize, Serialize};
+
+use crate::crash;
+use crate::game_state::GameState;
+use crate::gfx::background::BackgroundLayer;
+use crate::gfx::post::PostEffect;
+use crate::gfx::text::{LcdFilter, TextAntialiasing};
+use crate::i18n;
+use crate::input::Action;
+use crate::logging;
+use crate::palette::ColorVision;
+use crate::paths;
+use crate::radar::RadarSettings;
+use crate::tick_rate::TickRate;
+use crate::window::FullscreenMode;
+
+pub const SETTINGS_FILE_NAME: &str = "settings.toml";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    /// Logical inner width of the window
+    pub width: f64,
+    /// Logical inner height of the window
+    pub height: f64,
+    /// None = windowed
+    pub fullscreen: Option<FullscreenMode>,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: 1440.0,
+            height: 900.0,
+            fullscreen: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsConfig {
+    pub vsync: bool,
+    /// 1 (off), 2 or 4
+    pub msaa_samples: u32,
+    pub post_effects: Vec<PostEffect>,
+    /// "lcd", "grayscale" or "none"
+    pub text_antialiasing: TextAntialiasing,
+    pub lcd_filter: LcdFilter,
+    pub gamma_correct_text: bool,
+    /// The background's layers, leave out for the game's own (`background = []` for none)
+    pub background: Option<Vec<BackgroundLayer>>,
+}
+
+impl Default for GraphicsConfig {
+    fn default() -> Self {
+        Self {
+            vsync: true,
+            msaa_samples: 1,
+            post_effects: Vec::new(),
+            text_antialiasing: TextAntialiasing::default(),
+            lcd_filter: LcdFilter::default(),
+            gamma_correct_text: false,
+            background: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FontConfig {
+    /// Font files loaded in addition to the ones the game registers, resolved against the
+    /// asset roots like those
+    pub files: Vec<PathBuf>,
+    /// Font files downloaded on start, with the `remote-fonts` feature (see
+    /// `FontCache::load_font_url`)
+    pub urls: Vec<String>,
+}
+
+/// Key names per action. An empty list leaves the action without keys.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyConfig {
+    pub thrust: Vec<String>,
+    pub turn_left: Vec<String>,
+    pub turn_right: Vec<String>,
+    pub fire: Vec<String>,
+}
+
+impl Default for KeyConfig {
+    fn default() -> Self {
+        let keys = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect();
+        Self {
+            thrust: keys(&["W"]),
+            turn_left: keys(&["A"]),
+            turn_right: keys(&["D"]),
+            fire: keys(&["Space"]),
+        }
+    }
+}
+
+impl KeyConfig {
+    fn keys(&self, action: Action) -> &[String] {
+        match action {
+            Action::Thrust => &self.thrust,
+            Action::TurnLeft => &self.turn_left,
+            Action::TurnRight => &self.turn_right,
+            Action::Fire => &self.fire,
+        }
+    }
+}
+
+/// Volumes from 0 (muted) to 1. The music and effects volumes are scaled by the master
+/// volume.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub effects: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            music: 0.7,
+            effects: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulationConfig {
+    /// Ticks (and game state updates) per second
+    pub tick_rate: TickRate,
+    /// The master seed of every game, a random one for each without it
+    pub seed: Option<u64>,
+}
+
+/// The radar, see [`crate::radar`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HudConfig {
+    pub radar: bool,
+    /// World units from the ship to the radar's edge
+    pub radar_range: f32,
+    /// The player's ship and shots, the palette's without it (see crate::palette)
+    pub radar_friend_color: Option<[f32; 4]>,
+    /// Asteroids and the other players, the palette's without it
+    pub radar_foe_color: Option<[f32; 4]>,
+}
+
+impl Default for HudConfig {
+    fn default() -> Self {
+        let radar = RadarSettings::default();
+        Self {
+            radar: radar.enabled,
+            radar_range: radar.range,
+            radar_friend_color: radar.friend_color,
+            radar_foe_color: radar.foe_color,
+        }
+    }
+}
+
+/// The language (see [`crate::i18n`]), the size of the UI and its colors
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InterfaceConfig {
+    pub language: String,
+    /// Text and the UI are drawn this many times their usual size, on top of the window's
+    /// scale factor
+    pub ui_scale: f32,
+    /// The palette's preset, see [`crate::palette`]
+    pub color_vision: ColorVision,
+}
+
+impl Default for InterfaceConfig {
+    fn default() -> Self {
+        Self {
+            language: i18n::DEFAULT_LANGUAGE.to_string(),
+            ui_scale: 1.0,
+            color_vision: ColorVision::Normal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub window: WindowConfig,
+    pub graphics: GraphicsConfig,
+    pub fonts: FontConfig,
+    pub keys: KeyConfig,
+    pub audio: AudioSettings,
+    pub simulation: SimulationConfig,
+    pub hud: HudConfig,
+    pub interface: InterfaceConfig,
+}
+
+impl Settings {
+    /// `settings.toml` in the platform's config directory, see [`paths::config_dir`].
+    pub fn default_path() -> Option<PathBuf> {
+        paths::config_dir(paths::APP_NAME).map(|dir| dir.join(SETTINGS_FILE_NAME))
+    }
+
+    pub fn load(path: &Path) -> Result<Settings> {
+        let text = fs::read_to_string(path)?;
+        toml::from_str(&text)
+            .map_err(|e| anyhow!("invalid settings file {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Copies the settings into the game state. The engine passes the window and graphics
+    /// settings on to the window and GfxState after the update, so this works while the game
+    /// is running. Font changes are only picked up on start.
+    pub fn apply(&self, game_state: &mut GameState) {
+        self.apply_sections(game_state, true);
+    }
+
+    /// Like `apply`, but only for the sections that changed since the last applied settings,
+    /// so e.g. editing the key bindings doesn't undo a window resize or the vsync hotkey.
+    pub(crate) fn apply_changes(&self, game_state: &mut GameState) {
+        self.apply_sections(game_state, false);
+    }
+
+    fn apply_sections(&self, game_state: &mut GameState, all: bool) {
+        let previous = &game_state.settings;
+        if all || self.window != previous.window {
+            let window = &mut game_state.window_settings;
+            window.width = self.window.width;
+            window.height = self.window.height;
+            window.fullscreen = self.window.fullscreen;
+        }
+
+        if all || self.graphics != previous.graphics {
+            let gfx = &mut game_state.gfx_settings;
+            gfx.set_vsync(self.graphics.vsync);
+            gfx.msaa_sample_count = self.graphics.msaa_samples;
+            gfx.post_effects = self.graphics.post_effects.clone();
+            gfx.text_antialiasing = self.graphics.text_antialiasing;
+            gfx.lcd_filter = self.graphics.lcd_filter;
+            gfx.gamma_correct_text = self.graphics.gamma_correct_text;
+            gfx.background_layers = self
+                .graphics
+                .background
+                .clone()
+                .unwrap_or_else(BackgroundLayer::defaults);
+        }
+
+        if all || self.keys != previous.keys {
+            let input_map = &mut game_state.input_map;
+            for action in [
+                Action::Thrust,
+                Action::TurnLeft,
+                Action::TurnRight,
+                Action::Fire,
+            ] {
+                input_map.unbind_all(action);
+                for name in self.keys.keys(action) {
+                    match Keycode::from_str(name) {
+                        Ok(key_code) => input_map.bind(action, key_code),
+                        Err(_) => {
+                            log::warn!(
+                                target: logging::INPUT,
+                                "unknown key {:?} bound to {:?}",
+                                name,
+                                action
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        if !all && self.fonts != previous.fonts {
+            log::info!("font changes take effect after a restart");
+        }
+
+        if all || self.hud != previous.hud {
+            let radar = &mut game_state.radar;
+            radar.enabled = self.hud.radar;
+            radar.range = self.hud.radar_range;
+            radar.friend_color = self.hud.radar_friend_color;
+            radar.foe_color = self.hud.radar_foe_color;
+        }
+
+        if all || self.simulation != previous.simulation {
+            game_state.set_tick_rate(self.simulation.tick_rate);
+            game_state.set_seed(self.simulation.seed);
+        }
+
+        if all || self.interface != game_state.settings.interface {
+            if let Err(e) = i18n::set_language(&self.interface.language) {
+                log::warn!("{}, keeping the language", e);
+            }
+            game_state.set_ui_scale(self.interface.ui_scale);
+            game_state.set_color_vision(self.interface.color_vision);
+        }
+        crash::set_context(
+            "settings",
+            toml::to_string_pretty(self).unwrap_or_else(|e| e.to_string()),
+        );
+        game_state.settings = self.clone();
+    }
+}
+
+/// A settings file that is checked for changes now and then (see `GameState::update`).
+pub(crate) struct SettingsFile {
+    pub path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl SettingsFile {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = Self::modified_time(&path);
+        Self { path, modified }
+    }
+
+    fn modified_time(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|meta| meta.modified()).ok()
+    }
+
+    /// Loads the file if it was modified since the last call. Errors are logged and
+    /// otherwise ignored, the game keeps its current settings.
+    pub fn reload_if_changed(&mut self) -> Option<Settings> {
+        let modified = Self::modified_time(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        match Settings::load(&self.path) {
+            Ok(settings) => {
+                log::info!("reloaded settings from {}", self.path.display());
+                Some(settings)
+            }
+            Err(error) => {
+                log::warn!("not reloading settings: {}", error);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Crash reports: when the game panics, the panic hook writes what's needed to find out why
+//! into a text file in the user's data directory (`crash-reports/crash-<unix time>.txt`) and
+//! tells the player where it is, in a message box on Windows and on stderr elsewhere.
+//!
+//! The report has the panic message and where it happened, the backtrace, the last lines of
+//! the log (see [`logging::recent_lines`]) and the context the game registered with
+//! [`set_context`], like the graphics adapter and the settings. There's no minidump, that
+//! needs a crash reporting service; a backtrace with the log covers most crashes.
+
+use std::backtrace::Backtrace;
+use std::fmt::Write as _;
+use std::fs;
+use std::panic::PanicHookInfo;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use parking_lot::{Mutex, const_mutex};
+
+use crate::{logging, paths};
+
+// the sections of the report the game registered, by name
+static CONTEXT: Mutex<Vec<(&'static str, String)>> = const_mutex(Vec::new());
+// set by the first panic, later ones (e.g. while writing the report) only get the default hook
+static CRASHED: AtomicBool = AtomicBool::new(false);
+
+/// Sets the `name` section of crash reports (replacing what it was before), e.g. the graphics
+/// adapter.
+pub fn set_context(name: &'static str, text: impl Into<String>) {
+    let mut context = CONTEXT.lock();
+    let text = text.into();
+    match context.iter_mut().find(|(section, _)| *section == name) {
+        Some((_, previous)) => *previous = text,
+        None => context.push((name, text)),
+    }
+}
+
+/// Installs the panic hook, after the default one (which prints the panic to stderr). A
+/// panic on another thread is reported as well, but only a panic on the main thread (which
+/// ends the game) is shown to the player.
+pub fn install() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if CRASHED.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let message = match write(&report(info)) {
+            Ok(path) => format!(
+                "The game crashed. A crash report was written to\n\n{}",
+                path.display()
+            ),
+            Err(error) => format!(
+                "The game crashed, and the crash report couldn't be written: {}",
+                error
+            ),
+        };
+        if std::thread::current().name() == Some("main") {
+            show(&message);
+        } else {
+            // the game may go on, so the next panic is reported too
+            CRASHED.store(false, Ordering::SeqCst);
+            eprintln!("{}", message);
+        }
+    }));
+}
+
+fn report(info: &PanicHookInfo) -> String {
+    let mut report = String::new();
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("(not a string)");
+    let _ = writeln!(report, "panic: {}", message);
+    if let Some(location) = info.location() {
+        let _ = writeln!(report, "at: {}", location);
+    }
+    let thread = std::thread::current();
+    let _ = writeln!(report, "thread: {}", thread.name().unwrap_or("unnamed"));
+    let _ = writeln!(report, "version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(
+        report,
+        "system: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    if let Some(path) = logging::session_log_path() {
+        let _ = writeln!(report, "log file: {}", path.display());
+    }
+
+    let _ = write!(report, "\nbacktrace:\n{}\n", Backtrace::force_capture());
+    // whatever panicked may hold the lock
+    if let Some(context) = CONTEXT.try_lock() {
+        for (name, text) in context.iter() {
+            let _ = write!(report, "\n{}:\n{}\n", name, text.trim_end());
+        }
+    }
+    match logging::try_recent_lines() {
+        Some(lines) => {
+            let _ = write!(report, "\nlog:\n{}\n", lines.join("\n"));
+        }
+        None => report.push_str("\nlog: (locked by the panicking thread)\n"),
+    }
+    report
+}
+
+fn write(report: &str) -> Result<PathBuf> {
+    let directory = paths::data_dir(paths::APP_NAME)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("crash-reports");
+    fs::create_dir_all(&directory)?;
+    let unix_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let path = directory.join(format!("crash-{}.txt", unix_time));
+    fs::write(&path, report)?;
+    Ok(path)
+}
+
+#[cfg(windows)]
+fn show(message: &str) {
+    crate::os::dialog::show_error("Game", message);
+}
+
+#[cfg(not(windows))]
+fn show(message: &str) {
+    eprintln!("\n{}", message);
+}
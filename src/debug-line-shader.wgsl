@@ -7,10 +7,12 @@ var<uniform> surface_dimensions: SurfaceDimensionsUniform;
 
 struct VertexInput {
     @location(0) pos: vec2<f32>,
+    @location(1) color: vec4<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
 }
 
 @vertex
@@ -19,6 +21,7 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.clip_position = vec4<f32>(input.pos, 0.0, 1.0);
+    out.color = input.color;
     return out;
 }
 
@@ -26,5 +29,5 @@ fn vs_main(
 fn fs_main(
     in: VertexOutput,
 ) -> @location(0) vec4<f32> {
-    return vec4<f32>(0.0, 1.0, 0.0, 1.0);
-}
\ No newline at end of file
+    return in.color;
+}
@@ -0,0 +1,284 @@
+//! `--diagnose`: checks every subsystem on its own, without opening a window, and prints a
+//! pass/fail report that players with a broken setup can paste into a bug report.
+//!
+//! A failing (or panicking) check doesn't stop the others, so one report shows everything
+//! that's wrong. See [`EngineBuilder::diagnose`](crate::engine::EngineBuilder::diagnose).
+
+use std::fmt;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use pollster::FutureExt as _;
+use skrifa::MetadataProvider;
+
+use crate::config::Settings;
+use crate::gfx::GfxState;
+use crate::gfx::text::{FontCache, Rasterizer, TextAntialiasing, single_source_shader};
+use crate::paths;
+
+// every shader the renderers use, compiled on their own
+const SHADERS: [(&str, &str); 6] = [
+    ("color-shader.wgsl", include_str!("gfx/color-shader.wgsl")),
+    (
+        "debug-line-shader.wgsl",
+        include_str!("debug-line-shader.wgsl"),
+    ),
+    ("line-shader.wgsl", include_str!("line-shader.wgsl")),
+    (
+        "particle-shader.wgsl",
+        include_str!("gfx/particle-shader.wgsl"),
+    ),
+    ("post_shader.wgsl", include_str!("gfx/post_shader.wgsl")),
+    ("text_shader.wgsl", include_str!("gfx/text_shader.wgsl")),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Pass(String),
+    Fail(String),
+    /// The subsystem doesn't exist in this build
+    Skip(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// True if no check failed.
+    pub fn passed(&self) -> bool {
+        !self
+            .checks
+            .iter()
+            .any(|check| matches!(check.outcome, Outcome::Fail(_)))
+    }
+
+    // runs the check, turning errors and panics into a failure
+    fn check(&mut self, name: impl Into<String>, check: impl FnOnce() -> Result<String>) {
+        let outcome = match panic::catch_unwind(AssertUnwindSafe(check)) {
+            Ok(Ok(details)) => Outcome::Pass(details),
+            Ok(Err(error)) => Outcome::Fail(format!("{:#}", error)),
+            Err(payload) => Outcome::Fail(format!(
+                "panicked: {}",
+                payload
+                    .downcast_ref::<String>()
+                    .map(String::as_str)
+                    .or_else(|| payload.downcast_ref::<&str>().copied())
+                    .unwrap_or("(no message)")
+            )),
+        };
+        self.checks.push(Check {
+            name: name.into(),
+            outcome,
+        });
+    }
+
+    fn skip(&mut self, name: &str, reason: &str) {
+        self.checks.push(Check {
+            name: name.to_string(),
+            outcome: Outcome::Skip(reason.to_string()),
+        });
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} {} diagnostics ({} {})",
+            paths::APP_NAME,
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        )?;
+        let width = self.checks.iter().map(|check| check.name.len()).max();
+        for check in self.checks.iter() {
+            let (status, details) = match &check.outcome {
+                Outcome::Pass(details) => ("PASS", details),
+                Outcome::Fail(details) => ("FAIL", details),
+                Outcome::Skip(details) => ("SKIP", details),
+            };
+            writeln!(
+                f,
+                "{}  {:<width$}  {}",
+                status,
+                check.name,
+                details,
+                width = width.unwrap_or(0)
+            )?;
+        }
+        let failed = self
+            .checks
+            .iter()
+            .filter(|check| matches!(check.outcome, Outcome::Fail(_)))
+            .count();
+        writeln!(f, "{} of {} checks failed", failed, self.checks.len())
+    }
+}
+
+fn check_graphics(report: &mut Report) {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::PRIMARY,
+        ..Default::default()
+    });
+    let mut adapter = None;
+    report.check("graphics adapter", || {
+        let found = instance
+            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .block_on()?;
+        let info = found.get_info();
+        adapter = Some(found);
+        Ok(format!(
+            "{} ({:?}, {:?}), driver: {} {}",
+            info.name, info.device_type, info.backend, info.driver, info.driver_info
+        ))
+    });
+    let Some(adapter) = adapter else {
+        report.skip("graphics device", "no adapter");
+        return;
+    };
+
+    let mut device = None;
+    report.check("graphics device", || {
+        let (found, _queue) = adapter
+            .request_device(&GfxState::device_descriptor(&adapter))
+            .block_on()?;
+        let features = found.features();
+        let limits = found.limits();
+        device = Some(found);
+        Ok(format!(
+            "max texture size {}, timestamp queries: {}, dual source blending: {}",
+            limits.max_texture_dimension_2d,
+            features.contains(wgpu::Features::TIMESTAMP_QUERY),
+            features.contains(wgpu::Features::DUAL_SOURCE_BLENDING)
+        ))
+    });
+    let Some(device) = device else {
+        report.skip("shaders", "no device");
+        return;
+    };
+
+    let dual_source_blending = device
+        .features()
+        .contains(wgpu::Features::DUAL_SOURCE_BLENDING);
+    for (name, source) in SHADERS {
+        // like the text renderer builds it on this device
+        let source = if name == "text_shader.wgsl" && !dual_source_blending {
+            single_source_shader(source)
+        } else {
+            source.to_string()
+        };
+        report.check(format!("shader {}", name), || {
+            device.push_error_scope(wgpu::ErrorFilter::Validation);
+            let _module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(name),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+            match device.pop_error_scope().block_on() {
+                Some(error) => Err(anyhow!("{}", error)),
+                None => Ok("compiled".to_string()),
+            }
+        });
+    }
+}
+
+fn check_fonts(report: &mut Report, font_files: &[(&Path, Result<PathBuf>)]) {
+    let mut font_cache = FontCache::new();
+    for (font, resolved) in font_files {
+        report.check(format!("font {}", font.display()), || {
+            let path = resolved.as_ref().map_err(|error| anyhow!("{:#}", error))?;
+            let faces = font_cache.load_font_file(path)?;
+            Ok(format!("faces: {}, {}", faces.len(), path.display()))
+        });
+    }
+    report.check("system fonts", || {
+        Ok(format!("faces: {}", font_cache.load_system_fonts()?))
+    });
+
+    report.check("glyph rasterization", || {
+        let font = font_cache
+            .get_font(0)
+            .ok_or_else(|| anyhow!("no fonts loaded"))?;
+        let glyph_id = font
+            .ext_font_ref()
+            .charmap()
+            .map('A')
+            .ok_or_else(|| anyhow!("{} has no glyph for 'A'", font.family_name()))?;
+        let mut buffer = vec![0; 128 * 128 * 4];
+        let placement = Rasterizer::new().render_mask(
+            &font,
+            glyph_id,
+            skrifa::instance::Size::new(32.0),
+            &skrifa::instance::Location::default(),
+            &mut buffer,
+            0,
+            TextAntialiasing::Lcd,
+        )?;
+        if buffer.iter().all(|coverage| *coverage == 0) {
+            return Err(anyhow!("'A' in {} rendered empty", font.family_name()));
+        }
+        Ok(format!(
+            "'A' in {}, {}x{} px",
+            font.family_name(),
+            placement.width,
+            placement.height
+        ))
+    });
+}
+
+// creates the directory if needed, then writes, reads back and removes a file in it
+fn check_directory(directory: Option<PathBuf>) -> Result<String> {
+    let directory = directory.ok_or_else(|| anyhow!("no such directory on this platform"))?;
+    fs::create_dir_all(&directory)?;
+    let path = directory.join("diagnose.tmp");
+    let contents = b"diagnose";
+    fs::write(&path, contents)?;
+    let read = fs::read(&path);
+    fs::remove_file(&path)?;
+    if read? != contents {
+        return Err(anyhow!("read back different contents"));
+    }
+    Ok(directory.display().to_string())
+}
+
+/// Runs every check. `font_files` are the game's fonts with their resolved paths (an error
+/// if they couldn't be found), `settings_file` the settings file the game would load.
+pub(crate) fn run(font_files: &[(&Path, Result<PathBuf>)], settings_file: Option<&Path>) -> Report {
+    let mut report = Report::default();
+    check_graphics(&mut report);
+    check_fonts(&mut report, font_files);
+    report.skip("audio output", "no audio output in this build");
+    report.skip("gamepads", "no gamepad support in this build");
+
+    let config_dir = settings_file
+        .and_then(|path| path.parent())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .or_else(|| paths::config_dir(paths::APP_NAME));
+    report.check("config directory", || check_directory(config_dir));
+    report.check("data directory", || {
+        check_directory(paths::data_dir(paths::APP_NAME))
+    });
+    report.check("log directory", || {
+        check_directory(paths::log_dir(paths::APP_NAME))
+    });
+    if let Some(path) = settings_file {
+        report.check("settings file", || {
+            if !path.exists() {
+                return Ok(format!("{} doesn't exist yet", path.display()));
+            }
+            Settings::load(path)?;
+            Ok(path.display().to_string())
+        });
+    }
+    report
+}
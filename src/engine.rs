@@ -0,0 +1,883 @@
+//! The high level entry point of the engine: [`Engine::builder()`] configures the window,
+//! fonts/asset roots and the systems that update the game state every tick, and
+//! [`Engine::run()`] opens the window and runs the event loop until the game quits.
+//!
+//! ```no_run
+//! use game::{Engine, GameState};
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     Engine::builder()
+//!         .title("My game")
+//!         .asset_root("./assets")
+//!         .font("fonts/Roboto-Regular.ttf")
+//!         .system(|game_state: &mut GameState| {
+//!             game_state.set_text(Some(format!("frame {}", game_state.frame_number())));
+//!             Ok(())
+//!         })
+//!         .build()?
+//!         .run()
+//! }
+//! ```
+
+use anyhow::Result;
+
+use device_query::DeviceState;
+
+use winit::{
+    application::ApplicationHandler,
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{Ime, MouseButton, MouseScrollDelta, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    window::{Window, WindowId},
+};
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::assets::Assets;
+use crate::benchmark::Benchmark;
+use crate::bug_report;
+use crate::config::{Settings, SettingsFile};
+use crate::diagnose::{self, Report};
+use crate::error::GameError;
+use crate::events::WindowFocusChanged;
+use crate::frame_pacing::{FramePacer, Stage};
+use crate::game_state::GameState;
+use crate::gfx::text::{FontCache, FontLoadEvent};
+use crate::gfx::{GfxSettings, GfxState};
+use crate::high_scores::HighScores;
+use crate::i18n;
+use crate::input::KeyboardState;
+use crate::logging;
+use crate::paths::{self, AssetRoots};
+use crate::save::SaveSlot;
+use crate::tick_rate::TickRate;
+use crate::window::WindowSettings;
+
+/// Something that updates the game state once per tick, after the engine's own update.
+///
+/// Implemented for closures taking `&mut GameState`, so simple systems don't need a type.
+pub trait System {
+    fn update(&mut self, game_state: &mut GameState) -> Result<()>;
+}
+
+impl<F> System for F
+where
+    F: FnMut(&mut GameState) -> Result<()>,
+{
+    fn update(&mut self, game_state: &mut GameState) -> Result<()> {
+        self(game_state)
+    }
+}
+
+/// Builder for [`Engine`], see [`Engine::builder()`].
+pub struct EngineBuilder {
+    window_settings: WindowSettings,
+    gfx_settings: GfxSettings,
+    systems: Vec<Box<dyn System>>,
+    asset_roots: AssetRoots,
+    fonts: Vec<PathBuf>,
+    font_directories: Vec<PathBuf>,
+    language_directories: Vec<PathBuf>,
+    load_system_fonts: bool,
+    settings: Option<Settings>,
+    settings_file: Option<PathBuf>,
+    load_game: Option<SaveSlot>,
+    seed: Option<u64>,
+    benchmark: Option<Benchmark>,
+    // the server's address and the player's name
+    connect: Option<(String, String)>,
+}
+
+impl EngineBuilder {
+    pub fn window_settings(mut self, window_settings: WindowSettings) -> Self {
+        self.window_settings = window_settings;
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.window_settings.title = title.into();
+        self
+    }
+
+    /// Logical inner size of the window.
+    pub fn inner_size(mut self, width: f64, height: f64) -> Self {
+        self.window_settings.width = width;
+        self.window_settings.height = height;
+        self
+    }
+
+    /// Logical minimum inner size of the window.
+    pub fn min_inner_size(mut self, width: f64, height: f64) -> Self {
+        self.window_settings.min_size = Some((width, height));
+        self
+    }
+
+    pub fn gfx_settings(mut self, gfx_settings: GfxSettings) -> Self {
+        self.gfx_settings = gfx_settings;
+        self
+    }
+
+    /// Registers a system. Systems are updated in the order they were registered.
+    pub fn system(mut self, system: impl System + 'static) -> Self {
+        self.systems.push(Box::new(system));
+        self
+    }
+
+    /// Adds a directory that relative asset paths (e.g. fonts) are resolved against.
+    /// Roots are searched in the order they were added. A relative root is looked up in the
+    /// executable's directory and the others of [`paths::asset_base_dirs`], so the game
+    /// finds its files from any working directory. Without any roots, relative paths are
+    /// looked up in those directories themselves.
+    pub fn asset_root(mut self, path: impl Into<PathBuf>) -> Self {
+        self.asset_roots.add_root(path);
+        self
+    }
+
+    /// Adds a font file to load on build, through the game state's [`Assets`] (in debug
+    /// builds it's reloaded when it changes). Text is drawn with the first one that loads,
+    /// or with [`FALLBACK_FONT`](crate::gfx::text::FALLBACK_FONT) if none does.
+    pub fn font(mut self, path: impl Into<PathBuf>) -> Self {
+        self.fonts.push(path.into());
+        self
+    }
+
+    /// Loads the fonts in a directory on build, and (re)loads them while the game runs when
+    /// files there are added or changed, see [`FontCache::watch_directory`]. Meant for
+    /// working on the game's fonts without restarting it.
+    pub fn font_directory(mut self, path: impl Into<PathBuf>) -> Self {
+        self.font_directories.push(path.into());
+        self
+    }
+
+    /// Adds a directory with string tables for the UI's text, `<language>.toml` (see
+    /// [`crate::i18n`]).
+    pub fn language_directory(mut self, path: impl Into<PathBuf>) -> Self {
+        self.language_directories.push(path.into());
+        self
+    }
+
+    /// Settings to apply on build, overriding the window and graphics settings given to the
+    /// builder. With a settings file these are the defaults written to a new file.
+    pub fn settings(mut self, settings: Settings) -> Self {
+        self.settings = Some(settings);
+        self
+    }
+
+    /// Loads the settings from a TOML file on build (see [`crate::config`]), creating it with
+    /// the default settings if it doesn't exist. The file is reloaded while the game runs
+    /// when it changes.
+    pub fn settings_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.settings_file = Some(path.into());
+        self
+    }
+
+    /// Continues the game saved in `slot` on build (see [`crate::save`]). If it can't be
+    /// loaded the game starts at the main menu as usual.
+    pub fn load_game(mut self, slot: SaveSlot) -> Self {
+        self.load_game = Some(slot);
+        self
+    }
+
+    /// Fixes the master seed of every game (see [`crate::rng`]), over the settings file's.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Runs the benchmark scene for `frames` frames instead of the game, then writes its
+    /// report and quits. See [`crate::benchmark`].
+    pub fn benchmark(mut self, frames: u32) -> Self {
+        self.benchmark = Some(Benchmark::new(frames));
+        self
+    }
+
+    /// Joins the multiplayer server at `address` (e.g. `127.0.0.1:27450`) as `name` on build,
+    /// see [`crate::net`]. If it can't the game starts at the main menu as usual.
+    pub fn connect(mut self, address: impl Into<String>, name: impl Into<String>) -> Self {
+        self.connect = Some((address.into(), name.into()));
+        self
+    }
+
+    /// Whether the fonts installed on the system are loaded as well (default: true).
+    pub fn load_system_fonts(mut self, load_system_fonts: bool) -> Self {
+        self.load_system_fonts = load_system_fonts;
+        self
+    }
+
+    fn resolve_asset_path(&self, path: &PathBuf) -> Result<PathBuf> {
+        self.asset_roots.resolve(path)
+    }
+
+    // a broken settings file shouldn't keep the game from starting, so errors are only logged
+    fn load_settings(&self) -> Option<Settings> {
+        let Some(path) = &self.settings_file else {
+            return self.settings.clone();
+        };
+        let defaults = self.settings.clone().unwrap_or_default();
+        if !path.exists() {
+            match defaults.save(path) {
+                Ok(()) => log::info!("wrote default settings to {}", path.display()),
+                Err(e) => log::warn!("unable to write {}: {}", path.display(), e),
+            }
+            return Some(defaults);
+        }
+        match Settings::load(path) {
+            Ok(settings) => {
+                log::info!("loaded settings from {}", path.display());
+                Some(settings)
+            }
+            Err(e) => {
+                log::error!("{}, using the default settings", e);
+                Some(defaults)
+            }
+        }
+    }
+
+    /// Checks the graphics device, shaders, fonts and directories the engine would use on
+    /// build, without opening a window. See [`crate::diagnose`].
+    pub fn diagnose(&self) -> Report {
+        let fonts: Vec<(&Path, Result<PathBuf>)> = self
+            .fonts
+            .iter()
+            .map(|font| (font.as_path(), self.resolve_asset_path(font)))
+            .collect();
+        diagnose::run(&fonts, self.settings_file.as_deref())
+    }
+
+    /// Loads the fonts and sets up the game state. The window and gpu state are only
+    /// created once the event loop is running.
+    pub fn build(self) -> Result<Engine> {
+        let mut assets = Assets::default();
+        assets.set_roots(self.asset_roots.clone());
+        assets.set_hot_reload(cfg!(debug_assertions));
+        let mut font_cache = FontCache::new();
+        // the game starts with its own fonts, the system's are added as they're loaded
+        if self.load_system_fonts {
+            font_cache.load_system_fonts_in_background()?;
+        }
+        // text is drawn with the first of the game's fonts, or the one in the binary when
+        // none of them is found (e.g. the game was started from another directory)
+        let mut ui_font = None;
+        for font in self.fonts.iter() {
+            match self
+                .resolve_asset_path(font)
+                .and_then(|path| font_cache.load_font_asset(&assets.fonts.load_blocking(path)))
+            {
+                Ok(faces) => ui_font = ui_font.or(faces.first().copied()),
+                Err(e) => log::error!("unable to load font file {}: {}", font.display(), e),
+            }
+        }
+        if ui_font.is_none() {
+            ui_font = Some(font_cache.load_fallback_font()?);
+            log::warn!("drawing text with the fallback font");
+        }
+        for directory in self.font_directories.iter() {
+            match self
+                .resolve_asset_path(directory)
+                .and_then(|path| font_cache.watch_directory(&path))
+            {
+                Ok(count) => log::info!(
+                    "loaded {} font files from {}, watching it for changes",
+                    count,
+                    directory.display()
+                ),
+                Err(e) => log::warn!(
+                    "unable to watch font directory {}: {}",
+                    directory.display(),
+                    e
+                ),
+            }
+        }
+        for directory in self.language_directories.iter() {
+            match self.resolve_asset_path(directory) {
+                Ok(path) => i18n::add_directory(path),
+                Err(e) => log::warn!("unable to add language directory: {}", e),
+            }
+        }
+        let settings = self.load_settings();
+        // unlike the game's own fonts, the player's are optional
+        for font in settings
+            .iter()
+            .flat_map(|settings| settings.fonts.files.iter())
+        {
+            if let Err(e) = self
+                .resolve_asset_path(font)
+                .and_then(|path| font_cache.load_font_asset(&assets.fonts.load_blocking(path)))
+            {
+                log::warn!("unable to load font file {}: {}", font.display(), e);
+            }
+        }
+        let font_urls = settings
+            .iter()
+            .flat_map(|settings| settings.fonts.urls.iter());
+        #[cfg(feature = "remote-fonts")]
+        for url in font_urls {
+            if let Err(e) = font_cache.load_font_url(url, &mut assets.fonts) {
+                log::warn!("unable to load font {}: {:#}", url, e);
+            }
+        }
+        #[cfg(not(feature = "remote-fonts"))]
+        if font_urls.count() > 0 {
+            log::warn!("the settings' font URLs are only loaded with the remote-fonts feature");
+        }
+
+        let keyboard_state = KeyboardState::new(DeviceState::new());
+        let mut game_state = GameState::new(keyboard_state, font_cache, assets);
+        game_state.ui_font = ui_font;
+        game_state.high_scores_path = HighScores::default_path();
+        if let Some(path) = &game_state.high_scores_path {
+            match HighScores::load(path) {
+                Ok(high_scores) => game_state.high_scores = high_scores,
+                Err(e) => log::error!("{}, starting with an empty table", e),
+            }
+        }
+        game_state.gfx_settings = self.gfx_settings;
+        game_state.window_settings = self.window_settings.clone();
+        if let Some(settings) = &settings {
+            settings.apply(&mut game_state);
+        }
+        if let Some(seed) = self.seed {
+            game_state.set_seed(Some(seed));
+        }
+        game_state.settings_file = self.settings_file.map(SettingsFile::new);
+        if let Some(slot) = self.load_game
+            && let Err(e) = game_state.load_game(slot)
+        {
+            log::error!("unable to load save slot {}: {:#}", slot, e);
+        }
+        if let Some((address, name)) = &self.connect
+            && let Err(e) = game_state.connect(address, name)
+        {
+            log::error!("unable to connect to {}: {:#}", address, e);
+        }
+        if let Some(benchmark) = &self.benchmark {
+            benchmark.start(&mut game_state);
+        }
+
+        Ok(Engine {
+            app: App {
+                window_settings: game_state.window_settings.clone(),
+                systems: self.systems,
+                window: None,
+                gfx_state: None,
+                game_state: Some(game_state),
+                focused: true,
+                minimized: false,
+                rendered_state: 0,
+                last_frame: Instant::now(),
+                text_input: None,
+                benchmark: self.benchmark,
+                frame_pacer: FramePacer::new(),
+            },
+        })
+    }
+}
+
+/// A configured engine, ready to [`run`](Engine::run).
+pub struct Engine {
+    app: App,
+}
+
+impl Engine {
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder {
+            window_settings: WindowSettings::default(),
+            gfx_settings: GfxSettings::default(),
+            systems: Vec::new(),
+            asset_roots: AssetRoots::new(paths::APP_NAME),
+            fonts: Vec::new(),
+            font_directories: Vec::new(),
+            language_directories: Vec::new(),
+            load_system_fonts: true,
+            settings: None,
+            settings_file: None,
+            load_game: None,
+            seed: None,
+            benchmark: None,
+            connect: None,
+        }
+    }
+
+    /// Opens the window and runs the event loop. Blocks until the game quits.
+    pub fn run(mut self) -> Result<()> {
+        let event_loop = EventLoop::new()?;
+        event_loop.run_app(&mut self.app)?;
+        Ok(())
+    }
+}
+
+struct App {
+    // the window settings that were last applied to the window
+    window_settings: WindowSettings,
+    systems: Vec<Box<dyn System>>,
+    window: Option<Arc<Window>>,
+    gfx_state: Option<GfxState>,
+    game_state: Option<GameState>,
+    focused: bool,
+    minimized: bool,
+    // the state_number of the last rendered frame
+    rendered_state: u64,
+    last_frame: Instant,
+    // the text input area last applied to the window, see ScreenOverlay::text_input
+    text_input: Option<[f32; 4]>,
+    benchmark: Option<Benchmark>,
+    frame_pacer: FramePacer,
+}
+
+// how often the game state updates while the window is in the background
+const BACKGROUND_UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+// updates that are late are caught up on, up to this many ticks behind. Further behind (a
+// long hitch, or the window was being dragged) the game skips ahead instead.
+const MAX_CATCH_UP_UPDATES: u32 = 5;
+// frame rate cap for when vsync is off
+const MIN_FRAME_INTERVAL: Duration = Duration::from_micros(1_000_000 / 240);
+
+impl App {
+    // in the foreground frames are drawn as fast as vsync (or the frame cap) allows, in
+    // between updates (see crate::interpolation). In the background only updates get drawn.
+    fn should_render(&self, now: Instant) -> bool {
+        let Some(game_state) = &self.game_state else {
+            return false;
+        };
+        if self.window.is_none() || self.minimized {
+            return false;
+        }
+        // as fast as it goes, in the foreground or not
+        if self.benchmark.is_some() {
+            return true;
+        }
+        if self.focused {
+            now - self.last_frame >= MIN_FRAME_INTERVAL
+        } else {
+            game_state.state_number > self.rendered_state
+        }
+    }
+
+    fn window(&self) -> Option<&Arc<Window>> {
+        self.window.as_ref()
+    }
+
+    fn is_minimized(&self) -> bool {
+        self.minimized
+            || self
+                .window
+                .as_ref()
+                .and_then(|window| window.is_minimized())
+                .unwrap_or(false)
+    }
+
+    fn update_interval(&self, game_state: &GameState) -> Option<Duration> {
+        update_interval(
+            game_state.tick_rate,
+            self.focused,
+            self.is_minimized(),
+            game_state.net_client().is_some(),
+        )
+    }
+}
+
+/// How often the engine updates the game state: at `tick_rate` in the foreground, a lot less
+/// while nobody is looking and not at all while the window is minimized. A multiplayer game
+/// keeps the tick rate regardless, the server drops clients that go quiet (see [`crate::net`]).
+pub fn update_interval(
+    tick_rate: TickRate,
+    focused: bool,
+    minimized: bool,
+    online: bool,
+) -> Option<Duration> {
+    if online || (focused && !minimized) {
+        Some(tick_rate.update_interval())
+    } else if minimized {
+        None
+    } else {
+        Some(BACKGROUND_UPDATE_INTERVAL)
+    }
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(gfx_state) = &mut self.gfx_state {
+            // resumed after a suspend, the window is still around but the surface is not
+            if let Err(e) = gfx_state.resume() {
+                log::error!("unable to recreate surface: {:?}", e);
+                event_loop.exit();
+            }
+            return;
+        }
+        if self.window.is_none() {
+            let window_attributes = self.window_settings.window_attributes();
+            let window = match event_loop.create_window(window_attributes) {
+                Ok(window) => Arc::new(window),
+                Err(e) => {
+                    log::error!(
+                        "{}",
+                        GameError::InitError(format!("window creation failed ({})", e))
+                    );
+                    event_loop.exit();
+                    return;
+                }
+            };
+            self.window_settings.apply_initial_fullscreen(&window);
+            self.window = Some(window.clone());
+            if let Some(game_state) = &mut self.game_state {
+                let size = window.inner_size();
+                game_state.set_window_scale_factor(window.scale_factor() as f32);
+                game_state.window_size = [size.width as f32, size.height as f32];
+            }
+            let gfx_settings = self
+                .game_state
+                .as_ref()
+                .map(|gs| gs.gfx_settings.clone())
+                .unwrap_or_default();
+            let mut gfx_state = match GfxState::new(window.clone(), gfx_settings) {
+                Ok(gfx_state) => gfx_state,
+                Err(e) => {
+                    log::error!(target: logging::GFX, "unable to initialize graphics: {:#}", e);
+                    event_loop.exit();
+                    return;
+                }
+            };
+            if let Some(game_state) = &self.game_state {
+                gfx_state.set_ui_scale(game_state.ui_scale());
+                if let Some(font) = game_state.ui_font() {
+                    gfx_state.prewarm_glyphs(&font);
+                }
+            }
+            self.gfx_state = Some(gfx_state);
+        }
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(gfx_state) = &mut self.gfx_state {
+            gfx_state.suspend();
+        }
+    }
+
+    fn new_events(&mut self, event_loop: &ActiveEventLoop, cause: winit::event::StartCause) {
+        if cause == winit::event::StartCause::Init {
+            return;
+        }
+        let update_interval = match &self.game_state {
+            Some(state) => self.update_interval(state),
+            None => return,
+        };
+        // minimized, the game waits for the window to be restored
+        let Some(update_interval) = update_interval else {
+            return;
+        };
+        let game_state = match &mut self.game_state {
+            Some(state) => state,
+            None => return,
+        };
+        let now = Instant::now();
+        // updates happen on the tick schedule rather than whenever the loop wakes up, so the
+        // simulation keeps its pace when a frame takes longer than a tick
+        let mut updates = 0;
+        while updates < MAX_CATCH_UP_UPDATES && game_state.should_update(&now, update_interval) {
+            updates += 1;
+            let scheduled = game_state.now + update_interval;
+            let update_time = if now.saturating_duration_since(scheduled)
+                < update_interval * MAX_CATCH_UP_UPDATES
+            {
+                scheduled
+            } else {
+                now
+            };
+            let update_start = Instant::now();
+            if let Err(e) = game_state.update(update_time) {
+                log::error!("game update failed: {:?}", e);
+                event_loop.exit();
+                return;
+            }
+            for system in self.systems.iter_mut() {
+                if let Err(e) = system.update(game_state) {
+                    log::error!("system update failed: {:?}", e);
+                    event_loop.exit();
+                    return;
+                }
+            }
+            self.frame_pacer.add(Stage::Update, update_start.elapsed());
+            if let Some(benchmark) = &self.benchmark {
+                benchmark.update(game_state);
+            }
+            if let Some(window) = &self.window
+                && game_state.window_settings != self.window_settings
+            {
+                let fullscreen_changed = game_state
+                    .window_settings
+                    .apply(window, &self.window_settings);
+                self.window_settings = game_state.window_settings.clone();
+                if fullscreen_changed && let Some(gfx_state) = &mut self.gfx_state {
+                    // don't wait for the resize event, the old swapchain no longer fits
+                    gfx_state.resize(Some(window.inner_size()));
+                }
+            }
+            if let Some(window) = &self.window
+                && game_state.overlay.text_input != self.text_input
+            {
+                let text_input = game_state.overlay.text_input;
+                // the input method is only on while there's a text field to type in
+                if text_input.is_some() != self.text_input.is_some() {
+                    window.set_ime_allowed(text_input.is_some());
+                    if text_input.is_none() {
+                        game_state.ime.disabled();
+                    }
+                }
+                if let Some([left, top, right, bottom]) = text_input {
+                    window.set_ime_cursor_area(
+                        PhysicalPosition::new(left, top),
+                        PhysicalSize::new(right - left, bottom - top),
+                    );
+                }
+                self.text_input = text_input;
+            }
+            if let Some(gfx_state) = &mut self.gfx_state {
+                gfx_state.set_settings(game_state.gfx_settings.clone());
+                if gfx_state.ui_scale() != game_state.ui_scale() {
+                    gfx_state.set_ui_scale(game_state.ui_scale());
+                    if let Some(font) = game_state.ui_font() {
+                        gfx_state.prewarm_glyphs(&font);
+                    }
+                }
+            }
+            let mut reloaded_fonts = game_state.font_cache.poll_changes();
+            match game_state.font_cache.poll_background_load() {
+                Some(FontLoadEvent::Progress { loaded, total }) => {
+                    log::trace!("loaded {} of {} system font files", loaded, total)
+                }
+                Some(FontLoadEvent::Finished { fonts, replaced }) => {
+                    log::info!(
+                        "loaded {} system fonts in the background, {} fonts in total",
+                        fonts,
+                        game_state.font_cache.fonts().count()
+                    );
+                    reloaded_fonts.extend(replaced);
+                }
+                None => {}
+            }
+            if !reloaded_fonts.is_empty()
+                && let Some(gfx_state) = &mut self.gfx_state
+            {
+                gfx_state.invalidate_fonts(&reloaded_fonts);
+                if let Some(font) = game_state.ui_font() {
+                    gfx_state.prewarm_glyphs(&font);
+                }
+            }
+            let panel = &game_state.memory_panel;
+            if panel.visible
+                && (panel.current.entries.is_empty()
+                    || game_state
+                        .state_number
+                        .is_multiple_of(game_state.tick_rate.hz() as u64))
+            {
+                let mut report = game_state.memory_report();
+                if let Some(gfx_state) = &self.gfx_state {
+                    gfx_state.add_memory_usage(&mut report);
+                }
+                game_state.memory_panel.update(report);
+            }
+        }
+        if updates == MAX_CATCH_UP_UPDATES && game_state.should_update(&now, update_interval) {
+            // still behind, skip ahead
+            game_state.now = now;
+        }
+        if self.should_render(now) {
+            self.window().unwrap().request_redraw();
+        }
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        let gfx_state = match &mut self.gfx_state {
+            Some(state) => state,
+            None => return,
+        };
+
+        let game_state = match &mut self.game_state {
+            Some(state) => state,
+            None => return,
+        };
+
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(physical_size) => {
+                // windows get resized to nothing when they're minimized
+                self.minimized = physical_size.width == 0 || physical_size.height == 0;
+                game_state.window_size = [physical_size.width as f32, physical_size.height as f32];
+                gfx_state.resize(Some(physical_size));
+                // remember the windowed size, so leaving fullscreen can restore it
+                if let Some(window) = &self.window
+                    && window.fullscreen().is_none()
+                {
+                    let size = physical_size.to_logical::<f64>(window.scale_factor());
+                    for settings in [&mut self.window_settings, &mut game_state.window_settings] {
+                        settings.width = size.width;
+                        settings.height = size.height;
+                    }
+                }
+            }
+            WindowEvent::Focused(focused) => {
+                self.focused = focused;
+                game_state.event_bus.publish(WindowFocusChanged { focused });
+            }
+            WindowEvent::Moved(position) => {
+                if let Some(window) = &self.window
+                    && window.fullscreen().is_none()
+                {
+                    for settings in [&mut self.window_settings, &mut game_state.window_settings] {
+                        settings.position = Some((position.x, position.y));
+                    }
+                }
+            }
+            WindowEvent::RedrawRequested => match gfx_state.render(game_state) {
+                Ok(_) => {
+                    game_state.frame_number += 1;
+                    self.rendered_state = game_state.state_number;
+                    self.last_frame = Instant::now();
+                    if self.focused && !self.minimized {
+                        self.frame_pacer.frame_finished(
+                            game_state.frame_number,
+                            self.last_frame,
+                            gfx_state.stage_times(),
+                        );
+                    } else {
+                        self.frame_pacer.pause();
+                    }
+                    if game_state.bug_report_requested {
+                        game_state.bug_report_requested = false;
+                        let screenshot = gfx_state.take_screenshot();
+                        match bug_report::write(game_state, screenshot.as_ref()) {
+                            Ok(path) => log::info!("wrote bug report to {}", path.display()),
+                            Err(error) => log::error!("failed to write bug report: {}", error),
+                        }
+                    }
+                    if let Some(benchmark) = &mut self.benchmark
+                        && benchmark.frame_drawn(self.last_frame)
+                    {
+                        let report = benchmark.report(gfx_state.glyph_atlas_stats());
+                        log::info!(
+                            "benchmark: {:.1} fps, {:.2} ms mean, {:.2} ms p99",
+                            report.fps,
+                            report.mean_ms,
+                            report.p99_ms
+                        );
+                        match benchmark.write(&report) {
+                            Ok(path) => log::info!("wrote benchmark report to {}", path.display()),
+                            Err(error) => {
+                                log::error!("failed to write benchmark report: {}", error)
+                            }
+                        }
+                        event_loop.exit();
+                        self.benchmark = None;
+                    }
+                }
+                Err(e) => match e.downcast_ref::<GameError>() {
+                    // Reconfigure the surface if lost or if it no longer matches the window
+                    Some(GameError::WgpuError(
+                        wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated,
+                    )) => gfx_state.resize(None),
+                    // The frame took too long to acquire, just try again next frame
+                    Some(GameError::WgpuError(wgpu::SurfaceError::Timeout)) => {
+                        log::warn!("timed out acquiring the next surface texture")
+                    }
+                    // Nothing to render to until we get resumed
+                    Some(GameError::SurfaceSuspended) => {}
+                    // Out of graphics memory probably means we should quit.
+                    Some(GameError::WgpuError(wgpu::SurfaceError::OutOfMemory)) => {
+                        event_loop.exit()
+                    }
+                    // Rebuild everything on a new device, the frame is lost
+                    Some(GameError::DeviceLost(reason)) => {
+                        log::error!(
+                            target: logging::GFX,
+                            "the graphics device was lost ({}), recreating it",
+                            reason
+                        );
+                        if let Err(e) = gfx_state.recover_from_device_loss() {
+                            log::error!(
+                                target: logging::GFX,
+                                "unable to recreate the graphics device: {:#}",
+                                e
+                            );
+                            event_loop.exit();
+                        }
+                    }
+                    // Something is missing from the frame, the rest was drawn
+                    Some(error @ GameError::DeviceError(_)) => {
+                        log::error!(target: logging::GFX, "{}", error)
+                    }
+                    // Some glyphs were left out, the rest of the frame was drawn
+                    Some(error @ (GameError::FontError(_) | GameError::AtlasError(_))) => {
+                        log::warn!(target: logging::GFX, "{}", error)
+                    }
+                    _ => log::error!(target: logging::GFX, "unable to render: {:?}", e),
+                },
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                game_state
+                    .mouse_state
+                    .cursor_moved([position.x as f32, position.y as f32]);
+            }
+            WindowEvent::CursorLeft { .. } => game_state.mouse_state.cursor_left(),
+            WindowEvent::Ime(Ime::Preedit(text, cursor)) => {
+                game_state.ime.set_preedit(text, cursor)
+            }
+            WindowEvent::Ime(Ime::Commit(text)) => game_state.ime.commit(&text),
+            WindowEvent::Ime(Ime::Disabled) => game_state.ime.disabled(),
+            WindowEvent::Ime(Ime::Enabled) => {}
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => game_state.mouse_state.left_button(state.is_pressed()),
+            WindowEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(_, lines) => {
+                    game_state.mouse_state.scrolled_lines(lines)
+                }
+                MouseScrollDelta::PixelDelta(position) => {
+                    game_state.mouse_state.scrolled_pixels(position.y as f32)
+                }
+            },
+            // moved to a monitor with another DPI, or its setting changed
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                game_state.set_window_scale_factor(scale_factor as f32);
+                gfx_state.set_scale_factor(scale_factor as f32);
+                if let Some(font) = game_state.ui_font() {
+                    gfx_state.prewarm_glyphs(&font);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let game_state = match &self.game_state {
+            Some(state) => state,
+            None => return,
+        };
+
+        if game_state.should_quit {
+            event_loop.exit();
+        }
+
+        // sleep until the next update (or frame) instead of spinning. A requested redraw wakes
+        // the loop up on its own, and a minimized window isn't drawn.
+        let control_flow = match self.update_interval(game_state) {
+            None => ControlFlow::Wait,
+            Some(_) if self.benchmark.is_some() && !self.is_minimized() => ControlFlow::Poll,
+            Some(interval) if self.focused && !self.is_minimized() => ControlFlow::WaitUntil(
+                (game_state.now + interval).min(self.last_frame + MIN_FRAME_INTERVAL),
+            ),
+            Some(interval) => ControlFlow::WaitUntil(game_state.now + interval),
+        };
+        event_loop.set_control_flow(control_flow);
+    }
+}
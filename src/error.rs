@@ -9,4 +9,26 @@ pub enum GameError {
     _Error(String),
     #[error("[ERROR] Wgpu Error: {0}")]
     WgpuError(#[from] wgpu::SurfaceError), //CrosstermError(crossterm::ErrorKind),
+    #[error("[ERROR] No surface to render to (the app is suspended)")]
+    SurfaceSuspended,
+    /// A glyph couldn't be hinted or outlined. The glyph is skipped, the rest of the text is
+    /// drawn.
+    #[error("[ERROR] Font error: {0}")]
+    FontError(String),
+    /// The glyph atlas has no room left for a glyph. It's skipped until glyphs are dropped
+    /// from the atlas.
+    #[error("[ERROR] Glyph atlas error: {0}")]
+    AtlasError(String),
+    /// The window or the graphics device couldn't be set up.
+    #[error("[ERROR] Unable to initialize: {0}")]
+    InitError(String),
+    /// The graphics device rejected something (a validation error), or ran out of memory.
+    /// What it was about to create or draw is missing, the rest keeps working.
+    #[error("[ERROR] Graphics device error: {0}")]
+    DeviceError(String),
+    /// The graphics device is gone (the driver crashed or was updated, the GPU was reset or
+    /// removed). Nothing can be drawn until the graphics are set up again on a new one, see
+    /// `GfxState::recover_from_device_loss`.
+    #[error("[ERROR] Graphics device lost: {0}")]
+    DeviceLost(String),
 }
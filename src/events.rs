@@ -0,0 +1,138 @@
+//! Typed events between subsystems, so input, gameplay, collision and the UI can react to each
+//! other without holding on to each other. Anything with the game state can
+//! [`EventBus::publish`] an event. Once per update [`GameState::update`] hands everything
+//! published since the last update to the subscribers, in the order it was published, and the
+//! events stay readable with [`EventBus::read`] until the next update.
+//!
+//! Events published while the subscribers run (or by the systems after the update) go out with
+//! the next update.
+
+use crate::game_state::GameState;
+use crate::gfx::text::LinkId;
+use crate::math::SimVec2;
+use crate::world::EntityId;
+
+/// The ship, an asteroid or a bullet was destroyed during the last tick.
+#[derive(Debug, Clone, Copy)]
+pub struct EntityDestroyed {
+    pub entity: EntityId,
+    pub position: SimVec2,
+}
+
+/// A button or a link in the text on screen was clicked (see [`crate::input::Links`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ButtonClicked {
+    pub id: LinkId,
+}
+
+/// The window went to the background or came back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowFocusChanged {
+    pub focused: bool,
+}
+
+/// Every kind of event, as they're queued.
+#[derive(Debug, Clone, Copy)]
+pub enum GameEvent {
+    EntityDestroyed(EntityDestroyed),
+    ButtonClicked(ButtonClicked),
+    WindowFocusChanged(WindowFocusChanged),
+}
+
+/// An event type that goes through the bus.
+pub trait Event: Sized + 'static {
+    fn into_event(self) -> GameEvent;
+    /// Self if `event` is one
+    fn from_event(event: &GameEvent) -> Option<&Self>;
+}
+
+impl Event for EntityDestroyed {
+    fn into_event(self) -> GameEvent {
+        GameEvent::EntityDestroyed(self)
+    }
+
+    fn from_event(event: &GameEvent) -> Option<&Self> {
+        match event {
+            GameEvent::EntityDestroyed(event) => Some(event),
+            _ => None,
+        }
+    }
+}
+
+impl Event for ButtonClicked {
+    fn into_event(self) -> GameEvent {
+        GameEvent::ButtonClicked(self)
+    }
+
+    fn from_event(event: &GameEvent) -> Option<&Self> {
+        match event {
+            GameEvent::ButtonClicked(event) => Some(event),
+            _ => None,
+        }
+    }
+}
+
+impl Event for WindowFocusChanged {
+    fn into_event(self) -> GameEvent {
+        GameEvent::WindowFocusChanged(self)
+    }
+
+    fn from_event(event: &GameEvent) -> Option<&Self> {
+        match event {
+            GameEvent::WindowFocusChanged(event) => Some(event),
+            _ => None,
+        }
+    }
+}
+
+type Subscriber = Box<dyn Fn(&mut GameState, &GameEvent) + Send>;
+
+#[derive(Default)]
+pub struct EventBus {
+    // published since the last update
+    pending: Vec<GameEvent>,
+    // delivered by the last update
+    delivered: Vec<GameEvent>,
+    subscribers: Vec<Subscriber>,
+}
+
+impl EventBus {
+    /// Queues `event` for the subscribers, see the module docs for when they get it.
+    pub fn publish<E: Event>(&mut self, event: E) {
+        self.pending.push(event.into_event());
+    }
+
+    /// Calls `handler` with every event of type `E` from the next update on.
+    pub fn subscribe<E: Event>(&mut self, handler: impl Fn(&mut GameState, &E) + Send + 'static) {
+        self.subscribers.push(Box::new(move |game_state, event| {
+            if let Some(event) = E::from_event(event) {
+                handler(game_state, event);
+            }
+        }));
+    }
+
+    /// The events of type `E` the last update delivered.
+    pub fn read<E: Event>(&self) -> impl Iterator<Item = &E> {
+        self.delivered.iter().filter_map(E::from_event)
+    }
+
+    /// Every event the last update delivered.
+    pub fn delivered(&self) -> &[GameEvent] {
+        &self.delivered
+    }
+
+    // hands the pending events to the subscribers. The bus is taken out of the game state
+    // for this, what the subscribers publish or subscribe goes to the empty bus left in its
+    // place and is moved over afterwards.
+    pub(crate) fn dispatch(&mut self, game_state: &mut GameState) {
+        self.delivered = std::mem::take(&mut self.pending);
+        for event in &self.delivered {
+            for subscriber in &self.subscribers {
+                subscriber(game_state, event);
+            }
+        }
+        let published = std::mem::take(&mut game_state.event_bus);
+        self.pending.extend(published.pending);
+        self.subscribers.extend(published.subscribers);
+    }
+}
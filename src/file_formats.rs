@@ -0,0 +1,325 @@
+//! Minimal writers for the file formats debug tools produce (PNG screenshots, GIF recordings,
+//! zip bundles). Nothing is compressed: the files are written rarely, and this keeps it
+//! dependency free. The PNGs can be read back too, for the text golden tests.
+
+// CRC-32 (IEEE), as used by both PNG and zip
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, byte| {
+        CRC32_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in bytes.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+// zlib stream made of uncompressed ("stored") deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / 65535 * 5 + 16);
+    out.extend_from_slice(&[0x78, 0x01]);
+    let mut chunks = data.chunks(65535).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let length = chunk.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&length.to_le_bytes());
+        out.extend_from_slice(&(!length).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Encodes 8 bit RGBA pixels (rows top to bottom, no padding) as a PNG file.
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    assert_eq!(rgba.len(), width as usize * height as usize * 4);
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, color type 6 (RGBA), default compression/filter, no interlacing
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    // every row starts with its filter type, 0 = none
+    let mut raw = Vec::with_capacity(rgba.len() + height as usize);
+    for row in rgba.chunks_exact(width as usize * 4) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut out = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    png_chunk(&mut out, b"IHDR", &header);
+    png_chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+    png_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// Decodes the PNG files [`encode_png`] writes: 8 bit RGBA, stored without compression or
+/// filters. Returns the width, height and pixels, None for anything else (e.g. a file that
+/// was run through an optimizer).
+pub fn decode_png(bytes: &[u8]) -> Option<(u32, u32, Vec<u8>)> {
+    let mut rest = bytes.strip_prefix(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'])?;
+    let mut header = None;
+    let mut zlib = Vec::new();
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes(rest[..4].try_into().ok()?) as usize;
+        let kind = &rest[4..8];
+        let data = rest.get(8..8 + length)?;
+        rest = rest.get(12 + length..)?;
+        match kind {
+            b"IHDR" => header = Some(data),
+            b"IDAT" => zlib.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+    }
+    let header = header?;
+    let width = u32::from_be_bytes(header.get(..4)?.try_into().ok()?);
+    let height = u32::from_be_bytes(header.get(4..8)?.try_into().ok()?);
+    if header.get(8..13)? != [8, 6, 0, 0, 0] {
+        return None;
+    }
+
+    // the stored deflate blocks after the two byte zlib header
+    let mut raw = Vec::new();
+    let mut blocks = zlib.get(2..)?;
+    loop {
+        let last = *blocks.first()? & 1 != 0;
+        if *blocks.first()? & 0b110 != 0 {
+            // compressed
+            return None;
+        }
+        let length = u16::from_le_bytes(blocks.get(1..3)?.try_into().ok()?) as usize;
+        raw.extend_from_slice(blocks.get(5..5 + length)?);
+        blocks = blocks.get(5 + length..)?;
+        if last {
+            break;
+        }
+    }
+
+    let row_size = width as usize * 4;
+    if raw.len() != (row_size + 1) * height as usize {
+        return None;
+    }
+    let mut rgba = Vec::with_capacity(row_size * height as usize);
+    for row in raw.chunks_exact(row_size + 1) {
+        if row[0] != 0 {
+            return None;
+        }
+        rgba.extend_from_slice(&row[1..]);
+    }
+    Some((width, height, rgba))
+}
+
+// the GIF palette: 8 levels of red and green, 4 of blue (3-3-2 bits)
+fn gif_palette() -> Vec<u8> {
+    (0..=255u8)
+        .flat_map(|index| {
+            let r = (index >> 5) as u32 * 255 / 7;
+            let g = ((index >> 2) & 7) as u32 * 255 / 7;
+            let b = (index & 3) as u32 * 255 / 3;
+            [r as u8, g as u8, b as u8]
+        })
+        .collect()
+}
+
+// the palette index of the closest color
+fn gif_palette_index(r: u8, g: u8, b: u8) -> u8 {
+    let level = |value: u8, max: u32| ((value as u32 * max + 127) / 255) as u8;
+    (level(r, 7) << 5) | (level(g, 7) << 2) | level(b, 3)
+}
+
+// LZW data for 8 bit indexes without compressing: a clear code before the table would grow
+// to 10 bit codes, so every code is 9 bits and stands for one pixel
+fn gif_lzw_stored(indexes: impl Iterator<Item = u8>) -> Vec<u8> {
+    const CLEAR: u32 = 256;
+    const END: u32 = 257;
+    // the decoder adds a table entry per code after the first, 254 keeps it under 512
+    const CODES_PER_CLEAR: usize = 254;
+    let mut bytes = Vec::new();
+    let (mut bits, mut bit_count) = (0u32, 0);
+    let mut push = |code: u32| {
+        bits |= code << bit_count;
+        bit_count += 9;
+        while bit_count >= 8 {
+            bytes.push(bits as u8);
+            bits >>= 8;
+            bit_count -= 8;
+        }
+    };
+    for (i, index) in indexes.enumerate() {
+        if i % CODES_PER_CLEAR == 0 {
+            push(CLEAR);
+        }
+        push(index as u32);
+    }
+    push(END);
+    if bit_count > 0 {
+        bytes.push(bits as u8);
+    }
+    bytes
+}
+
+/// Encodes frames of 8 bit RGBA pixels (rows top to bottom, no padding, alpha ignored) as an
+/// animated GIF that loops. Each frame comes with how long it's shown, in hundredths of a
+/// second. The colors are mapped to a fixed palette of 256, without dithering.
+pub fn encode_gif<'a>(
+    width: u32,
+    height: u32,
+    frames: impl IntoIterator<Item = (&'a [u8], u16)>,
+) -> Vec<u8> {
+    let mut out = b"GIF89a".to_vec();
+    out.extend_from_slice(&(width as u16).to_le_bytes());
+    out.extend_from_slice(&(height as u16).to_le_bytes());
+    // a global palette of 256 colors (2^(7 + 1)), 8 bits per channel
+    out.extend_from_slice(&[0xf7, 0, 0]);
+    out.extend_from_slice(&gif_palette());
+    // NETSCAPE2.0 extension: loop forever
+    out.extend_from_slice(&[0x21, 0xff, 0x0b]);
+    out.extend_from_slice(b"NETSCAPE2.0");
+    out.extend_from_slice(&[3, 1, 0, 0, 0]);
+
+    for (rgba, delay) in frames {
+        assert_eq!(rgba.len(), width as usize * height as usize * 4);
+        // graphic control extension: no transparency, the delay
+        out.extend_from_slice(&[0x21, 0xf9, 4, 0]);
+        out.extend_from_slice(&delay.to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+        // image descriptor: the whole screen, no local palette, not interlaced
+        out.push(0x2c);
+        out.extend_from_slice(&[0, 0, 0, 0]);
+        out.extend_from_slice(&(width as u16).to_le_bytes());
+        out.extend_from_slice(&(height as u16).to_le_bytes());
+        out.push(0);
+        // the LZW minimum code size, then the data in blocks of up to 255 bytes
+        out.push(8);
+        let indexes = rgba
+            .chunks_exact(4)
+            .map(|pixel| gif_palette_index(pixel[0], pixel[1], pixel[2]));
+        for block in gif_lzw_stored(indexes).chunks(255) {
+            out.push(block.len() as u8);
+            out.extend_from_slice(block);
+        }
+        out.push(0);
+    }
+    out.push(0x3b);
+    out
+}
+
+struct ZipEntry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Builds a zip archive in memory. Entries are stored without compression.
+#[derive(Default)]
+pub struct ZipWriter {
+    bytes: Vec<u8>,
+    entries: Vec<ZipEntry>,
+}
+
+impl ZipWriter {
+    pub fn add_file(&mut self, name: &str, data: &[u8]) {
+        let entry = ZipEntry {
+            name: name.to_string(),
+            crc: crc32(data),
+            size: data.len() as u32,
+            offset: self.bytes.len() as u32,
+        };
+        let out = &mut self.bytes;
+        out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        // version needed (2.0), flags (bit 11: utf-8 names), method (stored), time, date
+        for value in [20u16, 1 << 11, 0, 0, 0x21] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [entry.crc, entry.size, entry.size] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(entry.name.as_bytes());
+        out.extend_from_slice(data);
+        self.entries.push(entry);
+    }
+
+    /// Appends the central directory and returns the finished archive.
+    pub fn finish(mut self) -> Vec<u8> {
+        let directory_offset = self.bytes.len() as u32;
+        let out = &mut self.bytes;
+        for entry in self.entries.iter() {
+            out.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            // version made by, version needed, flags, method, time, date
+            for value in [20u16, 20, 1 << 11, 0, 0, 0x21] {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            for value in [entry.crc, entry.size, entry.size] {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            // name length, extra length, comment length, disk number, internal attributes
+            for value in [entry.name.len() as u16, 0, 0, 0, 0] {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            // external attributes, offset of the local header
+            out.extend_from_slice(&0u32.to_le_bytes());
+            out.extend_from_slice(&entry.offset.to_le_bytes());
+            out.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = self.bytes.len() as u32 - directory_offset;
+
+        let out = &mut self.bytes;
+        out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        let count = self.entries.len() as u16;
+        // disk numbers, entries on this disk, total entries
+        for value in [0u16, 0, count, count] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.extend_from_slice(&directory_size.to_le_bytes());
+        out.extend_from_slice(&directory_offset.to_le_bytes());
+        // comment length
+        out.extend_from_slice(&0u16.to_le_bytes());
+        self.bytes
+    }
+}
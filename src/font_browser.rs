@@ -0,0 +1,270 @@
+//! A screen for looking through the loaded fonts, to try out the font cache's search and see
+//! how faces render (see [`crate::screen::Screen::FontBrowser`]). It lists the faces that
+//! match what's typed, a page at a time, each with a preview in the face itself. The selected
+//! face gets a slider for each axis if it's a variable font.
+//!
+//! Typing (or the input method) searches (see [`FontCache::search_fonts`]), backspace
+//! deletes. Up and down (or a click) select a face, page up and page down (or left and right)
+//! turn the pages.
+
+use device_query::Keycode;
+use harfrust::Variation;
+
+use crate::game_state::GameState;
+use crate::gfx::text::{FontCache, FontRef, LinkId, TextDraw, TextOverflow};
+use crate::screen::{ScreenOverlay, text_field, text_input_area};
+use crate::t;
+use crate::ui::{Anchor, Ui, Widget};
+
+const PAGE_SIZE: usize = 6;
+const NAME_SIZE: f32 = 18.0;
+const PREVIEW_SIZE: f32 = 28.0;
+const HINT_SIZE: f32 = 16.0;
+const PREVIEW: &str = "Sphinx of black quartz, judge my vow 0123456789";
+// in logical pixels, how wide the paths (cut in the middle) and the previews (cut at the end)
+// can get
+const MAX_PATH_WIDTH: f32 = 640.0;
+// the face buttons of the page, and the sliders of the selected face's axes
+const FACE_LINKS: u32 = 100;
+const AXIS_LINKS: u32 = 200;
+
+#[derive(Debug, Default)]
+pub struct FontBrowser {
+    search: String,
+    page: usize,
+    /// Font cache index of the selected face
+    selected: Option<usize>,
+    /// The selected face's axis values, in the order of its axes
+    axis_values: Vec<f32>,
+    /// The axis whose slider is being dragged
+    dragging: Option<usize>,
+}
+
+impl FontBrowser {
+    /// The faces that match the search, all of them without one.
+    fn faces<'a>(&self, font_cache: &'a FontCache) -> Vec<FontRef<'a>> {
+        let search = self.search.trim();
+        if search.is_empty() {
+            let mut faces: Vec<FontRef<'a>> = font_cache.fonts().collect();
+            faces.sort();
+            faces
+        } else {
+            font_cache.search_fonts(search)
+        }
+    }
+
+    fn select(&mut self, face: &FontRef<'_>) {
+        self.selected = Some(face.cache_index());
+        self.axis_values = face
+            .variation_axes()
+            .iter()
+            .map(|axis| axis.default_value())
+            .collect();
+        self.dragging = None;
+    }
+
+    pub(crate) fn update(&mut self, game_state: &GameState) {
+        let keyboard = game_state.keyboard_state();
+        let mouse = game_state.mouse_state();
+        let links = game_state.links();
+        // the keys go to the input method while it composes
+        let ime = game_state.ime();
+        // held down, the keys repeat
+        let pressed = |key_code| !ime.is_composing() && keyboard.is_repeat_pressed(key_code);
+
+        let typed = if ime.is_composing() {
+            ime.committed().chars().collect()
+        } else {
+            keyboard.pressed_characters()
+        };
+        let backspace = pressed(Keycode::Backspace);
+        if !typed.is_empty() || backspace {
+            if backspace {
+                let end = game_state.previous_caret(&self.search, self.search.len());
+                self.search.truncate(end);
+            }
+            self.search.extend(typed);
+            self.page = 0;
+        }
+
+        let faces = self.faces(&game_state.font_cache);
+        let pages = faces.len().div_ceil(PAGE_SIZE).max(1);
+        if pressed(Keycode::PageDown) || pressed(Keycode::Right) {
+            self.page += 1;
+        }
+        if pressed(Keycode::PageUp) || pressed(Keycode::Left) {
+            self.page = self.page.saturating_sub(1);
+        }
+        self.page = self.page.min(pages - 1);
+
+        let position = faces
+            .iter()
+            .position(|face| Some(face.cache_index()) == self.selected);
+        let moved = if pressed(Keycode::Down) {
+            Some(position.map_or(0, |i| i + 1))
+        } else if pressed(Keycode::Up) {
+            Some(position.map_or(0, |i| i.saturating_sub(1)))
+        } else {
+            None
+        };
+        if let Some(face) = moved.and_then(|i| faces.get(i)) {
+            self.select(face);
+            // the page follows the selection
+            self.page = faces.iter().position(|f| f == face).unwrap_or_default() / PAGE_SIZE;
+        }
+        let clicked = (0..PAGE_SIZE)
+            .find(|i| links.clicked(LinkId(FACE_LINKS + *i as u32)))
+            .and_then(|i| faces.get(self.page * PAGE_SIZE + i));
+        if let Some(face) = clicked {
+            self.select(face);
+        }
+
+        // dragging an axis slider, it's where the last render put it
+        if !mouse.is_left_down() {
+            self.dragging = None;
+        } else if mouse.is_left_pressed() {
+            self.dragging = links
+                .hovered()
+                .and_then(|id| id.0.checked_sub(AXIS_LINKS))
+                .map(|axis| axis as usize)
+                .filter(|axis| *axis < self.axis_values.len());
+        }
+        let face = self
+            .selected
+            .and_then(|index| game_state.font_cache.get_font(index));
+        if let (Some(axis_index), Some(face), Some([x, _])) =
+            (self.dragging, face, mouse.position())
+            && let Some(axis) = face.variation_axes().get(axis_index)
+            && let Some(region) = game_state
+                .overlay
+                .regions
+                .iter()
+                .find(|region| region.id == LinkId(AXIS_LINKS + axis_index as u32))
+        {
+            let [left, _, right, _] = region.rect;
+            let t = ((x - left) / (right - left).max(1.0)).clamp(0.0, 1.0);
+            self.axis_values[axis_index] =
+                axis.min_value() + t * (axis.max_value() - axis.min_value());
+        }
+    }
+
+    pub(crate) fn render(&self, game_state: &GameState) -> ScreenOverlay {
+        let faces = self.faces(&game_state.font_cache);
+        let pages = faces.len().div_ceil(PAGE_SIZE).max(1);
+        let hovered = game_state.links().hovered();
+        let scale = game_state.scale_factor;
+        let palette = game_state.palette();
+
+        let (search, search_caret) = text_field(
+            &t!("search_prompt"),
+            &self.search,
+            game_state.ime(),
+            NAME_SIZE,
+        );
+        let search = search.color(palette.highlight);
+        let mut column = vec![
+            Widget::label(t!("fonts"), PREVIEW_SIZE),
+            Widget::Label(search.clone()),
+            Widget::Label(
+                TextDraw::new(
+                    t!(
+                        "font_pages",
+                        faces = faces.len(),
+                        page = self.page + 1,
+                        pages = pages
+                    ),
+                    [0.0, 0.0],
+                    HINT_SIZE,
+                )
+                .color(palette.hint),
+            ),
+        ];
+        for (i, face) in faces
+            .iter()
+            .skip(self.page * PAGE_SIZE)
+            .take(PAGE_SIZE)
+            .enumerate()
+        {
+            let id = LinkId(FACE_LINKS + i as u32);
+            let selected = self.selected == Some(face.cache_index());
+            let color = if selected || hovered == Some(id) {
+                palette.highlight
+            } else {
+                palette.text
+            };
+            column.push(Widget::Button {
+                text: TextDraw::new(face.to_string(), [0.0, 0.0], NAME_SIZE).color(color),
+                id,
+            });
+            if let Some(path) = game_state.font_cache.font_path(face.cache_index()) {
+                column.push(Widget::Label(
+                    TextDraw::new(path.display().to_string(), [0.0, 0.0], HINT_SIZE)
+                        .color(palette.hint)
+                        .truncate(MAX_PATH_WIDTH * scale, TextOverflow::MiddleEllipsis),
+                ));
+            }
+            let mut preview = TextDraw::new(PREVIEW, [0.0, 0.0], PREVIEW_SIZE)
+                .font(face.cache_index())
+                .truncate(MAX_PATH_WIDTH * scale, TextOverflow::Ellipsis);
+            if selected {
+                preview =
+                    preview.variations(face.variation_axes().iter().zip(&self.axis_values).map(
+                        |(axis, value)| Variation {
+                            tag: axis.tag(),
+                            value: *value,
+                        },
+                    ));
+            }
+            column.push(Widget::Label(preview));
+        }
+
+        // the selected face's axes
+        let face = self
+            .selected
+            .and_then(|index| game_state.font_cache.get_font(index));
+        let mut axes = Vec::new();
+        if let Some(face) = &face {
+            for (i, (axis, value)) in face
+                .variation_axes()
+                .iter()
+                .zip(&self.axis_values)
+                .enumerate()
+            {
+                let range = axis.max_value() - axis.min_value();
+                let t = if range > 0.0 {
+                    (value - axis.min_value()) / range
+                } else {
+                    0.0
+                };
+                axes.push(Widget::row(vec![
+                    Widget::label(format!("{} {:.0}", axis.tag(), value), NAME_SIZE),
+                    Widget::slider(t, LinkId(AXIS_LINKS + i as u32)),
+                ]));
+            }
+        }
+
+        let mut ui = Ui::new(game_state);
+        ui.add(Anchor::TopLeft, Widget::panel(Widget::column(column)));
+        if !axes.is_empty() {
+            ui.add(Anchor::TopRight, Widget::panel(Widget::column(axes)));
+        }
+        ui.add(
+            Anchor::BottomLeft,
+            Widget::Label(
+                TextDraw::new(t!("font_browser_hints"), [0.0, 0.0], HINT_SIZE).color(palette.hint),
+            ),
+        );
+        let overlay = ui.finish();
+        // the search field, where the ui placed it
+        let text_input = overlay
+            .text
+            .iter()
+            .find(|draw| draw.text == search.text)
+            .and_then(|draw| text_input_area(game_state, draw, search_caret));
+        ScreenOverlay {
+            tint: Some([0.0, 0.0, 0.0, 0.75]),
+            text_input,
+            ..overlay
+        }
+    }
+}
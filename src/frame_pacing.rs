@@ -0,0 +1,212 @@
+//! Where the time of a frame goes, to chase hitches. The [`FramePacer`] keeps the CPU time
+//! of every [`Stage`] for the last few seconds of frames, and when a frame takes a lot longer
+//! than the ones before it (a spike), it logs that frame's breakdown, e.g.
+//!
+//! ```text
+//! frame 1234 took 48.1 ms (median 16.7 ms): update 0.4, shape 31.2, rasterize 9.8, ...
+//! ```
+//!
+//! The spikes are logged with the `frame_pacing` target, `RUST_LOG=frame_pacing=off` turns
+//! them off. Frames are only measured in the foreground, in the background they're drawn
+//! with every update and would all look late.
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+
+use crate::logging;
+
+// frames kept, a few seconds' worth
+const HISTORY: usize = 240;
+// frames needed before the median means anything
+const MIN_FRAMES: usize = 30;
+// a spike takes this many times the median frame
+const SPIKE_FACTOR: u32 = 2;
+// ...and at least this long, so a fast frame that's twice as fast as usual isn't a spike
+const MIN_SPIKE: Duration = Duration::from_millis(8);
+
+/// The parts of a frame that are timed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// The game updates since the last frame, and the systems'
+    Update,
+    /// Laying out the text, and its glyphs' vertices
+    Shape,
+    /// Rasterizing the glyphs that weren't in the atlas yet
+    Rasterize,
+    /// Writing the text's vertices and the changed parts of the atlas
+    Upload,
+    /// Recording the passes, without the text's work above
+    Encode,
+    /// Getting the surface texture, submitting and presenting. Waiting for vsync is here.
+    Present,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [
+        Stage::Update,
+        Stage::Shape,
+        Stage::Rasterize,
+        Stage::Upload,
+        Stage::Encode,
+        Stage::Present,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Update => "update",
+            Stage::Shape => "shape",
+            Stage::Rasterize => "rasterize",
+            Stage::Upload => "upload",
+            Stage::Encode => "encode",
+            Stage::Present => "present",
+        }
+    }
+}
+
+/// How long each [`Stage`] of a frame took.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StageTimes([Duration; Stage::ALL.len()]);
+
+impl StageTimes {
+    pub fn get(&self, stage: Stage) -> Duration {
+        self.0[stage as usize]
+    }
+
+    /// Adds `duration` to the stage's time.
+    pub fn add(&mut self, stage: Stage, duration: Duration) {
+        self.0[stage as usize] += duration;
+    }
+
+    /// Adds the time since `start` to the stage's time, and returns now.
+    pub fn add_since(&mut self, stage: Stage, start: Instant) -> Instant {
+        let now = Instant::now();
+        self.add(stage, now - start);
+        now
+    }
+
+    /// Adds every stage's time of `other`.
+    pub fn merge(&mut self, other: &StageTimes) {
+        for stage in Stage::ALL {
+            self.add(stage, other.get(stage));
+        }
+    }
+
+    /// All the stages together.
+    pub fn total(&self) -> Duration {
+        self.0.iter().sum()
+    }
+}
+
+/// A measured frame.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameTiming {
+    pub frame: u64,
+    /// From the end of the frame before to the end of this one
+    pub interval: Duration,
+    pub stages: StageTimes,
+}
+
+/// See [`crate::frame_pacing`].
+#[derive(Debug, Default)]
+pub struct FramePacer {
+    frames: VecDeque<FrameTiming>,
+    // the stages timed outside of the renderer since the last frame, i.e. the updates
+    pending: StageTimes,
+    last_frame: Option<Instant>,
+    spikes: u64,
+}
+
+impl FramePacer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds to the stage's time of the next frame.
+    pub fn add(&mut self, stage: Stage, duration: Duration) {
+        self.pending.add(stage, duration);
+    }
+
+    /// Ends the frame `frame`, drawn with the renderer's `stages`, at `now`. Returns its
+    /// timing if it was a spike (it's logged already).
+    pub fn frame_finished(
+        &mut self,
+        frame: u64,
+        now: Instant,
+        stages: &StageTimes,
+    ) -> Option<FrameTiming> {
+        let mut timing = FrameTiming {
+            frame,
+            interval: Duration::ZERO,
+            stages: std::mem::take(&mut self.pending),
+        };
+        timing.stages.merge(stages);
+        let last_frame = self.last_frame.replace(now)?;
+        timing.interval = now - last_frame;
+
+        let spike = self
+            .median_interval()
+            .filter(|median| timing.interval > (*median * SPIKE_FACTOR).max(MIN_SPIKE))
+            .map(|median| {
+                self.spikes += 1;
+                log::info!(target: logging::FRAME_PACING, "{}", describe(&timing, median));
+                timing
+            });
+
+        if self.frames.len() == HISTORY {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(timing);
+        spike
+    }
+
+    /// Forgets when the last frame was, so the time until the next one (e.g. after the
+    /// window was in the background) isn't counted.
+    pub fn pause(&mut self) {
+        self.last_frame = None;
+        self.pending = StageTimes::default();
+    }
+
+    /// The last few seconds of frames, oldest first.
+    pub fn frames(&self) -> impl Iterator<Item = &FrameTiming> {
+        self.frames.iter()
+    }
+
+    /// The median time between the recent frames, once there are enough of them.
+    pub fn median_interval(&self) -> Option<Duration> {
+        if self.frames.len() < MIN_FRAMES {
+            return None;
+        }
+        let mut intervals: Vec<Duration> = self.frames.iter().map(|f| f.interval).collect();
+        let middle = intervals.len() / 2;
+        Some(*intervals.select_nth_unstable(middle).1)
+    }
+
+    /// The spikes so far.
+    pub fn spikes(&self) -> u64 {
+        self.spikes
+    }
+}
+
+// the breakdown of a spike, see the module docs
+fn describe(timing: &FrameTiming, median: Duration) -> String {
+    let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let mut text = format!(
+        "frame {} took {:.1} ms (median {:.1} ms):",
+        timing.frame,
+        ms(timing.interval),
+        ms(median)
+    );
+    for stage in Stage::ALL {
+        let _ = write!(
+            text,
+            " {} {:.1},",
+            stage.name(),
+            ms(timing.stages.get(stage))
+        );
+    }
+    // e.g. the event loop, or time the process didn't get to run
+    let other = timing.interval.saturating_sub(timing.stages.total());
+    let _ = write!(text, " other {:.1}", ms(other));
+    text
+}
@@ -0,0 +1,904 @@
+use anyhow::Result;
+
+use device_query::Keycode;
+
+use std::{
+    borrow::Cow,
+    f32::consts::PI,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::assets::Assets;
+use crate::camera::{self, Camera};
+use crate::config::{Settings, SettingsFile};
+use crate::events::{ButtonClicked, EventBus, WindowFocusChanged};
+use crate::font_browser::FontBrowser;
+use crate::gfx::lines::Polyline;
+use crate::gfx::particles::{EmitterId, EmitterParams, ParticleSystem};
+use crate::gfx::post::PostEffect;
+use crate::gfx::text::{FontCache, FontRef, LinkId, LinkRegion, TextAlign, TextDraw};
+use crate::gfx::{GfxSettings, RecordingFormat};
+use crate::heat_map::HeatMap;
+use crate::high_scores::{HighScore, HighScores};
+use crate::input::{ImeState, InputMap, KeyboardState, LinkEvent, Links, MouseState};
+use crate::interpolation;
+use crate::log_panel::LogPanel;
+use crate::math::{Real, Scalar, SimVec2};
+use crate::memory::{HeapSize, MemoryPanel, MemoryReport, vec_size};
+use crate::net::Client;
+use crate::palette::{ColorVision, Palette};
+use crate::radar::RadarSettings;
+use crate::replay::Replay;
+use crate::rng;
+use crate::save::{SaveGame, SaveSlot};
+use crate::screen::{Screen, ScreenOverlay};
+use crate::ship::Ship;
+use crate::tick_rate::TickRate;
+use crate::time_scale::{self, TimeScale};
+use crate::time_travel::TimeTravel;
+use crate::ui;
+use crate::window::WindowSettings;
+use crate::world::{EntityId, World, WorldEvent, WorldEventKind};
+
+// how far back the time-travel debug buffer goes (debug builds only), 10 s at 60 Hz
+const TIME_TRAVEL_TICKS: usize = 10 * 60;
+// the frame rate is counted over half a second, and drawn in logical pixels
+const FPS_INTERVAL: Duration = Duration::from_millis(500);
+const FPS_TEXT_SIZE: f32 = 16.0;
+const FPS_MARGIN: f32 = 8.0;
+
+/// Everything the engine updates once per tick and the renderers draw from.
+#[allow(dead_code)]
+pub struct GameState {
+    pub(crate) start_time: Instant,
+    pub(crate) now: Instant,
+    pub(crate) delta_time: Duration,
+    /// How long the game has run, without the time it was paused
+    pub(crate) run_time: Duration,
+    pub(crate) state_number: u64,
+    pub(crate) frame_number: u64,
+    /// How often the engine updates the game state, one simulation tick per update
+    pub(crate) tick_rate: TickRate,
+    pub(crate) keyboard_state: KeyboardState,
+    pub(crate) input_map: InputMap,
+    pub(crate) mouse_state: MouseState,
+    pub(crate) ime: ImeState,
+    pub(crate) links: Links,
+    /// The window's scale factor times the UI scale, which text and the UI are drawn at
+    pub(crate) scale_factor: f32,
+    pub(crate) window_scale_factor: f32,
+    ui_scale: f32,
+    /// The window's size in physical pixels
+    pub(crate) window_size: [f32; 2],
+    pub(crate) screen: Screen,
+    /// The menu button chosen with the arrow keys (or last hovered), see
+    /// `screen::menu_choice`
+    pub(crate) menu_focus: Option<LinkId>,
+    pub(crate) overlay: ScreenOverlay,
+    pub(crate) world: World,
+    /// The master seed of every new game, a random one for each if None (see crate::rng)
+    pub(crate) fixed_seed: Option<u64>,
+    /// The world before the tick simulated by the last update, to draw between the two (None
+    /// if the last update didn't simulate a tick)
+    pub(crate) previous_world: Option<World>,
+    pub(crate) time_travel: TimeTravel<World>,
+    /// The multiplayer game on Screen::Multiplayer, see crate::net
+    pub(crate) net_client: Option<Client>,
+    /// The chat message typed so far on Screen::Multiplayer, while the chat is open
+    pub(crate) chat_entry: Option<String>,
+    /// Chat lines scrolled back from the newest
+    pub(crate) chat_scroll: usize,
+    /// Inputs of the current game, for bug reports
+    pub(crate) replay: Replay,
+    pub(crate) bug_report_requested: bool,
+    /// Gameplay is recorded while this is set, see crate::gfx::recording
+    pub(crate) recording: Option<RecordingFormat>,
+    /// The events of the last simulated tick
+    pub(crate) events: Vec<WorldEvent>,
+    pub(crate) event_bus: EventBus,
+    /// Screen shake and the hit flash, see [`crate::camera`]
+    pub(crate) camera: Camera,
+    /// Slow motion and the like, see [`crate::time_scale`]
+    pub(crate) time_scale: TimeScale,
+    pub(crate) particles: ParticleSystem,
+    thruster: EmitterId,
+    pub(crate) heat_map: HeatMap,
+    pub(crate) show_heat_map: bool,
+    /// The radar on the playing screens, see [`crate::radar`]
+    pub(crate) radar: RadarSettings,
+    color_vision: ColorVision,
+    palette: Palette,
+    /// The frame rate in the top right corner, toggled with F4
+    pub(crate) show_fps: bool,
+    fps: FpsCounter,
+    pub(crate) memory_panel: MemoryPanel,
+    pub(crate) log_panel: LogPanel,
+    pub(crate) font_cache: FontCache,
+    pub(crate) font_browser: FontBrowser,
+    /// Hot reloaded in debug builds
+    pub(crate) assets: Assets,
+    /// Font cache index of the font text is drawn with (None = the first font)
+    pub(crate) ui_font: Option<usize>,
+    pub(crate) high_scores: HighScores,
+    pub(crate) high_scores_path: Option<PathBuf>,
+    /// The name typed so far while the game over screen asks for one
+    pub(crate) name_entry: Option<String>,
+    /// Rank of the score added on the current game over screen
+    pub(crate) new_high_score: Option<usize>,
+    pub(crate) text: Option<String>,
+    /// Drawn by the line renderer until the next update, see [`GameState::draw_polyline`]
+    pub(crate) polylines: Vec<Polyline>,
+    /// Drawn by the text renderer until the next update, see [`GameState::queue_text`]
+    pub(crate) queued_text: Vec<TextDraw>,
+    pub(crate) gfx_settings: GfxSettings,
+    pub(crate) window_settings: WindowSettings,
+    /// The last settings applied from the settings file (or the builder)
+    pub(crate) settings: Settings,
+    pub(crate) settings_file: Option<SettingsFile>,
+    pub(crate) should_quit: bool,
+}
+
+impl GameState {
+    pub(crate) fn new(
+        mut keyboard_state: KeyboardState,
+        font_cache: FontCache,
+        assets: Assets,
+    ) -> Self {
+        let now = Instant::now();
+        keyboard_state.set_update_rate(TickRate::default().hz());
+        let world = World::new(rng::random_seed());
+        let mut particles = ParticleSystem::default();
+        particles.reseed(world.seed);
+        let thruster = particles.add_emitter(EmitterParams::thruster());
+        let mut event_bus = EventBus::default();
+        event_bus.subscribe(pause_on_focus_loss);
+        event_bus.subscribe(camera::on_entity_destroyed);
+        event_bus.subscribe(time_scale::on_entity_destroyed);
+        Self {
+            start_time: now,
+            now,
+            delta_time: Duration::from_millis(0),
+            run_time: Duration::from_millis(0),
+            frame_number: 0,
+            state_number: 1,
+            tick_rate: TickRate::default(),
+            keyboard_state,
+            input_map: InputMap::default(),
+            mouse_state: MouseState::default(),
+            ime: ImeState::default(),
+            links: Links::default(),
+            scale_factor: 1.0,
+            window_scale_factor: 1.0,
+            ui_scale: 1.0,
+            window_size: [0.0, 0.0],
+            screen: Screen::MainMenu,
+            menu_focus: None,
+            overlay: ScreenOverlay::default(),
+            replay: Replay::new(world.seed, TickRate::default()),
+            bug_report_requested: false,
+            recording: None,
+            world,
+            fixed_seed: None,
+            previous_world: None,
+            time_travel: TimeTravel::new(if cfg!(debug_assertions) {
+                TIME_TRAVEL_TICKS
+            } else {
+                0
+            }),
+            net_client: None,
+            chat_entry: None,
+            chat_scroll: 0,
+            events: Vec::new(),
+            event_bus,
+            camera: Camera::default(),
+            time_scale: TimeScale::default(),
+            particles,
+            thruster,
+            heat_map: HeatMap::default(),
+            show_heat_map: false,
+            radar: RadarSettings::default(),
+            color_vision: ColorVision::Normal,
+            palette: Palette::default(),
+            show_fps: false,
+            fps: FpsCounter::new(now),
+            memory_panel: MemoryPanel::default(),
+            log_panel: LogPanel::default(),
+            font_cache,
+            font_browser: FontBrowser::default(),
+            assets,
+            ui_font: None,
+            high_scores: HighScores::default(),
+            high_scores_path: None,
+            name_entry: None,
+            new_high_score: None,
+            text: None,
+            polylines: Vec::new(),
+            queued_text: Vec::new(),
+            gfx_settings: GfxSettings::default(),
+            window_settings: WindowSettings::default(),
+            settings: Settings::default(),
+            settings_file: None,
+            should_quit: false,
+        }
+    }
+
+    pub fn run_time(&self) -> Duration {
+        self.run_time
+    }
+
+    pub fn delta_time(&self) -> Duration {
+        self.delta_time
+    }
+
+    /// The delta time scaled by the time scale, for gameplay (the UI runs in real time).
+    /// Stops while paused.
+    pub fn scaled_delta_time(&self) -> Duration {
+        if self.screen.is_paused() {
+            return Duration::ZERO;
+        }
+        self.delta_time.mul_f32(self.time_scale.current())
+    }
+
+    /// How fast gameplay runs right now, 1 for real time. See [`crate::time_scale`].
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale.current()
+    }
+
+    /// Sets how fast gameplay runs, eased into. Slow motion goes below it for a moment.
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.time_scale.set(scale);
+    }
+
+    /// Slows gameplay down to `scale` for `duration` seconds of real time.
+    pub fn slow_motion(&mut self, scale: f32, duration: f32) {
+        self.time_scale.slow_motion(scale, duration);
+    }
+
+    pub fn frame_number(&self) -> u64 {
+        self.frame_number
+    }
+
+    pub fn tick_rate(&self) -> TickRate {
+        self.tick_rate
+    }
+
+    /// Switches the tick rate from the next update on. Replays record the change, so they
+    /// still play back the same.
+    pub fn set_tick_rate(&mut self, tick_rate: TickRate) {
+        if tick_rate != self.tick_rate {
+            log::info!("tick rate {} -> {}", self.tick_rate, tick_rate);
+            self.tick_rate = tick_rate;
+            // the keyboard is updated once per tick
+            self.keyboard_state.set_update_rate(tick_rate.hz());
+        }
+    }
+
+    /// The master seed of the current game, see [`crate::rng`].
+    pub fn seed(&self) -> u64 {
+        self.world.seed
+    }
+
+    /// Fixes the master seed of new games (None for a random one each), to play a run again.
+    /// On the main menu the game about to start gets it too.
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        if seed == self.fixed_seed {
+            return;
+        }
+        self.fixed_seed = seed;
+        if self.screen == Screen::MainMenu {
+            self.new_game();
+        }
+    }
+
+    /// The colors the renderers and screens draw with, see [`crate::palette`].
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    pub fn color_vision(&self) -> ColorVision {
+        self.color_vision
+    }
+
+    /// Switches the palette to the preset for `color_vision`. The particles already flying
+    /// keep their colors.
+    pub fn set_color_vision(&mut self, color_vision: ColorVision) {
+        if color_vision != self.color_vision {
+            log::info!("color vision {:?} -> {:?}", self.color_vision, color_vision);
+            self.color_vision = color_vision;
+            self.palette = Palette::new(color_vision);
+        }
+    }
+
+    pub fn ui_scale(&self) -> f32 {
+        self.ui_scale
+    }
+
+    /// Draws text and the UI `ui_scale` times their usual size (from 0.75 to 2), on top of
+    /// the window's scale factor.
+    pub fn set_ui_scale(&mut self, ui_scale: f32) {
+        let ui_scale = ui_scale.clamp(ui::MIN_UI_SCALE, ui::MAX_UI_SCALE);
+        if ui_scale != self.ui_scale {
+            log::info!("ui scale {} -> {}", self.ui_scale, ui_scale);
+            self.ui_scale = ui_scale;
+            self.scale_factor = self.window_scale_factor * ui_scale;
+        }
+    }
+
+    // the window's scale factor changed, e.g. on another monitor
+    pub(crate) fn set_window_scale_factor(&mut self, scale_factor: f32) {
+        self.window_scale_factor = scale_factor;
+        self.scale_factor = scale_factor * self.ui_scale;
+    }
+
+    /// The simulation time step of a tick at the current tick rate. Systems get one update
+    /// per tick, so this is what they should advance by.
+    pub fn dt(&self) -> Scalar {
+        self.tick_rate.dt()
+    }
+
+    pub fn keyboard_state(&self) -> &KeyboardState {
+        &self.keyboard_state
+    }
+
+    pub fn input_map_mut(&mut self) -> &mut InputMap {
+        &mut self.input_map
+    }
+
+    pub fn mouse_state(&self) -> &MouseState {
+        &self.mouse_state
+    }
+
+    /// Text from the input method, for text fields.
+    pub fn ime(&self) -> &ImeState {
+        &self.ime
+    }
+
+    /// Hovers and clicks of the links in the overlay's text this update.
+    pub fn links(&self) -> &Links {
+        &self.links
+    }
+
+    /// The font text is drawn with.
+    pub fn ui_font(&self) -> Option<FontRef<'_>> {
+        self.ui_font
+            .and_then(|index| self.font_cache.get_font(index))
+            .or_else(|| self.font_cache.fonts().next())
+    }
+
+    /// Where backspace at the byte `index` of `text` deletes from: the start of the cluster
+    /// before it as the UI font shapes the text, so a character goes with its combining marks
+    /// (see [`TextDraw::previous_caret`]). Without a font it's the character before.
+    pub fn previous_caret(&self, text: &str, index: usize) -> usize {
+        match self.ui_font() {
+            // the clusters are the same at any size
+            Some(font) => TextDraw::new(text, [0.0, 0.0], 16.0).previous_caret(&font, 1.0, index),
+            None => text[..index]
+                .char_indices()
+                .next_back()
+                .map_or(0, |(i, _)| i),
+        }
+    }
+
+    /// The font `draw` is drawn with: its own (see [`TextDraw::font`]) or the UI font.
+    pub fn font_for(&self, draw: &TextDraw) -> Option<FontRef<'_>> {
+        match draw.font {
+            Some(index) => self.font_cache.get_font(index),
+            None => self.ui_font(),
+        }
+    }
+
+    pub fn screen(&self) -> Screen {
+        self.screen
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// How far a frame drawn at `now` is between the last tick and the next one, from 0 to 1.
+    /// In slow motion a tick spans several updates. See [`crate::interpolation`].
+    pub fn render_alpha(&self, now: Instant) -> f32 {
+        let elapsed = now.saturating_duration_since(self.now).as_secs_f32();
+        let ticks = elapsed / self.tick_rate.seconds() * self.time_scale.current();
+        (self.time_scale.progress() + ticks).clamp(0.0, 1.0)
+    }
+
+    /// The world as it should be drawn at `alpha` (see [`GameState::render_alpha`]), between
+    /// the previous tick and the current one.
+    pub fn interpolated_world(&self, alpha: f32) -> Cow<'_, World> {
+        match &self.previous_world {
+            Some(previous) => Cow::Owned(interpolation::interpolate_world(
+                previous,
+                &self.world,
+                alpha,
+            )),
+            None => Cow::Borrowed(&self.world),
+        }
+    }
+
+    /// What happened in the world during the last update (spawns, deaths, collisions).
+    /// Empty while time travel is paused.
+    pub fn events(&self) -> &[WorldEvent] {
+        &self.events
+    }
+
+    /// Fonts, textures, sounds and shaders loaded by path, see [`crate::assets`].
+    pub fn assets_mut(&mut self) -> &mut Assets {
+        &mut self.assets
+    }
+
+    /// Events between subsystems, see [`crate::events`].
+    pub fn event_bus(&self) -> &EventBus {
+        &self.event_bus
+    }
+
+    pub fn event_bus_mut(&mut self) -> &mut EventBus {
+        &mut self.event_bus
+    }
+
+    /// The view's shake, punch and flash, see [`crate::camera`].
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    /// Cosmetic particles. Explosions and the engine trail are spawned from world events;
+    /// systems can add their own effects.
+    pub fn particles_mut(&mut self) -> &mut ParticleSystem {
+        &mut self.particles
+    }
+
+    /// Draws a thick line (see [`crate::gfx::lines`]) every frame until the next update, so
+    /// systems submit theirs every tick.
+    pub fn draw_polyline(&mut self, polyline: Polyline) {
+        self.polylines.push(polyline);
+    }
+
+    /// Draws text (over the screen's overlay) every frame until the next update, so systems
+    /// queue theirs every tick. The text renderer draws all of a frame's text in one batch.
+    pub fn queue_text(&mut self, text: TextDraw) {
+        self.queued_text.push(text);
+    }
+
+    pub fn high_scores(&self) -> &HighScores {
+        &self.high_scores
+    }
+
+    pub fn heat_map(&self) -> &HeatMap {
+        &self.heat_map
+    }
+
+    pub fn ship(&self) -> &Ship {
+        &self.world.ship
+    }
+
+    pub fn ship_mut(&mut self) -> &mut Ship {
+        &mut self.world.ship
+    }
+
+    pub fn gfx_settings_mut(&mut self) -> &mut GfxSettings {
+        &mut self.gfx_settings
+    }
+
+    pub fn window_settings_mut(&mut self) -> &mut WindowSettings {
+        &mut self.window_settings
+    }
+
+    /// The settings last loaded from the settings file, e.g. for the audio volumes.
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// The text drawn by the text renderer (None draws nothing).
+    pub fn set_text(&mut self, text: Option<String>) {
+        self.text = text;
+    }
+
+    /// Exits the event loop after the current update.
+    pub fn quit(&mut self) {
+        self.should_quit = true;
+    }
+
+    pub(crate) fn update(&mut self, now: Instant) -> Result<()> {
+        self.delta_time = now - self.now;
+        if !self.screen.is_paused() {
+            self.run_time += self.delta_time;
+        }
+        self.now = now;
+        self.state_number += 1;
+        // kept between the ticks of slow motion, see screen::update_playing
+        if self.screen != Screen::Playing {
+            self.previous_world = None;
+        }
+        self.keyboard_state.update(self.state_number);
+        self.mouse_state.update();
+        self.ime.update();
+        self.links.update(&self.mouse_state);
+        for event in self.links.events() {
+            if let LinkEvent::Click(id) = *event {
+                self.event_bus.publish(ButtonClicked { id });
+            }
+        }
+        // before anything else reacts to the input, e.g. so the game pauses before it
+        // simulates a tick in the background
+        self.dispatch_events();
+        self.polylines.clear();
+        self.queued_text.clear();
+        self.should_quit = self.keyboard_state.is_down(Keycode::LControl)
+            && self.keyboard_state.is_down(Keycode::Q);
+
+        if self.keyboard_state.is_down(Keycode::LControl)
+            && self.keyboard_state.is_pressed(Keycode::V)
+        {
+            let vsync = self.gfx_settings.vsync();
+            self.gfx_settings.set_vsync(!vsync);
+        }
+
+        if self.keyboard_state.is_down(Keycode::LControl)
+            && self.keyboard_state.is_pressed(Keycode::M)
+        {
+            // cycle 1x -> 2x -> 4x -> 1x
+            self.gfx_settings.msaa_sample_count = match self.gfx_settings.msaa_sample_count {
+                1 => 2,
+                2 => 4,
+                _ => 1,
+            };
+        }
+
+        if self.keyboard_state.is_down(Keycode::LControl)
+            && self.keyboard_state.is_pressed(Keycode::P)
+        {
+            // cycle none -> bloom -> bloom + vignette -> bloom + vignette + crt -> none
+            let effects = &mut self.gfx_settings.post_effects;
+            match effects.len() {
+                0 => effects.push(PostEffect::Bloom),
+                1 => effects.push(PostEffect::Vignette),
+                2 => effects.push(PostEffect::Crt),
+                _ => effects.clear(),
+            }
+        }
+
+        if self.keyboard_state.is_down(Keycode::LControl)
+            && self.keyboard_state.is_pressed(Keycode::T)
+        {
+            // cycle 30 -> 60 -> 120 Hz
+            self.set_tick_rate(self.tick_rate.next());
+        }
+
+        if self.keyboard_state.is_down(Keycode::LControl)
+            && self.keyboard_state.is_pressed(Keycode::G)
+        {
+            self.gfx_settings.gpu_profiling = !self.gfx_settings.gpu_profiling;
+        }
+
+        if self.keyboard_state.is_down(Keycode::LControl)
+            && self.keyboard_state.is_pressed(Keycode::A)
+        {
+            self.gfx_settings.glyph_atlas_overlay = !self.gfx_settings.glyph_atlas_overlay;
+        }
+
+        if self.keyboard_state.is_down(Keycode::LControl)
+            && self.keyboard_state.is_pressed(Keycode::H)
+        {
+            // ctrl+shift+h starts over
+            if self.keyboard_state.is_down(Keycode::LShift) {
+                self.heat_map.clear();
+            } else {
+                self.show_heat_map = !self.show_heat_map;
+            }
+        }
+
+        // playtesters attach the bundle to bug reports, see crate::bug_report
+        if self.keyboard_state.is_pressed(Keycode::F12) {
+            self.bug_report_requested = true;
+        }
+        // shift+F10 records PNG files instead of a GIF
+        if self.keyboard_state.is_pressed(Keycode::F10) {
+            self.recording = match self.recording {
+                Some(_) => None,
+                None if self.keyboard_state.is_down(Keycode::LShift) => Some(RecordingFormat::Png),
+                None => Some(RecordingFormat::Gif),
+            };
+        }
+
+        if self.keyboard_state.is_pressed(Keycode::F2) {
+            self.log_panel.toggle();
+        }
+        // taken out, the font borrows the game state
+        let mut log_panel = std::mem::take(&mut self.log_panel);
+        log_panel.update(
+            self.ui_font(),
+            &self.keyboard_state,
+            &self.mouse_state,
+            self.window_size,
+            self.scale_factor,
+            self.delta_time.as_secs_f32(),
+        );
+        self.log_panel = log_panel;
+
+        // the engine refreshes the panel's report, see GameState::memory_report
+        if self.keyboard_state.is_pressed(Keycode::F3) {
+            self.memory_panel.toggle();
+        }
+
+        // quicksave only makes sense with a game going on, quickload works everywhere
+        if self.keyboard_state.is_pressed(Keycode::F5)
+            && (self.screen == Screen::Playing || self.screen.is_paused())
+        {
+            match self.save_game(SaveSlot::Quick) {
+                Ok(path) => log::info!("saved the game to {}", path.display()),
+                Err(e) => log::warn!("unable to save the game: {:#}", e),
+            }
+        }
+        if self.keyboard_state.is_pressed(Keycode::F9)
+            && let Err(e) = self.load_game(SaveSlot::Quick)
+        {
+            log::warn!("unable to load the quicksave: {:#}", e);
+        }
+
+        if self.keyboard_state.is_pressed(Keycode::F11) {
+            self.window_settings.toggle_fullscreen();
+        }
+
+        if self.keyboard_state.is_pressed(Keycode::F4) {
+            self.show_fps = !self.show_fps;
+        }
+        self.fps.update(now, self.frame_number);
+        if self.show_fps {
+            // tabular figures, so the digits don't jitter as the number changes
+            let margin = FPS_MARGIN * self.scale_factor;
+            self.queue_text(
+                TextDraw::new(
+                    format!("{:.0} FPS", self.fps.fps),
+                    [self.window_size[0] - margin, margin],
+                    FPS_TEXT_SIZE,
+                )
+                .align(TextAlign::Right)
+                .tabular_figures()
+                .shadow([0.0, 2.0 * self.scale_factor], [0.0, 0.0, 0.0, 0.8]),
+            );
+        }
+
+        self.assets.poll_changes();
+
+        // about once a second is plenty for someone editing the file
+        if self.state_number.is_multiple_of(self.tick_rate.hz() as u64)
+            && let Some(settings) = self
+                .settings_file
+                .as_mut()
+                .and_then(|file| file.reload_if_changed())
+        {
+            settings.apply_changes(self);
+        }
+
+        self.events.clear();
+        if let Some(next) = self.screen.update(self) {
+            self.switch_screen(next);
+        }
+        // frozen mid-explosion while paused
+        if !self.screen.is_paused() {
+            let dt = self.tick_rate.seconds();
+            self.time_scale.update(dt);
+            let scaled_dt = dt * self.time_scale.current();
+            self.update_particles(scaled_dt);
+            self.camera.update(scaled_dt);
+        }
+        self.overlay = self.screen.render(self);
+        self.update_link_regions();
+
+        Ok(())
+    }
+
+    // hands the events published since the last update to the subscribers
+    fn dispatch_events(&mut self) {
+        // taken out, the subscribers get the game state
+        let mut event_bus = std::mem::take(&mut self.event_bus);
+        event_bus.dispatch(self);
+        self.event_bus = event_bus;
+    }
+
+    /// Memory used by the game state's subsystems. The renderer adds its own, see
+    /// GfxState::add_memory_usage.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        report.add(
+            "world",
+            self.world.heap_size() + self.previous_world.as_ref().map_or(0, HeapSize::heap_size),
+        );
+        report.add("time travel", self.time_travel.heap_size());
+        report.add("replay", self.replay.heap_size());
+        report.add("events", vec_size(&self.events));
+        report.add("particles", self.particles.heap_size());
+        report.add("heat map", self.heat_map.heap_size());
+        report.add("font data", self.font_cache.raw_data_size());
+        report.add("shaped lines", self.font_cache.shape_cache_size());
+        report
+    }
+
+    // where the overlay's links are, for the next update's hit tests
+    fn update_link_regions(&mut self) {
+        let mut regions: Vec<LinkRegion> = self
+            .overlay
+            .text
+            .iter()
+            .flat_map(|text| {
+                self.font_for(text)
+                    .map(|font| text.link_regions(&font, self.scale_factor))
+                    .unwrap_or_default()
+            })
+            .collect();
+        regions.extend(self.overlay.regions.iter().copied());
+        self.links.set_regions(regions);
+    }
+
+    pub(crate) fn switch_screen(&mut self, next: Screen) {
+        let screen = self.screen;
+        log::info!("switching from {:?} to {:?}", screen, next);
+        screen.exit(self);
+        self.screen = next;
+        self.menu_focus = None;
+        next.enter(self);
+    }
+
+    /// Saves the world and the replay so far to `slot`, see [`crate::save`]. Returns the
+    /// path of the save file.
+    pub fn save_game(&self, slot: SaveSlot) -> Result<PathBuf> {
+        SaveGame::new(self.world.clone(), self.replay.clone()).save(slot)
+    }
+
+    /// Continues the game saved in `slot`. Outside of a game this goes to the pause screen,
+    /// so the player can get their bearings first.
+    pub fn load_game(&mut self, slot: SaveSlot) -> Result<()> {
+        let save = SaveGame::load(slot)?;
+        log::info!(
+            "loaded save slot {} (seed {}, tick {}, score {})",
+            slot,
+            save.world.seed,
+            save.world.tick,
+            save.world.score
+        );
+        self.world = save.world;
+        self.previous_world = None;
+        self.replay = save.replay;
+        self.time_travel.clear();
+        self.events.clear();
+        self.particles.clear();
+        self.particles.reseed(self.world.seed);
+        self.time_scale.reset();
+        if self.screen != Screen::Playing && !self.screen.is_paused() {
+            self.switch_screen(Screen::Paused);
+        }
+        Ok(())
+    }
+
+    /// Joins the multiplayer server at `address` as `name`, see [`crate::net`]. Its arena is
+    /// played on [`Screen::Multiplayer`] until the player leaves.
+    pub fn connect(&mut self, address: &str, name: &str) -> Result<()> {
+        self.net_client = Some(Client::connect(address, name)?);
+        if self.screen != Screen::Multiplayer {
+            self.switch_screen(Screen::Multiplayer);
+        }
+        Ok(())
+    }
+
+    /// The multiplayer game, while there's one.
+    pub fn net_client(&self) -> Option<&Client> {
+        self.net_client.as_ref()
+    }
+
+    /// Adds the current game's score to the high score table (if it makes it in) and saves
+    /// the table.
+    pub(crate) fn add_high_score(&mut self, name: String) {
+        let entry = HighScore::new(name, self.world.score, self.world.wave);
+        self.new_high_score = self.high_scores.insert(entry);
+        if self.new_high_score.is_none() {
+            return;
+        }
+        if let Some(path) = &self.high_scores_path
+            && let Err(e) = self.high_scores.save(path)
+        {
+            log::warn!(
+                "unable to save the high scores to {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+
+    /// Starts over with a fresh world (the heat map is kept, it covers the whole session).
+    pub(crate) fn new_game(&mut self) {
+        let seed = self.fixed_seed.unwrap_or_else(rng::random_seed);
+        self.world = World::new(seed);
+        self.previous_world = None;
+        self.replay = Replay::new(seed, self.tick_rate);
+        self.time_travel.clear();
+        self.events.clear();
+        self.particles.clear();
+        self.particles.reseed(seed);
+        self.time_scale.reset();
+    }
+
+    // effects for what happened in the world. Existing particles keep fading out while time
+    // travel is paused, but the engine trail stops.
+    fn update_particles(&mut self, dt: f32) {
+        let palette = &self.palette;
+        for event in self.events.iter() {
+            let position = event.position.to_f32();
+            let (params, color, count) = match (event.kind, event.entity) {
+                (WorldEventKind::Death, EntityId::Ship) => {
+                    (EmitterParams::explosion(), palette.explosion, 80)
+                }
+                (WorldEventKind::Death, _) => (EmitterParams::debris(), palette.debris, 24),
+                (WorldEventKind::Collision, _) => (EmitterParams::sparks(), palette.sparks, 10),
+                (WorldEventKind::Spawn, _) => continue,
+            };
+            let params = EmitterParams { color, ..params };
+            self.particles
+                .burst(&params, position, 0.0, [0.0, 0.0], count);
+        }
+
+        let ship = &self.world.ship;
+        let thruster = self.particles.emitter_mut(self.thruster);
+        thruster.active = self.screen == Screen::Playing
+            && self.time_travel.is_live()
+            && self.world.ship_alive()
+            && ship.thrusting();
+        // the back of the hull
+        thruster.position =
+            (ship.position + SimVec2::from_angle(ship.angle).scale(Scalar::from_int(-9))).to_f32();
+        thruster.direction = ship.angle.to_f32() + PI;
+        thruster.velocity = ship.velocity.to_f32();
+        thruster.params.color = palette.thruster;
+
+        self.particles.update(dt);
+    }
+
+    /// True once `interval` has passed since the last update (the tick rate's interval, or
+    /// longer while the engine throttles updates).
+    #[inline]
+    pub(crate) fn should_update(&self, now: &Instant, interval: Duration) -> bool {
+        now.saturating_duration_since(self.now) >= interval
+    }
+}
+
+// frames drawn per second, counted over a short window so the number is readable
+struct FpsCounter {
+    since: Instant,
+    // the frame number when the window started
+    first_frame: u64,
+    fps: f32,
+}
+
+impl FpsCounter {
+    fn new(now: Instant) -> Self {
+        Self {
+            since: now,
+            first_frame: 0,
+            fps: 0.0,
+        }
+    }
+
+    fn update(&mut self, now: Instant, frame_number: u64) {
+        let elapsed = now.saturating_duration_since(self.since);
+        if elapsed >= FPS_INTERVAL {
+            self.fps = (frame_number - self.first_frame) as f32 / elapsed.as_secs_f32();
+            self.since = now;
+            self.first_frame = frame_number;
+        }
+    }
+}
+
+// the game shouldn't go on while the player is in another window
+fn pause_on_focus_loss(game_state: &mut GameState, event: &WindowFocusChanged) {
+    if !event.focused && game_state.screen == Screen::Playing {
+        game_state.switch_screen(Screen::Paused);
+    }
+}
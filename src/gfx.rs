@@ -1,15 +1,54 @@
+pub mod background;
+mod buffer;
+mod capture;
+mod debug_lines;
+mod gpu_cache;
+mod heat_map;
+pub mod lines;
+mod outlines;
+mod overlay;
+pub mod particles;
+pub mod post;
+mod profiler;
+mod recording;
+mod render_graph;
+pub mod shaders;
+pub mod shapes;
 pub mod text;
 mod vertex;
 
 use std::sync::Arc;
+use std::time::Instant;
 
+use parking_lot::Mutex;
 use pollster::FutureExt as _;
-use text::TextRenderer;
+use text::{TextRenderer, TextSurface};
 use wgpu::util::DeviceExt;
 use winit::window::Window;
 
-use anyhow::Result;
-use vertex::LineVertex;
+use crate::error::GameError;
+use crate::frame_pacing::{Stage, StageTimes};
+use crate::logging;
+use crate::memory::{HeapSize, MemoryReport};
+use anyhow::{Context, Result};
+use background::{BackgroundLayer, BackgroundRenderer};
+use capture::FrameCapture;
+pub use capture::Screenshot;
+use debug_lines::DebugLineRenderer;
+use gpu_cache::GpuCache;
+use heat_map::HeatMapRenderer;
+use lines::LineRenderer;
+use outlines::OutlineRenderer;
+use overlay::OverlayRenderer;
+use particles::ParticleRenderer;
+use post::{PostEffect, PostProcessor, SCENE_FORMAT};
+use profiler::Profiler;
+use recording::FrameRecorder;
+pub use recording::RecordingFormat;
+pub use render_graph::Layer;
+use render_graph::{FrameContext, RenderNode, RenderTargets};
+use shaders::{Shader, ShaderManager};
+use shapes::ShapeRenderer;
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
@@ -19,23 +58,167 @@ struct SurfaceDimensionsUniform {
     scale_factor: f32,
 }
 
+/// Device features the renderer uses if the adapter has them, and what it does without them.
+const OPTIONAL_FEATURES: [(wgpu::Features, &str); 2] = [
+    (
+        wgpu::Features::DUAL_SOURCE_BLENDING,
+        "text is drawn with grayscale instead of subpixel antialiasing",
+    ),
+    (
+        wgpu::Features::TIMESTAMP_QUERY,
+        "gpu profiling is unavailable",
+    ),
+];
+
+/// User-facing graphics settings that can be changed while the game is running.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GfxSettings {
+    /// The requested present mode. If the surface does not support it, the closest
+    /// supported alternative is used instead (see `select_present_mode`).
+    pub present_mode: wgpu::PresentMode,
+    /// Samples per pixel for the line passes (1, 2 or 4). 1 disables MSAA. Falls back to the
+    /// highest sample count the surface format supports.
+    pub msaa_sample_count: u32,
+    /// Post-processing effects applied (in order) to the scene before presenting it
+    pub post_effects: Vec<PostEffect>,
+    /// Time every render pass with timestamp queries and log the results (if the adapter
+    /// supports Features::TIMESTAMP_QUERY)
+    pub gpu_profiling: bool,
+    /// For text that doesn't set its own (see [`text::TextDraw::antialiasing`])
+    pub text_antialiasing: text::TextAntialiasing,
+    /// For LCD antialiased text
+    pub lcd_filter: text::LcdFilter,
+    /// See [`TextRenderer::set_gamma_correct`]
+    pub gamma_correct_text: bool,
+    /// Toggled with Ctrl+A, see [`TextRenderer::set_atlas_overlay`]
+    pub glyph_atlas_overlay: bool,
+    /// Back to front, see [`background`]
+    pub background_layers: Vec<BackgroundLayer>,
+}
+
+impl Default for GfxSettings {
+    fn default() -> Self {
+        Self {
+            present_mode: wgpu::PresentMode::Fifo,
+            msaa_sample_count: 1,
+            post_effects: Vec::new(),
+            gpu_profiling: false,
+            text_antialiasing: text::TextAntialiasing::default(),
+            lcd_filter: text::LcdFilter::default(),
+            gamma_correct_text: false,
+            glyph_atlas_overlay: false,
+            background_layers: BackgroundLayer::defaults(),
+        }
+    }
+}
+
+impl GfxSettings {
+    pub fn vsync(&self) -> bool {
+        self.present_mode == wgpu::PresentMode::Fifo
+    }
+
+    /// Vsync on means Fifo (no tearing, more latency), vsync off prefers Mailbox
+    /// (no tearing, low latency) and falls back to Immediate (tearing, lowest latency).
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.present_mode = if vsync {
+            wgpu::PresentMode::Fifo
+        } else {
+            wgpu::PresentMode::Mailbox
+        };
+    }
+
+    fn select_present_mode(&self, supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+        let preference: &[wgpu::PresentMode] = match self.present_mode {
+            wgpu::PresentMode::Mailbox => {
+                &[wgpu::PresentMode::Mailbox, wgpu::PresentMode::Immediate]
+            }
+            wgpu::PresentMode::Immediate => {
+                &[wgpu::PresentMode::Immediate, wgpu::PresentMode::Mailbox]
+            }
+            wgpu::PresentMode::FifoRelaxed => &[wgpu::PresentMode::FifoRelaxed],
+            _ => &[],
+        };
+
+        preference
+            .iter()
+            .find(|mode| supported.contains(mode))
+            .copied()
+            // Fifo is the only mode that is guaranteed to be supported
+            .unwrap_or(wgpu::PresentMode::Fifo)
+    }
+
+    fn select_msaa_sample_count(&self, supported: &[u32]) -> u32 {
+        supported
+            .iter()
+            .filter(|count| **count <= self.msaa_sample_count)
+            .max()
+            .copied()
+            .unwrap_or(1)
+    }
+}
+
 pub struct GfxState {
     pub _window: Arc<Window>,
-    surface: wgpu::Surface<'static>,
+    instance: wgpu::Instance,
+    // None while the app is suspended (the window's native surface may be gone)
+    surface: Option<wgpu::Surface<'static>>,
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
+    settings: GfxSettings,
+    supported_present_modes: Vec<wgpu::PresentMode>,
+    supported_sample_counts: Vec<u32>,
+    sample_count: u32,
+    msaa_view: Option<wgpu::TextureView>,
+    depth_view: wgpu::TextureView,
+    msaa_depth_view: Option<wgpu::TextureView>,
+    post_processor: PostProcessor,
+    profiler: Profiler,
     size: winit::dpi::PhysicalSize<u32>,
     screen_scale_factor: f32,
-    debug_line_vertex_buffer: wgpu::Buffer,
-    debug_line_render_pipeline: wgpu::RenderPipeline,
+    // on top of the scale factor for text and the UI, see GameState::set_ui_scale
+    ui_scale: f32,
+    background_renderer: BackgroundRenderer,
+    debug_line_renderer: DebugLineRenderer,
+    outline_renderer: OutlineRenderer,
+    line_renderer: LineRenderer,
+    particle_renderer: ParticleRenderer,
+    heat_map_renderer: HeatMapRenderer,
+    overlay_renderer: OverlayRenderer,
+    shape_renderer: ShapeRenderer,
     text_renderer: TextRenderer,
-    _line_vertex_buffer: wgpu::Buffer,
-    _line_render_pipeline: wgpu::RenderPipeline,
+    shaders: ShaderManager,
+    // the layouts and samplers the renderers share
+    gpu_cache: GpuCache,
+    // how long the last frame's stages took
+    stage_times: StageTimes,
+    screenshot: Option<Screenshot>,
+    // while GameState::recording is set
+    recorder: Option<FrameRecorder>,
+    device_status: Arc<DeviceStatus>,
     surface_dimensions_buffer: wgpu::Buffer,
+    surface_dimensions_bind_group_layout: wgpu::BindGroupLayout,
     surface_dimensions_bind_group: wgpu::BindGroup,
 }
 
+// what the device reports on its own, from any thread: the errors no error scope caught and
+// its loss. `render` hands them on as GameErrors.
+#[derive(Default)]
+struct DeviceStatus {
+    // the first one since the last frame
+    error: Mutex<Option<String>>,
+    lost: Mutex<Option<String>>,
+}
+
+impl DeviceStatus {
+    fn set_error(&self, error: String) {
+        self.error.lock().get_or_insert(error);
+    }
+}
+
+// the text sizes of the log panel, the menus' prompts and the screens, in logical pixels per em
+const PREWARM_TEXT_SIZES: [f32; 3] = [14.0, 16.0, 24.0];
+
 #[rustfmt::skip]
 pub const _OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
     1.0, 0.0, 0.0, 0.0,
@@ -45,44 +228,55 @@ pub const _OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
 );
 
 impl GfxState {
-    pub fn new(window: Arc<Window>) -> Self {
+    /// Fails if there is no adapter that can draw to the window, on any backend. Blocks until
+    /// the device is ready, see [`GfxState::new_async`].
+    pub fn new(window: Arc<Window>, settings: GfxSettings) -> Result<Self> {
+        Self::new_async(window, settings).block_on()
+    }
+
+    /// Like [`GfxState::new`], for an event loop that can't block while the adapter and device
+    /// are requested (e.g. a browser's).
+    pub async fn new_async(window: Arc<Window>, settings: GfxSettings) -> Result<Self> {
         let size = window.inner_size();
         let screen_scale_factor = window.scale_factor() as f32;
 
-        // The instance's main purpose is to create Adapters and Surfaces
-        // Backends::all => Vulkan + Metal + DX12 + Browser WebGPU
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::PRIMARY,
-            ..Default::default()
-        });
-
-        // The surface is the part of the window that we draw to.
-        let surface = instance.create_surface(window.clone()).unwrap();
-
-        let (adapter, device, queue) =
-            async { Self::load_adapter_device_queue(&instance, &surface).await }.block_on();
+        let (instance, surface, adapter, device, queue) = Self::load_adapter_device_queue(&window)
+            .await
+            .context("unable to set up the graphics device")?;
 
         let surface_caps = surface.get_capabilities(&adapter);
 
-        let surface_format = surface_caps
-            .formats
-            .iter()
-            .find(|f| f.is_srgb())
-            .copied()
-            .unwrap_or(surface_caps.formats[0]);
+        let surface_format = match surface_caps.formats.iter().find(|f| f.is_srgb()) {
+            Some(format) => *format,
+            None => *surface_caps.formats.first().ok_or_else(|| {
+                GameError::InitError(format!(
+                    "{} can't present to the window",
+                    adapter.get_info().name
+                ))
+            })?,
+        };
+
+        // everything except the post-processing passes renders into the offscreen scene texture
+        let format_features = adapter.get_texture_format_features(SCENE_FORMAT);
+        let supported_sample_counts: Vec<u32> = [1, 2, 4]
+            .into_iter()
+            .filter(|count| format_features.flags.sample_count_supported(*count))
+            .collect();
 
         let config = wgpu::SurfaceConfiguration {
             // How SurfaceTextures will be used.
             // RENDER_ATTACHMENT specifies that the textures fill be used to write to the screen.
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            // COPY_SRC (where supported) lets the bug report read back a screenshot.
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | (surface_caps.usages & wgpu::TextureUsages::COPY_SRC),
             // How SurfaceTextures fill be stored on the GPU.
             format: surface_format,
             // width and height in pixels of a SurfaceTexture
             width: size.width,
             height: size.height,
             // How to sync the surface with the display.
-            // Fifo = VSync
-            present_mode: wgpu::PresentMode::Fifo,
+            // Fifo = VSync, Mailbox/Immediate = no VSync
+            present_mode: settings.select_present_mode(&surface_caps.present_modes),
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
@@ -90,6 +284,20 @@ impl GfxState {
 
         surface.configure(&device, &config);
 
+        // instead of wgpu's handler, which panics
+        let device_status = Arc::new(DeviceStatus::default());
+        let status = device_status.clone();
+        device.on_uncaptured_error(Arc::new(move |error| status.set_error(error.to_string())));
+        let status = device_status.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            *status.lost.lock() = Some(format!("{} ({:?})", message, reason));
+        });
+
+        // everything below fails as a whole with the first error, rather than later when it's
+        // used
+        device.push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+
         let surface_dimensions_px_uniform = SurfaceDimensionsUniform {
             width: size.width,
             height: size.height,
@@ -103,8 +311,11 @@ impl GfxState {
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             });
 
-        let surface_dimensions_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        let gpu_cache = GpuCache::new();
+
+        let surface_dimensions_bind_group_layout = gpu_cache.bind_group_layout(
+            &device,
+            &wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
                     visibility: wgpu::ShaderStages::VERTEX,
@@ -116,7 +327,8 @@ impl GfxState {
                     count: None,
                 }],
                 label: Some("surface_dimensions_bind_group_layout"),
-            });
+            },
+        );
 
         let surface_dimensions_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &surface_dimensions_bind_group_layout,
@@ -127,192 +339,294 @@ impl GfxState {
             label: Some("surface_dimensions_bind_group"),
         });
 
-        let debug_line_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Debug line shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("debug-line-shader.wgsl").into()),
-        });
+        let sample_count = settings.select_msaa_sample_count(&supported_sample_counts);
 
-        let debug_line_render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Debug line renderer pipeline layout"),
-                bind_group_layouts: &[&surface_dimensions_bind_group_layout],
-                push_constant_ranges: &[],
-            });
+        let shaders = ShaderManager::new();
 
-        let debug_line_render_pipeline =
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("Debug line renderer pipeline"),
-                layout: Some(&debug_line_render_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &debug_line_shader,
-                    entry_point: Some("vs_main"),
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                    buffers: &[wgpu::VertexBufferLayout {
-                        array_stride: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
-                        step_mode: wgpu::VertexStepMode::Vertex,
-                        attributes: &[wgpu::VertexAttribute {
-                            format: wgpu::VertexFormat::Float32x2,
-                            offset: 0,
-                            shader_location: 0,
-                        }],
-                    }],
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &debug_line_shader,
-                    entry_point: Some("fs_main"),
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: config.format,
-                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::LineList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: None,
-                    // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    // Requires Features::DEPTH_CLIP_CONTROL
-                    unclipped_depth: false,
-                    // Requires Features::CONSERVATIVE_RASTERIZATION
-                    conservative: false,
-                },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-                cache: None,
-            });
+        let background_renderer = BackgroundRenderer::new(
+            &device,
+            &queue,
+            &gpu_cache,
+            SCENE_FORMAT,
+            sample_count,
+            &settings.background_layers,
+        );
 
-        let debug_line_vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("debug_line_vertex_buffer"),
-            size: (256 as usize * std::mem::size_of::<[f32; 2]>()) as wgpu::BufferAddress,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        let debug_line_renderer = DebugLineRenderer::new(
+            &device,
+            &surface_dimensions_bind_group_layout,
+            SCENE_FORMAT,
+            sample_count,
+            shaders.source(Shader::DebugLine),
+        );
+
+        let outline_renderer = OutlineRenderer::new(&device, SCENE_FORMAT, sample_count);
 
-        let text_renderer = TextRenderer::new(
+        let line_renderer = LineRenderer::new(
             &device,
-            &config,
             &surface_dimensions_bind_group_layout,
-            size.width,
-            size.height,
-            screen_scale_factor,
+            SCENE_FORMAT,
+            sample_count,
+            shaders.source(Shader::Line),
         );
 
-        let line_vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("line_vertex_buffer"),
-            size: (4000 as usize * std::mem::size_of::<LineVertex>()) as wgpu::BufferAddress,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        let particle_renderer = ParticleRenderer::new(&device, SCENE_FORMAT);
 
-        let line_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Line Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("line-shader.wgsl").into()),
-        });
+        let heat_map_renderer = HeatMapRenderer::new(
+            &device,
+            SCENE_FORMAT,
+            crate::heat_map::HeatMap::default().cell_count(),
+        );
 
-        let line_render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Line Render Pipeline Layout"),
-                bind_group_layouts: &[&surface_dimensions_bind_group_layout],
-                push_constant_ranges: &[],
-            });
+        let overlay_renderer = OverlayRenderer::new(&device, SCENE_FORMAT);
 
-        let line_render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Line Render Pipeline"),
-            layout: Some(&line_render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &line_shader,
-                entry_point: Some("vs_main"),
-                // What type of vertices we want to pass to the vertex shader.
-                buffers: &[LineVertex::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &line_shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None,
-                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-                polygon_mode: wgpu::PolygonMode::Fill,
-                // Requires Features::DEPTH_CLIP_CONTROL
-                unclipped_depth: false,
-                // Requires Features::CONSERVATIVE_RASTERIZATION
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
+        let shape_renderer =
+            ShapeRenderer::new(&device, SCENE_FORMAT, &surface_dimensions_bind_group_layout);
+
+        let mut text_renderer = TextRenderer::new(
+            &device,
+            &gpu_cache,
+            SCENE_FORMAT,
+            &surface_dimensions_bind_group_layout,
+            TextSurface {
+                width: size.width,
+                height: size.height,
+                scale_factor: screen_scale_factor,
             },
-            multiview: None,
-            cache: None,
-        });
+            shaders.source(Shader::Text),
+        );
+        Self::apply_text_settings(&mut text_renderer, &device, &settings);
+
+        let msaa_view = Self::create_msaa_view(&device, &config, sample_count);
+        let depth_view = Self::create_depth_view(&device, &config, 1);
+        let msaa_depth_view =
+            (sample_count > 1).then(|| Self::create_depth_view(&device, &config, sample_count));
 
-        GfxState {
+        let post_processor =
+            PostProcessor::new(&device, &gpu_cache, config.format, size.width, size.height);
+
+        let profiler = Profiler::new(&device, &queue, settings.gpu_profiling);
+
+        if let Some(error) = pop_error_scopes(&device).await {
+            return Err(GameError::DeviceError(error.to_string()).into());
+        }
+
+        Ok(GfxState {
             _window: window,
-            surface,
+            instance,
+            surface: Some(surface),
             device,
             queue,
             config,
+            settings,
+            supported_present_modes: surface_caps.present_modes,
+            supported_sample_counts,
+            sample_count,
+            msaa_view,
+            depth_view,
+            msaa_depth_view,
+            post_processor,
+            profiler,
             size,
             screen_scale_factor,
-            debug_line_vertex_buffer,
-            debug_line_render_pipeline,
+            ui_scale: 1.0,
+            background_renderer,
+            debug_line_renderer,
+            outline_renderer,
+            line_renderer,
+            particle_renderer,
+            heat_map_renderer,
+            overlay_renderer,
+            shape_renderer,
             text_renderer,
-            _line_vertex_buffer: line_vertex_buffer,
-            _line_render_pipeline: line_render_pipeline,
+            shaders,
+            gpu_cache,
+            stage_times: StageTimes::default(),
+            screenshot: None,
+            recorder: None,
+            device_status,
             surface_dimensions_buffer,
+            surface_dimensions_bind_group_layout,
             surface_dimensions_bind_group,
+        })
+    }
+
+    /// Creates the multisampled color target that the MSAA passes render into before
+    /// resolving to the surface. Returns None if MSAA is disabled.
+    fn create_msaa_view(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) -> Option<wgpu::TextureView> {
+        if sample_count <= 1 {
+            return None;
         }
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("msaa_color_texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: SCENE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
+    /// Creates the depth/stencil buffer of the layered passes (see render_graph::Layer), with
+    /// the sample count of the passes that use it.
+    fn create_depth_view(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) -> wgpu::TextureView {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(if sample_count > 1 {
+                "msaa_depth_texture"
+            } else {
+                "depth_texture"
+            }),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: render_graph::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
     }
 
+    /// Finds an adapter that can draw to the window, on the primary backends (Vulkan, Metal,
+    /// DX12) or else on GL, and creates its device. The device gets the optional features the
+    /// adapter has (see [`OPTIONAL_FEATURES`]) and the best limits it supports. If a device
+    /// with those can't be created after all, it's created with none of the optional features
+    /// and the downlevel limits. The renderers check the device's features and degrade
+    /// without them, e.g. the text renderer blends grayscale text.
     async fn load_adapter_device_queue(
-        instance: &wgpu::Instance,
-        surface: &wgpu::Surface<'_>,
-    ) -> (wgpu::Adapter, wgpu::Device, wgpu::Queue) {
-        // The adapter is the handle to the actual graphics card.
-        // We use this to create the Device and Queue.
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .expect("Unable to load adapter");
-
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                required_features: wgpu::Features::empty()
-                    .union(wgpu::Features::DUAL_SOURCE_BLENDING),
-                required_limits: wgpu::Limits::default(),
-                experimental_features: wgpu::ExperimentalFeatures::disabled(),
-                label: None,
-                memory_hints: Default::default(),
-                trace: wgpu::Trace::Off,
-            })
-            .await
-            .expect("Unable to load device/queue");
+        window: &Arc<Window>,
+    ) -> Result<(
+        wgpu::Instance,
+        wgpu::Surface<'static>,
+        wgpu::Adapter,
+        wgpu::Device,
+        wgpu::Queue,
+    )> {
+        let mut errors = Vec::new();
+        for backends in [wgpu::Backends::PRIMARY, wgpu::Backends::GL] {
+            // The instance's main purpose is to create Adapters and Surfaces
+            let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+                backends,
+                ..Default::default()
+            });
+            // The surface is the part of the window that we draw to.
+            let surface = match instance.create_surface(window.clone()) {
+                Ok(surface) => surface,
+                Err(e) => {
+                    errors.push(format!("{:?}: {}", backends, e));
+                    continue;
+                }
+            };
+            // The adapter is the handle to the actual graphics card.
+            // We use this to create the Device and Queue.
+            let adapter = match instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    compatible_surface: Some(&surface),
+                    force_fallback_adapter: false,
+                })
+                .await
+            {
+                Ok(adapter) => adapter,
+                Err(e) => {
+                    errors.push(format!("{:?}: {}", backends, e));
+                    continue;
+                }
+            };
+            let info = adapter.get_info();
+            let adapter_line = format!(
+                "{} ({:?}, {:?}), driver: {} {}",
+                info.name, info.device_type, info.backend, info.driver, info.driver_info
+            );
+            crate::crash::set_context("graphics adapter", adapter_line.clone());
+            crate::logging::header_info(format!("graphics adapter: {}", adapter_line));
+
+            let (device, queue) = match adapter
+                .request_device(&Self::device_descriptor(&adapter))
+                .await
+            {
+                Ok(device_queue) => device_queue,
+                Err(e) => {
+                    log::warn!(
+                        target: logging::GFX,
+                        "unable to create the device ({}), retrying without optional features",
+                        e
+                    );
+                    match adapter
+                        .request_device(&Self::minimal_device_descriptor(&adapter))
+                        .await
+                    {
+                        Ok(device_queue) => device_queue,
+                        Err(e) => {
+                            errors.push(format!("{}: {}", info.name, e));
+                            continue;
+                        }
+                    }
+                }
+            };
+            for (feature, without) in OPTIONAL_FEATURES {
+                if !device.features().contains(feature) {
+                    log::warn!(target: logging::GFX, "no {:?}: {}", feature, without);
+                }
+            }
+            return Ok((instance, surface, adapter, device, queue));
+        }
+        Err(GameError::InitError(format!(
+            "no usable graphics adapter ({})",
+            errors.join(", ")
+        ))
+        .into())
+    }
+
+    /// The features and limits the renderer asks the device for: the optional features the
+    /// adapter has, and the default limits or the downlevel ones (GL, older GPUs) if the
+    /// adapter doesn't have those (also checked by `--diagnose`, see crate::diagnose).
+    pub(crate) fn device_descriptor(adapter: &wgpu::Adapter) -> wgpu::DeviceDescriptor<'static> {
+        let optional_features = OPTIONAL_FEATURES
+            .iter()
+            .fold(wgpu::Features::empty(), |features, (feature, _)| {
+                features | *feature
+            });
+        wgpu::DeviceDescriptor {
+            required_features: adapter.features() & optional_features,
+            ..Self::minimal_device_descriptor(adapter)
+        }
+    }
 
-        (adapter, device, queue)
+    // no optional features, and limits any adapter has
+    fn minimal_device_descriptor(adapter: &wgpu::Adapter) -> wgpu::DeviceDescriptor<'static> {
+        let required_limits = if wgpu::Limits::default().check_limits(&adapter.limits()) {
+            wgpu::Limits::default()
+        } else {
+            wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits())
+        };
+        wgpu::DeviceDescriptor {
+            required_features: wgpu::Features::empty(),
+            required_limits,
+            experimental_features: wgpu::ExperimentalFeatures::disabled(),
+            label: None,
+            memory_hints: Default::default(),
+            trace: wgpu::Trace::Off,
+        }
     }
 
     pub fn resize(&mut self, new_size: Option<winit::dpi::PhysicalSize<u32>>) {
@@ -321,7 +635,26 @@ impl GfxState {
             self.size = new_size_apply;
             self.config.width = new_size_apply.width;
             self.config.height = new_size_apply.height;
-            self.surface.configure(&self.device, &self.config);
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.config);
+            }
+            let created = with_error_scopes(&self.device, || {
+                self.msaa_view =
+                    Self::create_msaa_view(&self.device, &self.config, self.sample_count);
+                self.depth_view = Self::create_depth_view(&self.device, &self.config, 1);
+                self.msaa_depth_view = (self.sample_count > 1).then(|| {
+                    Self::create_depth_view(&self.device, &self.config, self.sample_count)
+                });
+                self.post_processor.resize(
+                    &self.device,
+                    new_size_apply.width,
+                    new_size_apply.height,
+                );
+            });
+            // the next frame reports it
+            if let Err(error) = created {
+                self.device_status.set_error(error.to_string());
+            }
 
             let surface_dimensions_px_uniform = SurfaceDimensionsUniform {
                 width: new_size_apply.width,
@@ -336,14 +669,250 @@ impl GfxState {
             self.text_renderer.surface_resized(
                 new_size_apply.width,
                 new_size_apply.height,
-                self.screen_scale_factor,
+                self.ui_scale_factor(),
             );
         }
     }
 
+    /// Draws at the window's new scale factor, e.g. after it moved to a monitor with another
+    /// DPI. The glyphs rasterized for the previous one are dropped, see
+    /// [`GfxState::prewarm_glyphs`] to fill the cache again.
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        if scale_factor == self.screen_scale_factor {
+            return;
+        }
+        log::info!(
+            target: logging::GFX,
+            "scale factor {} -> {}",
+            self.screen_scale_factor,
+            scale_factor
+        );
+        self.screen_scale_factor = scale_factor;
+        self.text_renderer.glyph_cache.clear();
+        // updates the uniform and the text renderer
+        self.resize(None);
+    }
+
+    pub fn ui_scale(&self) -> f32 {
+        self.ui_scale
+    }
+
+    /// Draws text and the UI `ui_scale` times their usual size, on top of the window's scale
+    /// factor. Glyphs are cached by their size in pixels, so the ones rasterized at the
+    /// previous scale stay cached next to the new ones, for switching back.
+    pub fn set_ui_scale(&mut self, ui_scale: f32) {
+        if ui_scale == self.ui_scale {
+            return;
+        }
+        log::info!(target: logging::GFX, "ui scale {} -> {}", self.ui_scale, ui_scale);
+        self.ui_scale = ui_scale;
+        self.text_renderer.surface_resized(
+            self.size.width,
+            self.size.height,
+            self.ui_scale_factor(),
+        );
+    }
+
+    // logical pixels of text and the UI to physical ones
+    fn ui_scale_factor(&self) -> f32 {
+        self.screen_scale_factor * self.ui_scale
+    }
+
+    /// Applies new graphics settings, reconfiguring the surface if the effective
+    /// present mode changes and rebuilding the line pipelines if the MSAA sample count changes.
+    pub fn set_settings(&mut self, settings: GfxSettings) {
+        if settings == self.settings {
+            return;
+        }
+        let present_mode = settings.select_present_mode(&self.supported_present_modes);
+        let sample_count = settings.select_msaa_sample_count(&self.supported_sample_counts);
+        if settings.gpu_profiling != self.settings.gpu_profiling {
+            self.profiler.set_enabled(settings.gpu_profiling);
+        }
+        Self::apply_text_settings(&mut self.text_renderer, &self.device, &settings);
+        if settings.background_layers != self.settings.background_layers {
+            self.background_renderer.set_layers(
+                &self.device,
+                &self.queue,
+                &settings.background_layers,
+            );
+        }
+        self.settings = settings;
+        if present_mode != self.config.present_mode {
+            log::info!(target: logging::GFX, "switching present mode to {:?}", present_mode);
+            self.config.present_mode = present_mode;
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.config);
+            }
+        }
+        if sample_count != self.sample_count {
+            log::info!(target: logging::GFX, "switching MSAA sample count to {}", sample_count);
+            self.sample_count = sample_count;
+            let created = with_error_scopes(&self.device, || {
+                self.msaa_view = Self::create_msaa_view(&self.device, &self.config, sample_count);
+                self.msaa_depth_view = (sample_count > 1)
+                    .then(|| Self::create_depth_view(&self.device, &self.config, sample_count));
+                self.background_renderer
+                    .rebuild_pipeline(&self.device, SCENE_FORMAT, sample_count);
+                self.debug_line_renderer.rebuild_pipeline(
+                    &self.device,
+                    &self.surface_dimensions_bind_group_layout,
+                    SCENE_FORMAT,
+                    sample_count,
+                    self.shaders.source(Shader::DebugLine),
+                );
+                self.outline_renderer
+                    .rebuild_pipeline(&self.device, SCENE_FORMAT, sample_count);
+                self.line_renderer.rebuild_pipeline(
+                    &self.device,
+                    &self.surface_dimensions_bind_group_layout,
+                    SCENE_FORMAT,
+                    sample_count,
+                    self.shaders.source(Shader::Line),
+                );
+            });
+            if let Err(error) = created {
+                self.device_status.set_error(error.to_string());
+            }
+        }
+    }
+
+    fn apply_text_settings(
+        text_renderer: &mut TextRenderer,
+        device: &wgpu::Device,
+        settings: &GfxSettings,
+    ) {
+        text_renderer.set_default_antialiasing(settings.text_antialiasing);
+        text_renderer
+            .glyph_cache
+            .set_lcd_filter(settings.lcd_filter);
+        text_renderer.set_gamma_correct(device, settings.gamma_correct_text);
+        text_renderer.set_atlas_overlay(settings.glyph_atlas_overlay);
+    }
+
+    /// Rebuilds the pipelines of the shaders that were edited on disk (debug builds, see
+    /// [`shaders`]). A pipeline that fails to build is logged and the old one is kept.
+    fn reload_shaders(&mut self) {
+        for (shader, source) in self.shaders.poll_changes() {
+            let pipeline = with_error_scopes(&self.device, || match shader {
+                Shader::Text => TextRenderer::create_pipeline(
+                    &self.device,
+                    &self.gpu_cache,
+                    &self.surface_dimensions_bind_group_layout,
+                    self.text_renderer.texture_bind_group_layout(),
+                    SCENE_FORMAT,
+                    &source,
+                    self.text_renderer.dual_source_blending(),
+                ),
+                Shader::Line => LineRenderer::create_pipeline(
+                    &self.device,
+                    &self.surface_dimensions_bind_group_layout,
+                    SCENE_FORMAT,
+                    self.sample_count,
+                    &source,
+                ),
+                Shader::DebugLine => DebugLineRenderer::create_pipeline(
+                    &self.device,
+                    &self.surface_dimensions_bind_group_layout,
+                    SCENE_FORMAT,
+                    self.sample_count,
+                    &source,
+                ),
+            });
+            let pipeline = match pipeline {
+                Ok(pipeline) => pipeline,
+                Err(error) => {
+                    log::error!(
+                        target: logging::GFX,
+                        "unable to reload {}: {}",
+                        shader.file_name(),
+                        error
+                    );
+                    continue;
+                }
+            };
+            match shader {
+                Shader::Text => self.text_renderer.render_pipeline = pipeline,
+                Shader::Line => self.line_renderer.render_pipeline = pipeline,
+                Shader::DebugLine => self.debug_line_renderer.render_pipeline = pipeline,
+            }
+            self.shaders.set_source(shader, source);
+            log::info!(target: logging::GFX, "reloaded {}", shader.file_name());
+        }
+    }
+
+    /// Rasterizes the printable ASCII glyphs of `font` at the text sizes the UI uses, so the
+    /// first frames of the menus and panels don't stall on it (see
+    /// [`GlyphCache::prewarm`](text::GlyphCache::prewarm)).
+    pub fn prewarm_glyphs(&mut self, font: &text::FontRef<'_>) {
+        let start = Instant::now();
+        let sizes = PREWARM_TEXT_SIZES
+            .map(|size| skrifa::instance::Size::new(size * self.ui_scale_factor()));
+        let count = self.text_renderer.glyph_cache.prewarm(
+            font,
+            &sizes,
+            text::PRINTABLE_ASCII,
+            self.text_renderer.antialiasing(None),
+        );
+        log::debug!(
+            target: logging::GFX,
+            "prewarmed {} glyphs of {} in {:?}",
+            count,
+            font.family_name(),
+            start.elapsed()
+        );
+    }
+
+    /// Drops the cached glyphs of fonts that were reloaded, see
+    /// [`FontCache::poll_changes`](text::FontCache::poll_changes). The font cache drops their
+    /// shaped lines itself.
+    pub fn invalidate_fonts(&mut self, font_cache_indexes: &[usize]) {
+        for index in font_cache_indexes {
+            self.text_renderer.glyph_cache.invalidate_font(*index);
+        }
+    }
+
+    /// Drops the surface. Must be called when the app gets suspended, since the native
+    /// window surface may be destroyed (e.g. on Android) before the app is resumed.
+    pub fn suspend(&mut self) {
+        self.surface = None;
+    }
+
+    /// Recreates (and configures) the surface after a suspend. Device, pipelines and
+    /// other gpu resources survive the suspend and are reused.
+    pub fn resume(&mut self) -> Result<()> {
+        if self.surface.is_some() {
+            return Ok(());
+        }
+        let surface = self.instance.create_surface(self._window.clone())?;
+        surface.configure(&self.device, &self.config);
+        self.surface = Some(surface);
+        // the window may have been resized while suspended
+        let size = self._window.inner_size();
+        if size != self.size {
+            self.resize(Some(size));
+        }
+        Ok(())
+    }
+
     pub fn render(&mut self, game_state: &super::GameState) -> Result<()> {
+        if let Some(reason) = self.device_status.lost.lock().clone() {
+            return Err(GameError::DeviceLost(reason).into());
+        }
+        self.reload_shaders();
+        let surface = self.surface.as_ref().ok_or(GameError::SurfaceSuspended)?;
+        let mut times = StageTimes::default();
+        let start = Instant::now();
+
         // Get SurfaceTexture
-        let output = self.surface.get_current_texture()?;
+        // (wrapped in GameError so the caller can react to lost/outdated surfaces)
+        let output = surface
+            .get_current_texture()
+            .map_err(GameError::WgpuError)?;
+        let start = times.add_since(Stage::Present, start);
+
+        self.profiler.begin_frame(&self.device);
+
         // Create TextureView with default settings
         let view = output
             .texture
@@ -355,236 +924,200 @@ impl GfxState {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Debug line render pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    // The view to save the colors to. In this case, the screen.
-                    view: &view,
-                    // Target that will receive the resolved output. Is the same as `view` unless multisampling is enabled.
-                    resolve_target: None,
-                    // What to do with the colors on the view (i.e. the screen)
-                    ops: wgpu::Operations {
-                        // Load tells wgpu how to handle colors stored from the previous frame (we clear the screen)
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 1.0, //242.0 / 255.0,
-                            g: 1.0, //240.0 / 255.0,
-                            b: 1.0, //239.0 / 255.0,
-                            a: 1.0,
-                        }),
-                        // We want to store the rendered results to the (Surface)Texture behind the TextureView (the view)
-                        store: wgpu::StoreOp::Store,
-                    },
-                    depth_slice: None,
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-
-            let line_vertices: [[f32; 2]; 2] = [
-                [
-                    -1.0,
-                    1.0 - logical_px_to_screen_surface_offset(
-                        257,
-                        self.size.height,
-                        self.screen_scale_factor,
-                    ),
-                ],
-                [
-                    1.0,
-                    1.0 - logical_px_to_screen_surface_offset(
-                        257,
-                        self.size.height,
-                        self.screen_scale_factor,
-                    ),
-                ],
-            ];
-
-            /*self.queue.write_buffer(
-                &self.debug_line_vertex_buffer,
-                0,
-                bytemuck::cast_slice(&line_vertices),
-            );*/
-
-            render_pass.set_pipeline(&self.debug_line_render_pipeline);
-            render_pass.set_bind_group(0, &self.surface_dimensions_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.debug_line_vertex_buffer.slice(..));
-            render_pass.draw(0..line_vertices.len() as u32, 0..1);
-        }
+        let ctx = FrameContext {
+            device: &self.device,
+            queue: &self.queue,
+            game_state,
+            surface_dimensions_bind_group: &self.surface_dimensions_bind_group,
+            surface_width: self.size.width,
+            surface_height: self.size.height,
+            screen_scale_factor: self.ui_scale_factor(),
+            window_scale_factor: self.screen_scale_factor,
+            render_alpha: game_state.render_alpha(Instant::now()),
+        };
+        let targets = RenderTargets {
+            scene: self.post_processor.scene_view(),
+            msaa: self.msaa_view.as_ref(),
+            depth: &self.depth_view,
+            msaa_depth: self.msaa_depth_view.as_ref(),
+        };
+        // Passes are recorded in this order
+        let mut render_nodes: [&mut dyn RenderNode; 9] = [
+            &mut self.background_renderer,
+            &mut self.debug_line_renderer,
+            &mut self.outline_renderer,
+            &mut self.line_renderer,
+            &mut self.particle_renderer,
+            &mut self.heat_map_renderer,
+            &mut self.overlay_renderer,
+            &mut self.shape_renderer,
+            &mut self.text_renderer,
+        ];
+        // the debug groups are what a frame debugger shows of the frame at the top level
+        render_graph::debug_group(&mut encoder, "Scene", |encoder| {
+            render_graph::record_passes(
+                &mut render_nodes,
+                &targets,
+                encoder,
+                &ctx,
+                &mut self.profiler,
+            );
+        });
 
-        // begin_render_pass borrows encoder mutably, so we need to make sure that the borrow
-        // is dropped before we can call encoder.finish()
-        {
-            let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Glyph Render Pass"),
-                color_attachments: &[
-                    // This is what [[location(0)]] in the fragment shader targets
-                    Some(wgpu::RenderPassColorAttachment {
-                        // The view to save the colors to. In this case, the screen.
-                        view: &view,
-                        // Target that will receive the resolved output. Is the same as `view` unless multisampling is enabled.
-                        resolve_target: None,
-                        // What to do with the colors on the view (i.e. the screen)
-                        ops: wgpu::Operations {
-                            // Load tells wgpu how to handle colors stored from the previous frame (load from previous render pass)
-                            load: wgpu::LoadOp::Load,
-                            // We want to store the rendered results to the (Surface)Texture behind the TextureView (the view)
-                            store: wgpu::StoreOp::Store,
-                        },
-                        depth_slice: None,
-                    }),
-                ],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
-            self.text_renderer.render(
-                &game_state,
-                render_pass,
-                &self.surface_dimensions_bind_group,
+        render_graph::debug_group(&mut encoder, "Post-processing", |encoder| {
+            self.post_processor.record(
+                encoder,
                 &self.queue,
+                &self.settings.post_effects,
+                game_state.camera().flash_color(),
+                &view,
+                &mut self.profiler,
             );
-        }
-        /*
-        // begin_render_pass borrows encoder mutably, so we need to make sure that the borrow
-        // is dropped before we can call encoder.finish()
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Line Render Pass"),
-                color_attachments: &[
-                    // This is what [[location(0)]] in the fragment shader targets
-                    Some(wgpu::RenderPassColorAttachment {
-                        // The view to save the colors to. In this case, the screen.
-                        view: &view,
-                        // Target that will receive the resolved output. Is the same as `view` unless multisampling is enabled.
-                        resolve_target: None,
-                        // What to do with the colors on the view (i.e. the screen)
-                        ops: wgpu::Operations {
-                            // Load tells wgpu how to handle colors stored from the previous frame (keep what we have)
-                            load: wgpu::LoadOp::Load,
-                            // We want to store the rendered results to the (Surface)Texture behind the TextureView (the view)
-                            store: wgpu::StoreOp::Store,
-                        },
-                        depth_slice: None,
-                    }),
-                ],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
+        });
 
-            /*let line_vertices = Self::generate_line_vertices(
-                &vec![
-                    [0.0, 0.0, 0.0],
-                    [0.5, 0.5, 0.0],
-                    [0.5, 0.0, 0.0],
-                    [0.75, 0.5, 0.0],
-                    [0.75, 0.0, 0.0],
-                ],
-                10.0,
-            );*/
-
-            let line_vertices: Vec<LineVertex> = if let Some(multiline) = game_state.test_multiline
-            {
-                self.generate_line_vertices(&Vec::from(multiline), 10.0)
-            } else {
-                Vec::new()
-            };
+        render_graph::debug_group(&mut encoder, "Timestamp queries", |encoder| {
+            self.profiler.resolve(encoder);
+        });
 
-            self.queue.write_buffer(
-                &self.line_vertex_buffer,
-                0,
-                bytemuck::cast_slice(&line_vertices[..]),
-            );
+        // the bug report wants the frame exactly as presented
+        let can_capture = self.config.usage.contains(wgpu::TextureUsages::COPY_SRC);
+        let capture = if game_state.bug_report_requested && can_capture {
+            render_graph::debug_group(&mut encoder, "Bug report capture", |encoder| {
+                FrameCapture::record(&self.device, encoder, &output.texture)
+            })
+        } else {
+            None
+        };
+        self.update_recording(game_state.recording);
+        let now = Instant::now();
+        let recording_capture = match &self.recorder {
+            Some(recorder) if can_capture && recorder.wants_frame(now) => {
+                render_graph::debug_group(&mut encoder, "Recording capture", |encoder| {
+                    FrameCapture::record(&self.device, encoder, &output.texture)
+                })
+            }
+            _ => None,
+        };
+        // the text renderer times its own stages, while its pass is recorded
+        let text_times = self.text_renderer.stage_times();
+        times.add(
+            Stage::Encode,
+            start.elapsed().saturating_sub(text_times.total()),
+        );
+        times.merge(text_times);
+        let start = Instant::now();
 
-            render_pass.set_pipeline(&self.line_render_pipeline);
-            render_pass.set_bind_group(0, &self.surface_dimensions_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.line_vertex_buffer.slice(..));
-            render_pass.draw(0..line_vertices.len() as u32, 0..1);
-        }
-        */
         self.queue.submit(std::iter::once(encoder.finish()));
+        self.profiler.after_submit();
+        if let Some(recorder) = &mut self.recorder {
+            if let Some(capture) = recording_capture {
+                recorder.add(capture, now);
+            }
+            recorder.poll(&self.device);
+        }
+        if let Some(capture) = capture {
+            match capture.read(&self.device) {
+                Ok(screenshot) => self.screenshot = Some(screenshot),
+                Err(error) => {
+                    log::warn!(target: logging::GFX, "failed to read back the frame: {}", error)
+                }
+            }
+        }
         output.present();
+        times.add_since(Stage::Present, start);
+        self.stage_times = times;
 
-        Ok(())
+        if let Some(error) = self.device_status.error.lock().take() {
+            return Err(GameError::DeviceError(error).into());
+        }
+        // the frame went out without the glyphs that failed
+        match self.text_renderer.take_error() {
+            Some(error) => Err(error.into()),
+            None => Ok(()),
+        }
     }
 
-    fn _generate_line_vertices(
-        &self,
-        positions: &Vec<[f32; 3]>,
-        thickness: f32,
-    ) -> Vec<LineVertex> {
-        assert!(positions.len() > 1);
-
-        let mut vertices: Vec<LineVertex> = Vec::with_capacity((positions.len() - 1) * 6);
-        let scaled_thickness: f32 = thickness * self.screen_scale_factor;
+    /// How long the stages of the last frame took, see [`crate::frame_pacing`].
+    pub fn stage_times(&self) -> &StageTimes {
+        &self.stage_times
+    }
 
-        for i in 0..positions.len() - 1 {
-            let position = positions[i];
+    // starts or stops recording to match the game state, see crate::gfx::recording
+    fn update_recording(&mut self, format: Option<RecordingFormat>) {
+        if self.recorder.as_ref().map(FrameRecorder::format) == format {
+            return;
+        }
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish(&self.device);
+        }
+        if let Some(format) = format {
+            if !self.config.usage.contains(wgpu::TextureUsages::COPY_SRC) {
+                log::warn!(
+                    target: logging::GFX,
+                    "the surface can't be copied from, the recording stays empty"
+                );
+            }
+            self.recorder = Some(FrameRecorder::new(format));
+        }
+    }
 
-            let previous_point = if i > 0 {
-                positions[i - 1]
-            } else {
-                [-2.0, -2.0, 0.0]
-            };
+    /// Sets the graphics up again on a new device, after the old one was lost (see
+    /// [`GameError::DeviceLost`]). The glyph atlas is uploaded again from its CPU copy, so
+    /// the text doesn't have to be rasterized again.
+    pub fn recover_from_device_loss(&mut self) -> Result<()> {
+        // a window can only have one surface at a time on some platforms
+        self.surface = None;
+        let mut gfx_state = Self::new(self._window.clone(), self.settings.clone())?;
+        gfx_state.set_ui_scale(self.ui_scale);
+        gfx_state
+            .text_renderer
+            .restore_glyph_cache(&mut self.text_renderer.glyph_cache);
+        *self = gfx_state;
+        log::info!(target: logging::GFX, "recreated the graphics device");
+        Ok(())
+    }
 
-            let next_point = positions[i + 1];
+    /// Adds the CPU memory used by the renderers to the report.
+    pub fn add_memory_usage(&self, report: &mut MemoryReport) {
+        report.add("glyph atlas", self.text_renderer.glyph_cache.heap_size());
+    }
 
-            let next_next_point = if i < positions.len() - 2 {
-                positions[i + 2]
-            } else {
-                [2.0, 2.0, 0.0]
-            };
+    /// How full the glyph atlas is, e.g. for the benchmark's report.
+    pub fn glyph_atlas_stats(&self) -> text::AtlasStats {
+        self.text_renderer.glyph_cache.stats()
+    }
 
-            vertices.push(LineVertex {
-                position,
-                previous_point,
-                next_point,
-                thickness: scaled_thickness,
-                miter_dir: -1.0,
-            });
-            vertices.push(LineVertex {
-                position,
-                previous_point,
-                next_point,
-                thickness: scaled_thickness,
-                miter_dir: 1.0,
-            });
-            vertices.push(LineVertex {
-                position: next_point,
-                previous_point: position,
-                next_point: next_next_point,
-                thickness: scaled_thickness,
-                miter_dir: 1.0,
-            });
-            vertices.push(LineVertex {
-                position: next_point,
-                previous_point: position,
-                next_point: next_next_point,
-                thickness: scaled_thickness,
-                miter_dir: -1.0,
-            });
-            vertices.push(LineVertex {
-                position: next_point,
-                previous_point: position,
-                next_point: next_next_point,
-                thickness: scaled_thickness,
-                miter_dir: 1.0,
-            });
-            vertices.push(LineVertex {
-                position,
-                previous_point,
-                next_point,
-                thickness: scaled_thickness,
-                miter_dir: -1.0,
-            });
-        }
+    /// The frame captured during the last `render` while `GameState::bug_report_requested`
+    /// was set. None if the surface can't be copied from.
+    pub fn take_screenshot(&mut self) -> Option<Screenshot> {
+        self.screenshot.take()
+    }
+}
 
-        vertices
+/// Runs `create` in validation and out-of-memory error scopes. What it created is only
+/// usable if this is Ok, otherwise the error is returned (as a [`GameError::DeviceError`])
+/// instead of going to the device's uncaptured error handler.
+pub(crate) fn with_error_scopes<T>(
+    device: &wgpu::Device,
+    create: impl FnOnce() -> T,
+) -> Result<T, GameError> {
+    device.push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let created = create();
+    match pop_error_scopes(device).block_on() {
+        Some(error) => Err(GameError::DeviceError(error.to_string())),
+        None => Ok(created),
     }
 }
 
+// pops the validation and the out-of-memory scope pushed by with_error_scopes (in that
+// order), the first error of either
+async fn pop_error_scopes(device: &wgpu::Device) -> Option<wgpu::Error> {
+    let validation = device.pop_error_scope().await;
+    let out_of_memory = device.pop_error_scope().await;
+    validation.or(out_of_memory)
+}
+
 // OPENGL CORDINATES ARE CENTERED AND GO FROM -1.0 TO +1.0
 // !!!!! THIS MEANS THAT THE LOGICAL WIDTH AND HEIGHT OF THE SCREEN IS *** 2 *** !!!!!
 // DON'T FORGET THIS *AGAIN*
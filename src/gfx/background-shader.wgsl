@@ -0,0 +1,44 @@
+// A background layer: its tile repeated over the whole surface (the sampler wraps), tinted
+// with the layer's color
+
+struct Layer {
+    // texture coordinates in the middle of the surface, and from there to its edges
+    offset: vec2<f32>,
+    scale: vec2<f32>,
+    color: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> layer: Layer;
+@group(0) @binding(1)
+var t_tile: texture_2d<f32>;
+@group(0) @binding(2)
+var s_tile: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    // a triangle that covers the surface
+    let clip = vec2<f32>(f32(vertex_index % 2u) * 4.0 - 1.0, f32(vertex_index / 2u) * 4.0 - 1.0);
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(clip, 0.0, 1.0);
+    // y is up in the world and down in the texture
+    let position = layer.offset + clip * layer.scale;
+    out.uv = vec2<f32>(position.x, -position.y);
+    return out;
+}
+
+@fragment
+fn fs_main(
+    in: VertexOutput,
+) -> @location(0) vec4<f32> {
+    let coverage = textureSample(t_tile, s_tile, in.uv).r;
+    return vec4<f32>(layer.color.rgb, layer.color.a * coverage);
+}
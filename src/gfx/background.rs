@@ -0,0 +1,508 @@
+//! The scrolling background behind the world: layers of tiled patterns (nebulae, dust),
+//! repeated across the surface by wrapping the texture coordinates. Each layer follows the
+//! camera's shake and punch (see crate::camera) by its own scroll factor, so the far layers
+//! barely move while the near ones move almost with the world, and drifts slowly on its own.
+//!
+//! The tiles are generated on start, there are no image files to load them from. The layers
+//! are part of the graphics settings (`[[graphics.background]]` in the settings file, see
+//! crate::config).
+
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use wgpu::util::DeviceExt;
+
+use super::gpu_cache::GpuCache;
+use super::render_graph::{FrameContext, PassDescriptor, PassTarget, RenderNode, mark_upload};
+use crate::world::{WORLD_HEIGHT, WORLD_WIDTH};
+
+// the pixels of a tile's side
+const TILE_PIXELS: u32 = 256;
+// what the frame starts from, under the layers
+const CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 1.0,
+    g: 1.0,
+    b: 1.0,
+    a: 1.0,
+};
+
+/// What a background layer's tile looks like.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackgroundPattern {
+    /// Soft clouds
+    Nebula,
+    /// Scattered specks
+    Dust,
+}
+
+/// A layer of the background, see [`crate::gfx::background`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackgroundLayer {
+    pub pattern: BackgroundPattern,
+    /// The pattern's color, its alpha how strongly it covers what's below
+    pub color: [f32; 4],
+    /// How much the layer follows the camera, 0 for not at all (infinitely far) and 1 for
+    /// as much as the world
+    pub scroll_factor: f32,
+    /// How fast the layer moves on its own, in world units per second
+    pub drift: [f32; 2],
+    /// How big a tile is, in world units
+    pub tile_size: f32,
+    /// Picks another tile of the same pattern
+    pub seed: u32,
+}
+
+impl Default for BackgroundLayer {
+    fn default() -> Self {
+        Self::nebula()
+    }
+}
+
+impl BackgroundLayer {
+    pub fn nebula() -> Self {
+        Self {
+            pattern: BackgroundPattern::Nebula,
+            color: [0.45, 0.35, 0.8, 0.12],
+            scroll_factor: 0.05,
+            drift: [1.5, 0.5],
+            tile_size: 1200.0,
+            seed: 1,
+        }
+    }
+
+    pub fn dust() -> Self {
+        Self {
+            pattern: BackgroundPattern::Dust,
+            color: [0.4, 0.4, 0.45, 0.35],
+            scroll_factor: 0.4,
+            drift: [4.0, -1.5],
+            tile_size: 300.0,
+            seed: 3,
+        }
+    }
+
+    /// The layers the game starts with, back to front
+    pub fn defaults() -> Vec<BackgroundLayer> {
+        vec![
+            Self::nebula(),
+            BackgroundLayer {
+                color: [0.2, 0.55, 0.7, 0.08],
+                scroll_factor: 0.15,
+                drift: [-2.0, 1.0],
+                tile_size: 800.0,
+                seed: 2,
+                ..Self::nebula()
+            },
+            Self::dust(),
+        ]
+    }
+
+    // the tile's coverage, a byte per pixel. Both patterns wrap around at the edges.
+    fn tile(&self) -> Vec<u8> {
+        match self.pattern {
+            BackgroundPattern::Nebula => nebula_tile(self.seed),
+            BackgroundPattern::Dust => dust_tile(self.seed),
+        }
+    }
+}
+
+// integer hash of a lattice point, for the patterns
+fn hash(x: u32, y: u32, seed: u32) -> u32 {
+    let mut h =
+        x.wrapping_mul(0x8da6_b343) ^ y.wrapping_mul(0xd816_3841) ^ seed.wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^ (h >> 15)
+}
+
+// between 0 and 1
+fn unit(h: u32) -> f32 {
+    (h >> 8) as f32 / (1 << 24) as f32
+}
+
+// a few octaves of value noise on lattices that divide the tile, so it tiles
+fn nebula_tile(seed: u32) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity((TILE_PIXELS * TILE_PIXELS) as usize);
+    for y in 0..TILE_PIXELS {
+        for x in 0..TILE_PIXELS {
+            let mut value = 0.0;
+            let mut amplitude = 0.5;
+            let mut total = 0.0;
+            for octave in 0..5 {
+                let cells = 4 << octave;
+                value += amplitude * value_noise(x, y, cells, seed.wrapping_add(octave));
+                total += amplitude;
+                amplitude *= 0.5;
+            }
+            // patches of cloud with clear sky between them
+            let t = ((value / total - 0.4) / 0.4).clamp(0.0, 1.0);
+            pixels.push((t * t * (3.0 - 2.0 * t) * 255.0) as u8);
+        }
+    }
+    pixels
+}
+
+// smoothly interpolated noise with `cells` lattice cells across the tile
+fn value_noise(x: u32, y: u32, cells: u32, seed: u32) -> f32 {
+    let cell_pixels = TILE_PIXELS as f32 / cells as f32;
+    let fx = x as f32 / cell_pixels;
+    let fy = y as f32 / cell_pixels;
+    let (x0, y0) = (fx as u32 % cells, fy as u32 % cells);
+    let (x1, y1) = ((x0 + 1) % cells, (y0 + 1) % cells);
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, ty) = (smooth(fx.fract()), smooth(fy.fract()));
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let top = lerp(unit(hash(x0, y0, seed)), unit(hash(x1, y0, seed)), tx);
+    let bottom = lerp(unit(hash(x0, y1, seed)), unit(hash(x1, y1, seed)), tx);
+    lerp(top, bottom, ty)
+}
+
+// round specks of a few pixels, some brighter than others
+fn dust_tile(seed: u32) -> Vec<u8> {
+    const SPECKS: u32 = 120;
+    let size = TILE_PIXELS as i32;
+    let mut pixels = vec![0u8; (TILE_PIXELS * TILE_PIXELS) as usize];
+    for speck in 0..SPECKS {
+        let h = hash(speck, 0, seed);
+        let center_x = unit(h) * size as f32;
+        let center_y = unit(hash(speck, 1, seed)) * size as f32;
+        let radius = 0.8 + 1.7 * unit(hash(speck, 2, seed));
+        let brightness = 0.4 + 0.6 * unit(hash(speck, 3, seed));
+        let reach = radius.ceil() as i32 + 1;
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let x = center_x as i32 + dx;
+                let y = center_y as i32 + dy;
+                let distance = ((x as f32 + 0.5 - center_x).powi(2)
+                    + (y as f32 + 0.5 - center_y).powi(2))
+                .sqrt();
+                let coverage = (radius - distance + 0.5).clamp(0.0, 1.0) * brightness;
+                let pixel = &mut pixels[(y.rem_euclid(size) * size + x.rem_euclid(size)) as usize];
+                *pixel = (*pixel).max((coverage * 255.0) as u8);
+            }
+        }
+    }
+    pixels
+}
+
+// the uniforms of a layer, see background-shader.wgsl
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct LayerUniforms {
+    // the texture coordinates in the middle of the surface
+    offset: [f32; 2],
+    // texture coordinates from the middle to the edges of the surface
+    scale: [f32; 2],
+    color: [f32; 4],
+}
+
+struct GpuLayer {
+    layer: BackgroundLayer,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Draws the background layers, see [`crate::gfx::background`]. This is the first pass of
+/// the frame, so it also clears the scene.
+pub struct BackgroundRenderer {
+    render_pipeline: wgpu::RenderPipeline,
+    pipeline_layout: wgpu::PipelineLayout,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    layers: Vec<GpuLayer>,
+    started: Instant,
+}
+
+impl BackgroundRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        gpu_cache: &GpuCache,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        layers: &[BackgroundLayer],
+    ) -> Self {
+        let bind_group_layout = gpu_cache.bind_group_layout(
+            device,
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("background_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            },
+        );
+
+        // the tiles repeat across the surface
+        let sampler = gpu_cache.sampler(
+            device,
+            &wgpu::SamplerDescriptor {
+                label: Some("background_sampler"),
+                address_mode_u: wgpu::AddressMode::Repeat,
+                address_mode_v: wgpu::AddressMode::Repeat,
+                address_mode_w: wgpu::AddressMode::Repeat,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            },
+        );
+
+        let pipeline_layout = gpu_cache.pipeline_layout(
+            device,
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Background pipeline layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            },
+        );
+
+        let render_pipeline = Self::create_pipeline(device, &pipeline_layout, format, sample_count);
+
+        let mut renderer = Self {
+            render_pipeline,
+            pipeline_layout,
+            bind_group_layout,
+            sampler,
+            layers: Vec::new(),
+            started: Instant::now(),
+        };
+        renderer.set_layers(device, queue, layers);
+        renderer
+    }
+
+    /// Recreates the pipeline, e.g. when the MSAA sample count changes.
+    pub fn rebuild_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        self.render_pipeline =
+            Self::create_pipeline(device, &self.pipeline_layout, format, sample_count);
+    }
+
+    /// Replaces the layers, generating the tiles of the new patterns.
+    pub fn set_layers(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layers: &[BackgroundLayer],
+    ) {
+        let mut old_layers = std::mem::take(&mut self.layers);
+        for layer in layers {
+            // the same tile keeps its texture
+            let reused = old_layers
+                .iter()
+                .position(|old| old.layer.pattern == layer.pattern && old.layer.seed == layer.seed);
+            let gpu_layer = match reused {
+                Some(index) => GpuLayer {
+                    layer: *layer,
+                    ..old_layers.swap_remove(index)
+                },
+                None => self.create_layer(device, queue, layer),
+            };
+            self.layers.push(gpu_layer);
+        }
+    }
+
+    fn create_layer(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layer: &BackgroundLayer,
+    ) -> GpuLayer {
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("background_tile_texture"),
+                size: wgpu::Extent3d {
+                    width: TILE_PIXELS,
+                    height: TILE_PIXELS,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::R8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            &layer.tile(),
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("background_uniform_buffer"),
+            size: std::mem::size_of::<LayerUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("background_bind_group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+        GpuLayer {
+            layer: *layer,
+            uniform_buffer,
+            bind_group,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        pipeline_layout: &wgpu::PipelineLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Background shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("background-shader.wgsl").into()),
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Background pipeline"),
+            layout: Some(pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                // a fullscreen triangle, from the vertex index
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+                polygon_mode: wgpu::PolygonMode::Fill,
+                // Requires Features::DEPTH_CLIP_CONTROL
+                unclipped_depth: false,
+                // Requires Features::CONSERVATIVE_RASTERIZATION
+                conservative: false,
+            },
+            depth_stencil: None,
+            // the line passes after this one load what it drew into the multisampled target
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    // where the layer's tile is for this frame
+    fn uniforms(layer: &BackgroundLayer, ctx: &FrameContext, seconds: f64) -> LayerUniforms {
+        let width = ctx.surface_width as f32;
+        let height = ctx.surface_height as f32;
+        let camera = ctx.game_state.camera();
+        let factor = layer.scroll_factor;
+        // a far layer follows the punch less, like it follows the shake less
+        let zoom = 1.0 + (camera.zoom() - 1.0) * factor;
+        let scale = (width / WORLD_WIDTH).min(height / WORLD_HEIGHT) * zoom;
+        let [shake_x, shake_y] = camera.shake_offset();
+        let tile_size = layer.tile_size.max(1.0);
+        // wrapped in double precision, so it doesn't lose precision in a long session
+        let drift = layer
+            .drift
+            .map(|speed| (speed as f64 * seconds).rem_euclid(tile_size as f64) as f32);
+        let center = [
+            (WORLD_WIDTH / 2.0 - shake_x) * factor + drift[0],
+            (WORLD_HEIGHT / 2.0 - shake_y) * factor + drift[1],
+        ];
+        LayerUniforms {
+            offset: center.map(|c| (c / tile_size).rem_euclid(1.0)),
+            scale: [
+                width / 2.0 / scale / tile_size,
+                height / 2.0 / scale / tile_size,
+            ],
+            color: layer.color,
+        }
+    }
+}
+
+impl RenderNode for BackgroundRenderer {
+    fn pass_descriptor(&self) -> PassDescriptor {
+        PassDescriptor {
+            label: "Background render pass",
+            target: PassTarget::Scene,
+            // we clear the screen
+            load: wgpu::LoadOp::Clear(CLEAR_COLOR),
+            multisampled: true,
+            depth: false,
+        }
+    }
+
+    fn record(&mut self, render_pass: &mut wgpu::RenderPass<'_>, ctx: &FrameContext) {
+        if self.layers.is_empty() {
+            return;
+        }
+        let seconds = self.started.elapsed().as_secs_f64();
+        render_pass.set_pipeline(&self.render_pipeline);
+        for gpu_layer in &self.layers {
+            let uniforms = Self::uniforms(&gpu_layer.layer, ctx, seconds);
+            ctx.queue
+                .write_buffer(&gpu_layer.uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
+            mark_upload(
+                render_pass,
+                "background_uniform_buffer",
+                std::mem::size_of_val(&uniforms),
+            );
+            render_pass.set_bind_group(0, &gpu_layer.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+}
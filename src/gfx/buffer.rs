@@ -0,0 +1,79 @@
+//! GPU buffers for data that is uploaded every frame and can be any size (glyphs, lines,
+//! shapes). Instead of a fixed capacity that silently cuts off whatever doesn't fit, a
+//! [`GrowableBuffer`] is recreated with headroom when a frame's data doesn't fit anymore, and
+//! keeps that size for the frames after.
+
+use crate::logging;
+
+/// Never smaller than this, in bytes
+const MIN_SIZE: wgpu::BufferAddress = 4096;
+
+pub struct GrowableBuffer {
+    buffer: wgpu::Buffer,
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+}
+
+impl GrowableBuffer {
+    /// A buffer of `size` bytes to start with. `usage` gets COPY_DST added.
+    pub fn new(
+        device: &wgpu::Device,
+        label: &'static str,
+        usage: wgpu::BufferUsages,
+        size: wgpu::BufferAddress,
+    ) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        Self {
+            buffer: Self::create(device, label, usage, size),
+            label,
+            usage,
+        }
+    }
+
+    fn create(
+        device: &wgpu::Device,
+        label: &'static str,
+        usage: wgpu::BufferUsages,
+        size: wgpu::BufferAddress,
+    ) -> wgpu::Buffer {
+        // buffer writes go in multiples of 4 bytes
+        let size = size
+            .max(MIN_SIZE)
+            .next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT);
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Uploads `data` to the start of the buffer, first growing it to half as large again as
+    /// the data if it doesn't fit. The data's size must be a multiple of 4 bytes.
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[u8]) {
+        let len = data.len() as wgpu::BufferAddress;
+        if len > self.buffer.size() {
+            let size = len + len / 2;
+            log::debug!(
+                target: logging::GFX,
+                "growing {} from {} to {} bytes",
+                self.label,
+                self.buffer.size(),
+                size
+            );
+            self.buffer = Self::create(device, self.label, self.usage, size);
+        }
+        if !data.is_empty() {
+            queue.write_buffer(&self.buffer, 0, data);
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        self.label
+    }
+
+    /// The first `len` bytes, e.g. what the last write filled.
+    pub fn slice(&self, len: usize) -> wgpu::BufferSlice<'_> {
+        self.buffer.slice(..len as wgpu::BufferAddress)
+    }
+}
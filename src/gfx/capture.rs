@@ -0,0 +1,175 @@
+use std::sync::mpsc::{self, Receiver};
+
+use anyhow::{Result, anyhow};
+
+use crate::logging;
+
+/// An image read back from the GPU: 8 bit RGBA, rows top to bottom.
+#[derive(Debug, Clone)]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Screenshot {
+    pub fn to_png(&self) -> Vec<u8> {
+        crate::file_formats::encode_png(self.width, self.height, &self.rgba)
+    }
+
+    /// Shrunk by a whole factor (averaging the pixels) until it's at most `max_width` wide.
+    pub fn downscaled(self, max_width: u32) -> Self {
+        let factor = self.width.div_ceil(max_width.max(1));
+        if factor <= 1 {
+            return self;
+        }
+        let (width, height) = (self.width / factor, self.height / factor);
+        let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0u32; 4];
+                for source_y in y * factor..(y + 1) * factor {
+                    let row = (source_y * self.width) as usize;
+                    for source_x in x * factor..(x + 1) * factor {
+                        let pixel = (row + source_x as usize) * 4;
+                        for (channel, total) in sum.iter_mut().enumerate() {
+                            *total += self.rgba[pixel + channel] as u32;
+                        }
+                    }
+                }
+                rgba.extend(sum.map(|total| (total / (factor * factor)) as u8));
+            }
+        }
+        Self {
+            width,
+            height,
+            rgba,
+        }
+    }
+}
+
+/// A copy of a frame into a buffer the CPU can read, recorded into the frame's encoder.
+pub(crate) struct FrameCapture {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    bgra: bool,
+}
+
+impl FrameCapture {
+    /// Records a copy of `texture` (which needs TextureUsages::COPY_SRC). None if the
+    /// texture's format can't be converted to RGBA8.
+    pub fn record(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+    ) -> Option<Self> {
+        let bgra = match texture.format() {
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            format => {
+                log::warn!(target: logging::GFX, "can't capture frames in format {:?}", format);
+                return None;
+            }
+        };
+        let (width, height) = (texture.width(), texture.height());
+        // rows of a texture copy have to be aligned to 256 bytes
+        let padded_bytes_per_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frame_capture_buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+
+        Some(Self {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            bgra,
+        })
+    }
+
+    /// Waits for the copy (the encoder must have been submitted) and converts it.
+    pub fn read(self, device: &wgpu::Device) -> Result<Screenshot> {
+        let mapped = self.map();
+        device.poll(wgpu::PollType::wait_indefinitely())?;
+        mapped.recv()??;
+        self.take_mapped().convert()
+    }
+
+    /// Starts mapping the buffer for reading (the encoder must have been submitted). The
+    /// receiver gets the result once the device is polled after the copy is done.
+    pub fn map(&self) -> Receiver<Result<(), wgpu::BufferAsyncError>> {
+        let (sender, receiver) = mpsc::channel();
+        self.buffer
+            .map_async(wgpu::MapMode::Read, .., move |result| {
+                let _ = sender.send(result);
+            });
+        receiver
+    }
+
+    /// Copies the mapped buffer out and unmaps it, so converting it can be done elsewhere
+    /// (see [`MappedFrame::convert`]).
+    pub fn take_mapped(self) -> MappedFrame {
+        let data = self.buffer.get_mapped_range(..).to_vec();
+        self.buffer.unmap();
+        MappedFrame {
+            data,
+            width: self.width,
+            height: self.height,
+            padded_bytes_per_row: self.padded_bytes_per_row,
+            bgra: self.bgra,
+        }
+    }
+}
+
+/// A frame copied out of a [`FrameCapture`]'s buffer, as the GPU wrote it.
+pub(crate) struct MappedFrame {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    bgra: bool,
+}
+
+impl MappedFrame {
+    pub fn convert(self) -> Result<Screenshot> {
+        let mut rgba = Vec::with_capacity((self.width * self.height * 4) as usize);
+        for row in self.data.chunks_exact(self.padded_bytes_per_row as usize) {
+            for pixel in row[..(self.width * 4) as usize].chunks_exact(4) {
+                let [r, g, b] = if self.bgra {
+                    [pixel[2], pixel[1], pixel[0]]
+                } else {
+                    [pixel[0], pixel[1], pixel[2]]
+                };
+                // the surface's alpha isn't meaningful (it's composited as opaque)
+                rgba.extend_from_slice(&[r, g, b, 255]);
+            }
+        }
+
+        if rgba.is_empty() {
+            return Err(anyhow!("captured an empty frame"));
+        }
+        Ok(Screenshot {
+            width: self.width,
+            height: self.height,
+            rgba,
+        })
+    }
+}
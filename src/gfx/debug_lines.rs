@@ -0,0 +1,173 @@
+use super::buffer::GrowableBuffer;
+use super::render_graph::{FrameContext, PassDescriptor, PassTarget, RenderNode, mark_upload};
+use super::vertex::ColorVertex;
+
+// to start with, the buffer grows to fit
+const INITIAL_VERTICES: usize = 8192;
+// the lines that aren't the world's
+const DEBUG_COLOR: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+
+/// Draws single pixel debug lines (LineList topology) in clip space, and the multiplayer
+/// arena's in the palette's world color (see crate::palette), over the background.
+pub struct DebugLineRenderer {
+    pub(super) render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: GrowableBuffer,
+}
+
+impl DebugLineRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        surface_dimensions_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        shader_source: &str,
+    ) -> Self {
+        let render_pipeline = Self::create_pipeline(
+            device,
+            surface_dimensions_bind_group_layout,
+            format,
+            sample_count,
+            shader_source,
+        );
+
+        let vertex_buffer = GrowableBuffer::new(
+            device,
+            "debug_line_vertex_buffer",
+            wgpu::BufferUsages::VERTEX,
+            (INITIAL_VERTICES * std::mem::size_of::<ColorVertex>()) as wgpu::BufferAddress,
+        );
+
+        Self {
+            render_pipeline,
+            vertex_buffer,
+        }
+    }
+
+    /// Recreates the pipeline, e.g. when the MSAA sample count changes.
+    pub fn rebuild_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        surface_dimensions_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        shader_source: &str,
+    ) {
+        self.render_pipeline = Self::create_pipeline(
+            device,
+            surface_dimensions_bind_group_layout,
+            format,
+            sample_count,
+            shader_source,
+        );
+    }
+
+    pub(super) fn create_pipeline(
+        device: &wgpu::Device,
+        surface_dimensions_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        shader_source: &str,
+    ) -> wgpu::RenderPipeline {
+        let debug_line_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Debug line shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+
+        let debug_line_render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Debug line renderer pipeline layout"),
+                bind_group_layouts: &[surface_dimensions_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Debug line renderer pipeline"),
+            layout: Some(&debug_line_render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &debug_line_shader,
+                entry_point: Some("vs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[ColorVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &debug_line_shader,
+                entry_point: Some("fs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+                polygon_mode: wgpu::PolygonMode::Fill,
+                // Requires Features::DEPTH_CLIP_CONTROL
+                unclipped_depth: false,
+                // Requires Features::CONSERVATIVE_RASTERIZATION
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+}
+
+impl RenderNode for DebugLineRenderer {
+    fn pass_descriptor(&self) -> PassDescriptor {
+        PassDescriptor {
+            label: "Debug line render pass",
+            target: PassTarget::Scene,
+            // over the background
+            load: wgpu::LoadOp::Load,
+            multisampled: true,
+            depth: false,
+        }
+    }
+
+    fn record(&mut self, render_pass: &mut wgpu::RenderPass<'_>, ctx: &FrameContext) {
+        let y = 1.0
+            - super::logical_px_to_screen_surface_offset(
+                257,
+                ctx.surface_height,
+                ctx.screen_scale_factor,
+            );
+        let mut line_vertices: Vec<ColorVertex> = [[-1.0, y], [1.0, y]]
+            .map(|position| ColorVertex {
+                position,
+                color: DEBUG_COLOR,
+            })
+            .to_vec();
+
+        // the multiplayer arena while there's one, the world is drawn instanced by the
+        // outline renderer
+        let segments = match ctx.game_state.net_client() {
+            Some(client) => client.line_segments(),
+            None => Vec::new(),
+        };
+        let color = ctx.game_state.palette().world;
+        line_vertices.extend(segments.into_iter().map(|point| ColorVertex {
+            position: ctx.world_to_clip(point),
+            color,
+        }));
+
+        let data: &[u8] = bytemuck::cast_slice(&line_vertices);
+        self.vertex_buffer.write(ctx.device, ctx.queue, data);
+        mark_upload(render_pass, self.vertex_buffer.label(), data.len());
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, ctx.surface_dimensions_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(data.len()));
+        render_pass.draw(0..line_vertices.len() as u32, 0..1);
+    }
+}
@@ -0,0 +1,113 @@
+//! GPU objects that renderers would otherwise each create for themselves, although they're
+//! the same: bind group layouts, samplers and pipeline layouts. A [`GpuCache`] hands out the
+//! object created for an equal descriptor before (wgpu's objects are cheap handles to clone),
+//! so e.g. every renderer sampling a texture the same way shares one sampler.
+//!
+//! Descriptors are compared without their labels, the first object's label is the one the
+//! debugging tools show. Render pipelines stay with their renderers: they're rebuilt when the
+//! settings change, and a shared cache would keep the old ones alive.
+
+use std::collections::HashMap;
+
+use parking_lot::Mutex;
+
+// a sampler descriptor without its label, and with the lod clamps as bits so it can be hashed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct SamplerKey {
+    address_modes: [wgpu::AddressMode; 3],
+    filters: [wgpu::FilterMode; 3],
+    lod_clamp: [u32; 2],
+    compare: Option<wgpu::CompareFunction>,
+    anisotropy_clamp: u16,
+    border_color: Option<wgpu::SamplerBorderColor>,
+}
+
+impl SamplerKey {
+    fn new(descriptor: &wgpu::SamplerDescriptor) -> Self {
+        Self {
+            address_modes: [
+                descriptor.address_mode_u,
+                descriptor.address_mode_v,
+                descriptor.address_mode_w,
+            ],
+            filters: [
+                descriptor.mag_filter,
+                descriptor.min_filter,
+                descriptor.mipmap_filter,
+            ],
+            lod_clamp: [
+                descriptor.lod_min_clamp.to_bits(),
+                descriptor.lod_max_clamp.to_bits(),
+            ],
+            compare: descriptor.compare,
+            anisotropy_clamp: descriptor.anisotropy_clamp,
+            border_color: descriptor.border_color,
+        }
+    }
+}
+
+// bind group layouts compare by identity, so equal layouts have to come from the cache too
+// for their pipeline layouts to be shared
+type PipelineLayoutKey = (Vec<wgpu::BindGroupLayout>, Vec<wgpu::PushConstantRange>);
+
+/// See [`crate::gfx::gpu_cache`]. Shared by reference, it locks itself.
+#[derive(Default)]
+pub struct GpuCache {
+    bind_group_layouts: Mutex<HashMap<Vec<wgpu::BindGroupLayoutEntry>, wgpu::BindGroupLayout>>,
+    samplers: Mutex<HashMap<SamplerKey, wgpu::Sampler>>,
+    pipeline_layouts: Mutex<HashMap<PipelineLayoutKey, wgpu::PipelineLayout>>,
+}
+
+impl GpuCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A bind group layout with the descriptor's entries, created the first time.
+    pub fn bind_group_layout(
+        &self,
+        device: &wgpu::Device,
+        descriptor: &wgpu::BindGroupLayoutDescriptor,
+    ) -> wgpu::BindGroupLayout {
+        self.bind_group_layouts
+            .lock()
+            .entry(descriptor.entries.to_vec())
+            .or_insert_with(|| device.create_bind_group_layout(descriptor))
+            .clone()
+    }
+
+    /// A sampler like the descriptor, created the first time.
+    pub fn sampler(
+        &self,
+        device: &wgpu::Device,
+        descriptor: &wgpu::SamplerDescriptor,
+    ) -> wgpu::Sampler {
+        self.samplers
+            .lock()
+            .entry(SamplerKey::new(descriptor))
+            .or_insert_with(|| device.create_sampler(descriptor))
+            .clone()
+    }
+
+    /// A pipeline layout with the descriptor's bind group layouts and push constants, created
+    /// the first time.
+    pub fn pipeline_layout(
+        &self,
+        device: &wgpu::Device,
+        descriptor: &wgpu::PipelineLayoutDescriptor,
+    ) -> wgpu::PipelineLayout {
+        let key = (
+            descriptor
+                .bind_group_layouts
+                .iter()
+                .map(|&layout| layout.clone())
+                .collect(),
+            descriptor.push_constant_ranges.to_vec(),
+        );
+        self.pipeline_layouts
+            .lock()
+            .entry(key)
+            .or_insert_with(|| device.create_pipeline_layout(descriptor))
+            .clone()
+    }
+}
@@ -0,0 +1,147 @@
+use super::render_graph::{FrameContext, PassDescriptor, PassTarget, RenderNode, mark_upload};
+use super::vertex::ColorVertex;
+use crate::palette::Palette;
+
+/// Draws the session's HeatMap as translucent colored cells on top of the scene (if enabled
+/// with GameState::show_heat_map).
+pub struct HeatMapRenderer {
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    max_vertices: usize,
+}
+
+// cold (mostly transparent) -> warm -> hot (more opaque), in the palette's colors
+fn heat_color(heat: f32, palette: &Palette) -> [f32; 4] {
+    let alpha = 0.15 + 0.45 * heat;
+    let [cold, warm, hot] = palette.heat;
+    let (from, to, t) = if heat < 0.5 {
+        (cold, warm, heat * 2.0)
+    } else {
+        (warm, hot, (heat - 0.5) * 2.0)
+    };
+    let [r, g, b] = std::array::from_fn(|i| from[i] + (to[i] - from[i]) * t);
+    [r, g, b, alpha]
+}
+
+impl HeatMapRenderer {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, cell_count: usize) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Color shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("color-shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Heat map pipeline layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Heat map pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[ColorVertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+                polygon_mode: wgpu::PolygonMode::Fill,
+                // Requires Features::DEPTH_CLIP_CONTROL
+                unclipped_depth: false,
+                // Requires Features::CONSERVATIVE_RASTERIZATION
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        // two triangles per cell
+        let max_vertices = cell_count * 6;
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("heat_map_vertex_buffer"),
+            size: (max_vertices * std::mem::size_of::<ColorVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            render_pipeline,
+            vertex_buffer,
+            max_vertices,
+        }
+    }
+}
+
+impl RenderNode for HeatMapRenderer {
+    fn pass_descriptor(&self) -> PassDescriptor {
+        PassDescriptor {
+            label: "Heat map render pass",
+            target: PassTarget::Scene,
+            // overlay on top of the scene
+            load: wgpu::LoadOp::Load,
+            multisampled: false,
+            depth: false,
+        }
+    }
+
+    fn record(&mut self, render_pass: &mut wgpu::RenderPass<'_>, ctx: &FrameContext) {
+        if !ctx.game_state.show_heat_map {
+            return;
+        }
+
+        let mut vertices: Vec<ColorVertex> = Vec::new();
+        for ([x, y], size, heat) in ctx.game_state.heat_map().hot_cells() {
+            let color = heat_color(heat, ctx.game_state.palette());
+            let [left, bottom] = ctx.world_to_clip([x, y]);
+            let [right, top] = ctx.world_to_clip([x + size, y + size]);
+            for position in [
+                [left, bottom],
+                [right, bottom],
+                [right, top],
+                [left, bottom],
+                [right, top],
+                [left, top],
+            ] {
+                vertices.push(ColorVertex { position, color });
+            }
+        }
+        vertices.truncate(self.max_vertices);
+        if vertices.is_empty() {
+            return;
+        }
+
+        ctx.queue
+            .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        mark_upload(
+            render_pass,
+            "heat_map_vertex_buffer",
+            std::mem::size_of_val(vertices.as_slice()),
+        );
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..vertices.len() as u32, 0..1);
+    }
+}
@@ -0,0 +1,632 @@
+//! Thick lines: polylines with joins, caps, dashes and a color per point, submitted through
+//! [`crate::GameState::draw_polyline`] and drawn every frame until the next update. They are
+//! tessellated into triangles on the CPU, so sharp corners fall back to a bevel instead of
+//! shooting off to infinity like a plain miter does, and drawn with MSAA like the debug lines.
+//!
+//! Points are in physical pixels from the top left of the window, or in world units (see
+//! [`LineSpace`]). Widths and dash lengths are in logical pixels, like text sizes.
+
+use std::f32::consts::PI;
+
+use super::buffer::GrowableBuffer;
+use super::render_graph::{
+    FrameContext, Layer, PassDescriptor, PassTarget, RenderNode, layered_depth_stencil, mark_upload,
+};
+use super::vertex::LineVertex;
+
+// to start with, the buffer grows to fit
+const INITIAL_VERTICES: usize = 4096;
+// round joins and caps get this many triangles per half turn
+const ROUND_SEGMENTS: f32 = 16.0;
+
+/// How the segments of a polyline are connected where it turns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineJoin {
+    /// Sharp corners, falling back to a bevel where the miter would be longer than `limit`
+    /// times the line's width (half way to infinity at a hairpin turn)
+    Miter {
+        limit: f32,
+    },
+    /// Corners cut off flat
+    Bevel,
+    Round,
+}
+
+impl Default for LineJoin {
+    fn default() -> Self {
+        LineJoin::Miter { limit: 4.0 }
+    }
+}
+
+/// How the ends of a polyline (and of every dash) look.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineCap {
+    /// Square, at the end point
+    #[default]
+    Butt,
+    /// Square, half the width past the end point
+    Square,
+    Round,
+}
+
+/// What a polyline's points are measured in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineSpace {
+    /// Physical pixels from the top left of the window
+    #[default]
+    Screen,
+    /// World units, scaled and centered like the world (see `FrameContext::world_to_clip`)
+    World,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineStyle {
+    /// In logical pixels
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// Lengths (in logical pixels) of the dashes and the gaps between them, alternating and
+    /// repeated along the line. Empty for a solid line.
+    pub dashes: Vec<f32>,
+    /// How far into the pattern the line starts, e.g. to animate marching ants
+    pub dash_offset: f32,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        Self {
+            width: 2.0,
+            join: LineJoin::default(),
+            cap: LineCap::default(),
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+}
+
+/// A line through a list of points, with the color blended between them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<[f32; 2]>,
+    /// One per point. A polyline with fewer colors uses its last color for the rest of the
+    /// points (so a single color colors the whole line).
+    pub colors: Vec<[f32; 4]>,
+    pub style: LineStyle,
+    pub space: LineSpace,
+    /// Connects the last point back to the first, with a join instead of caps
+    pub closed: bool,
+    /// World by default
+    pub layer: Layer,
+}
+
+impl Polyline {
+    pub fn new(points: Vec<[f32; 2]>, color: [f32; 4]) -> Self {
+        Self {
+            points,
+            colors: vec![color],
+            style: LineStyle::default(),
+            space: LineSpace::default(),
+            closed: false,
+            layer: Layer::World,
+        }
+    }
+
+    /// A color per point, see [`Polyline::colors`].
+    pub fn colors(mut self, colors: Vec<[f32; 4]>) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    pub fn width(mut self, width: f32) -> Self {
+        self.style.width = width;
+        self
+    }
+
+    pub fn join(mut self, join: LineJoin) -> Self {
+        self.style.join = join;
+        self
+    }
+
+    pub fn cap(mut self, cap: LineCap) -> Self {
+        self.style.cap = cap;
+        self
+    }
+
+    /// See [`LineStyle::dashes`].
+    pub fn dashed(mut self, dashes: Vec<f32>, offset: f32) -> Self {
+        self.style.dashes = dashes;
+        self.style.dash_offset = offset;
+        self
+    }
+
+    pub fn space(mut self, space: LineSpace) -> Self {
+        self.space = space;
+        self
+    }
+
+    pub fn closed(mut self) -> Self {
+        self.closed = true;
+        self
+    }
+
+    pub fn layer(mut self, layer: Layer) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    fn color(&self, i: usize) -> [f32; 4] {
+        self.colors
+            .get(i)
+            .or(self.colors.last())
+            .copied()
+            .unwrap_or([1.0; 4])
+    }
+}
+
+type Vec2 = [f32; 2];
+
+fn add(a: Vec2, b: Vec2) -> Vec2 {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn sub(a: Vec2, b: Vec2) -> Vec2 {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn scale(a: Vec2, s: f32) -> Vec2 {
+    [a[0] * s, a[1] * s]
+}
+
+fn dot(a: Vec2, b: Vec2) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+fn cross(a: Vec2, b: Vec2) -> f32 {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+fn length(a: Vec2) -> f32 {
+    dot(a, a).sqrt()
+}
+
+fn normal(direction: Vec2) -> Vec2 {
+    [-direction[1], direction[0]]
+}
+
+fn lerp_color(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+/// Tessellates a polyline (with its points already in physical pixels) into a triangle list.
+/// `scale_factor` converts the style's logical pixels.
+pub fn tessellate(polyline: &Polyline, scale_factor: f32) -> Vec<LineVertex> {
+    let mut points = without_repeats(
+        polyline
+            .points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| (*point, polyline.color(i))),
+    );
+    let closed = polyline.closed && points.len() > 2;
+    if closed {
+        if length(sub(points[0].0, points[points.len() - 1].0)) <= 1e-4 {
+            points.pop();
+        }
+        points.push(points[0]);
+    }
+    let style = &polyline.style;
+    let half_width = style.width * scale_factor / 2.0;
+    let mut vertices = Vec::new();
+    if points.len() < 2 || half_width <= 0.0 {
+        return vertices;
+    }
+
+    let mut dashes: Vec<f32> = style
+        .dashes
+        .iter()
+        .map(|length| length.max(0.0) * scale_factor)
+        .collect();
+    // an odd pattern is repeated, so its dashes alternate with gaps
+    if dashes.len() % 2 == 1 {
+        dashes.extend_from_within(..);
+    }
+    if dashes.iter().sum::<f32>() <= 0.0 {
+        let mut tessellator = Tessellator {
+            vertices: &mut vertices,
+            half_width,
+            style,
+            depth: polyline.layer.depth(),
+        };
+        tessellator.stroke(&points, closed);
+    } else {
+        for dash in split_dashes(&points, &dashes, style.dash_offset * scale_factor) {
+            let mut tessellator = Tessellator {
+                vertices: &mut vertices,
+                half_width,
+                style,
+                depth: polyline.layer.depth(),
+            };
+            tessellator.stroke(&dash, false);
+        }
+    }
+    vertices
+}
+
+// repeated points have no direction
+fn without_repeats(points: impl IntoIterator<Item = (Vec2, [f32; 4])>) -> Vec<(Vec2, [f32; 4])> {
+    let mut unique: Vec<(Vec2, [f32; 4])> = Vec::new();
+    for (point, color) in points {
+        if unique
+            .last()
+            .is_none_or(|(last, _)| length(sub(point, *last)) > 1e-4)
+        {
+            unique.push((point, color));
+        }
+    }
+    unique
+}
+
+// the pieces of the line that are drawn (the dashes), as polylines of their own
+fn split_dashes(
+    points: &[(Vec2, [f32; 4])],
+    dashes: &[f32],
+    offset: f32,
+) -> Vec<Vec<(Vec2, [f32; 4])>> {
+    let period: f32 = dashes.iter().sum();
+    // where in the pattern the line starts: which entry and how much of it is left
+    let mut entry = 0;
+    let mut left = offset.rem_euclid(period);
+    while left >= dashes[entry] && entry + 1 < dashes.len() {
+        left -= dashes[entry];
+        entry += 1;
+    }
+    left = (dashes[entry] - left).max(0.0);
+
+    let mut pieces = Vec::new();
+    let mut current: Vec<(Vec2, [f32; 4])> = Vec::new();
+    // even entries are dashes, odd ones gaps
+    let mut drawing = entry % 2 == 0;
+    if drawing {
+        current.push(points[0]);
+    }
+    for pair in points.windows(2) {
+        let ((start, start_color), (end, end_color)) = (pair[0], pair[1]);
+        let segment_length = length(sub(end, start));
+        let mut position = 0.0;
+        while segment_length - position > left {
+            position += left;
+            let t = position / segment_length;
+            let point = (
+                add(start, scale(sub(end, start), t)),
+                lerp_color(start_color, end_color, t),
+            );
+            // ends a dash or starts the next one
+            current.push(point);
+            if drawing {
+                pieces.push(std::mem::take(&mut current));
+            }
+            drawing = !drawing;
+            entry = (entry + 1) % dashes.len();
+            left = dashes[entry];
+        }
+        left -= segment_length - position;
+        if drawing {
+            current.push((end, end_color));
+        }
+    }
+    if drawing && current.len() > 1 {
+        pieces.push(current);
+    }
+    pieces
+        .into_iter()
+        .map(without_repeats)
+        .filter(|piece| piece.len() > 1)
+        .collect()
+}
+
+struct Tessellator<'a> {
+    vertices: &'a mut Vec<LineVertex>,
+    half_width: f32,
+    style: &'a LineStyle,
+    depth: f32,
+}
+
+impl Tessellator<'_> {
+    fn triangle(&mut self, corners: [(Vec2, [f32; 4]); 3]) {
+        for (position, color) in corners {
+            self.vertices.push(LineVertex {
+                position,
+                color,
+                depth: self.depth,
+            });
+        }
+    }
+
+    // a polyline without repeated points, the last point the same as the first if closed
+    fn stroke(&mut self, points: &[(Vec2, [f32; 4])], closed: bool) {
+        let directions: Vec<Vec2> = points
+            .windows(2)
+            .map(|pair| {
+                let delta = sub(pair[1].0, pair[0].0);
+                scale(delta, 1.0 / length(delta))
+            })
+            .collect();
+        if directions.is_empty() {
+            return;
+        }
+        let square = self.style.cap == LineCap::Square && !closed;
+        let last = directions.len() - 1;
+        for (i, direction) in directions.iter().enumerate() {
+            let (mut start, start_color) = points[i];
+            let (mut end, end_color) = points[i + 1];
+            if square && i == 0 {
+                start = sub(start, scale(*direction, self.half_width));
+            }
+            if square && i == last {
+                end = add(end, scale(*direction, self.half_width));
+            }
+            let offset = scale(normal(*direction), self.half_width);
+            let quad = [
+                (sub(start, offset), start_color),
+                (add(start, offset), start_color),
+                (add(end, offset), end_color),
+                (sub(end, offset), end_color),
+            ];
+            self.triangle([quad[0], quad[1], quad[2]]);
+            self.triangle([quad[0], quad[2], quad[3]]);
+        }
+        for i in 1..directions.len() {
+            self.join(points[i], directions[i - 1], directions[i]);
+        }
+        if closed {
+            self.join(points[0], directions[last], directions[0]);
+        } else if self.style.cap == LineCap::Round {
+            let [x, y] = directions[0];
+            self.fan(points[0], normal([-x, -y]), PI);
+            self.fan(points[last + 1], normal(directions[last]), PI);
+        }
+    }
+
+    // fills the gap on the outside of the turn from `before` to `after` at `point`
+    fn join(&mut self, point: (Vec2, [f32; 4]), before: Vec2, after: Vec2) {
+        let turn = cross(before, after);
+        if turn.abs() < 1e-6 && dot(before, after) > 0.0 {
+            return;
+        }
+        // the outside is on the right of a left turn
+        let side = if turn > 0.0 { -1.0 } else { 1.0 };
+        let outer_before = scale(normal(before), side);
+        let outer_after = scale(normal(after), side);
+        let (center, color) = point;
+        let from = (add(center, scale(outer_before, self.half_width)), color);
+        let to = (add(center, scale(outer_after, self.half_width)), color);
+        match self.style.join {
+            LineJoin::Miter { limit } => {
+                let bisector = add(outer_before, outer_after);
+                let bisector_length = length(bisector);
+                // how far the tip is from the point, in half widths: 1 / cos(half the turn).
+                // Infinite for a hairpin turn.
+                let miter_ratio = if bisector_length > 1e-6 {
+                    bisector_length / dot(bisector, outer_before)
+                } else {
+                    f32::INFINITY
+                };
+                if miter_ratio <= limit {
+                    let tip = add(
+                        center,
+                        scale(bisector, self.half_width * miter_ratio / bisector_length),
+                    );
+                    self.triangle([point, from, (tip, color)]);
+                    self.triangle([point, (tip, color), to]);
+                } else {
+                    self.triangle([point, from, to]);
+                }
+            }
+            LineJoin::Bevel => self.triangle([point, from, to]),
+            LineJoin::Round => {
+                let angle = dot(outer_before, outer_after).clamp(-1.0, 1.0).acos();
+                // the fan turns from `before`'s outside towards `after`'s
+                let sweep = if cross(outer_before, outer_after) >= 0.0 {
+                    angle
+                } else {
+                    -angle
+                };
+                self.fan(point, outer_before, sweep);
+            }
+        }
+    }
+
+    // a pie slice of the line's width around `point`, from the unit vector `from` turning
+    // by `sweep` radians
+    fn fan(&mut self, point: (Vec2, [f32; 4]), from: Vec2, sweep: f32) {
+        let (center, color) = point;
+        let segments = (sweep.abs() / PI * ROUND_SEGMENTS).ceil().max(1.0) as usize;
+        let start = from[1].atan2(from[0]);
+        let half_width = self.half_width;
+        let at = |i: usize| {
+            let angle = start + sweep * i as f32 / segments as f32;
+            (
+                add(center, [angle.cos() * half_width, angle.sin() * half_width]),
+                color,
+            )
+        };
+        for i in 0..segments {
+            let (a, b) = (at(i), at(i + 1));
+            self.triangle([point, a, b]);
+        }
+    }
+}
+
+/// Draws the polylines submitted to the GameState since its last update, into the scene with
+/// MSAA.
+pub struct LineRenderer {
+    pub(super) render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: GrowableBuffer,
+}
+
+impl LineRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        surface_dimensions_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        shader_source: &str,
+    ) -> Self {
+        let render_pipeline = Self::create_pipeline(
+            device,
+            surface_dimensions_bind_group_layout,
+            format,
+            sample_count,
+            shader_source,
+        );
+
+        let vertex_buffer = GrowableBuffer::new(
+            device,
+            "line_vertex_buffer",
+            wgpu::BufferUsages::VERTEX,
+            (INITIAL_VERTICES * std::mem::size_of::<LineVertex>()) as wgpu::BufferAddress,
+        );
+
+        Self {
+            render_pipeline,
+            vertex_buffer,
+        }
+    }
+
+    /// Recreates the pipeline, e.g. when the MSAA sample count changes.
+    pub fn rebuild_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        surface_dimensions_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        shader_source: &str,
+    ) {
+        self.render_pipeline = Self::create_pipeline(
+            device,
+            surface_dimensions_bind_group_layout,
+            format,
+            sample_count,
+            shader_source,
+        );
+    }
+
+    pub(super) fn create_pipeline(
+        device: &wgpu::Device,
+        surface_dimensions_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        shader_source: &str,
+    ) -> wgpu::RenderPipeline {
+        let line_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Line Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+
+        let line_render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Line Render Pipeline Layout"),
+                bind_group_layouts: &[surface_dimensions_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Line Render Pipeline"),
+            layout: Some(&line_render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &line_shader,
+                entry_point: Some("vs_main"),
+                // What type of vertices we want to pass to the vertex shader.
+                buffers: &[LineVertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &line_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+                polygon_mode: wgpu::PolygonMode::Fill,
+                // Requires Features::DEPTH_CLIP_CONTROL
+                unclipped_depth: false,
+                // Requires Features::CONSERVATIVE_RASTERIZATION
+                conservative: false,
+            },
+            depth_stencil: Some(layered_depth_stencil()),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+}
+
+impl RenderNode for LineRenderer {
+    fn pass_descriptor(&self) -> PassDescriptor {
+        PassDescriptor {
+            label: "Line render pass",
+            target: PassTarget::Scene,
+            load: wgpu::LoadOp::Load,
+            multisampled: true,
+            depth: true,
+        }
+    }
+
+    fn record(&mut self, render_pass: &mut wgpu::RenderPass<'_>, ctx: &FrameContext) {
+        let game_state = ctx.game_state;
+        let mut polylines: Vec<&Polyline> = game_state
+            .polylines
+            .iter()
+            .chain(game_state.overlay.polylines.iter())
+            .collect();
+        if polylines.is_empty() {
+            return;
+        }
+        // back to front, so translucent lines blend over the layers behind them (stable, so
+        // the order within a layer stays)
+        polylines.sort_by_key(|polyline| polyline.layer);
+        let width = ctx.surface_width as f32;
+        let height = ctx.surface_height as f32;
+        let mut vertices: Vec<LineVertex> = Vec::new();
+        for polyline in polylines {
+            match polyline.space {
+                LineSpace::Screen => vertices.extend(tessellate(polyline, ctx.screen_scale_factor)),
+                LineSpace::World => {
+                    // tessellated in pixels, so the width and the joins aren't stretched
+                    let mut on_screen = polyline.clone();
+                    for point in &mut on_screen.points {
+                        let [x, y] = ctx.world_to_clip(*point);
+                        *point = [(x + 1.0) * width / 2.0, (1.0 - y) * height / 2.0];
+                    }
+                    // the world's lines don't grow with the UI scale
+                    vertices.extend(tessellate(&on_screen, ctx.window_scale_factor));
+                }
+            }
+        }
+        if vertices.is_empty() {
+            return;
+        }
+
+        let data: &[u8] = bytemuck::cast_slice(&vertices);
+        self.vertex_buffer.write(ctx.device, ctx.queue, data);
+        mark_upload(render_pass, self.vertex_buffer.label(), data.len());
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, ctx.surface_dimensions_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(data.len()));
+        render_pass.draw(0..vertices.len() as u32, 0..1);
+    }
+}
@@ -0,0 +1,46 @@
+// The world's objects as single pixel lines: a shared mesh per kind of object, placed,
+// rotated and scaled per instance, with the mesh's corners scaled by the instance's radii
+struct MeshVertex {
+    @location(0) position: vec2<f32>,
+    @location(1) corner: u32,
+}
+
+struct Instance {
+    @location(2) center: vec2<f32>,
+    @location(3) x_axis: vec2<f32>,
+    @location(4) y_axis: vec2<f32>,
+    @location(5) color: vec4<f32>,
+    @location(6) radii_0: vec4<f32>,
+    @location(7) radii_1: vec4<f32>,
+    @location(8) radii_2: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(
+    vertex: MeshVertex,
+    instance: Instance,
+) -> VertexOutput {
+    var radii = array<vec4<f32>, 3>(instance.radii_0, instance.radii_1, instance.radii_2);
+    let local = vertex.position * radii[vertex.corner / 4u][vertex.corner % 4u];
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(
+        instance.center + local.x * instance.x_axis + local.y * instance.y_axis,
+        0.0,
+        1.0,
+    );
+    out.color = instance.color;
+    return out;
+}
+
+@fragment
+fn fs_main(
+    in: VertexOutput,
+) -> @location(0) vec4<f32> {
+    return in.color;
+}
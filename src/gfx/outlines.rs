@@ -0,0 +1,256 @@
+//! The world's asteroids, shots and ship, drawn with instancing: every kind of object has a
+//! mesh of single pixel lines that's uploaded once, and each object is an [`OutlineInstance`]
+//! placing, rotating and scaling it. A frame uploads one small instance per object instead
+//! of every transformed line, and draws each kind in one call. (The particles are instanced
+//! too, see crate::gfx::particles.)
+//!
+//! Asteroids all share one mesh even though their outlines differ: the corners are evenly
+//! spaced around the center (see crate::asteroid::CORNERS), so the mesh has the directions
+//! and each instance how far out every corner is.
+
+use std::f32::consts::TAU;
+use std::ops::Range;
+
+use wgpu::util::DeviceExt;
+
+use super::buffer::GrowableBuffer;
+use super::render_graph::{FrameContext, PassDescriptor, PassTarget, RenderNode, mark_upload};
+use super::vertex::{MeshVertex, OutlineInstance};
+use crate::asteroid::CORNERS;
+use crate::math::{Real, Scalar};
+use crate::ship::{BULLET_STREAK, FLAME, HULL};
+
+// to start with, the buffer grows to fit
+const INITIAL_INSTANCES: usize = 256;
+
+// where each kind's mesh is in the vertex buffer
+struct Meshes {
+    asteroid: Range<u32>,
+    bullet: Range<u32>,
+    hull: Range<u32>,
+    flame: Range<u32>,
+}
+
+impl Meshes {
+    // the meshes' line lists, and where each of them is
+    fn build() -> (Vec<MeshVertex>, Meshes) {
+        let mut vertices = Vec::new();
+        let mut push_lines = |lines: &mut dyn Iterator<Item = MeshVertex>| {
+            let start = vertices.len() as u32;
+            vertices.extend(lines);
+            start..vertices.len() as u32
+        };
+        let corner = |i: i32| {
+            let angle = TAU * (i % CORNERS) as f32 / CORNERS as f32;
+            MeshVertex {
+                position: [angle.cos(), angle.sin()],
+                corner: (i % CORNERS) as u32,
+            }
+        };
+        let fixed = |position: [f32; 2]| MeshVertex {
+            position,
+            corner: 0,
+        };
+
+        let asteroid = push_lines(&mut (0..CORNERS).flat_map(|i| [corner(i), corner(i + 1)]));
+        let bullet = push_lines(&mut [[-BULLET_STREAK, 0.0], [0.0, 0.0]].into_iter().map(fixed));
+        let hull = push_lines(
+            &mut (0..HULL.len()).flat_map(|i| [fixed(HULL[i]), fixed(HULL[(i + 1) % HULL.len()])]),
+        );
+        let flame = push_lines(
+            &mut FLAME
+                .windows(2)
+                .flat_map(|pair| [fixed(pair[0]), fixed(pair[1])]),
+        );
+        (
+            vertices,
+            Meshes {
+                asteroid,
+                bullet,
+                hull,
+                flame,
+            },
+        )
+    }
+}
+
+/// Draws the world's objects, see [`crate::gfx::outlines`]. The multiplayer arena is still
+/// drawn as plain lines by the debug line renderer.
+pub struct OutlineRenderer {
+    render_pipeline: wgpu::RenderPipeline,
+    mesh_buffer: wgpu::Buffer,
+    meshes: Meshes,
+    instance_buffer: GrowableBuffer,
+    // reused every frame
+    instances: Vec<OutlineInstance>,
+}
+
+impl OutlineRenderer {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        let render_pipeline = Self::create_pipeline(device, format, sample_count);
+
+        let (vertices, meshes) = Meshes::build();
+        let mesh_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("outline_mesh_buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let instance_buffer = GrowableBuffer::new(
+            device,
+            "outline_instance_buffer",
+            wgpu::BufferUsages::VERTEX,
+            (INITIAL_INSTANCES * std::mem::size_of::<OutlineInstance>()) as wgpu::BufferAddress,
+        );
+
+        Self {
+            render_pipeline,
+            mesh_buffer,
+            meshes,
+            instance_buffer,
+            instances: Vec::new(),
+        }
+    }
+
+    /// Recreates the pipeline, e.g. when the MSAA sample count changes.
+    pub fn rebuild_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        self.render_pipeline = Self::create_pipeline(device, format, sample_count);
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Outline shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("outline-shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Outline pipeline layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Outline pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[MeshVertex::desc(), OutlineInstance::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+                polygon_mode: wgpu::PolygonMode::Fill,
+                // Requires Features::DEPTH_CLIP_CONTROL
+                unclipped_depth: false,
+                // Requires Features::CONSERVATIVE_RASTERIZATION
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+}
+
+impl RenderNode for OutlineRenderer {
+    fn pass_descriptor(&self) -> PassDescriptor {
+        PassDescriptor {
+            label: "Outline render pass",
+            target: PassTarget::Scene,
+            load: wgpu::LoadOp::Load,
+            multisampled: true,
+            depth: false,
+        }
+    }
+
+    fn record(&mut self, render_pass: &mut wgpu::RenderPass<'_>, ctx: &FrameContext) {
+        if ctx.game_state.net_client().is_some() {
+            return;
+        }
+        let world = ctx.game_state.interpolated_world(ctx.render_alpha);
+        let color = ctx.game_state.palette().world;
+        let origin = ctx.world_to_clip([0.0, 0.0]);
+        let unit = ctx.world_to_clip([1.0, 1.0]);
+        let clip_per_unit = [unit[0] - origin[0], unit[1] - origin[1]];
+        // the mesh's axes turned to `[cos, sin]` (and scaled by its length)
+        let instance = |center: [f32; 2], [cos, sin]: [f32; 2]| OutlineInstance {
+            center: ctx.world_to_clip(center),
+            x_axis: [cos * clip_per_unit[0], sin * clip_per_unit[1]],
+            y_axis: [-sin * clip_per_unit[0], cos * clip_per_unit[1]],
+            color,
+            radii: [1.0; 12],
+        };
+        // the same table the outlines' line segments are turned with
+        let rotation = |angle: Scalar| [angle.table_cos().to_f32(), angle.table_sin().to_f32()];
+
+        self.instances.clear();
+        for asteroid in &world.asteroids {
+            let mut outline = instance(asteroid.position.to_f32(), rotation(asteroid.angle));
+            for (radius, corner_radius) in outline.radii.iter_mut().zip(asteroid.corner_radii()) {
+                *radius = corner_radius;
+            }
+            self.instances.push(outline);
+        }
+        let asteroids = self.instances.len() as u32;
+        for bullet in &world.bullets {
+            let [vx, vy] = bullet.velocity.to_f32();
+            // the streak is along the velocity
+            let speed = (vx * vx + vy * vy).sqrt().max(1.0);
+            self.instances
+                .push(instance(bullet.position.to_f32(), [vx / speed, vy / speed]));
+        }
+        let bullets = self.instances.len() as u32;
+        let ship = &world.ship;
+        if world.ship_alive() {
+            self.instances
+                .push(instance(ship.position.to_f32(), rotation(ship.angle)));
+        }
+        let ships = self.instances.len() as u32;
+        if self.instances.is_empty() {
+            return;
+        }
+
+        let data: &[u8] = bytemuck::cast_slice(&self.instances);
+        self.instance_buffer.write(ctx.device, ctx.queue, data);
+        mark_upload(render_pass, self.instance_buffer.label(), data.len());
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_vertex_buffer(0, self.mesh_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(data.len()));
+        render_pass.draw(self.meshes.asteroid.clone(), 0..asteroids);
+        render_pass.draw(self.meshes.bullet.clone(), asteroids..bullets);
+        render_pass.draw(self.meshes.hull.clone(), bullets..ships);
+        if ship.thrusting() {
+            render_pass.draw(self.meshes.flame.clone(), bullets..ships);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use super::render_graph::{FrameContext, PassDescriptor, PassTarget, RenderNode, mark_upload};
+use super::vertex::ColorVertex;
+use crate::memory::{MemoryPanel, PANEL_COLORS};
+use crate::screen::OverlayIcon;
+use crate::world::{WORLD_HEIGHT, WORLD_WIDTH};
+
+// tint quad, the largest icon (two bars) and the memory panel (background, a bar and a growth
+// marker per entry)
+const MAX_VERTICES: usize = 18 + 6 + PANEL_COLORS.len() * 12;
+const ICON_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.85];
+// memory panel layout, in logical pixels
+const PANEL_MARGIN: f32 = 16.0;
+const PANEL_PADDING: f32 = 8.0;
+const PANEL_BAR_WIDTH: f32 = 240.0;
+const PANEL_BAR_HEIGHT: f32 = 10.0;
+const PANEL_BAR_SPACING: f32 = 4.0;
+const PANEL_BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+
+/// Draws the current screen's overlay (see crate::screen::ScreenOverlay): a translucent tint
+/// over the whole surface and an icon in the middle of the world. Also draws the memory panel
+/// (see crate::memory). The overlay's shapes are drawn by the shape renderer.
+pub struct OverlayRenderer {
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+}
+
+fn push_quad(vertices: &mut Vec<ColorVertex>, corners: [[f32; 2]; 4], color: [f32; 4]) {
+    for i in [0, 1, 2, 0, 2, 3] {
+        vertices.push(ColorVertex {
+            position: corners[i],
+            color,
+        });
+    }
+}
+
+// icon outlines in world units, relative to the center of the world. Every shape is a quad
+// (the play triangle repeats a corner).
+fn icon_quads(icon: OverlayIcon) -> Vec<[[f32; 2]; 4]> {
+    let bar = |[x, y]: [f32; 2], length: f32, width: f32, angle: f32| {
+        let (sin, cos) = angle.sin_cos();
+        let along = [cos * length / 2.0, sin * length / 2.0];
+        let across = [-sin * width / 2.0, cos * width / 2.0];
+        [
+            [x - along[0] - across[0], y - along[1] - across[1]],
+            [x + along[0] - across[0], y + along[1] - across[1]],
+            [x + along[0] + across[0], y + along[1] + across[1]],
+            [x - along[0] + across[0], y - along[1] + across[1]],
+        ]
+    };
+    match icon {
+        OverlayIcon::Play => vec![[[-40.0, -50.0], [60.0, 0.0], [60.0, 0.0], [-40.0, 50.0]]],
+        OverlayIcon::Pause => vec![
+            bar([-30.0, 0.0], 100.0, 30.0, std::f32::consts::FRAC_PI_2),
+            bar([30.0, 0.0], 100.0, 30.0, std::f32::consts::FRAC_PI_2),
+        ],
+        OverlayIcon::GameOver => vec![
+            bar([0.0, 0.0], 140.0, 24.0, std::f32::consts::FRAC_PI_4),
+            bar([0.0, 0.0], 140.0, 24.0, -std::f32::consts::FRAC_PI_4),
+        ],
+    }
+}
+
+// the memory panel in the top left corner: a bar per entry, relative to the largest one, with
+// a marker in the error color after the entries that grew since the last refresh
+fn push_memory_panel(vertices: &mut Vec<ColorVertex>, panel: &MemoryPanel, ctx: &FrameContext) {
+    let entries = &panel.current.entries[..panel.current.entries.len().min(PANEL_COLORS.len())];
+    if entries.is_empty() {
+        return;
+    }
+    let scale = ctx.screen_scale_factor;
+    let to_clip = |x: f32, y: f32| {
+        [
+            -1.0 + 2.0 * x * scale / ctx.surface_width as f32,
+            1.0 - 2.0 * y * scale / ctx.surface_height as f32,
+        ]
+    };
+    let rect = |x: f32, y: f32, width: f32, height: f32| {
+        [
+            to_clip(x, y + height),
+            to_clip(x + width, y + height),
+            to_clip(x + width, y),
+            to_clip(x, y),
+        ]
+    };
+
+    let row_height = PANEL_BAR_HEIGHT + PANEL_BAR_SPACING;
+    push_quad(
+        vertices,
+        rect(
+            PANEL_MARGIN,
+            PANEL_MARGIN,
+            PANEL_BAR_WIDTH + PANEL_BAR_HEIGHT + PANEL_BAR_SPACING + 2.0 * PANEL_PADDING,
+            entries.len() as f32 * row_height - PANEL_BAR_SPACING + 2.0 * PANEL_PADDING,
+        ),
+        PANEL_BACKGROUND,
+    );
+    let largest = entries
+        .iter()
+        .map(|entry| entry.bytes)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    for (i, entry) in entries.iter().enumerate() {
+        let x = PANEL_MARGIN + PANEL_PADDING;
+        let y = PANEL_MARGIN + PANEL_PADDING + i as f32 * row_height;
+        // at least a sliver, so empty entries still show their color
+        let width = (entry.bytes as f32 / largest as f32 * PANEL_BAR_WIDTH).max(2.0);
+        push_quad(
+            vertices,
+            rect(x, y, width, PANEL_BAR_HEIGHT),
+            PANEL_COLORS[i].1,
+        );
+        if panel.grew(entry.name) {
+            push_quad(
+                vertices,
+                rect(
+                    x + PANEL_BAR_WIDTH + PANEL_BAR_SPACING,
+                    y,
+                    PANEL_BAR_HEIGHT,
+                    PANEL_BAR_HEIGHT,
+                ),
+                ctx.game_state.palette().error,
+            );
+        }
+    }
+}
+
+impl OverlayRenderer {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Color shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("color-shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay pipeline layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overlay pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[ColorVertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+                polygon_mode: wgpu::PolygonMode::Fill,
+                // Requires Features::DEPTH_CLIP_CONTROL
+                unclipped_depth: false,
+                // Requires Features::CONSERVATIVE_RASTERIZATION
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("overlay_vertex_buffer"),
+            size: (MAX_VERTICES * std::mem::size_of::<ColorVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            render_pipeline,
+            vertex_buffer,
+        }
+    }
+}
+
+impl RenderNode for OverlayRenderer {
+    fn pass_descriptor(&self) -> PassDescriptor {
+        PassDescriptor {
+            label: "Overlay render pass",
+            target: PassTarget::Scene,
+            load: wgpu::LoadOp::Load,
+            multisampled: false,
+            depth: false,
+        }
+    }
+
+    fn record(&mut self, render_pass: &mut wgpu::RenderPass<'_>, ctx: &FrameContext) {
+        let overlay = &ctx.game_state.overlay;
+        let mut vertices: Vec<ColorVertex> = Vec::with_capacity(MAX_VERTICES);
+        if let Some(tint) = overlay.tint {
+            push_quad(
+                &mut vertices,
+                [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]],
+                tint,
+            );
+        }
+        if let Some(icon) = overlay.icon {
+            for quad in icon_quads(icon) {
+                let corners = quad.map(|[x, y]| {
+                    ctx.world_to_clip([x + WORLD_WIDTH / 2.0, y + WORLD_HEIGHT / 2.0])
+                });
+                push_quad(&mut vertices, corners, ICON_COLOR);
+            }
+        }
+        if ctx.game_state.memory_panel.visible {
+            push_memory_panel(&mut vertices, &ctx.game_state.memory_panel, ctx);
+        }
+        vertices.truncate(MAX_VERTICES);
+        if vertices.is_empty() {
+            return;
+        }
+
+        ctx.queue
+            .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        mark_upload(
+            render_pass,
+            "overlay_vertex_buffer",
+            std::mem::size_of_val(vertices.as_slice()),
+        );
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..vertices.len() as u32, 0..1);
+    }
+}
@@ -0,0 +1,384 @@
+//! Particles for explosions, sparks and engine trails. They are simulated on the CPU in world
+//! units (purely cosmetic, so not part of the deterministic World) and drawn as instanced
+//! quads, one instance per particle.
+
+use super::render_graph::{FrameContext, PassDescriptor, PassTarget, RenderNode, mark_upload};
+use super::vertex::ParticleInstance;
+use crate::interpolation::{lerp, lerp_vec2};
+use crate::math::SimRng;
+use crate::memory::{HeapSize, vec_size};
+use crate::rng::{self, RngStream};
+
+/// More particles than this are not spawned
+pub const MAX_PARTICLES: usize = 4096;
+
+/// How particles are spawned and how they look over their life.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmitterParams {
+    /// Particles per second while an emitter is active (not used by bursts)
+    pub spawn_rate: f32,
+    /// Lifetime range in seconds
+    pub lifetime: (f32, f32),
+    /// Speed range in units/s
+    pub speed: (f32, f32),
+    /// Random spread around the emitter's direction in radians (TAU = every direction)
+    pub spread: f32,
+    /// Fraction of the velocity lost per second
+    pub drag: f32,
+    /// Size in world units at the start and at the end of the particle's life
+    pub size: (f32, f32),
+    /// Color (with alpha) at the start and at the end of the particle's life. The game's
+    /// effects take theirs from the palette, see crate::palette
+    pub color: ([f32; 4], [f32; 4]),
+}
+
+impl EmitterParams {
+    pub fn explosion() -> Self {
+        Self {
+            spawn_rate: 0.0,
+            lifetime: (0.4, 1.1),
+            speed: (40.0, 220.0),
+            spread: std::f32::consts::TAU,
+            drag: 2.5,
+            size: (9.0, 3.0),
+            color: ([1.0, 0.55, 0.1, 1.0], [0.25, 0.1, 0.05, 0.0]),
+        }
+    }
+
+    /// Grey dust for breaking asteroids
+    pub fn debris() -> Self {
+        Self {
+            lifetime: (0.3, 0.8),
+            speed: (20.0, 120.0),
+            size: (5.0, 2.0),
+            color: ([0.35, 0.35, 0.35, 0.9], [0.6, 0.6, 0.6, 0.0]),
+            ..Self::explosion()
+        }
+    }
+
+    pub fn sparks() -> Self {
+        Self {
+            lifetime: (0.1, 0.3),
+            speed: (150.0, 350.0),
+            drag: 4.0,
+            size: (3.0, 1.0),
+            color: ([1.0, 0.9, 0.4, 1.0], [1.0, 0.3, 0.0, 0.0]),
+            ..Self::explosion()
+        }
+    }
+
+    /// Exhaust, pointed backwards out of the engine
+    pub fn thruster() -> Self {
+        Self {
+            spawn_rate: 90.0,
+            lifetime: (0.15, 0.35),
+            speed: (120.0, 200.0),
+            spread: 0.5,
+            drag: 1.0,
+            size: (5.0, 1.5),
+            color: ([1.0, 0.75, 0.2, 0.9], [0.9, 0.15, 0.05, 0.0]),
+        }
+    }
+}
+
+/// Index of an emitter added with ParticleSystem::add_emitter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmitterId(usize);
+
+/// Continuously spawns particles while active. Move it around by updating its fields every
+/// tick (e.g. to follow the ship's engine).
+#[derive(Debug, Clone)]
+pub struct Emitter {
+    pub params: EmitterParams,
+    pub position: [f32; 2],
+    /// Direction particles are emitted in, in radians
+    pub direction: f32,
+    /// Added to the particles' velocity, e.g. the velocity of what the emitter is attached to
+    pub velocity: [f32; 2],
+    pub active: bool,
+    // fractional particles carried over to the next update
+    pending: f32,
+}
+
+#[derive(Debug, Clone)]
+struct Particle {
+    position: [f32; 2],
+    // where it was before the last update, to draw it in between
+    previous_position: [f32; 2],
+    velocity: [f32; 2],
+    age: f32,
+    lifetime: f32,
+    params: EmitterParams,
+}
+
+impl Particle {
+    // 0 at birth, 1 at the end of its life
+    fn life(&self) -> f32 {
+        (self.age / self.lifetime).min(1.0)
+    }
+}
+
+pub struct ParticleSystem {
+    emitters: Vec<Emitter>,
+    particles: Vec<Particle>,
+    rng: SimRng,
+}
+
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        Self {
+            emitters: Vec::new(),
+            particles: Vec::new(),
+            rng: SimRng::new(0x9a47),
+        }
+    }
+}
+
+impl ParticleSystem {
+    /// Adds an (inactive) emitter.
+    pub fn add_emitter(&mut self, params: EmitterParams) -> EmitterId {
+        self.emitters.push(Emitter {
+            params,
+            position: [0.0, 0.0],
+            direction: 0.0,
+            velocity: [0.0, 0.0],
+            active: false,
+            pending: 0.0,
+        });
+        EmitterId(self.emitters.len() - 1)
+    }
+
+    pub fn emitter_mut(&mut self, id: EmitterId) -> &mut Emitter {
+        &mut self.emitters[id.0]
+    }
+
+    /// Spawns `count` particles at once, e.g. for an explosion.
+    pub fn burst(
+        &mut self,
+        params: &EmitterParams,
+        position: [f32; 2],
+        direction: f32,
+        velocity: [f32; 2],
+        count: usize,
+    ) {
+        for _ in 0..count {
+            self.spawn(params, position, direction, velocity);
+        }
+    }
+
+    /// Moves and ages the particles and lets the active emitters spawn new ones.
+    pub fn update(&mut self, dt: f32) {
+        self.particles.retain_mut(|particle| {
+            particle.age += dt;
+            particle.previous_position = particle.position;
+            let drag = (1.0 - particle.params.drag * dt).max(0.0);
+            particle.velocity = [particle.velocity[0] * drag, particle.velocity[1] * drag];
+            particle.position[0] += particle.velocity[0] * dt;
+            particle.position[1] += particle.velocity[1] * dt;
+            particle.age < particle.lifetime
+        });
+
+        for i in 0..self.emitters.len() {
+            if !self.emitters[i].active {
+                self.emitters[i].pending = 0.0;
+                continue;
+            }
+            let emitter = &mut self.emitters[i];
+            emitter.pending += emitter.params.spawn_rate * dt;
+            let count = emitter.pending.floor();
+            emitter.pending -= count;
+            let emitter = emitter.clone();
+            self.burst(
+                &emitter.params,
+                emitter.position,
+                emitter.direction,
+                emitter.velocity,
+                count as usize,
+            );
+        }
+    }
+
+    /// Removes all particles (emitters are kept).
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    /// Restarts the particles' random numbers from the game's master seed, see [`crate::rng`].
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = rng::stream(seed, RngStream::Particles);
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    // uniformly distributed in [min, max), cosmetic so f32 precision is plenty
+    fn random(&mut self, (min, max): (f32, f32)) -> f32 {
+        let unit = (self.rng.next_u64() >> 40) as f32 / (1 << 24) as f32;
+        lerp(min, max, unit)
+    }
+
+    fn spawn(
+        &mut self,
+        params: &EmitterParams,
+        position: [f32; 2],
+        direction: f32,
+        velocity: [f32; 2],
+    ) {
+        if self.particles.len() >= MAX_PARTICLES {
+            return;
+        }
+        let angle = direction + self.random((-params.spread / 2.0, params.spread / 2.0));
+        let speed = self.random(params.speed);
+        let lifetime = self.random(params.lifetime);
+        self.particles.push(Particle {
+            position,
+            previous_position: position,
+            velocity: [
+                velocity[0] + angle.cos() * speed,
+                velocity[1] + angle.sin() * speed,
+            ],
+            age: 0.0,
+            lifetime,
+            params: *params,
+        });
+    }
+}
+
+impl HeapSize for ParticleSystem {
+    fn heap_size(&self) -> usize {
+        vec_size(&self.emitters) + vec_size(&self.particles)
+    }
+}
+
+/// Draws the GameState's particles, on top of the scene.
+pub struct ParticleRenderer {
+    render_pipeline: wgpu::RenderPipeline,
+    instance_buffer: wgpu::Buffer,
+}
+
+impl ParticleRenderer {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Particle shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("particle-shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Particle pipeline layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Particle pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                // no vertex buffer, the quad's corners come from the vertex index
+                buffers: &[ParticleInstance::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+                polygon_mode: wgpu::PolygonMode::Fill,
+                // Requires Features::DEPTH_CLIP_CONTROL
+                unclipped_depth: false,
+                // Requires Features::CONSERVATIVE_RASTERIZATION
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particle_instance_buffer"),
+            size: (MAX_PARTICLES * std::mem::size_of::<ParticleInstance>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            render_pipeline,
+            instance_buffer,
+        }
+    }
+}
+
+impl RenderNode for ParticleRenderer {
+    fn pass_descriptor(&self) -> PassDescriptor {
+        PassDescriptor {
+            label: "Particle render pass",
+            target: PassTarget::Scene,
+            load: wgpu::LoadOp::Load,
+            multisampled: false,
+            depth: false,
+        }
+    }
+
+    fn record(&mut self, render_pass: &mut wgpu::RenderPass<'_>, ctx: &FrameContext) {
+        let particles = &ctx.game_state.particles.particles;
+        if particles.is_empty() {
+            return;
+        }
+
+        let origin = ctx.world_to_clip([0.0, 0.0]);
+        let unit = ctx.world_to_clip([1.0, 1.0]);
+        let clip_per_unit = [unit[0] - origin[0], unit[1] - origin[1]];
+        let instances: Vec<ParticleInstance> = particles
+            .iter()
+            .map(|particle| {
+                let life = particle.life();
+                let (start_size, end_size) = particle.params.size;
+                let (start_color, end_color) = particle.params.color;
+                let size = lerp(start_size, end_size, life);
+                ParticleInstance {
+                    center: ctx.world_to_clip(lerp_vec2(
+                        particle.previous_position,
+                        particle.position,
+                        ctx.render_alpha,
+                    )),
+                    half_size: [size / 2.0 * clip_per_unit[0], size / 2.0 * clip_per_unit[1]],
+                    color: std::array::from_fn(|i| lerp(start_color[i], end_color[i], life)),
+                }
+            })
+            .collect();
+
+        ctx.queue
+            .write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+        mark_upload(
+            render_pass,
+            "particle_instance_buffer",
+            std::mem::size_of_val(instances.as_slice()),
+        );
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..instances.len() as u32);
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+
+use super::gpu_cache::GpuCache;
+use super::profiler::Profiler;
+use super::render_graph;
+
+/// Format of the offscreen scene texture. 16 bit float so bright values (> 1.0) survive
+/// until the post-processing passes (e.g. bloom) have used them.
+pub const SCENE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Fullscreen effects applied, in order, to the scene before it is presented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostEffect {
+    Bloom,
+    Vignette,
+    Crt,
+}
+
+// a fullscreen pass of the chain: the configured effects, the hit flash the game adds while
+// it fades (see crate::camera), or a plain blit if there's neither
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Pass {
+    Blit,
+    Effect(PostEffect),
+    Flash,
+}
+
+impl Pass {
+    const ALL: [Pass; 5] = [
+        Pass::Blit,
+        Pass::Effect(PostEffect::Bloom),
+        Pass::Effect(PostEffect::Vignette),
+        Pass::Effect(PostEffect::Crt),
+        Pass::Flash,
+    ];
+
+    fn entry_point(self) -> &'static str {
+        match self {
+            Pass::Blit => "fs_blit",
+            Pass::Effect(PostEffect::Bloom) => "fs_bloom",
+            Pass::Effect(PostEffect::Vignette) => "fs_vignette",
+            Pass::Effect(PostEffect::Crt) => "fs_crt",
+            Pass::Flash => "fs_flash",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Pass::Blit => "Blit pass",
+            Pass::Effect(PostEffect::Bloom) => "Bloom pass",
+            Pass::Effect(PostEffect::Vignette) => "Vignette pass",
+            Pass::Effect(PostEffect::Crt) => "CRT pass",
+            Pass::Flash => "Flash pass",
+        }
+    }
+}
+
+struct OffscreenTarget {
+    _texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+impl OffscreenTarget {
+    fn new(
+        device: &wgpu::Device,
+        label: &str,
+        width: u32,
+        height: u32,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        flash: &wgpu::Buffer,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SCENE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: flash.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            _texture: texture,
+            view,
+            bind_group,
+        }
+    }
+}
+
+/// Owns the offscreen scene texture the render nodes draw into, and runs the configured
+/// post-processing chain (ping-ponging between two offscreen textures) with the last pass
+/// writing into the surface.
+pub struct PostProcessor {
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    // the hit flash's color, premultiplied by how strong it is
+    flash: wgpu::Buffer,
+    // keyed on pass and target format
+    pipelines: HashMap<(Pass, wgpu::TextureFormat), wgpu::RenderPipeline>,
+    scene: OffscreenTarget,
+    swap: OffscreenTarget,
+    surface_format: wgpu::TextureFormat,
+}
+
+impl PostProcessor {
+    pub fn new(
+        device: &wgpu::Device,
+        gpu_cache: &GpuCache,
+        surface_format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let bind_group_layout = gpu_cache.bind_group_layout(
+            device,
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("post_process_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            },
+        );
+
+        let sampler = gpu_cache.sampler(
+            device,
+            &wgpu::SamplerDescriptor {
+                label: Some("post_process_sampler"),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            },
+        );
+
+        let flash = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("post_process_flash_buffer"),
+            size: std::mem::size_of::<[f32; 4]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Post process shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("post_shader.wgsl").into()),
+        });
+
+        let pipeline_layout = gpu_cache.pipeline_layout(
+            device,
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Post process pipeline layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            },
+        );
+
+        let mut pipelines = HashMap::new();
+        for pass in Pass::ALL {
+            // every pass can either be an intermediate step or the last one in the chain
+            for format in [SCENE_FORMAT, surface_format] {
+                pipelines.insert(
+                    (pass, format),
+                    Self::create_pipeline(device, &pipeline_layout, &shader, pass, format),
+                );
+            }
+        }
+
+        let scene = OffscreenTarget::new(
+            device,
+            "scene_texture",
+            width,
+            height,
+            &bind_group_layout,
+            &sampler,
+            &flash,
+        );
+        let swap = OffscreenTarget::new(
+            device,
+            "post_process_swap_texture",
+            width,
+            height,
+            &bind_group_layout,
+            &sampler,
+            &flash,
+        );
+
+        Self {
+            bind_group_layout,
+            sampler,
+            flash,
+            pipelines,
+            scene,
+            swap,
+            surface_format,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        pass: Pass,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&format!("Post process pipeline ({:?}, {:?})", pass, format)),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: Some("vs_fullscreen"),
+                // the fullscreen triangle is generated from the vertex index
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: Some(pass.entry_point()),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+                polygon_mode: wgpu::PolygonMode::Fill,
+                // Requires Features::DEPTH_CLIP_CONTROL
+                unclipped_depth: false,
+                // Requires Features::CONSERVATIVE_RASTERIZATION
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.scene = OffscreenTarget::new(
+            device,
+            "scene_texture",
+            width,
+            height,
+            &self.bind_group_layout,
+            &self.sampler,
+            &self.flash,
+        );
+        self.swap = OffscreenTarget::new(
+            device,
+            "post_process_swap_texture",
+            width,
+            height,
+            &self.bind_group_layout,
+            &self.sampler,
+            &self.flash,
+        );
+    }
+
+    /// The texture the scene render nodes draw into.
+    pub fn scene_view(&self) -> &wgpu::TextureView {
+        &self.scene.view
+    }
+
+    /// Records one fullscreen pass per effect, then the hit flash if `flash` (a color, its
+    /// alpha how strong the flash is) isn't transparent, or a plain blit if there's neither.
+    pub fn record(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        queue: &wgpu::Queue,
+        effects: &[PostEffect],
+        flash: [f32; 4],
+        surface_view: &wgpu::TextureView,
+        profiler: &mut Profiler,
+    ) {
+        let mut chain: Vec<Pass> = effects.iter().copied().map(Pass::Effect).collect();
+        if flash[3] > 0.0 {
+            queue.write_buffer(&self.flash, 0, bytemuck::cast_slice(&flash));
+            chain.push(Pass::Flash);
+        }
+        if chain.is_empty() {
+            chain.push(Pass::Blit);
+        }
+
+        let mut source = &self.scene;
+        let mut destination = &self.swap;
+
+        for (i, pass) in chain.iter().enumerate() {
+            let is_last = i == chain.len() - 1;
+            let (target_view, target_format) = if is_last {
+                (surface_view, self.surface_format)
+            } else {
+                (&destination.view, SCENE_FORMAT)
+            };
+
+            let mut render_pass = render_graph::begin_pass(
+                encoder,
+                pass.label(),
+                target_view,
+                None,
+                wgpu::Operations {
+                    // every pixel gets overwritten by the fullscreen triangle
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+                None,
+                profiler.timestamp_writes(pass.label()),
+            );
+            render_pass.set_pipeline(&self.pipelines[&(*pass, target_format)]);
+            render_pass.set_bind_group(0, &source.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+
+            std::mem::swap(&mut source, &mut destination);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+// Vertex shader
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>
+}
+
+// A single triangle that covers the whole screen: (-1, -1), (3, -1), (-1, 3)
+@vertex
+fn vs_fullscreen(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    let xy = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(xy * 2.0 - 1.0, 0.0, 1.0);
+    // texture coordinates have the y axis pointing down
+    out.uv = vec2<f32>(xy.x, 1.0 - xy.y);
+    return out;
+}
+
+// Fragment shaders
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+// the hit flash's color, its alpha how strong the flash is (see fs_flash)
+@group(0) @binding(2)
+var<uniform> flash: vec4<f32>;
+
+@fragment
+fn fs_blit(
+    in: VertexOutput,
+) -> @location(0) vec4<f32> {
+    return textureSample(t_source, s_source, in.uv);
+}
+
+const BLOOM_THRESHOLD: f32 = 0.8;
+const BLOOM_INTENSITY: f32 = 0.6;
+const BLOOM_RADIUS_PX: f32 = 4.0;
+
+// Single pass "poor man's" bloom: samples two rings around the pixel and adds
+// whatever is above the threshold back on top.
+@fragment
+fn fs_bloom(
+    in: VertexOutput,
+) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_source));
+    let color = textureSample(t_source, s_source, in.uv);
+
+    var glow = vec3<f32>(0.0);
+    for (var ring = 1; ring <= 2; ring++) {
+        for (var i = 0; i < 8; i++) {
+            let angle = f32(i) * 0.785398 + f32(ring) * 0.392699;
+            let offset = vec2<f32>(cos(angle), sin(angle)) * f32(ring) * BLOOM_RADIUS_PX * texel;
+            let s = textureSample(t_source, s_source, in.uv + offset).rgb;
+            glow += max(s - vec3<f32>(BLOOM_THRESHOLD), vec3<f32>(0.0));
+        }
+    }
+    glow /= 16.0;
+
+    return vec4<f32>(color.rgb + glow * BLOOM_INTENSITY, color.a);
+}
+
+const VIGNETTE_STRENGTH: f32 = 0.45;
+
+@fragment
+fn fs_vignette(
+    in: VertexOutput,
+) -> @location(0) vec4<f32> {
+    let color = textureSample(t_source, s_source, in.uv);
+    let distance = length(in.uv - vec2<f32>(0.5));
+    let vignette = 1.0 - VIGNETTE_STRENGTH * smoothstep(0.3, 0.75, distance);
+    return vec4<f32>(color.rgb * vignette, color.a);
+}
+
+const CRT_CURVATURE: f32 = 0.08;
+const CRT_SCANLINE_STRENGTH: f32 = 0.2;
+const CRT_ABERRATION_PX: f32 = 1.0;
+
+@fragment
+fn fs_crt(
+    in: VertexOutput,
+) -> @location(0) vec4<f32> {
+    let dimensions = vec2<f32>(textureDimensions(t_source));
+
+    // barrel distortion
+    let centered = in.uv * 2.0 - 1.0;
+    let distorted = centered * (1.0 + CRT_CURVATURE * dot(centered, centered));
+    let uv = distorted * 0.5 + 0.5;
+
+    // chromatic aberration (sampled before the bounds check to keep control flow uniform)
+    let aberration = vec2<f32>(CRT_ABERRATION_PX / dimensions.x, 0.0);
+    let r = textureSample(t_source, s_source, uv + aberration).r;
+    let ga = textureSample(t_source, s_source, uv).ga;
+    let b = textureSample(t_source, s_source, uv - aberration).b;
+
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    // scanlines every other physical pixel row
+    let scanline = 1.0 - CRT_SCANLINE_STRENGTH * (0.5 + 0.5 * cos(uv.y * dimensions.y * 3.14159265));
+
+    return vec4<f32>(vec3<f32>(r, ga.x, b) * scanline, ga.y);
+}
+
+// The hit flash: the scene washed out towards the flash color, more towards the edges
+@fragment
+fn fs_flash(
+    in: VertexOutput,
+) -> @location(0) vec4<f32> {
+    let color = textureSample(t_source, s_source, in.uv);
+    let edge = smoothstep(0.2, 0.75, length(in.uv - vec2<f32>(0.5)));
+    let amount = flash.a * mix(0.5, 1.0, edge);
+    return vec4<f32>(mix(color.rgb, flash.rgb, amount), color.a);
+}
@@ -0,0 +1,233 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use crate::logging;
+
+// Every pass writes a begin and an end timestamp
+const MAX_PASSES: u32 = 16;
+const QUERY_COUNT: u32 = MAX_PASSES * 2;
+const BUFFER_SIZE: wgpu::BufferAddress =
+    QUERY_COUNT as wgpu::BufferAddress * std::mem::size_of::<u64>() as wgpu::BufferAddress;
+// Number of read back frames that are averaged before the timings are logged
+const REPORT_INTERVAL: u32 = 120;
+
+/// GPU pass timings using timestamp queries. Only does anything if the device was created
+/// with Features::TIMESTAMP_QUERY and profiling is enabled in the GfxSettings.
+///
+/// Per frame: passes ask for timestamp writes with `timestamp_writes()`, `resolve()` copies the
+/// queries into a readback buffer, and `after_submit()` maps it. The results show up a frame
+/// or more later (we never wait on the gpu for them) and are logged every REPORT_INTERVAL
+/// read back frames.
+pub struct Profiler {
+    enabled: bool,
+    query_set: Option<wgpu::QuerySet>,
+    resolve_buffer: Option<wgpu::Buffer>,
+    readback_buffer: Option<wgpu::Buffer>,
+    timestamp_period_ns: f32,
+    // labels of the passes that got timestamps in the frame currently being recorded
+    frame_labels: Vec<&'static str>,
+    // labels of the passes in the frame that is currently in the readback buffer
+    readback_labels: Vec<&'static str>,
+    copy_pending: bool,
+    readback_in_flight: bool,
+    readback_mapped: Arc<AtomicBool>,
+    // summed durations (in ms) per pass label since the last report
+    totals: Vec<(&'static str, f64)>,
+    frames_sampled: u32,
+}
+
+impl Profiler {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, enabled: bool) -> Self {
+        let supported = device.features().contains(wgpu::Features::TIMESTAMP_QUERY);
+        if !supported {
+            log::info!(
+                target: logging::GFX,
+                "timestamp queries not supported, gpu profiling unavailable"
+            );
+        }
+
+        let query_set = supported.then(|| {
+            device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("profiler_query_set"),
+                ty: wgpu::QueryType::Timestamp,
+                count: QUERY_COUNT,
+            })
+        });
+        let resolve_buffer = supported.then(|| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("profiler_resolve_buffer"),
+                size: BUFFER_SIZE,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            })
+        });
+        let readback_buffer = supported.then(|| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("profiler_readback_buffer"),
+                size: BUFFER_SIZE,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+
+        Self {
+            enabled,
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            timestamp_period_ns: queue.get_timestamp_period(),
+            frame_labels: Vec::with_capacity(MAX_PASSES as usize),
+            readback_labels: Vec::with_capacity(MAX_PASSES as usize),
+            copy_pending: false,
+            readback_in_flight: false,
+            readback_mapped: Arc::new(AtomicBool::new(false)),
+            totals: Vec::new(),
+            frames_sampled: 0,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled && self.query_set.is_none() {
+            log::warn!(
+                target: logging::GFX,
+                "gpu profiling requested, but timestamp queries are not supported"
+            );
+        }
+        if !enabled {
+            self.totals.clear();
+            self.frames_sampled = 0;
+        }
+        self.enabled = enabled;
+    }
+
+    /// Picks up the results of an earlier frame (if they are ready) and starts a new frame.
+    pub fn begin_frame(&mut self, device: &wgpu::Device) {
+        self.frame_labels.clear();
+
+        if !self.readback_in_flight {
+            return;
+        }
+        // map_async callbacks only get called when the device is polled
+        let _ = device.poll(wgpu::PollType::Poll);
+        if !self.readback_mapped.load(Ordering::Acquire) {
+            return;
+        }
+
+        let readback_buffer = self.readback_buffer.as_ref().unwrap();
+        {
+            let data = readback_buffer.get_mapped_range(..);
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            if self.enabled {
+                for (i, label) in self.readback_labels.iter().enumerate() {
+                    let ticks = timestamps[i * 2 + 1].saturating_sub(timestamps[i * 2]);
+                    let ms = ticks as f64 * self.timestamp_period_ns as f64 / 1_000_000.0;
+                    match self.totals.iter_mut().find(|(l, _)| l == label) {
+                        Some((_, total)) => *total += ms,
+                        None => self.totals.push((label, ms)),
+                    }
+                }
+                self.frames_sampled += 1;
+            }
+        }
+        readback_buffer.unmap();
+        self.readback_mapped.store(false, Ordering::Release);
+        self.readback_in_flight = false;
+
+        if self.frames_sampled >= REPORT_INTERVAL {
+            self.report();
+        }
+    }
+
+    fn report(&mut self) {
+        let frames = self.frames_sampled as f64;
+        let passes = self
+            .totals
+            .iter()
+            .map(|(label, total)| format!("{}: {:.3}ms", label, total / frames))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sum: f64 = self.totals.iter().map(|(_, total)| total / frames).sum();
+        log::info!(
+            target: logging::GFX,
+            "gpu pass timings (avg over {} frames): {} (total {:.3}ms)",
+            frames,
+            passes,
+            sum
+        );
+        self.totals.clear();
+        self.frames_sampled = 0;
+    }
+
+    /// Timestamp writes for a pass with the given label, or None if profiling is off
+    /// (or unsupported, or the frame already has MAX_PASSES timed passes).
+    pub fn timestamp_writes(
+        &mut self,
+        label: &'static str,
+    ) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        if !self.enabled || self.frame_labels.len() >= MAX_PASSES as usize {
+            return None;
+        }
+        let query_set = self.query_set.as_ref()?;
+        let index = self.frame_labels.len() as u32;
+        self.frame_labels.push(label);
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(index * 2),
+            end_of_pass_write_index: Some(index * 2 + 1),
+        })
+    }
+
+    /// Resolves this frame's queries. Should be called after all passes have been recorded.
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let (Some(query_set), Some(resolve_buffer), Some(readback_buffer)) = (
+            self.query_set.as_ref(),
+            self.resolve_buffer.as_ref(),
+            self.readback_buffer.as_ref(),
+        ) else {
+            return;
+        };
+        // the readback buffer can't be written to while it's (being) mapped, in that case
+        // this frame's timings are simply dropped
+        if self.frame_labels.is_empty() || self.readback_in_flight {
+            return;
+        }
+        let query_count = self.frame_labels.len() as u32 * 2;
+        encoder.resolve_query_set(query_set, 0..query_count, resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            resolve_buffer,
+            0,
+            readback_buffer,
+            0,
+            query_count as wgpu::BufferAddress * std::mem::size_of::<u64>() as wgpu::BufferAddress,
+        );
+        self.readback_labels.clear();
+        self.readback_labels.extend_from_slice(&self.frame_labels);
+        self.copy_pending = true;
+    }
+
+    /// Maps the readback buffer. Must be called after the encoder passed to `resolve()` has
+    /// been submitted.
+    pub fn after_submit(&mut self) {
+        if !self.copy_pending {
+            return;
+        }
+        self.copy_pending = false;
+        self.readback_in_flight = true;
+        let mapped = self.readback_mapped.clone();
+        self.readback_buffer
+            .as_ref()
+            .unwrap()
+            .map_async(wgpu::MapMode::Read, .., move |result| match result {
+                Ok(()) => mapped.store(true, Ordering::Release),
+                Err(e) => {
+                    log::error!(
+                        target: logging::GFX,
+                        "failed to map profiler readback buffer: {:?}",
+                        e
+                    );
+                }
+            });
+    }
+}
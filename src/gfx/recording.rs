@@ -0,0 +1,223 @@
+//! Recording gameplay for bug reports: F10 starts and stops an animated GIF, Shift+F10 a
+//! sequence of PNG files. They're written to the `recordings` directory in the user's data
+//! directory.
+//!
+//! Frames are captured at a reduced rate and read back without waiting for the GPU: a
+//! frame's copy is mapped after it's submitted and picked up on a later frame, and the next
+//! capture waits for it. Converting and shrinking the frames, and encoding the files, happen on
+//! rayon's thread pool, so recording doesn't stall the frames.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, anyhow};
+
+use super::capture::{FrameCapture, Screenshot};
+use crate::file_formats;
+use crate::{logging, paths};
+
+// 10 frames a second
+const CAPTURE_INTERVAL: Duration = Duration::from_millis(100);
+// frames are shrunk to this width (by a whole factor) to keep the recording's memory down
+const MAX_WIDTH: u32 = 480;
+// 30 seconds
+const MAX_FRAMES: usize = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat {
+    Gif,
+    /// A PNG file per frame
+    Png,
+}
+
+// a capture whose copy is being mapped
+struct InFlight {
+    capture: FrameCapture,
+    mapped: Receiver<Result<(), wgpu::BufferAsyncError>>,
+    time: Instant,
+}
+
+// a frame read back from the GPU, converted on the thread pool
+struct Frame {
+    screenshot: Screenshot,
+    // since the recording started
+    time: Duration,
+}
+
+pub(crate) struct FrameRecorder {
+    format: RecordingFormat,
+    started: Instant,
+    last_capture: Option<Instant>,
+    in_flight: Option<InFlight>,
+    converted_sender: Sender<Frame>,
+    converted: Receiver<Frame>,
+    // counted when the capture's copy is picked up, the frames arrive later
+    captured: usize,
+    frames: Vec<Frame>,
+}
+
+impl FrameRecorder {
+    pub fn new(format: RecordingFormat) -> Self {
+        log::info!(target: logging::GFX, "recording started ({:?})", format);
+        let (converted_sender, converted) = mpsc::channel();
+        Self {
+            format,
+            started: Instant::now(),
+            last_capture: None,
+            in_flight: None,
+            converted_sender,
+            converted,
+            captured: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn format(&self) -> RecordingFormat {
+        self.format
+    }
+
+    /// True if a frame should be captured now: the interval has passed, the previous capture
+    /// was read back and there's room for more.
+    pub fn wants_frame(&self, now: Instant) -> bool {
+        self.in_flight.is_none()
+            && self.captured < MAX_FRAMES
+            && self
+                .last_capture
+                .is_none_or(|last| now - last >= CAPTURE_INTERVAL)
+    }
+
+    /// Starts reading back `capture`, once its encoder was submitted.
+    pub fn add(&mut self, capture: FrameCapture, now: Instant) {
+        let mapped = capture.map();
+        self.in_flight = Some(InFlight {
+            capture,
+            mapped,
+            time: now,
+        });
+        self.last_capture = Some(now);
+    }
+
+    /// Picks up the frame being read back if the GPU is done with it, without waiting.
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        if let Some(in_flight) = self.in_flight.take() {
+            // a failed poll shows up as a failed map below
+            let _ = device.poll(wgpu::PollType::Poll);
+            match in_flight.mapped.try_recv() {
+                Err(mpsc::TryRecvError::Empty) => self.in_flight = Some(in_flight),
+                Ok(Ok(())) => {
+                    let frame = in_flight.capture.take_mapped();
+                    let time = in_flight.time - self.started;
+                    let converted = self.converted_sender.clone();
+                    rayon::spawn(move || match frame.convert() {
+                        Ok(screenshot) => {
+                            let screenshot = screenshot.downscaled(MAX_WIDTH);
+                            // the receiver is dropped if the recording was thrown away
+                            let _ = converted.send(Frame { screenshot, time });
+                        }
+                        Err(e) => log::warn!(target: logging::GFX, "dropped a frame: {}", e),
+                    });
+                    self.captured += 1;
+                    if self.captured == MAX_FRAMES {
+                        log::info!(
+                            target: logging::GFX,
+                            "recording is full ({} frames), stop it to write it out",
+                            MAX_FRAMES
+                        );
+                    }
+                }
+                Ok(Err(e)) => log::warn!(target: logging::GFX, "dropped a frame: {}", e),
+                Err(mpsc::TryRecvError::Disconnected) => {}
+            }
+        }
+        self.frames.extend(self.converted.try_iter());
+    }
+
+    /// Writes the recording out on the thread pool. Frames still being read back are waited
+    /// for first.
+    pub fn finish(mut self, device: &wgpu::Device) {
+        if self.in_flight.is_some() {
+            let _ = device.poll(wgpu::PollType::wait_indefinitely());
+            self.poll(device);
+        }
+        let format = self.format;
+        let converted = self.converted;
+        let mut frames = self.frames;
+        let expected = self.captured;
+        rayon::spawn(move || {
+            // the conversions still going on finish before the channel is empty
+            while frames.len() < expected {
+                match converted.recv_timeout(Duration::from_secs(5)) {
+                    Ok(frame) => frames.push(frame),
+                    Err(_) => break,
+                }
+            }
+            // converted in any order
+            frames.sort_by_key(|frame| frame.time);
+            match write(format, &frames) {
+                Ok(path) => log::info!(
+                    target: logging::GFX,
+                    "wrote {} frames to {}",
+                    frames.len(),
+                    path.display()
+                ),
+                Err(e) => log::error!(target: logging::GFX, "unable to write the recording: {}", e),
+            }
+        });
+    }
+}
+
+// writes the GIF, or the directory of PNG files, and returns its path
+fn write(format: RecordingFormat, frames: &[Frame]) -> Result<PathBuf> {
+    let first = frames
+        .first()
+        .ok_or_else(|| anyhow!("no frames were captured"))?;
+    let directory = paths::data_dir(paths::APP_NAME)
+        .ok_or_else(|| anyhow!("no data directory for this platform"))?
+        .join("recordings");
+    let unix_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let name = format!("recording-{}", unix_time);
+    match format {
+        RecordingFormat::Gif => {
+            fs::create_dir_all(&directory)?;
+            let path = directory.join(format!("{}.gif", name));
+            let (width, height) = (first.screenshot.width, first.screenshot.height);
+            // a GIF has one size, frames from after the window was resized are left out
+            let frames: Vec<&Frame> = frames
+                .iter()
+                .filter(|frame| {
+                    frame.screenshot.width == width && frame.screenshot.height == height
+                })
+                .collect();
+            // each frame is shown until the next one was captured, in hundredths of a second
+            let delays = frames
+                .windows(2)
+                .map(|pair| (pair[1].time - pair[0].time).as_millis() / 10)
+                .chain(std::iter::once(CAPTURE_INTERVAL.as_millis() / 10));
+            let gif = file_formats::encode_gif(
+                width,
+                height,
+                frames
+                    .iter()
+                    .zip(delays)
+                    .map(|(frame, delay)| (frame.screenshot.rgba.as_slice(), delay as u16)),
+            );
+            fs::write(&path, gif)?;
+            Ok(path)
+        }
+        RecordingFormat::Png => {
+            let path = directory.join(name);
+            fs::create_dir_all(&path)?;
+            for (i, frame) in frames.iter().enumerate() {
+                fs::write(
+                    path.join(format!("frame-{:04}.png", i)),
+                    frame.screenshot.to_png(),
+                )?;
+            }
+            Ok(path)
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use super::profiler::Profiler;
+use crate::world::{WORLD_HEIGHT, WORLD_WIDTH};
+
+// A minimal pass list. Renderers implement RenderNode to declare the pass they want
+// (target, load op, multisampling) and record their draw calls into it. GfxState::render
+// builds the render passes for all nodes in order, so adding a renderer means adding it
+// to GfxState::render_nodes instead of copying render pass boilerplate into render().
+
+/// Format of the depth/stencil buffer the layered passes share, see [`Layer`]
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+
+/// Which layer a 2D draw (text, shapes, lines) belongs to, back to front. Layers are written to
+/// the depth buffer, so something on a nearer layer stays on top of a farther one no matter
+/// which pass draws it first. Within a layer, later draws go over earlier ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    Background,
+    /// Ships, particles, trails
+    World,
+    /// Score, menus, widgets
+    Hud,
+    /// The log panel, over everything
+    Console,
+}
+
+impl Layer {
+    /// Depth in clip space. The depth buffer is cleared to 1, nearer is smaller.
+    pub fn depth(self) -> f32 {
+        match self {
+            Layer::Background => 0.8,
+            Layer::World => 0.6,
+            Layer::Hud => 0.4,
+            Layer::Console => 0.2,
+        }
+    }
+}
+
+/// Depth test for the pipelines of layered passes: draws on the same or a nearer layer pass,
+/// and write their layer's depth so farther layers drawn later stay underneath.
+pub fn layered_depth_stencil() -> wgpu::DepthStencilState {
+    wgpu::DepthStencilState {
+        format: DEPTH_FORMAT,
+        depth_write_enabled: true,
+        depth_compare: wgpu::CompareFunction::LessEqual,
+        stencil: wgpu::StencilState::default(),
+        bias: wgpu::DepthBiasState::default(),
+    }
+}
+
+/// The attachment a pass renders into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassTarget {
+    /// The offscreen (HDR) scene texture, which is post-processed into the surface.
+    Scene,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PassDescriptor {
+    pub label: &'static str,
+    pub target: PassTarget,
+    /// What to do with the target's existing contents at the start of the pass
+    pub load: wgpu::LoadOp<wgpu::Color>,
+    /// Render into the multisampled color target (if MSAA is enabled) and resolve into `target`
+    pub multisampled: bool,
+    /// Attach the depth/stencil buffer (multisampled too if `multisampled`), for renderers that
+    /// draw in layers (see [`Layer`]). Their pipelines use [`layered_depth_stencil`].
+    pub depth: bool,
+}
+
+/// Per-frame data that every node may need while recording.
+pub struct FrameContext<'a> {
+    /// For growing buffers (see [`super::buffer::GrowableBuffer`])
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub game_state: &'a crate::GameState,
+    pub surface_dimensions_bind_group: &'a wgpu::BindGroup,
+    pub surface_width: u32,
+    pub surface_height: u32,
+    /// Logical pixels of text and the UI to physical ones: the window's scale factor times
+    /// the UI scale
+    pub screen_scale_factor: f32,
+    pub window_scale_factor: f32,
+    /// How far the frame is between the previous tick and the current one, see
+    /// [`crate::interpolation`]
+    pub render_alpha: f32,
+}
+
+impl FrameContext<'_> {
+    /// Converts a point in world units to clip space. The world is scaled uniformly to fit
+    /// the surface, and centered, then moved and zoomed by the camera's shake and punch (see
+    /// [`crate::camera`]).
+    pub fn world_to_clip(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        let width = self.surface_width as f32;
+        let height = self.surface_height as f32;
+        let camera = self.game_state.camera();
+        let scale = (width / WORLD_WIDTH).min(height / WORLD_HEIGHT) * camera.zoom();
+        let [shake_x, shake_y] = camera.shake_offset();
+        [
+            (x + shake_x - WORLD_WIDTH / 2.0) * scale * 2.0 / width,
+            (y + shake_y - WORLD_HEIGHT / 2.0) * scale * 2.0 / height,
+        ]
+    }
+}
+
+pub trait RenderNode {
+    fn pass_descriptor(&self) -> PassDescriptor;
+
+    fn record(&mut self, render_pass: &mut wgpu::RenderPass<'_>, ctx: &FrameContext);
+}
+
+pub struct RenderTargets<'a> {
+    pub scene: &'a wgpu::TextureView,
+    pub msaa: Option<&'a wgpu::TextureView>,
+    pub depth: &'a wgpu::TextureView,
+    /// Depth buffer of the multisampled passes, if MSAA is enabled
+    pub msaa_depth: Option<&'a wgpu::TextureView>,
+}
+
+/// Records `record` in a debug group called `label`, so the frame's commands are grouped by
+/// what they're for in RenderDoc, PIX or Xcode.
+pub fn debug_group<T>(
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    record: impl FnOnce(&mut wgpu::CommandEncoder) -> T,
+) -> T {
+    encoder.push_debug_group(label);
+    let result = record(encoder);
+    encoder.pop_debug_group();
+    result
+}
+
+/// Marks where in its pass a node queued an upload. Queue writes aren't commands of the
+/// pass, they run before the frame's command buffer; the marker says which pass they were
+/// for and how large they were.
+pub fn mark_upload(render_pass: &mut wgpu::RenderPass<'_>, label: &str, bytes: usize) {
+    render_pass.insert_debug_marker(&format!("upload {} ({} bytes)", label, bytes));
+}
+
+pub fn begin_pass<'e>(
+    encoder: &'e mut wgpu::CommandEncoder,
+    label: &str,
+    view: &wgpu::TextureView,
+    resolve_target: Option<&wgpu::TextureView>,
+    ops: wgpu::Operations<wgpu::Color>,
+    depth_stencil_attachment: Option<wgpu::RenderPassDepthStencilAttachment<'_>>,
+    timestamp_writes: Option<wgpu::RenderPassTimestampWrites<'_>>,
+) -> wgpu::RenderPass<'e> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            // The view to save the colors to
+            view,
+            // Target that will receive the resolved output if multisampling is enabled
+            resolve_target,
+            ops,
+            depth_slice: None,
+        })],
+        depth_stencil_attachment,
+        timestamp_writes,
+        occlusion_query_set: None,
+    })
+}
+
+pub fn record_passes(
+    nodes: &mut [&mut dyn RenderNode],
+    targets: &RenderTargets,
+    encoder: &mut wgpu::CommandEncoder,
+    ctx: &FrameContext,
+    profiler: &mut Profiler,
+) {
+    let descriptors: Vec<PassDescriptor> = nodes.iter().map(|n| n.pass_descriptor()).collect();
+    // the depth buffers are cleared by the first pass that uses them each frame
+    let mut depth_cleared = false;
+    let mut msaa_depth_cleared = false;
+
+    for (i, node) in nodes.iter_mut().enumerate() {
+        let descriptor = &descriptors[i];
+
+        let target_view = match descriptor.target {
+            PassTarget::Scene => targets.scene,
+        };
+
+        let msaa_view = targets.msaa.filter(|_| descriptor.multisampled);
+
+        // The multisampled target only has to survive the pass if a later pass still
+        // renders into it (and loads its contents), otherwise the resolve is all we need.
+        let store = if msaa_view.is_some()
+            && !descriptors[i + 1..]
+                .iter()
+                .any(|d| d.multisampled && d.target == descriptor.target)
+        {
+            wgpu::StoreOp::Discard
+        } else {
+            wgpu::StoreOp::Store
+        };
+
+        let depth_stencil_attachment = if descriptor.depth {
+            let (view, cleared) = match targets.msaa_depth.filter(|_| msaa_view.is_some()) {
+                Some(view) => (view, &mut msaa_depth_cleared),
+                None => (targets.depth, &mut depth_cleared),
+            };
+            let (depth_load, stencil_load) = if *cleared {
+                (wgpu::LoadOp::Load, wgpu::LoadOp::Load)
+            } else {
+                (wgpu::LoadOp::Clear(1.0), wgpu::LoadOp::Clear(0))
+            };
+            *cleared = true;
+            Some(wgpu::RenderPassDepthStencilAttachment {
+                view,
+                depth_ops: Some(wgpu::Operations {
+                    load: depth_load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: Some(wgpu::Operations {
+                    load: stencil_load,
+                    store: wgpu::StoreOp::Store,
+                }),
+            })
+        } else {
+            None
+        };
+
+        // begin_render_pass borrows encoder mutably, so the pass is dropped at the end of
+        // every iteration before the next one can begin (and before encoder.finish())
+        let mut render_pass = begin_pass(
+            encoder,
+            descriptor.label,
+            msaa_view.unwrap_or(target_view),
+            msaa_view.map(|_| target_view),
+            wgpu::Operations {
+                load: descriptor.load,
+                store,
+            },
+            depth_stencil_attachment,
+            profiler.timestamp_writes(descriptor.label),
+        );
+
+        node.record(&mut render_pass, ctx);
+    }
+}
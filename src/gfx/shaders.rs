@@ -0,0 +1,138 @@
+//! Shaders that can be edited while the game runs. Debug builds load them from the source
+//! tree and watch the files (through crate::assets), and GfxState rebuilds the pipelines of a
+//! changed shader once it's reloaded. A shader that doesn't compile is logged and its pipeline
+//! keeps the previous version. Release builds, and debug builds that can't find the source
+//! tree, use the copies compiled into the executable.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+
+use crate::assets::{AssetStore, Handle, ShaderSource};
+use crate::logging;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Shader {
+    Text,
+    Line,
+    DebugLine,
+}
+
+impl Shader {
+    pub const ALL: [Shader; 3] = [Shader::Text, Shader::Line, Shader::DebugLine];
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Shader::Text => "text_shader.wgsl",
+            Shader::Line => "line-shader.wgsl",
+            Shader::DebugLine => "debug-line-shader.wgsl",
+        }
+    }
+
+    /// Where the shader is in the source tree
+    pub fn source_path(self) -> PathBuf {
+        let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        match self {
+            Shader::Text => src.join("gfx").join(self.file_name()),
+            Shader::Line | Shader::DebugLine => src.join(self.file_name()),
+        }
+    }
+
+    fn embedded_source(self) -> &'static str {
+        match self {
+            Shader::Text => include_str!("text_shader.wgsl"),
+            Shader::Line => include_str!("../line-shader.wgsl"),
+            Shader::DebugLine => include_str!("../debug-line-shader.wgsl"),
+        }
+    }
+}
+
+/// Parses and validates WGSL, with the error formatted like the compiler does.
+pub fn validate(name: &str, source: &str) -> Result<()> {
+    use wgpu::naga;
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|error| anyhow!("{}", error.emit_to_string_with_path(source, name)))?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|error| anyhow!("{}", error.emit_to_string_with_path(source, name)))?;
+    Ok(())
+}
+
+pub struct ShaderManager {
+    // the shaders in the source tree, loaded and watched in debug builds
+    store: AssetStore<ShaderSource>,
+    handles: HashMap<Shader, Handle<ShaderSource>>,
+    // the sources the pipelines were built with, the others use the embedded source
+    sources: HashMap<Shader, String>,
+}
+
+impl ShaderManager {
+    /// Loads and watches the shaders in debug builds, if the source tree is there.
+    pub fn new() -> Self {
+        let mut manager = Self {
+            store: AssetStore::default(),
+            handles: HashMap::new(),
+            sources: HashMap::new(),
+        };
+        if !cfg!(debug_assertions) {
+            return manager;
+        }
+        if !Shader::ALL
+            .iter()
+            .all(|shader| shader.source_path().exists())
+        {
+            log::info!(
+                target: logging::GFX,
+                "shader sources not found, shader hot reloading is off"
+            );
+            return manager;
+        }
+        manager.store.set_hot_reload(true);
+        for shader in Shader::ALL {
+            // a shader that doesn't compile is logged by the store
+            let handle = manager.store.load_blocking(shader.source_path());
+            if let Some(loaded) = handle.get() {
+                manager.sources.insert(shader, loaded.source.clone());
+            }
+            manager.handles.insert(shader, handle);
+        }
+        manager
+    }
+
+    /// The current source of the shader.
+    pub fn source(&self, shader: Shader) -> &str {
+        self.sources
+            .get(&shader)
+            .map_or(shader.embedded_source(), String::as_str)
+    }
+
+    /// Makes `source` the shader's current source, once its pipelines were rebuilt with it.
+    pub fn set_source(&mut self, shader: Shader, source: String) {
+        self.sources.insert(shader, source);
+    }
+
+    /// The new sources of the shaders whose files were reloaded since the last call. Changes
+    /// that don't compile are logged and left out.
+    pub fn poll_changes(&mut self) -> Vec<(Shader, String)> {
+        let reloaded = self.store.poll_changes();
+        Shader::ALL
+            .into_iter()
+            .filter_map(|shader| {
+                let handle = self.handles.get(&shader)?;
+                let loaded = handle.get().filter(|_| reloaded.contains(handle))?;
+                Some((shader, loaded.source.clone()))
+            })
+            .filter(|(shader, source)| source != self.source(*shader))
+            .collect()
+    }
+}
+
+impl Default for ShaderManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
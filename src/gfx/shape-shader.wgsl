@@ -0,0 +1,97 @@
+// Instanced rectangles with rounded corners and a border, in physical pixels. The edges are
+// antialiased with the distance to the rounded rectangle (a signed distance field).
+struct SurfaceDimensionsUniform {
+    width: u32,
+    height: u32,
+    scale_factor: f32
+}
+
+@group(0) @binding(0)
+var<uniform> surface_dimensions: SurfaceDimensionsUniform;
+
+struct InstanceInput {
+    // left, top, right, bottom
+    @location(0) rect: vec4<f32>,
+    @location(1) fill_color: vec4<f32>,
+    @location(2) border_color: vec4<f32>,
+    @location(3) corner_radius: f32,
+    @location(4) border_width: f32,
+    // layer, see render_graph::Layer
+    @location(5) depth: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // pixels from the center of the rectangle
+    @location(0) local: vec2<f32>,
+    @location(1) half_size: vec2<f32>,
+    @location(2) fill_color: vec4<f32>,
+    @location(3) border_color: vec4<f32>,
+    @location(4) corner_radius: f32,
+    @location(5) border_width: f32,
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    instance: InstanceInput,
+) -> VertexOutput {
+    // two triangles
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let center = (instance.rect.xy + instance.rect.zw) / 2.0;
+    let half_size = (instance.rect.zw - instance.rect.xy) / 2.0;
+    // a pixel bigger, for the antialiased edge
+    let local = corners[vertex_index] * (half_size + 1.0);
+    let position = center + local;
+    let size = vec2<f32>(f32(surface_dimensions.width), f32(surface_dimensions.height));
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(
+        -1.0 + 2.0 * position.x / size.x,
+        1.0 - 2.0 * position.y / size.y,
+        instance.depth,
+        1.0,
+    );
+    out.local = local;
+    out.half_size = half_size;
+    out.fill_color = instance.fill_color;
+    out.border_color = instance.border_color;
+    out.corner_radius = min(instance.corner_radius, min(half_size.x, half_size.y));
+    out.border_width = instance.border_width;
+    return out;
+}
+
+// negative inside, positive outside
+fn rounded_rect_distance(local: vec2<f32>, half_size: vec2<f32>, radius: f32) -> f32 {
+    let q = abs(local) - half_size + radius;
+    return length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+@fragment
+fn fs_main(
+    in: VertexOutput,
+) -> @location(0) vec4<f32> {
+    let distance = rounded_rect_distance(in.local, in.half_size, in.corner_radius);
+    let coverage = clamp(0.5 - distance, 0.0, 1.0);
+    // 1 in the border, blended into the fill over a pixel
+    var border = 0.0;
+    if in.border_width > 0.0 {
+        border = clamp(0.5 + distance + in.border_width, 0.0, 1.0);
+    }
+    // blended premultiplied, so a transparent fill doesn't darken the border's inner edge
+    let fill = vec4<f32>(in.fill_color.rgb * in.fill_color.a, in.fill_color.a);
+    let stroke = vec4<f32>(in.border_color.rgb * in.border_color.a, in.border_color.a);
+    let color = mix(fill, stroke, border);
+    // nothing to draw, and it mustn't write the layer's depth over what's behind it
+    if color.a * coverage <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(color.rgb / color.a, color.a * coverage);
+}
@@ -0,0 +1,198 @@
+//! Rectangles with rounded corners and borders, for UI panels and buttons (see crate::ui),
+//! bars and debug boxes. They are drawn as instanced quads and the fragment shader works out
+//! the rounded corners, the border and the antialiased edges from the distance to the shape, so
+//! they stay crisp at any size and scale factor.
+
+use super::buffer::GrowableBuffer;
+use super::render_graph::{
+    FrameContext, Layer, PassDescriptor, PassTarget, RenderNode, layered_depth_stencil, mark_upload,
+};
+use super::vertex::ShapeInstance;
+
+// to start with, the buffer grows to fit
+const INITIAL_SHAPES: usize = 256;
+// the log panel's background, its corner radius in logical pixels
+const LOG_PANEL_BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const LOG_PANEL_CORNER_RADIUS: f32 = 6.0;
+
+/// A filled and/or outlined rectangle. Everything is in physical pixels, like the text
+/// renderer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoundedRect {
+    /// Left, top, right and bottom edges in physical pixels from the top left of the window
+    pub rect: [f32; 4],
+    /// Transparent for just the border
+    pub fill_color: [f32; 4],
+    pub border_color: [f32; 4],
+    /// Inside the rectangle's edges, 0 for no border
+    pub border_width: f32,
+    /// Limited to half the rectangle's width and height (a pill)
+    pub corner_radius: f32,
+    /// Hud by default
+    pub layer: Layer,
+}
+
+impl RoundedRect {
+    /// A filled rectangle with square corners and no border.
+    pub fn new(rect: [f32; 4], fill_color: [f32; 4]) -> Self {
+        Self {
+            rect,
+            fill_color,
+            border_color: [0.0; 4],
+            border_width: 0.0,
+            corner_radius: 0.0,
+            layer: Layer::Hud,
+        }
+    }
+
+    /// Just the border, e.g. for debug boxes.
+    pub fn outline(rect: [f32; 4], width: f32, color: [f32; 4]) -> Self {
+        Self::new(rect, [0.0; 4]).border(width, color)
+    }
+
+    pub fn border(mut self, width: f32, color: [f32; 4]) -> Self {
+        self.border_width = width;
+        self.border_color = color;
+        self
+    }
+
+    pub fn corner_radius(mut self, radius: f32) -> Self {
+        self.corner_radius = radius;
+        self
+    }
+
+    pub fn layer(mut self, layer: Layer) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    fn instance(&self) -> ShapeInstance {
+        ShapeInstance {
+            rect: self.rect,
+            fill_color: self.fill_color,
+            border_color: self.border_color,
+            corner_radius: self.corner_radius.max(0.0),
+            border_width: self.border_width.max(0.0),
+            depth: self.layer.depth(),
+        }
+    }
+}
+
+/// Draws the shapes of the current screen's overlay (see crate::screen::ScreenOverlay) and the
+/// background of the log panel (see crate::log_panel), back to front by layer.
+pub struct ShapeRenderer {
+    render_pipeline: wgpu::RenderPipeline,
+    instance_buffer: GrowableBuffer,
+}
+
+impl ShapeRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        surface_dimensions_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shape shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shape-shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shape pipeline layout"),
+            bind_group_layouts: &[surface_dimensions_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shape pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                // no vertex buffer, the quad's corners come from the vertex index
+                buffers: &[ShapeInstance::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+                polygon_mode: wgpu::PolygonMode::Fill,
+                // Requires Features::DEPTH_CLIP_CONTROL
+                unclipped_depth: false,
+                // Requires Features::CONSERVATIVE_RASTERIZATION
+                conservative: false,
+            },
+            depth_stencil: Some(layered_depth_stencil()),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        let instance_buffer = GrowableBuffer::new(
+            device,
+            "shape_instance_buffer",
+            wgpu::BufferUsages::VERTEX,
+            (INITIAL_SHAPES * std::mem::size_of::<ShapeInstance>()) as wgpu::BufferAddress,
+        );
+
+        Self {
+            render_pipeline,
+            instance_buffer,
+        }
+    }
+}
+
+impl RenderNode for ShapeRenderer {
+    fn pass_descriptor(&self) -> PassDescriptor {
+        PassDescriptor {
+            label: "Shape render pass",
+            target: PassTarget::Scene,
+            load: wgpu::LoadOp::Load,
+            multisampled: false,
+            depth: true,
+        }
+    }
+
+    fn record(&mut self, render_pass: &mut wgpu::RenderPass<'_>, ctx: &FrameContext) {
+        let game_state = ctx.game_state;
+        let mut shapes: Vec<&RoundedRect> = game_state.overlay.shapes.iter().collect();
+        let background = RoundedRect::new(game_state.log_panel.rect(), LOG_PANEL_BACKGROUND)
+            .corner_radius(LOG_PANEL_CORNER_RADIUS * ctx.screen_scale_factor)
+            .layer(Layer::Console);
+        if game_state.log_panel.visible {
+            shapes.push(&background);
+        }
+        if shapes.is_empty() {
+            return;
+        }
+        // back to front, so the edges of nearer shapes blend over farther ones (stable, so
+        // the order within a layer stays)
+        shapes.sort_by_key(|shape| shape.layer);
+        let instances: Vec<ShapeInstance> = shapes.into_iter().map(RoundedRect::instance).collect();
+
+        let data: &[u8] = bytemuck::cast_slice(&instances);
+        self.instance_buffer.write(ctx.device, ctx.queue, data);
+        mark_upload(render_pass, self.instance_buffer.label(), data.len());
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, ctx.surface_dimensions_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(data.len()));
+        render_pass.draw(0..6, 0..instances.len() as u32);
+    }
+}
@@ -1,12 +1,18 @@
 use std::ffi::OsStr;
 use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Range;
 use std::path::PathBuf;
 use std::str::FromStr;
-use std::sync::{Arc, LazyLock, OnceLock};
-use std::{collections::HashMap, path::Path};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, OnceLock, Weak};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
 
 use anyhow::{Context, Result};
-use harfrust::{Feature, GlyphBuffer, ShaperData, ShaperInstance, UnicodeBuffer, Variation};
+use harfrust::{Feature, GlyphBuffer, ShaperData, ShaperInstance, Tag, UnicodeBuffer, Variation};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use parking_lot::Mutex;
 use rayon::prelude::*;
 use skrifa::raw::TableProvider;
@@ -15,9 +21,18 @@ use skrifa::{
 };
 use smallvec::SmallVec;
 use thiserror::Error;
-use typed_arena::Arena;
 use zeno::PathBuilder;
 
+use super::buffer::GrowableBuffer;
+use super::gpu_cache::GpuCache;
+use super::render_graph::{
+    FrameContext, Layer, PassDescriptor, PassTarget, RenderNode, layered_depth_stencil, mark_upload,
+};
+use crate::assets::{FontData, Handle};
+use crate::error::GameError;
+use crate::frame_pacing::{Stage, StageTimes};
+use crate::logging;
+use crate::memory::{HeapSize, vec_size};
 use crate::os::font_util;
 
 #[repr(C)]
@@ -26,6 +41,9 @@ pub struct GlyphVertex {
     pub caret_position: [i32; 2],
     pub px_bounds_offset: [i32; 2],
     pub tex_coords: [u32; 2],
+    pub color: [f32; 4],
+    /// See [`Layer::depth`]
+    pub depth: f32,
 }
 
 impl GlyphVertex {
@@ -49,33 +67,1276 @@ impl GlyphVertex {
                     offset: 2 * size_of::<[i32; 2]>() as wgpu::BufferAddress,
                     shader_location: 2,
                 },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 3 * size_of::<[i32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32,
+                    offset: (3 * size_of::<[i32; 2]>() + size_of::<[f32; 4]>())
+                        as wgpu::BufferAddress,
+                    shader_location: 4,
+                },
             ],
         }
     }
 }
 
+// initial capacity of the glyph vertex buffer (4 per glyph), enough for a full log panel (see
+// crate::log_panel). It grows to fit.
+const INITIAL_GLYPH_VERTICES: usize = 32768;
+
+// where the text shader's dual source blending part starts, see single_source_shader
+const DUAL_SOURCE_SECTION: &str = "// dual source blending from here on";
+
+/// The text shader for adapters without Features::DUAL_SOURCE_BLENDING: without its `enable`
+/// directive and its dual source section, which leaves the grayscale fragment shader.
+pub(crate) fn single_source_shader(source: &str) -> String {
+    let source = source.split(DUAL_SOURCE_SECTION).next().unwrap_or(source);
+    source.replace("enable dual_source_blending;", "")
+}
+
+/// Where the lines of a [`TextDraw`] go relative to its x position.
+///
+/// Right and decimal aligned text uses tabular figures (digits that all have the same
+/// advance), so numbers stacked in a column line up digit by digit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    /// Lines start at the position
+    #[default]
+    Left,
+    /// Lines end at the position
+    Right,
+    /// The first `.` of each line is at the position (the end of a line without one)
+    Decimal,
+}
+
+/// What happens to the lines of a [`TextDraw`] that are wider than its `max_width`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextOverflow {
+    /// The glyphs that don't fit are left out
+    #[default]
+    Clip,
+    /// The end is cut off and replaced by "…"
+    Ellipsis,
+    /// The middle is cut out and replaced by "…", the start and the end stay (e.g. for paths)
+    MiddleEllipsis,
+}
+
+/// A copy of the text drawn behind it, see [`TextDraw::shadow`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextShadow {
+    /// In pixels, y down
+    pub offset: [f32; 2],
+    pub color: [f32; 4],
+}
+
+/// A border around the glyphs, see [`TextDraw::outline`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextOutline {
+    /// In pixels
+    pub width: f32,
+    pub color: [f32; 4],
+}
+
+/// A rectangle behind each line, see [`TextDraw::background`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextBackground {
+    pub color: [f32; 4],
+    /// Around the line, in pixels
+    pub padding: f32,
+}
+
+impl TextAlign {
+    // how far left of the position a line starts
+    fn offset(self, layout: &LineLayout) -> f32 {
+        match self {
+            TextAlign::Left => 0.0,
+            TextAlign::Right => layout.width,
+            TextAlign::Decimal => layout.decimal_x,
+        }
+    }
+}
+
+/// How the glyphs of a [`TextDraw`] are rasterized and blended.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum TextAntialiasing {
+    /// Subpixel coverage per color channel (see [`LcdFilter`]). Sharpest on a horizontal LCD
+    /// panel, but colored fringes on other screens and in screenshots and captures that are
+    /// scaled. Needs Features::DUAL_SOURCE_BLENDING, Grayscale is used instead without it.
+    #[default]
+    Lcd,
+    /// One coverage value per pixel
+    Grayscale,
+    /// Fully covered pixels or nothing, for pixel fonts
+    None,
+}
+
+/// The subpixel layout of the screen and the filter that spreads a glyph's subpixel coverage
+/// over the neighbouring subpixels. Without a filter subpixel text has strong color fringes,
+/// more filtering trades them for blur.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct LcdFilter {
+    /// Taken from the subpixels two to the left up to two to the right, they should add up
+    /// to 1
+    pub weights: [f32; 5],
+    /// The screen's subpixels are blue, green, red from left to right
+    pub bgr: bool,
+}
+
+impl LcdFilter {
+    /// FreeType's default filter
+    pub const DEFAULT: LcdFilter = LcdFilter {
+        weights: [0.031, 0.302, 0.334, 0.302, 0.031],
+        bgr: false,
+    };
+    /// FreeType's light filter, sharper with more color
+    pub const LIGHT: LcdFilter = LcdFilter {
+        weights: [0.0, 0.333, 0.334, 0.333, 0.0],
+        bgr: false,
+    };
+    /// Unfiltered subpixel coverage
+    pub const NONE: LcdFilter = LcdFilter {
+        weights: [0.0, 0.0, 1.0, 0.0, 0.0],
+        bgr: false,
+    };
+
+    // filters an RGBA subpixel mask (rows of `width * 4` bytes) in place
+    fn apply(&self, mask: &mut [u8], width: usize) {
+        if *self == LcdFilter::NONE || width == 0 {
+            return;
+        }
+        // the channels in the order the subpixels are on the screen
+        let channels = if self.bgr { [2, 1, 0] } else { [0, 1, 2] };
+        let mut subpixels = Vec::with_capacity(width * 3);
+        for row in mask.chunks_exact_mut(width * 4) {
+            subpixels.clear();
+            for pixel in row.chunks_exact(4) {
+                subpixels.extend(channels.map(|channel| pixel[channel] as f32));
+            }
+            for (i, pixel) in row.chunks_exact_mut(4).enumerate() {
+                for (j, channel) in channels.into_iter().enumerate() {
+                    let subpixel = i * 3 + j;
+                    let filtered: f32 = self
+                        .weights
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(k, weight)| {
+                            let neighbour = (subpixel + k).checked_sub(2)?;
+                            Some(weight * subpixels.get(neighbour)?)
+                        })
+                        .sum();
+                    pixel[channel] = filtered.round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+    }
+}
+
+impl Default for LcdFilter {
+    fn default() -> Self {
+        LcdFilter::DEFAULT
+    }
+}
+
+/// Identifies a link in text, see [`TextDraw::link`]. What a link does is up to the screen
+/// that draws it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LinkId(pub u32);
+
+/// A clickable span of a [`TextDraw`]'s text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLink {
+    /// Byte range in the text
+    pub range: Range<usize>,
+    pub id: LinkId,
+}
+
+/// Where a link is on the screen, see [`TextDraw::link_regions`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkRegion {
+    pub id: LinkId,
+    /// Left, top, right and bottom edges in physical pixels from the top left of the window
+    pub rect: [f32; 4],
+}
+
+impl LinkRegion {
+    pub fn contains(&self, point: [f32; 2]) -> bool {
+        let [left, top, right, bottom] = self.rect;
+        (left..right).contains(&point[0]) && (top..bottom).contains(&point[1])
+    }
+}
+
+/// Tab stops are this many spaces apart by default
+pub const DEFAULT_TAB_WIDTH: f32 = 4.0;
+
+/// Text for the text renderer to draw, e.g. from a screen's overlay. Lines are split at
+/// `\n` (and `\r\n`), `\t` goes to the next tab stop and other control characters are left
+/// out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextDraw {
+    pub text: String,
+    /// Top of the first line, in logical pixels from the top left of the surface. The x
+    /// coordinate is where the lines are aligned to, see `align`.
+    pub position: [f32; 2],
+    /// Font size in logical pixels per em
+    pub size: f32,
+    pub color: [f32; 4],
+    pub align: TextAlign,
+    /// Spans that can be hovered and clicked, see [`crate::input::Links`]
+    pub links: Vec<TextLink>,
+    /// Variations (e.g. a named instance like "Bold" of a variable font) and features the
+    /// text is shaped and drawn with
+    pub shaper_settings: ShaperSettings,
+    /// Hud by default
+    pub layer: Layer,
+    /// None for the renderer's default (see [`TextRenderer::set_default_antialiasing`])
+    pub antialiasing: Option<TextAntialiasing>,
+    /// Distance between tab stops, in widths of a space
+    pub tab_width: f32,
+    /// Lines wider than this (in pixels) are cut down to it as `overflow` says
+    pub max_width: Option<f32>,
+    pub overflow: TextOverflow,
+    pub shadow: Option<TextShadow>,
+    pub outline: Option<TextOutline>,
+    pub background: Option<TextBackground>,
+    /// Font cache index of the font the text is drawn with, None for the UI font (see
+    /// [`crate::game_state::GameState::font_for`])
+    pub font: Option<usize>,
+    /// All digits get the same advance, so changing numbers don't jitter. Right and decimal
+    /// aligned text has them either way.
+    pub tabular_figures: bool,
+    /// Space added between clusters, in ems (negative tightens the text)
+    pub letter_spacing: f32,
+    /// Space added after each space, in ems
+    pub word_spacing: f32,
+    /// Byte ranges of the text that are underlined, e.g. what an input method is composing
+    pub underlines: Vec<Range<usize>>,
+    /// Byte ranges of the text drawn in another color than the text's, see
+    /// [`TextDraw::color_range`]
+    pub color_ranges: Vec<(Range<usize>, [f32; 4])>,
+}
+
+impl TextDraw {
+    /// White, left aligned text.
+    pub fn new(text: impl Into<String>, position: [f32; 2], size: f32) -> Self {
+        Self {
+            text: text.into(),
+            position,
+            size,
+            color: [1.0, 1.0, 1.0, 1.0],
+            align: TextAlign::Left,
+            links: Vec::new(),
+            shaper_settings: ShaperSettings::new(),
+            layer: Layer::Hud,
+            antialiasing: None,
+            tab_width: DEFAULT_TAB_WIDTH,
+            max_width: None,
+            overflow: TextOverflow::Clip,
+            shadow: None,
+            outline: None,
+            background: None,
+            font: None,
+            tabular_figures: false,
+            letter_spacing: 0.0,
+            word_spacing: 0.0,
+            underlines: Vec::new(),
+            color_ranges: Vec::new(),
+        }
+    }
+
+    pub fn layer(mut self, layer: Layer) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn antialiasing(mut self, antialiasing: TextAntialiasing) -> Self {
+        self.antialiasing = Some(antialiasing);
+        self
+    }
+
+    /// Puts the tab stops `tab_width` spaces apart.
+    pub fn tab_width(mut self, tab_width: f32) -> Self {
+        self.tab_width = tab_width;
+        self
+    }
+
+    /// Cuts lines wider than `max_width` (in pixels) down to it, see [`TextOverflow`].
+    pub fn truncate(mut self, max_width: f32, overflow: TextOverflow) -> Self {
+        self.max_width = Some(max_width);
+        self.overflow = overflow;
+        self
+    }
+
+    /// Draws the text again in `color`, `offset` pixels (y down) behind it.
+    pub fn shadow(mut self, offset: [f32; 2], color: [f32; 4]) -> Self {
+        self.shadow = Some(TextShadow { offset, color });
+        self
+    }
+
+    /// Draws a `width` pixels wide border in `color` around the glyphs (and their shadow).
+    pub fn outline(mut self, width: f32, color: [f32; 4]) -> Self {
+        self.outline = Some(TextOutline { width, color });
+        self
+    }
+
+    /// Fills a rectangle in `color` behind each line, `padding` pixels larger than the line.
+    pub fn background(mut self, color: [f32; 4], padding: f32) -> Self {
+        self.background = Some(TextBackground { color, padding });
+        self
+    }
+
+    /// Underlines the byte `range` of the text, in the text's color. The line is the font's
+    /// underline, it goes under whole clusters.
+    pub fn underline(mut self, range: Range<usize>) -> Self {
+        self.underlines.push(range);
+        self
+    }
+
+    /// Draws the byte `range` of the text in `color` instead of the text's color (a glyph by
+    /// where its cluster starts), e.g. a name in a line of chat. The last range a glyph is in
+    /// wins. Shadows, outlines and underlines keep their colors.
+    pub fn color_range(mut self, range: Range<usize>, color: [f32; 4]) -> Self {
+        self.color_ranges.push((range, color));
+        self
+    }
+
+    /// Draws the text in the font with the font cache index `font` instead of the UI font.
+    pub fn font(mut self, font: usize) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    /// Makes the `range` (in bytes) of the text a link.
+    pub fn link(mut self, range: Range<usize>, id: LinkId) -> Self {
+        self.links.push(TextLink { range, id });
+        self
+    }
+
+    /// Draws the text at the variable font's `variations`, see
+    /// [`ShaperSettings::with_variations`].
+    pub fn variations(mut self, variations: impl IntoIterator<Item: Into<Variation>>) -> Self {
+        self.shaper_settings = self.shaper_settings.with_variations(variations);
+        self
+    }
+
+    /// Turns the OpenType feature `tag` (e.g. `liga`, `calt`, `tnum` or `ss01`) on or off
+    /// for the text, see [`ShaperSettings::with_feature`].
+    pub fn feature(mut self, tag: Tag, enabled: bool) -> Self {
+        self.shaper_settings = self.shaper_settings.with_feature(tag, enabled);
+        self
+    }
+
+    /// Gives all digits the same advance: the font's tabular figures, or each digit centered
+    /// in the advance of the widest one for fonts without them.
+    pub fn tabular_figures(mut self) -> Self {
+        self.tabular_figures = true;
+        self
+    }
+
+    /// Spaces the clusters `em`s apart (see [`LineLayout::spaced`]), e.g. for titles. Spaced
+    /// out text doesn't use the font's ligatures, unless `liga` is turned back on with
+    /// [`TextDraw::feature`] afterwards.
+    pub fn letter_spacing(mut self, em: f32) -> Self {
+        self.letter_spacing = em;
+        if em != 0.0 {
+            self.shaper_settings = self.shaper_settings.with_feature(Tag::new(b"liga"), false);
+        }
+        self
+    }
+
+    /// Widens each space by `em`s.
+    pub fn word_spacing(mut self, em: f32) -> Self {
+        self.word_spacing = em;
+        self
+    }
+
+    /// Draws the text in one of the variable font's named instances, see
+    /// [`FontRef::named_instance`].
+    pub fn named_instance(mut self, named_instance: NamedInstanceInfo) -> Self {
+        self.shaper_settings = self.shaper_settings.with_named_instance(named_instance);
+        self
+    }
+
+    /// Width (of the widest line) and height of the text in pixels, when drawn with `font` in
+    /// a window with `scale_factor`.
+    pub fn measure(&self, font: &FontRef<'_>, scale_factor: f32) -> [f32; 2] {
+        let font_size = skrifa::instance::Size::new(self.size * scale_factor);
+        let line_height = line_height(font, font_size);
+        let width = lines_with_offsets(&self.text)
+            .map(|(_, line)| self.layout_line(font, line, font_size).width)
+            .fold(0.0, f32::max);
+        [
+            width,
+            line_height * lines_with_offsets(&self.text).count() as f32,
+        ]
+    }
+
+    /// The text broken into lines at spaces, so none is wider than `max_width` (in pixels)
+    /// when drawn with `font` in a window with `scale_factor`. A word that's wider than that
+    /// gets a line of its own (cut off if the draw is truncated). The spaces become line
+    /// breaks, so the byte offsets of links and underlines stay where they were.
+    pub fn wrapped(mut self, font: &FontRef<'_>, scale_factor: f32, max_width: f32) -> Self {
+        let font_size = skrifa::instance::Size::new(self.size * scale_factor);
+        let mut breaks = Vec::new();
+        for (offset, line) in lines_with_offsets(&self.text) {
+            let mut start = 0;
+            // the end of the last word that fit on the line
+            let mut fits = None;
+            let word_ends = line.match_indices(' ').map(|(i, _)| i).chain([line.len()]);
+            for end in word_ends.filter(|end| *end > 0) {
+                let width = self
+                    .layout_line_untruncated(font, &line[start..end], font_size)
+                    .width;
+                if width > max_width
+                    && let Some(space) = fits.take()
+                {
+                    breaks.push(offset + space);
+                    start = space + 1;
+                }
+                if end < line.len() {
+                    fits = Some(end);
+                }
+            }
+        }
+        // spaces and line breaks are a byte each
+        let mut bytes = std::mem::take(&mut self.text).into_bytes();
+        for index in breaks {
+            bytes[index] = b'\n';
+        }
+        self.text = String::from_utf8(bytes).unwrap_or_default();
+        self
+    }
+
+    /// Where the links are when the text is drawn with `font` in a window with
+    /// `scale_factor`: a region for each line a link is on, from the start of its first glyph
+    /// to the end of its last, as tall as the line.
+    pub fn link_regions(&self, font: &FontRef<'_>, scale_factor: f32) -> Vec<LinkRegion> {
+        if self.links.is_empty() {
+            return Vec::new();
+        }
+        let (line_height, lines) = self.laid_out_lines(font, scale_factor);
+        self.links
+            .iter()
+            .flat_map(|link| {
+                selection_rects(&lines, line_height, link.range.clone())
+                    .into_iter()
+                    .map(|rect| LinkRegion { id: link.id, rect })
+            })
+            .collect()
+    }
+
+    /// The byte index in the text of the caret position closest to `point` (in pixels, like
+    /// `position`), when the text is drawn with `font` in a window with `scale_factor`. Points
+    /// above or below the text go to its first or last line, see [`LineLayout::hit_test`].
+    pub fn hit_test(&self, font: &FontRef<'_>, scale_factor: f32, point: [f32; 2]) -> usize {
+        let (line_height, lines) = self.laid_out_lines(font, scale_factor);
+        let Some(last) = lines.len().checked_sub(1) else {
+            return 0;
+        };
+        let line = (((point[1] - self.position[1]) / line_height)
+            .floor()
+            .max(0.0) as usize)
+            .min(last);
+        let (line_start, [left, _], layout) = &lines[line];
+        line_start + layout.hit_test(point[0] - left)
+    }
+
+    /// Where the caret goes for the byte `index` in the text: the top of a line as tall as the
+    /// text's lines, in pixels like `position`. Indexes inside a cluster (e.g. a ligature) go
+    /// to its start.
+    pub fn caret_position(&self, font: &FontRef<'_>, scale_factor: f32, index: usize) -> [f32; 2] {
+        let (_, lines) = self.laid_out_lines(font, scale_factor);
+        lines
+            .iter()
+            .rev()
+            .find(|(line_start, ..)| *line_start <= index)
+            .map_or(self.position, |(line_start, [left, top], layout)| {
+                [left + layout.caret_x(index - line_start), *top]
+            })
+    }
+
+    /// The caret position before the byte `index` in the text, when it's drawn with `font` in
+    /// a window with `scale_factor`: the start of the cluster before it (see
+    /// [`LineLayout::previous_caret`]), or the end of the line before at the start of a line.
+    pub fn previous_caret(&self, font: &FontRef<'_>, scale_factor: f32, index: usize) -> usize {
+        let (_, lines) = self.laid_out_lines(font, scale_factor);
+        let Some(line) = lines
+            .iter()
+            .rposition(|(line_start, ..)| *line_start < index)
+        else {
+            return 0;
+        };
+        let (line_start, _, layout) = &lines[line];
+        // from the start of the next line, past the line break
+        if index > line_start + layout.len {
+            return line_start + layout.len;
+        }
+        line_start + layout.previous_caret(index - line_start)
+    }
+
+    /// The caret position after the byte `index` in the text, when it's drawn with `font` in a
+    /// window with `scale_factor`: the end of the cluster it's in (see
+    /// [`LineLayout::next_caret`]), or the start of the next line at the end of a line.
+    pub fn next_caret(&self, font: &FontRef<'_>, scale_factor: f32, index: usize) -> usize {
+        let (_, lines) = self.laid_out_lines(font, scale_factor);
+        for (i, (line_start, _, layout)) in lines.iter().enumerate() {
+            // in the middle of a line break
+            if index < *line_start {
+                return *line_start;
+            }
+            let line_end = line_start + layout.len;
+            if index < line_end {
+                return line_start + layout.next_caret(index - line_start);
+            }
+            if index == line_end {
+                // past the line break
+                return lines.get(i + 1).map_or(line_end, |(next, ..)| *next);
+            }
+        }
+        self.text.len()
+    }
+
+    /// The rectangles (left, top, right and bottom edges) that highlight the byte `range` of
+    /// the text, one per line it's on. Clusters that are partly in the range are highlighted
+    /// as a whole.
+    pub fn selection_rects(
+        &self,
+        font: &FontRef<'_>,
+        scale_factor: f32,
+        range: Range<usize>,
+    ) -> Vec<[f32; 4]> {
+        let (line_height, lines) = self.laid_out_lines(font, scale_factor);
+        selection_rects(&lines, line_height, range)
+    }
+
+    // the lines like the text renderer draws them: the byte offset each starts at, the left
+    // and top of the line and its layout. Also returns the line height.
+    fn laid_out_lines(
+        &self,
+        font: &FontRef<'_>,
+        scale_factor: f32,
+    ) -> (f32, Vec<(usize, [f32; 2], LineLayout)>) {
+        let font_size = skrifa::instance::Size::new(self.size * scale_factor);
+        let line_height = line_height(font, font_size);
+        let lines = lines_with_offsets(&self.text)
+            .enumerate()
+            .map(|(i, (line_start, line))| {
+                let layout = self.layout_line(font, line, font_size);
+                let left = self.position[0] - self.align.offset(&layout);
+                let top = self.position[1] + i as f32 * line_height;
+                (line_start, [left, top], layout)
+            })
+            .collect();
+        (line_height, lines)
+    }
+
+    // lays out every line, with its byte offset in the text
+    fn layout_lines(
+        &self,
+        font: &FontRef<'_>,
+        font_size: skrifa::instance::Size,
+    ) -> Vec<(usize, LineLayout)> {
+        lines_with_offsets(&self.text)
+            .map(|(line_start, line)| (line_start, self.layout_line(font, line, font_size)))
+            .collect()
+    }
+
+    // lays out a line of the text with its settings, spaced out and truncated to max_width
+    fn layout_line(
+        &self,
+        font: &FontRef<'_>,
+        line: &str,
+        font_size: skrifa::instance::Size,
+    ) -> LineLayout {
+        let layout = self.layout_line_untruncated(font, line, font_size);
+        match self.max_width {
+            Some(max_width) if layout.width > max_width => {
+                let ellipsis = match self.overflow {
+                    TextOverflow::Clip => LineLayout::default(),
+                    TextOverflow::Ellipsis | TextOverflow::MiddleEllipsis => {
+                        ellipsis(font, font_size, &self.shaper_settings)
+                    }
+                };
+                layout.truncated(max_width, self.overflow, &ellipsis)
+            }
+            _ => layout,
+        }
+    }
+
+    // the line as wide as it gets, before it's cut down to the max width
+    fn layout_line_untruncated(
+        &self,
+        font: &FontRef<'_>,
+        line: &str,
+        font_size: skrifa::instance::Size,
+    ) -> LineLayout {
+        let layout = layout_line(
+            font,
+            line,
+            font_size,
+            &self.shaper_settings,
+            self.tabular_figures || self.align != TextAlign::Left,
+            self.tab_width,
+        );
+        if self.letter_spacing != 0.0 || self.word_spacing != 0.0 {
+            let ppem = font_size.ppem().unwrap_or_default();
+            layout.spaced(line, self.letter_spacing * ppem, self.word_spacing * ppem)
+        } else {
+            layout
+        }
+    }
+}
+
+// "…" laid out, or "..." in fonts without it
+fn ellipsis(
+    font: &FontRef<'_>,
+    font_size: skrifa::instance::Size,
+    settings: &ShaperSettings,
+) -> LineLayout {
+    let text = if font.ext_font_ref().charmap().map('…').is_some() {
+        "…"
+    } else {
+        "..."
+    };
+    layout_line(font, text, font_size, settings, false, DEFAULT_TAB_WIDTH)
+}
+
+// see TextDraw::selection_rects
+fn selection_rects(
+    lines: &[(usize, [f32; 2], LineLayout)],
+    line_height: f32,
+    range: Range<usize>,
+) -> Vec<[f32; 4]> {
+    let mut rects = Vec::new();
+    for (line_start, [left, top], layout) in lines {
+        let line_end = line_start + layout.len;
+        if range.start >= line_end || range.end <= *line_start {
+            continue;
+        }
+        let line_range = range.start.saturating_sub(*line_start)..range.end - line_start;
+        for [span_left, span_right] in layout.selection_spans(line_range) {
+            rects.push([left + span_left, *top, left + span_right, top + line_height]);
+        }
+    }
+    rects
+}
+
+// the lines like str::lines, with the byte offset each starts at
+fn lines_with_offsets(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_inclusive('\n').scan(0, |start, line| {
+        let line_start = *start;
+        *start += line.len();
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        Some((line_start, line.strip_suffix('\r').unwrap_or(line)))
+    })
+}
+
+/// Distance between the lines of text in `font` at `font_size`, in pixels.
+pub fn line_height(font: &FontRef<'_>, font_size: skrifa::instance::Size) -> f32 {
+    let metrics = font
+        .ext_font_ref()
+        .metrics(font_size, skrifa::instance::LocationRef::default());
+    metrics.ascent - metrics.descent + metrics.leading
+}
+
+/// A glyph of a [`LineLayout`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionedGlyph {
+    pub glyph_id: GlyphId,
+    /// Byte offset in the line of the text the glyph is for
+    pub cluster: usize,
+    /// Pen position in pixels from the start of the line's baseline, y up
+    pub x: f32,
+    pub y: f32,
+}
+
+/// The space a glyph of a [`LineLayout`] takes up on its line, for hit testing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphAdvance {
+    /// Byte offset in the line of the text the glyph is for
+    pub cluster: usize,
+    /// Where the glyph's advance starts, in pixels from the start of the line
+    pub x: f32,
+    pub width: f32,
+}
+
+/// Text of a [`LineLayout`] that's shaped as a whole, e.g. a character with its combining
+/// marks, a ligature or an emoji ZWJ sequence, and the glyphs it was shaped into. The caret
+/// only goes between clusters and editing deletes whole ones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cluster {
+    /// Byte range in the line
+    pub text: Range<usize>,
+    /// Range of the cluster's glyphs in the line's advances
+    pub glyphs: Range<usize>,
+    /// Where the cluster starts and ends, in pixels from the start of the line
+    pub left: f32,
+    pub right: f32,
+}
+
+/// A shaped line of text, see [`layout_line`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineLayout {
+    /// The visible glyphs (whitespace is left out)
+    pub glyphs: Vec<PositionedGlyph>,
+    /// The advances of all the glyphs, whitespace included, in shaping order
+    pub advances: Vec<GlyphAdvance>,
+    /// The advance of the whole line in pixels
+    pub width: f32,
+    /// Where the line's first `.` starts, in pixels (the width if it has none)
+    pub decimal_x: f32,
+    /// Length of the line's text in bytes
+    pub len: usize,
+}
+
+impl LineLayout {
+    /// The clusters in text order, mapping the byte ranges of the line to the glyphs they were
+    /// shaped into.
+    pub fn clusters(&self) -> Vec<Cluster> {
+        let mut starts: Vec<usize> = self
+            .advances
+            .iter()
+            .map(|advance| advance.cluster)
+            .collect();
+        starts.sort_unstable();
+        starts.dedup();
+        starts
+            .iter()
+            .enumerate()
+            .map(|(i, &start)| {
+                let end = starts.get(i + 1).copied().unwrap_or(self.len);
+                let in_cluster = |advance: &GlyphAdvance| advance.cluster == start;
+                let first = self
+                    .advances
+                    .iter()
+                    .position(in_cluster)
+                    .unwrap_or_default();
+                let last = self.advances.iter().rposition(in_cluster).unwrap_or(first);
+                let (left, right) = self
+                    .advances
+                    .iter()
+                    .filter(|advance| in_cluster(advance))
+                    .fold(
+                        (f32::INFINITY, f32::NEG_INFINITY),
+                        |(left, right), advance| {
+                            (left.min(advance.x), right.max(advance.x + advance.width))
+                        },
+                    );
+                Cluster {
+                    text: start..end,
+                    glyphs: first..last + 1,
+                    left,
+                    right,
+                }
+            })
+            .collect()
+    }
+
+    /// The cluster the byte `index` is in, None past the end of the line.
+    pub fn cluster_at(&self, index: usize) -> Option<Cluster> {
+        self.clusters()
+            .into_iter()
+            .find(|cluster| cluster.text.contains(&index))
+    }
+
+    /// The caret position before the byte `index`: the start of the cluster before it, or of
+    /// the one it's in. Backspace deletes from there to `index`, left arrow moves there.
+    pub fn previous_caret(&self, index: usize) -> usize {
+        self.clusters()
+            .into_iter()
+            .rev()
+            .find(|cluster| cluster.text.start < index)
+            .map_or(0, |cluster| cluster.text.start)
+    }
+
+    /// The caret position after the byte `index`: the end of the cluster it's in (the end
+    /// of the line past the last one). Delete deletes from `index` to there, right arrow
+    /// moves there.
+    pub fn next_caret(&self, index: usize) -> usize {
+        self.clusters()
+            .into_iter()
+            .find(|cluster| cluster.text.end > index)
+            .map_or(self.len, |cluster| cluster.text.end)
+    }
+
+    /// The byte index of the caret position closest to `x` (in pixels from the start of the
+    /// line): the start of the cluster `x` is on, or its end past the cluster's middle. The
+    /// caret only goes between clusters, never into the middle of a ligature or a character
+    /// with combining marks.
+    pub fn hit_test(&self, x: f32) -> usize {
+        self.clusters()
+            .into_iter()
+            .find(|cluster| x < (cluster.left + cluster.right) / 2.0)
+            .map_or(self.len, |cluster| cluster.text.start)
+    }
+
+    /// Where the caret for the byte `index` is, in pixels from the start of the line. Indexes
+    /// inside a cluster go to its start, indexes past the end to the end of the line.
+    pub fn caret_x(&self, index: usize) -> f32 {
+        if index >= self.len {
+            return self.width;
+        }
+        self.cluster_at(index).map_or(0.0, |cluster| cluster.left)
+    }
+
+    /// The line with `letter_spacing` pixels added between its clusters and `word_spacing`
+    /// after each space (`line` is the text it was laid out from). The glyphs of a cluster
+    /// (a ligature, or a character and its combining marks) stay together, and the space is
+    /// part of the advance of the cluster before it, so it's hit tested and selected with it.
+    /// Nothing is added after the last cluster, so aligned text lines up with its position.
+    /// Tabs are widened like any other cluster, they don't go to the next tab stop again.
+    pub fn spaced(&self, line: &str, letter_spacing: f32, word_spacing: f32) -> LineLayout {
+        let mut spaced = self.clone();
+        // how far each cluster moves right, in the order they're on the line
+        let mut shifts: Vec<(usize, f32)> = Vec::new();
+        let mut shift = 0.0;
+        for i in 0..spaced.advances.len() {
+            let cluster = spaced.advances[i].cluster;
+            spaced.advances[i].x += shift;
+            if shifts.last().is_none_or(|(last, _)| *last != cluster) {
+                shifts.push((cluster, shift));
+            }
+            let next = spaced.advances.get(i + 1).map(|advance| advance.cluster);
+            if next == Some(cluster) {
+                continue;
+            }
+            // the end of the cluster
+            let mut extra = if next.is_some() { letter_spacing } else { 0.0 };
+            if line[cluster..].starts_with([' ', '\u{a0}']) {
+                extra += word_spacing;
+            }
+            spaced.advances[i].width += extra;
+            shift += extra;
+        }
+        let shift_of = |cluster: usize| {
+            shifts
+                .iter()
+                .find(|(start, _)| *start == cluster)
+                .map_or(shift, |(_, shift)| *shift)
+        };
+        for glyph in &mut spaced.glyphs {
+            glyph.x += shift_of(glyph.cluster);
+        }
+        // the `.` moves with its cluster, the end of a line without one with the end
+        spaced.decimal_x = self.decimal_x
+            + self
+                .advances
+                .iter()
+                .find(|advance| self.decimal_x < self.width && advance.x >= self.decimal_x)
+                .map_or(shift, |advance| shift_of(advance.cluster));
+        spaced.width = self.width + shift;
+        spaced
+    }
+
+    /// The line cut down to `max_width` pixels with `ellipsis` (a laid out "…", empty to clip)
+    /// in place of what was cut, see [`TextOverflow`]. Whole clusters are cut. The glyphs
+    /// and advances that are left keep their byte offsets in the line, the ellipsis gets the
+    /// offset of the first cluster it replaces.
+    pub fn truncated(
+        &self,
+        max_width: f32,
+        overflow: TextOverflow,
+        ellipsis: &LineLayout,
+    ) -> LineLayout {
+        if self.width <= max_width {
+            return self.clone();
+        }
+        let clusters = self.clusters();
+        let room = (max_width - ellipsis.width).max(0.0);
+        // the start is kept up to head_end, the end from tail_start on
+        let head_room = match overflow {
+            TextOverflow::Clip | TextOverflow::Ellipsis => room,
+            TextOverflow::MiddleEllipsis => room / 2.0,
+        };
+        let head = clusters
+            .iter()
+            .take_while(|cluster| cluster.right <= head_room)
+            .count();
+        let head_end = clusters
+            .get(head)
+            .map_or(self.len, |cluster| cluster.text.start);
+        let head_width = clusters[..head].last().map_or(0.0, |cluster| cluster.right);
+        let (tail_start, tail_left) = match overflow {
+            TextOverflow::Clip | TextOverflow::Ellipsis => (self.len, self.width),
+            TextOverflow::MiddleEllipsis => clusters[head..]
+                .iter()
+                .rev()
+                .take_while(|cluster| self.width - cluster.left <= room - head_width)
+                .last()
+                .map_or((self.len, self.width), |cluster| {
+                    (cluster.text.start, cluster.left)
+                }),
+        };
+        // how far the end moves left
+        let shift = tail_left - head_width - ellipsis.width;
+        let mut truncated = LineLayout {
+            width: head_width + ellipsis.width + (self.width - tail_left),
+            len: self.len,
+            ..LineLayout::default()
+        };
+        truncated.decimal_x = if self.decimal_x < head_width {
+            self.decimal_x
+        } else if self.decimal_x >= tail_left && tail_start < self.len {
+            self.decimal_x - shift
+        } else {
+            truncated.width
+        };
+        for glyph in &self.glyphs {
+            if glyph.cluster < head_end {
+                truncated.glyphs.push(*glyph);
+            } else if glyph.cluster >= tail_start {
+                truncated.glyphs.push(PositionedGlyph {
+                    x: glyph.x - shift,
+                    ..*glyph
+                });
+            }
+        }
+        truncated
+            .glyphs
+            .extend(ellipsis.glyphs.iter().map(|glyph| PositionedGlyph {
+                cluster: head_end,
+                x: glyph.x + head_width,
+                ..*glyph
+            }));
+        for advance in &self.advances {
+            if advance.cluster < head_end {
+                truncated.advances.push(*advance);
+            } else if advance.cluster >= tail_start {
+                truncated.advances.push(GlyphAdvance {
+                    x: advance.x - shift,
+                    ..*advance
+                });
+            }
+        }
+        truncated
+            .advances
+            .extend(ellipsis.advances.iter().map(|advance| GlyphAdvance {
+                cluster: head_end,
+                x: advance.x + head_width,
+                ..*advance
+            }));
+        truncated
+    }
+
+    /// The spans (start and end in pixels from the start of the line) that highlight the byte
+    /// `range`, neighbouring clusters merged. Clusters that are partly in the range are
+    /// highlighted as a whole.
+    pub fn selection_spans(&self, range: Range<usize>) -> Vec<[f32; 2]> {
+        let mut spans: Vec<[f32; 2]> = Vec::new();
+        for Cluster {
+            text, left, right, ..
+        } in self.clusters()
+        {
+            if text.start >= range.end || text.end <= range.start {
+                continue;
+            }
+            match spans.last_mut() {
+                Some(span) if (span[1] - left).abs() < 0.5 => span[1] = span[1].max(right),
+                _ => spans.push([left, right]),
+            }
+        }
+        spans
+    }
+}
+
+/// Shapes a line of text at `font_size` with `settings`. With `tabular_figures` all digits get
+/// the same advance: the font's own tabular figures (the `tnum` feature) if it has them,
+/// otherwise each digit is centered in the advance of the widest one.
+///
+/// A `\t` advances to the next tab stop, every `tab_width` spaces from the start of the line.
+/// Other control characters (a `\r` or `\n` left in the line too) take no space and aren't
+/// drawn, instead of showing the font's missing glyph box.
+///
+/// The layouts are cached in the font's [`FontCache`] by everything that goes into them, so
+/// the same line (a HUD label, a menu item) is only shaped once while it's drawn every frame.
+/// Changed settings are a different key, the least recently used lines are dropped past
+/// [`SHAPE_CACHE_BUDGET`] and the lines of a font that's reloaded or unloaded go with it.
+pub fn layout_line(
+    font: &FontRef<'_>,
+    line: &str,
+    font_size: skrifa::instance::Size,
+    settings: &ShaperSettings,
+    tabular_figures: bool,
+    tab_width: f32,
+) -> LineLayout {
+    let key = ShapeKey {
+        line: line.to_string(),
+        font_cache_index: font.cache_index,
+        ppem: font_size.ppem().unwrap_or_default().to_bits(),
+        settings: settings.clone(),
+        tabular_figures,
+        tab_width: tab_width.to_bits(),
+    };
+    if let Some(layout) = font.shape_cache.lock().get(&key) {
+        return layout;
+    }
+    let layout = shape_line(font, line, font_size, settings, tabular_figures, tab_width);
+    font.shape_cache.lock().insert(key, layout.clone());
+    layout
+}
+
+/// Roughly the most memory the lines a [`FontCache`] keeps for [`layout_line`] take, in bytes.
+pub const SHAPE_CACHE_BUDGET: usize = 4 * 1024 * 1024;
+
+// everything a line's layout depends on
+#[derive(PartialEq)]
+struct ShapeKey {
+    line: String,
+    font_cache_index: usize,
+    // the bits of the floats
+    ppem: u32,
+    settings: ShaperSettings,
+    tabular_figures: bool,
+    tab_width: u32,
+}
+
+impl Eq for ShapeKey {}
+
+impl Hash for ShapeKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.line.hash(state);
+        self.font_cache_index.hash(state);
+        self.ppem.hash(state);
+        self.tabular_figures.hash(state);
+        self.tab_width.hash(state);
+        // the variations are floats, so they're only compared
+        self.settings.shape_features.hash(state);
+    }
+}
+
+// the lines shaped with the fonts of one FontCache, whose cache indexes mean nothing in
+// another
+#[derive(Default)]
+struct ShapeCache {
+    // the layouts with the tick they were last used at
+    lines: HashMap<ShapeKey, (LineLayout, u64)>,
+    // counts the lookups, so every line was last used at a different tick
+    tick: u64,
+    bytes: usize,
+}
+
+impl ShapeCache {
+    fn get(&mut self, key: &ShapeKey) -> Option<LineLayout> {
+        self.tick += 1;
+        let (layout, last_used) = self.lines.get_mut(key)?;
+        *last_used = self.tick;
+        Some(layout.clone())
+    }
+
+    fn insert(&mut self, key: ShapeKey, layout: LineLayout) {
+        self.bytes += Self::entry_size(&key, &layout);
+        if let Some((key, (layout, _))) = self.lines.remove_entry(&key) {
+            self.bytes -= Self::entry_size(&key, &layout);
+        }
+        self.lines.insert(key, (layout, self.tick));
+        if self.bytes > SHAPE_CACHE_BUDGET {
+            self.evict();
+        }
+    }
+
+    // drops the least recently used lines, down to 3/4 of the budget so it doesn't happen
+    // again with the next line
+    fn evict(&mut self) {
+        let mut entries: Vec<(u64, usize)> = self
+            .lines
+            .iter()
+            .map(|(key, (layout, last_used))| (*last_used, Self::entry_size(key, layout)))
+            .collect();
+        entries.sort_unstable();
+        let mut oldest_kept = 0;
+        for (last_used, size) in entries {
+            if self.bytes <= SHAPE_CACHE_BUDGET / 4 * 3 {
+                break;
+            }
+            self.bytes -= size;
+            oldest_kept = last_used + 1;
+        }
+        self.lines
+            .retain(|_, (_, last_used)| *last_used >= oldest_kept);
+    }
+
+    fn forget_font(&mut self, font_cache_index: usize) {
+        self.lines
+            .retain(|key, _| key.font_cache_index != font_cache_index);
+        self.bytes = self
+            .lines
+            .iter()
+            .map(|(key, (layout, _))| Self::entry_size(key, layout))
+            .sum();
+    }
+
+    fn entry_size(key: &ShapeKey, layout: &LineLayout) -> usize {
+        std::mem::size_of::<(ShapeKey, (LineLayout, u64))>()
+            + key.line.capacity()
+            + layout.heap_size()
+    }
+}
+
+impl HeapSize for LineLayout {
+    fn heap_size(&self) -> usize {
+        vec_size(&self.glyphs) + vec_size(&self.advances)
+    }
+}
+
+// layout_line without the cache
+fn shape_line(
+    font: &FontRef<'_>,
+    line: &str,
+    font_size: skrifa::instance::Size,
+    settings: &ShaperSettings,
+    tabular_figures: bool,
+    tab_width: f32,
+) -> LineLayout {
+    let units_per_em = font
+        .ext_font_ref()
+        .metrics(font_size, skrifa::instance::LocationRef::default())
+        .units_per_em;
+    let font_units_to_px = font_size.ppem().unwrap_or(0.0) / units_per_em as f32;
+    let has_tnum = font.features().iter().any(|feature| feature == "tnum");
+    let mut settings = settings.clone();
+    if tabular_figures && has_tnum {
+        settings
+            .shape_features
+            .get_or_insert_with(Vec::new)
+            .extend(Feature::from_str("tnum").ok());
+    }
+    let shaper = font.shaper(settings);
+    // the column every digit is centered in, for fonts without tabular figures
+    let digit_advance = if tabular_figures && !has_tnum {
+        let digits = shaper.shape("0123456789", None, Some(font_size));
+        digits
+            .glyph_positions()
+            .iter()
+            .map(|position| position.x_advance)
+            .max()
+    } else {
+        None
+    };
+    let tab_stop = if line.contains('\t') {
+        let space = shaper.shape(" ", None, Some(font_size));
+        let space_advance = space
+            .glyph_positions()
+            .first()
+            .map_or(0, |position| position.x_advance);
+        (space_advance as f32 * tab_width).round() as i32
+    } else {
+        0
+    };
+
+    let glyphs = shaper.shape(line, None, Some(font_size));
+    let mut layout = LineLayout::default();
+    let mut decimal_x = None;
+    let mut caret_x = 0;
+    for (info, position) in glyphs.glyph_infos().iter().zip(glyphs.glyph_positions()) {
+        let character = line[info.cluster as usize..].chars().next();
+        let mut x_offset = position.x_offset;
+        let mut x_advance = position.x_advance;
+        if let Some(digit_advance) = digit_advance
+            && character.is_some_and(|c| c.is_ascii_digit())
+        {
+            x_offset += (digit_advance - x_advance) / 2;
+            x_advance = digit_advance;
+        }
+        if character == Some('\t') {
+            x_advance = if tab_stop > 0 {
+                (caret_x / tab_stop + 1) * tab_stop - caret_x
+            } else {
+                0
+            };
+        } else if character.is_some_and(char::is_control) {
+            x_advance = 0;
+        }
+        if character == Some('.') && decimal_x.is_none() {
+            decimal_x = Some(caret_x);
+        }
+        if !character.is_none_or(|c| c.is_whitespace() || c.is_control()) {
+            layout.glyphs.push(PositionedGlyph {
+                glyph_id: info.glyph_id.into(),
+                cluster: info.cluster as usize,
+                x: (caret_x + x_offset) as f32 * font_units_to_px,
+                y: position.y_offset as f32 * font_units_to_px,
+            });
+        }
+        layout.advances.push(GlyphAdvance {
+            cluster: info.cluster as usize,
+            x: caret_x as f32 * font_units_to_px,
+            width: x_advance as f32 * font_units_to_px,
+        });
+        caret_x += x_advance;
+    }
+    layout.width = caret_x as f32 * font_units_to_px;
+    layout.len = line.len();
+    layout.decimal_x = decimal_x.unwrap_or(caret_x) as f32 * font_units_to_px;
+    layout
+}
+
+/// The surface text is drawn on, in physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextSurface {
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+}
+
 pub struct TextRenderer {
     pub glyph_cache: GlyphCache,
     surface_width: u32,
     surface_height: u32,
     surface_scale_factor: f32,
     texture_row_size_bytes: usize,
-    texture_rows: usize,
     pub texture: wgpu::Texture,
     pub texture_bind_group: wgpu::BindGroup,
-    render_pipeline: wgpu::RenderPipeline,
-    glyph_vertex_buffer: wgpu::Buffer,
-    glyph_index_buffer: wgpu::Buffer,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    // the atlas is sampled without the sRGB decode, see set_gamma_correct
+    gamma_correct: bool,
+    pub(super) render_pipeline: wgpu::RenderPipeline,
+    // without Features::DUAL_SOURCE_BLENDING, Lcd text is drawn as Grayscale
+    dual_source_blending: bool,
+    default_antialiasing: TextAntialiasing,
+    // see set_atlas_overlay
+    atlas_overlay: bool,
+    glyph_vertex_buffer: GrowableBuffer,
+    glyph_index_buffer: GrowableBuffer,
+    // text for the next flush
+    queued_text: Vec<TextDraw>,
+    // the last flush's glyphs, kept to reuse their allocations
+    glyph_vertices: Vec<GlyphVertex>,
+    glyph_indices: Vec<u32>,
+    vertex_count: usize,
+    index_count: u32,
+    // the last flush's error while recording, for GfxState::render to return
+    error: Option<GameError>,
+    // how long the last flush's stages took
+    stage_times: StageTimes,
+    // the atlas, vertex and index bytes the last flush uploaded
+    uploaded_bytes: usize,
 }
 
 impl TextRenderer {
     pub fn new(
         device: &wgpu::Device,
-        surface_configuration: &wgpu::SurfaceConfiguration,
+        gpu_cache: &GpuCache,
+        target_format: wgpu::TextureFormat,
         surface_dimensions_bind_group_layout: &wgpu::BindGroupLayout,
-        surface_width: u32,
-        surface_height: u32,
-        surface_scale_factor: f32,
+        surface: TextSurface,
+        shader_source: &str,
     ) -> Self {
         // keep this simple for now, just a 2K texture
         // Note that this (probably?) needs to be aligned to wgpu::COPY_BYTES_PER_ROW_ALIGNMENT (256)
@@ -95,24 +1356,28 @@ impl TextRenderer {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            format: wgpu::TextureFormat::Rgba8Unorm,
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
+            view_formats: &[wgpu::TextureFormat::Rgba8UnormSrgb],
         });
 
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
+        let sampler = gpu_cache.sampler(
+            device,
+            &wgpu::SamplerDescriptor {
+                label: Some("glyph_cache_sampler"),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Nearest,
+                min_filter: wgpu::FilterMode::Nearest,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            },
+        );
 
-        let texture_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        let texture_bind_group_layout = gpu_cache.bind_group_layout(
+            device,
+            &wgpu::BindGroupLayoutDescriptor {
                 label: Some("glyph_cache_texture_bind_group_layout"),
                 entries: &[
                     wgpu::BindGroupLayoutEntry {
@@ -132,11 +1397,99 @@ impl TextRenderer {
                         count: None,
                     },
                 ],
-            });
+            },
+        );
 
-        let texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        let texture_bind_group = Self::create_texture_bind_group(
+            device,
+            &texture_bind_group_layout,
+            &texture,
+            &sampler,
+            false,
+        );
+
+        let dual_source_blending = device
+            .features()
+            .contains(wgpu::Features::DUAL_SOURCE_BLENDING);
+        if !dual_source_blending {
+            log::warn!(
+                target: logging::GFX,
+                "no dual source blending, text is drawn without subpixel antialiasing"
+            );
+        }
+
+        let render_pipeline = Self::create_pipeline(
+            device,
+            gpu_cache,
+            surface_dimensions_bind_group_layout,
+            &texture_bind_group_layout,
+            target_format,
+            shader_source,
+            dual_source_blending,
+        );
+
+        let glyph_vertex_buffer = GrowableBuffer::new(
+            device,
+            "glyph_vertex_buffer",
+            wgpu::BufferUsages::VERTEX,
+            (INITIAL_GLYPH_VERTICES * std::mem::size_of::<GlyphVertex>()) as wgpu::BufferAddress,
+        );
+
+        let glyph_index_buffer = GrowableBuffer::new(
+            device,
+            "glyph_index_buffer",
+            wgpu::BufferUsages::INDEX,
+            (INITIAL_GLYPH_VERTICES / 4 * 6 * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+        );
+
+        Self {
+            glyph_cache: GlyphCache::new(texture_row_size_bytes, texture_rows),
+            surface_width: surface.width,
+            surface_height: surface.height,
+            surface_scale_factor: surface.scale_factor,
+            texture_row_size_bytes,
+            texture,
+            texture_bind_group,
+            texture_bind_group_layout,
+            sampler,
+            gamma_correct: false,
+            render_pipeline,
+            dual_source_blending,
+            default_antialiasing: TextAntialiasing::default(),
+            atlas_overlay: false,
+            glyph_vertex_buffer,
+            glyph_index_buffer,
+            queued_text: Vec::new(),
+            glyph_vertices: Vec::new(),
+            glyph_indices: Vec::new(),
+            vertex_count: 0,
+            index_count: 0,
+            error: None,
+            stage_times: StageTimes::default(),
+            uploaded_bytes: 0,
+        }
+    }
+
+    // the atlas through an sRGB view decodes the coverage like a color, which makes the text
+    // thinner than the linear coverage does
+    fn create_texture_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        texture: &wgpu::Texture,
+        sampler: &wgpu::Sampler,
+        gamma_correct: bool,
+    ) -> wgpu::BindGroup {
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(if gamma_correct {
+                wgpu::TextureFormat::Rgba8Unorm
+            } else {
+                wgpu::TextureFormat::Rgba8UnormSrgb
+            }),
+            ..Default::default()
+        });
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("glyph_cache_texture_bind_group"),
-            layout: &texture_bind_group_layout,
+            layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
@@ -144,27 +1497,87 @@ impl TextRenderer {
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
+                    resource: wgpu::BindingResource::Sampler(sampler),
                 },
             ],
-        });
+        })
+    }
 
+    /// Blends the glyphs' coverage as it is into the (linear) scene, instead of decoding it
+    /// from sRGB first like the atlas did before this was an option. Gamma-correct text is a
+    /// bit bolder, and light and dark text look equally heavy.
+    pub fn set_gamma_correct(&mut self, device: &wgpu::Device, gamma_correct: bool) {
+        if gamma_correct == self.gamma_correct {
+            return;
+        }
+        self.gamma_correct = gamma_correct;
+        self.texture_bind_group = Self::create_texture_bind_group(
+            device,
+            &self.texture_bind_group_layout,
+            &self.texture,
+            &self.sampler,
+            gamma_correct,
+        );
+    }
+
+    /// The antialiasing of text that doesn't set its own, see [`TextDraw::antialiasing`].
+    pub fn set_default_antialiasing(&mut self, antialiasing: TextAntialiasing) {
+        self.default_antialiasing = antialiasing;
+    }
+
+    /// Draws the glyph atlas over everything, for debugging the glyph cache: the texture
+    /// with an outline around each allocation, and how full it is (see [`AtlasStats`]).
+    pub fn set_atlas_overlay(&mut self, atlas_overlay: bool) {
+        self.atlas_overlay = atlas_overlay;
+    }
+
+    /// What glyphs are rasterized with for the `requested` antialiasing (None for the
+    /// default), never Lcd without dual source blending.
+    pub fn antialiasing(&self, requested: Option<TextAntialiasing>) -> TextAntialiasing {
+        match requested.unwrap_or(self.default_antialiasing) {
+            TextAntialiasing::Lcd if !self.dual_source_blending => TextAntialiasing::Grayscale,
+            antialiasing => antialiasing,
+        }
+    }
+
+    pub(super) fn dual_source_blending(&self) -> bool {
+        self.dual_source_blending
+    }
+
+    /// Builds the glyph pipeline from `shader_source`. Without `dual_source_blending` the
+    /// glyphs are blended by their grayscale coverage (see [`single_source_shader`]).
+    pub(super) fn create_pipeline(
+        device: &wgpu::Device,
+        gpu_cache: &GpuCache,
+        surface_dimensions_bind_group_layout: &wgpu::BindGroupLayout,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+        target_format: wgpu::TextureFormat,
+        shader_source: &str,
+        dual_source_blending: bool,
+    ) -> wgpu::RenderPipeline {
+        let shader_source = if dual_source_blending {
+            shader_source.to_string()
+        } else {
+            single_source_shader(shader_source)
+        };
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Glyph Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("text_shader.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
         });
 
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        let render_pipeline_layout = gpu_cache.pipeline_layout(
+            device,
+            &wgpu::PipelineLayoutDescriptor {
                 label: Some("Glyph Render Pipeline Layout"),
                 bind_group_layouts: &[
-                    &surface_dimensions_bind_group_layout,
-                    &texture_bind_group_layout,
+                    surface_dimensions_bind_group_layout,
+                    texture_bind_group_layout,
                 ],
                 push_constant_ranges: &[],
-            });
+            },
+        );
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Render Pipeline"),
             layout: Some(&render_pipeline_layout),
             vertex: wgpu::VertexState {
@@ -176,22 +1589,29 @@ impl TextRenderer {
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
-                entry_point: Some("fs_main"),
+                entry_point: Some(if dual_source_blending {
+                    "fs_main"
+                } else {
+                    "fs_grayscale"
+                }),
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_configuration.format,
-                    //blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    blend: Some(wgpu::BlendState {
-                        // Dual source blending
-                        color: wgpu::BlendComponent {
-                            src_factor: wgpu::BlendFactor::Src1,
-                            dst_factor: wgpu::BlendFactor::OneMinusSrc1,
-                            operation: wgpu::BlendOperation::Add,
-                        },
-                        alpha: wgpu::BlendComponent {
-                            src_factor: wgpu::BlendFactor::One,
-                            dst_factor: wgpu::BlendFactor::OneMinusSrc1Alpha,
-                            operation: wgpu::BlendOperation::Add,
-                        },
+                    format: target_format,
+                    blend: Some(if dual_source_blending {
+                        wgpu::BlendState {
+                            // Dual source blending
+                            color: wgpu::BlendComponent {
+                                src_factor: wgpu::BlendFactor::Src1,
+                                dst_factor: wgpu::BlendFactor::OneMinusSrc1,
+                                operation: wgpu::BlendOperation::Add,
+                            },
+                            alpha: wgpu::BlendComponent {
+                                src_factor: wgpu::BlendFactor::One,
+                                dst_factor: wgpu::BlendFactor::OneMinusSrc1Alpha,
+                                operation: wgpu::BlendOperation::Add,
+                            },
+                        }
+                    } else {
+                        wgpu::BlendState::ALPHA_BLENDING
                     }),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -209,7 +1629,7 @@ impl TextRenderer {
                 // Requires Features::CONSERVATIVE_RASTERIZATION
                 conservative: false,
             },
-            depth_stencil: None,
+            depth_stencil: Some(layered_depth_stencil()),
             multisample: wgpu::MultisampleState {
                 count: 1,
                 mask: !0,
@@ -217,312 +1637,546 @@ impl TextRenderer {
             },
             multiview: None,
             cache: None,
-        });
+        })
+    }
 
-        let glyph_vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("glyph_vertex_buffer"),
-            size: (4000 as usize * std::mem::size_of::<GlyphVertex>()) as wgpu::BufferAddress,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+    /// Why glyphs were left out of the last recorded frame, if any were.
+    pub(super) fn take_error(&mut self) -> Option<GameError> {
+        self.error.take()
+    }
 
-        let glyph_index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("glyph_index_buffer"),
-            size: (6000 as usize * std::mem::size_of::<u16>()) as wgpu::BufferAddress,
-            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+    pub(super) fn texture_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.texture_bind_group_layout
+    }
 
-        Self {
-            glyph_cache: GlyphCache::new(texture_row_size_bytes, texture_rows),
-            surface_width,
-            surface_height,
-            surface_scale_factor,
-            texture_row_size_bytes,
-            texture_rows,
-            texture,
-            texture_bind_group,
-            render_pipeline,
-            glyph_vertex_buffer,
-            glyph_index_buffer,
-        }
+    /// Adds text to draw this frame. Nothing is shaped or uploaded until
+    /// [`TextRenderer::flush`].
+    pub fn queue_text(&mut self, text: TextDraw) {
+        self.queued_text.push(text);
     }
 
-    pub fn render(
+    /// Shapes the queued text with `ui_font` (or the font of `font_cache` a draw asks for) and
+    /// uploads all of its glyphs at once: the vertices and indices, and the atlas if glyphs
+    /// were added to it. Every glyph is in the one atlas texture, so [`TextRenderer::draw`]
+    /// draws it all in a single batch. Text without a font is dropped. Glyphs that fail are
+    /// left out and the rest is still drawn, the first failure is returned.
+    pub fn flush(
         &mut self,
-        game_state: &crate::GameState,
-        mut render_pass: wgpu::RenderPass,
-        surface_dimensions_bind_group: &wgpu::BindGroup,
+        font_cache: &FontCache,
+        ui_font: Option<&FontRef<'_>>,
+        device: &wgpu::Device,
         queue: &wgpu::Queue,
-    ) {
-        let ppem = 14f32 * self.surface_scale_factor;
-
-        let font_size = skrifa::instance::Size::new(ppem);
-
-        self.queue_write_texture_if_changed(queue);
-
-        let font = &game_state.font_cache.search_fonts("cascadia code")[0];
-
-        let shaper = font.shaper(ShaperSettings::new());
-
-        let glyphs = shaper.shape("abpAj", None, Some(font_size.clone()));
-
-        let upem = font
-            .ext_font_ref()
-            .metrics(font_size.clone(), skrifa::instance::LocationRef::default())
-            .units_per_em;
-
-        let a_advance = glyphs.glyph_positions()[0].x_advance as f32 * ppem / upem as f32;
-        let b_advance = glyphs.glyph_positions()[1].x_advance as f32 * ppem / upem as f32;
-        let p_advance = glyphs.glyph_positions()[2].x_advance as f32 * ppem / upem as f32;
-        let cap_a_advance = glyphs.glyph_positions()[3].x_advance as f32 * ppem / upem as f32;
+    ) -> Result<(), GameError> {
+        // taken out, laying out the text borrows the renderer mutably
+        let mut times = StageTimes::default();
+        let start = std::time::Instant::now();
+        let queued_text = std::mem::take(&mut self.queued_text);
+        let mut vertices = std::mem::take(&mut self.glyph_vertices);
+        let mut indices = std::mem::take(&mut self.glyph_indices);
+        vertices.clear();
+        indices.clear();
+        // back to front, so the edges of nearer glyphs blend over farther layers (stable, so
+        // the order within a layer stays)
+        let mut sorted_text: Vec<&TextDraw> = queued_text.iter().collect();
+        sorted_text.sort_by_key(|text| text.layer);
+        let mut laid_out = Vec::with_capacity(sorted_text.len());
+        for text in sorted_text {
+            let font = match text.font {
+                Some(index) => font_cache.get_font(index),
+                None => ui_font.cloned(),
+            };
+            if let Some(font) = font {
+                let lines = text.layout_lines(&font, self.font_size(text));
+                laid_out.push((font, text, lines));
+            }
+        }
+        let start = times.add_since(Stage::Shape, start);
 
-        let a_glyph_id = glyphs.glyph_infos()[0].glyph_id;
+        // the glyphs of the frame that aren't cached yet are rasterized all at once, in
+        // parallel, so a screen of new text doesn't stall the frame
+        let glyphs: Vec<GlyphRequest<'_>> = laid_out
+            .iter()
+            .flat_map(|(font, text, lines)| {
+                let size = self.font_size(text);
+                let coords = text.shaper_settings.coords(font);
+                let antialiasing = self.antialiasing(text.antialiasing);
+                lines
+                    .iter()
+                    .flat_map(|(_, layout)| layout.glyphs.iter())
+                    .map(move |glyph| GlyphRequest {
+                        font,
+                        glyph_id: glyph.glyph_id,
+                        size,
+                        coords: coords.clone(),
+                        antialiasing,
+                    })
+            })
+            .collect();
+        let mut result = self.glyph_cache.rasterize_glyphs(glyphs).map(|_| ());
+        let start = times.add_since(Stage::Rasterize, start);
 
-        let (a_placement, a_uv_bounds) = self.glyph_cache.get_glyph_texture_bounds(
-            &font,
-            a_glyph_id.into(),
-            font_size,
-            Default::default(),
-        );
+        for (font, text, lines) in &laid_out {
+            let prepared =
+                self.prepare_draw_for_text(font, text, lines, &mut vertices, &mut indices);
+            result = result.and(prepared);
+        }
+        if self.atlas_overlay {
+            let prepared = self.prepare_draw_for_atlas(ui_font, &mut vertices, &mut indices);
+            result = result.and(prepared);
+        }
 
-        let b_glyph_id = glyphs.glyph_infos()[1].glyph_id;
+        let start = times.add_since(Stage::Shape, start);
 
-        let (b_placement, b_uv_bounds) = self.glyph_cache.get_glyph_texture_bounds(
-            &font,
-            b_glyph_id.into(),
-            font_size,
-            Default::default(),
-        );
+        // after caching this frame's glyphs, so they don't show up a frame late
+        let mut uploaded_bytes = self.queue_write_texture_if_changed(queue);
 
-        let p_glyph_id = glyphs.glyph_infos()[2].glyph_id;
+        if !indices.is_empty() {
+            self.glyph_vertex_buffer
+                .write(device, queue, bytemuck::cast_slice(&vertices));
+            self.glyph_index_buffer
+                .write(device, queue, bytemuck::cast_slice(&indices));
+            uploaded_bytes += std::mem::size_of_val(vertices.as_slice())
+                + std::mem::size_of_val(indices.as_slice());
+        }
+        self.uploaded_bytes = uploaded_bytes;
+        times.add_since(Stage::Upload, start);
+        self.stage_times = times;
+        self.index_count = indices.len() as u32;
+        self.vertex_count = vertices.len();
+        self.queued_text = queued_text;
+        self.queued_text.clear();
+        self.glyph_vertices = vertices;
+        self.glyph_indices = indices;
+        result
+    }
 
-        let (p_placement, p_uv_bounds) = self.glyph_cache.get_glyph_texture_bounds(
-            &font,
-            p_glyph_id.into(),
-            font_size,
-            Default::default(),
+    /// Draws the text of the last [`TextRenderer::flush`].
+    pub fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        surface_dimensions_bind_group: &wgpu::BindGroup,
+    ) {
+        if self.index_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, surface_dimensions_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.texture_bind_group, &[]);
+        render_pass.set_vertex_buffer(
+            0,
+            self.glyph_vertex_buffer
+                .slice(self.vertex_count * std::mem::size_of::<GlyphVertex>()),
         );
-
-        let cap_a_glyph_id = glyphs.glyph_infos()[3].glyph_id;
-
-        let (cap_a_placement, cap_a_uv_bounds) = self.glyph_cache.get_glyph_texture_bounds(
-            &font,
-            cap_a_glyph_id.into(),
-            font_size,
-            Default::default(),
+        render_pass.set_index_buffer(
+            self.glyph_index_buffer
+                .slice(self.index_count as usize * std::mem::size_of::<u32>()),
+            wgpu::IndexFormat::Uint32,
         );
+        render_pass.draw_indexed(0..self.index_count, 0, 0..1);
+    }
 
-        let j_glyph_id = glyphs.glyph_infos()[4].glyph_id;
-
-        let (j_placement, j_uv_bounds) = self.glyph_cache.get_glyph_texture_bounds(
-            &font,
-            j_glyph_id.into(),
-            font_size,
-            Default::default(),
-        );
+    /// How long the last [`TextRenderer::flush`] took laying out, rasterizing and uploading.
+    pub fn stage_times(&self) -> &StageTimes {
+        &self.stage_times
+    }
 
-        let mut glyph_vertices: Vec<GlyphVertex> = Vec::with_capacity(4000);
-        let mut glyph_indices: Vec<u16> = Vec::with_capacity(6000);
+    // the size the text is rasterized at, in pixels
+    fn font_size(&self, text: &TextDraw) -> skrifa::instance::Size {
+        skrifa::instance::Size::new(text.size * self.surface_scale_factor)
+    }
 
-        let half_s_width = self.surface_width as i32 / 2;
-        let half_s_height = self.surface_height as i32 / 2;
+    // adds the glyphs of the text's laid out lines, returns the first glyph that failed
+    fn prepare_draw_for_text(
+        &mut self,
+        font: &FontRef<'_>,
+        text: &TextDraw,
+        lines: &[(usize, LineLayout)],
+        vertices: &mut Vec<GlyphVertex>,
+        indices: &mut Vec<u32>,
+    ) -> Result<(), GameError> {
+        let font_size = self.font_size(text);
+        let ppem = font_size.ppem().unwrap_or_default();
+        // glyphs of different instances are cached separately
+        let coords = text.shaper_settings.coords(font);
+        let metrics = font.ext_font_ref().metrics(font_size, &coords);
+        let line_height = metrics.ascent - metrics.descent + metrics.leading;
+        let antialiasing = self.antialiasing(text.antialiasing);
+        let first_vertex = vertices.len();
+        let mut result = Ok(());
+
+        // the caret is in pixels from the middle of the surface, y up
+        let half_s_width = self.surface_width as f32 / 2.0;
+        let half_s_height = self.surface_height as f32 / 2.0;
+        let mut baseline = half_s_height - text.position[1] - metrics.ascent;
+        // the glyphs with where they go and their byte offset in the text, drawn once for
+        // every style
+        let mut glyphs: Vec<(RenderGlyphData, i32, i32, usize)> = Vec::new();
+        // left, bottom, right and top of each line, and of the underlines, y up
+        let mut line_rects: Vec<[f32; 4]> = Vec::new();
+        let mut underline_rects: Vec<[f32; 4]> = Vec::new();
+        let underline = metrics.underline.unwrap_or(skrifa::metrics::Decoration {
+            offset: -ppem / 10.0,
+            thickness: ppem / 16.0,
+        });
+        for (line_start, layout) in lines {
+            let line_start = *line_start;
+            let line_x = text.position[0] - half_s_width - text.align.offset(layout);
+            let line_top = baseline + metrics.ascent;
+            line_rects.push([
+                line_x,
+                line_top - line_height,
+                line_x + layout.width,
+                line_top,
+            ]);
+            for range in &text.underlines {
+                let line_range =
+                    range.start.saturating_sub(line_start)..range.end.saturating_sub(line_start);
+                if line_range.is_empty() || line_range.start >= layout.len {
+                    continue;
+                }
+                let top = (baseline + underline.offset).round();
+                for [left, right] in layout.selection_spans(line_range) {
+                    underline_rects.push([
+                        line_x + left,
+                        top - underline.thickness.round().max(1.0),
+                        line_x + right,
+                        top,
+                    ]);
+                }
+            }
+            for glyph in layout.glyphs.iter() {
+                let (placement, uv_bounds) = match self.glyph_cache.get_glyph_texture_bounds(
+                    font,
+                    glyph.glyph_id,
+                    font_size,
+                    coords.clone(),
+                    antialiasing,
+                ) {
+                    Ok(Some(bounds)) => bounds,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        result = result.and(Err(e));
+                        continue;
+                    }
+                };
+                glyphs.push((
+                    (&uv_bounds).into(),
+                    (line_x + glyph.x) as i32 + placement.left,
+                    (baseline + glyph.y) as i32 - (placement.height as i32 - placement.top),
+                    line_start + glyph.cluster,
+                ));
+            }
+            baseline -= line_height;
+        }
 
-        self.glyph_cache.prepare_draw_for_glyph(
-            &mut glyph_vertices,
-            &mut glyph_indices,
-            (&a_uv_bounds).into(),
-            -half_s_width + 256,
-            half_s_height - (256 + (a_placement.height as i32 - a_placement.top as i32)),
-        );
-        self.glyph_cache.prepare_draw_for_glyph(
-            &mut glyph_vertices,
-            &mut glyph_indices,
-            (&b_uv_bounds).into(),
-            -half_s_width + 256 + a_advance as i32,
-            half_s_height - (256 + (b_placement.height as i32 - b_placement.top as i32)),
-        );
-        self.glyph_cache.prepare_draw_for_glyph(
-            &mut glyph_vertices,
-            &mut glyph_indices,
-            (&p_uv_bounds).into(),
-            -half_s_width + 256 + (a_advance + b_advance) as i32,
-            half_s_height - (256 + (p_placement.height as i32 - p_placement.top as i32)),
-        );
-        self.glyph_cache.prepare_draw_for_glyph(
-            &mut glyph_vertices,
-            &mut glyph_indices,
-            (&cap_a_uv_bounds).into(),
-            -half_s_width + 256 + (a_advance + b_advance + p_advance) as i32,
-            half_s_height - (256 + (cap_a_placement.height as i32 - cap_a_placement.top as i32)),
-        );
-        self.glyph_cache.prepare_draw_for_glyph(
-            &mut glyph_vertices,
-            &mut glyph_indices,
-            (&j_uv_bounds).into(),
-            -half_s_width + 256 + (a_advance + b_advance + p_advance + cap_a_advance) as i32,
-            half_s_height - (256 + (j_placement.height as i32 - j_placement.top as i32)),
-        );
+        // back to front: the backgrounds, the shadow, the outline and then the glyphs
+        if let Some(background) = text.background {
+            match self.glyph_cache.solid_uv_bounds() {
+                Ok(uv_bounds) => {
+                    for [left, bottom, right, top] in line_rects {
+                        let padding = background.padding;
+                        let px_bounds = etagere::euclid::Box2D::new(
+                            etagere::euclid::point2(0, 0),
+                            etagere::euclid::point2(
+                                (right - left + 2.0 * padding).round() as i32,
+                                (top - bottom + 2.0 * padding).round() as i32,
+                            ),
+                        );
+                        self.glyph_cache.prepare_draw_for_glyph(
+                            vertices,
+                            indices,
+                            RenderGlyphData {
+                                px_bounds,
+                                uv_bounds,
+                            },
+                            background.color,
+                            (left - padding).round() as i32,
+                            (bottom - padding).round() as i32,
+                        );
+                    }
+                }
+                Err(e) => result = result.and(Err(e)),
+            }
+        }
+        let mut styles: Vec<([i32; 2], [f32; 4])> = Vec::new();
+        let shadow_offset = text.shadow.map(|shadow| {
+            [
+                shadow.offset[0].round() as i32,
+                -shadow.offset[1].round() as i32,
+            ]
+        });
+        if let Some(shadow) = text.shadow
+            && let Some(offset) = shadow_offset
+        {
+            styles.push((offset, shadow.color));
+        }
+        if let Some(outline) = text.outline {
+            // the glyphs are drawn at every offset within the width, which dilates them by it.
+            // Around the shadow too, so it doesn't poke out from under the outline.
+            let width = outline.width.round().max(1.0) as i32;
+            for origin in [Some([0, 0]), shadow_offset].into_iter().flatten() {
+                for y in -width..=width {
+                    for x in -width..=width {
+                        if (x, y) != (0, 0) && x * x + y * y <= width * width {
+                            styles.push(([origin[0] + x, origin[1] + y], outline.color));
+                        }
+                    }
+                }
+            }
+        }
+        for ([x, y], color) in styles {
+            for (glyph, caret_x, caret_y, _) in &glyphs {
+                self.glyph_cache.prepare_draw_for_glyph(
+                    vertices,
+                    indices,
+                    *glyph,
+                    color,
+                    caret_x + x,
+                    caret_y + y,
+                );
+            }
+        }
+        for (glyph, caret_x, caret_y, offset) in &glyphs {
+            let color = text
+                .color_ranges
+                .iter()
+                .rev()
+                .find(|(range, _)| range.contains(offset))
+                .map_or(text.color, |(_, color)| *color);
+            self.glyph_cache
+                .prepare_draw_for_glyph(vertices, indices, *glyph, color, *caret_x, *caret_y);
+        }
+        if !underline_rects.is_empty() {
+            match self.glyph_cache.solid_uv_bounds() {
+                Ok(uv_bounds) => {
+                    for [left, bottom, right, top] in underline_rects {
+                        let px_bounds = etagere::euclid::Box2D::new(
+                            etagere::euclid::point2(0, 0),
+                            etagere::euclid::point2(
+                                (right - left).round() as i32,
+                                (top - bottom).round() as i32,
+                            ),
+                        );
+                        self.glyph_cache.prepare_draw_for_glyph(
+                            vertices,
+                            indices,
+                            RenderGlyphData {
+                                px_bounds,
+                                uv_bounds,
+                            },
+                            text.color,
+                            left.round() as i32,
+                            bottom.round() as i32,
+                        );
+                    }
+                }
+                Err(e) => result = result.and(Err(e)),
+            }
+        }
+        // glyph vertices are made without knowing the text they're in
+        for vertex in &mut vertices[first_vertex..] {
+            vertex.depth = text.layer.depth();
+        }
+        result
+    }
 
-        /*let mut caret_x = -1.0 + self.logical_px_to_horizontal_screen_space_offset(256);
-        let mut caret_y = 1.0 - self.logical_px_to_vertical_screen_space_offset(512);
-
-        if let Some(txt) = &game_state.text {
-            self.glyph_cache.prepare_draw_for_text(
-                txt,
-                1,
-                px_scale,
-                &mut caret_x,
-                &mut caret_y,
-                &mut glyph_vertices,
-                &mut glyph_indices,
+    // the atlas overlay (see set_atlas_overlay) in the top right corner, scaled down to fit the
+    // surface, with its stats to the left of it
+    fn prepare_draw_for_atlas(
+        &mut self,
+        font: Option<&FontRef<'_>>,
+        vertices: &mut Vec<GlyphVertex>,
+        indices: &mut Vec<u32>,
+    ) -> Result<(), GameError> {
+        let first_vertex = vertices.len();
+        // before the stats, the solid block is one of the allocations
+        let solid = self.glyph_cache.solid_uv_bounds()?;
+        let stats = self.glyph_cache.stats();
+        let [atlas_width, atlas_height] = stats.size;
+        let margin = ATLAS_OVERLAY_MARGIN * self.surface_scale_factor;
+        let scale = ((self.surface_height as f32 - 2.0 * margin) / atlas_height as f32).min(1.0);
+        // left, bottom, right and top, in pixels from the middle of the surface, y up
+        let half_s_width = self.surface_width as f32 / 2.0;
+        let half_s_height = self.surface_height as f32 / 2.0;
+        let right = half_s_width - margin;
+        let top = half_s_height - margin;
+        let left = right - atlas_width as f32 * scale;
+        let bottom = top - atlas_height as f32 * scale;
+        let mut rect = |rect: [f32; 4], uv_bounds, color| {
+            let [left, bottom, right, top] = rect.map(f32::round);
+            self.glyph_cache.prepare_draw_for_glyph(
+                vertices,
+                indices,
+                RenderGlyphData {
+                    px_bounds: etagere::euclid::Box2D::new(
+                        etagere::euclid::point2(0, 0),
+                        etagere::euclid::point2(
+                            ((right - left) as i32).max(1),
+                            ((top - bottom) as i32).max(1),
+                        ),
+                    ),
+                    uv_bounds,
+                },
+                color,
+                left as i32,
+                bottom as i32,
             );
-        }
+        };
 
-        let fps = 1_000_000.0 / game_state.delta_time.as_micros() as f64;
-        let fps_text = &format!(
-            "Elapsed time: {}; Runtime: {}; dt: {:.2}, State number: {}, Frame number: {}; FPS: {:.2}",
-            game_state.start_time.elapsed().as_millis(),
-            game_state.run_time.as_millis(),
-            game_state.delta_time.as_micros() as f64 / 1_000.0,
-            game_state.state_number,
-            game_state.frame_number,
-            fps
+        rect(
+            [left, bottom, right, top],
+            solid,
+            ATLAS_OVERLAY_BACKGROUND_COLOR,
         );
-
-        let px_scale = self.glyph_cache.create_glyph_px_scale(32.0);
-
-        caret_x = -1.0 + self.logical_px_to_horizontal_screen_space_offset(64);
-        caret_y = 1.0 - self.logical_px_to_vertical_screen_space_offset(64);
-
-        self.glyph_cache.prepare_draw_for_text(
-            fps_text,
-            0,
-            px_scale,
-            &mut caret_x,
-            &mut caret_y,
-            &mut glyph_vertices,
-            &mut glyph_indices,
-        );*/
-
-        let old_vertices_len = glyph_vertices.len() as u16;
-
-        glyph_vertices.append(&mut vec![
-            /*GlyphVertex {
-                caret_position: [0.0, 0.0, 0.0],
-                px_bounds_offset: [0.0, 0.0],
-                tex_coords: [0.0, 0.0],
-            },
-            GlyphVertex {
-                caret_position: [0.0, -1.0, 0.0],
-                px_bounds_offset: [0.0, 0.0],
-                tex_coords: [0.0, 2048.0],
-            },
-            GlyphVertex {
-                caret_position: [
-                    0.0 + super::logical_px_to_screen_surface_offset(
-                        (512.0 * scale).floor() as i16,
-                        self.surface_width,
-                        self.surface_scale_factor,
-                    ),
-                    -1.0,
-                    0.0,
-                ],
-                px_bounds_offset: [0.0, 0.0],
-                tex_coords: [512.0, 2048.0],
-            },
-            GlyphVertex {
-                caret_position: [
-                    0.0 + super::logical_px_to_screen_surface_offset(
-                        (512.0 * scale).floor() as i16,
-                        self.surface_width,
-                        self.surface_scale_factor,
-                    ),
-                    0.0,
-                    0.0,
-                ],
-                px_bounds_offset: [0.0, 0.0],
-                tex_coords: [512.0, 0.0],
-            },*/
-            GlyphVertex {
-                caret_position: [0, 0],
-                px_bounds_offset: [0, 0],
-                tex_coords: [0, 0],
-            },
-            GlyphVertex {
-                caret_position: [0, -half_s_height],
-                px_bounds_offset: [0, 0],
-                tex_coords: [0, 2048],
-            },
-            GlyphVertex {
-                caret_position: [512 / (2048 / half_s_height), -half_s_height],
-                px_bounds_offset: [0, 0],
-                tex_coords: [512, 2048],
-            },
-            GlyphVertex {
-                caret_position: [512 / (2048 / half_s_height), 0],
-                px_bounds_offset: [0, 0],
-                tex_coords: [512, 0],
-            },
-        ]);
-
-        glyph_indices.append(&mut vec![
-            0 + old_vertices_len,
-            1 + old_vertices_len,
-            2 + old_vertices_len,
-            2 + old_vertices_len,
-            3 + old_vertices_len,
-            0 + old_vertices_len,
-        ]);
-
-        queue.write_buffer(
-            &self.glyph_vertex_buffer,
-            0,
-            bytemuck::cast_slice(&glyph_vertices),
+        rect(
+            [left, bottom, right, top],
+            etagere::euclid::Box2D::new(
+                etagere::euclid::point2(0, 0),
+                etagere::euclid::point2(atlas_width, atlas_height),
+            ),
+            [1.0, 1.0, 1.0, 1.0],
         );
+        // an outline around each allocation, a pixel wide however far the atlas is scaled down
+        let line = self.surface_scale_factor.max(1.0);
+        for [alloc_left, alloc_top, alloc_right, alloc_bottom] in self.glyph_cache.allocated_rects()
+        {
+            let x0 = left + alloc_left as f32 * scale;
+            let x1 = left + alloc_right as f32 * scale;
+            let y0 = top - alloc_bottom as f32 * scale;
+            let y1 = top - alloc_top as f32 * scale;
+            let color = ATLAS_OVERLAY_ALLOCATION_COLOR;
+            rect([x0, y0, x1, y0 + line], solid, color);
+            rect([x0, y1 - line, x1, y1], solid, color);
+            rect([x0, y0, x0 + line, y1], solid, color);
+            rect([x1 - line, y0, x1, y1], solid, color);
+        }
+        for vertex in &mut vertices[first_vertex..] {
+            vertex.depth = Layer::Console.depth();
+        }
 
-        queue.write_buffer(
-            &self.glyph_index_buffer,
-            0,
-            bytemuck::cast_slice(&glyph_indices),
+        let Some(font) = font else {
+            return Ok(());
+        };
+        let stats_text = format!(
+            "GLYPH ATLAS\n{} x {} PX, 1 PAGE\n{} GLYPHS\n{} UNDRAWABLE\n{} ALLOCATIONS\n{:.1}% OCCUPIED",
+            atlas_width,
+            atlas_height,
+            stats.glyphs,
+            stats.undrawable_glyphs,
+            stats.allocations,
+            stats.occupancy * 100.0,
         );
-
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, surface_dimensions_bind_group, &[]);
-        render_pass.set_bind_group(1, &self.texture_bind_group, &[]);
-        render_pass.set_vertex_buffer(0, self.glyph_vertex_buffer.slice(..));
-        render_pass.set_index_buffer(self.glyph_index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        render_pass.draw_indexed(0..glyph_indices.len() as u32, 0, 0..1);
+        let stats_draw = TextDraw::new(
+            stats_text,
+            [half_s_width + left - margin, margin],
+            ATLAS_OVERLAY_TEXT_SIZE,
+        )
+        .align(TextAlign::Right)
+        .tabular_figures()
+        .background(ATLAS_OVERLAY_BACKGROUND_COLOR, margin / 2.0)
+        .layer(Layer::Console);
+        let lines = stats_draw.layout_lines(font, self.font_size(&stats_draw));
+        self.prepare_draw_for_text(font, &stats_draw, &lines, vertices, indices)
     }
 
-    pub fn surface_resized(&mut self, surface_width: u32, surface_height: u32, _scale_factor: f32) {
+    /// Text is rasterized at its size times `scale_factor` from now on. The glyphs cached
+    /// for the previous scale factor are left to [`GlyphCache::clear`].
+    pub fn surface_resized(&mut self, surface_width: u32, surface_height: u32, scale_factor: f32) {
         self.surface_width = surface_width;
         self.surface_height = surface_height;
+        self.surface_scale_factor = scale_factor;
+    }
+
+    /// Takes over the glyphs of `glyph_cache`, e.g. the ones cached for a device that was
+    /// lost. The atlas is uploaded with the next frame. Only if the atlases are the same
+    /// size (the new device may have other limits), otherwise the cache stays empty.
+    pub(crate) fn restore_glyph_cache(&mut self, glyph_cache: &mut GlyphCache) {
+        let cache = &self.glyph_cache;
+        if (glyph_cache.texture_row_size, glyph_cache.texture_rows)
+            != (cache.texture_row_size, cache.texture_rows)
+        {
+            log::info!(target: logging::GFX, "the glyph atlas changed size, starting it over");
+            return;
+        }
+        std::mem::swap(&mut self.glyph_cache, glyph_cache);
+        self.glyph_cache.mark_all_dirty();
     }
 
-    pub fn queue_write_texture_if_changed(&mut self, queue: &wgpu::Queue) {
-        if self.glyph_cache.texture_data_dirty {
+    /// Uploads the parts of the atlas that changed since the last frame, usually the few
+    /// glyphs that were just rasterized rather than the whole texture. Returns how many bytes
+    /// that was.
+    pub fn queue_write_texture_if_changed(&mut self, queue: &wgpu::Queue) -> usize {
+        let mut bytes = 0;
+        let glyph_cache = &mut self.glyph_cache;
+        for rect in glyph_cache.dirty_rects.drain(..) {
+            let size = rect.size();
             queue.write_texture(
                 wgpu::TexelCopyTextureInfo {
                     texture: &self.texture,
                     mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
+                    origin: wgpu::Origin3d {
+                        x: (rect.min.x / 4) as u32,
+                        y: rect.min.y as u32,
+                        z: 0,
+                    },
                     aspect: wgpu::TextureAspect::All,
                 },
-                &self.glyph_cache.texture,
+                &glyph_cache.texture,
                 wgpu::TexelCopyBufferLayout {
-                    offset: 0,
+                    offset: (rect.min.y * self.texture_row_size_bytes + rect.min.x) as u64,
                     bytes_per_row: Some(self.texture_row_size_bytes as u32),
-                    rows_per_image: Some(self.texture_rows as u32),
+                    rows_per_image: Some(size.height as u32),
                 },
                 wgpu::Extent3d {
-                    width: (self.texture_row_size_bytes / 4) as u32,
-                    height: self.texture_rows as u32,
+                    width: (size.width / 4) as u32,
+                    height: size.height as u32,
                     depth_or_array_layers: 1,
                 },
             );
-            self.glyph_cache.texture_data_dirty = false;
+            bytes += size.width * size.height;
+        }
+        bytes
+    }
+}
+
+impl RenderNode for TextRenderer {
+    fn pass_descriptor(&self) -> PassDescriptor {
+        PassDescriptor {
+            label: "Glyph Render Pass",
+            target: PassTarget::Scene,
+            // draw on top of the previous passes
+            load: wgpu::LoadOp::Load,
+            multisampled: false,
+            depth: true,
+        }
+    }
+
+    fn record(&mut self, render_pass: &mut wgpu::RenderPass<'_>, ctx: &FrameContext) {
+        let game_state = ctx.game_state;
+        for text in &game_state.overlay.text {
+            self.queue_text(text.clone());
         }
+        for text in game_state
+            .log_panel
+            .text(ctx.render_alpha, game_state.palette())
+        {
+            self.queue_text(text);
+        }
+        for text in &game_state.queued_text {
+            self.queue_text(text.clone());
+        }
+        self.error = self
+            .flush(
+                &game_state.font_cache,
+                game_state.ui_font().as_ref(),
+                ctx.device,
+                ctx.queue,
+            )
+            .err();
+        if self.uploaded_bytes > 0 {
+            mark_upload(render_pass, "glyph atlas and vertices", self.uploaded_bytes);
+        }
+        self.draw(render_pass, ctx.surface_dimensions_bind_group);
     }
 }
 
@@ -543,6 +2197,12 @@ enum FontError {
         family_name: String,
         subfamily_name: Option<String>,
     },
+    #[error("{0}: no font of the file at collection index {1} is cached")]
+    NotCachedInFile(String, u32),
+    #[error("{0}: the file changed since it was loaded, it has to be reloaded instead")]
+    ChangedSinceLoaded(String),
+    #[error("the font cache is inconsistent: {0}")]
+    Inconsistent(String),
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -556,26 +2216,7 @@ impl FontFileType {
         if std::fs::exists(&path)
             .with_context(|| format!("font file might not exist: {}", &path.as_ref().display()))?
         {
-            match path
-                .as_ref()
-                .extension()
-                .and_then(OsStr::to_str)
-                .map(str::to_ascii_lowercase)
-                .as_deref()
-            {
-                Some("ttc") | Some("otc") => Ok(FontFileType::Collection),
-                Some("ttf") | Some("otf") => Ok(FontFileType::Single),
-                Some(ext) => Err(FontError::FileExtension(
-                    path.as_ref().to_string_lossy().into_owned(),
-                    ext.into(),
-                )
-                .into()),
-                None => Err(FontError::FileExtension(
-                    path.as_ref().to_string_lossy().into_owned(),
-                    "no extension".to_string(),
-                )
-                .into()),
-            }
+            Self::from_extension(path)
         } else {
             Err(std::io::Error::new(
                 std::io::ErrorKind::NotFound,
@@ -584,6 +2225,30 @@ impl FontFileType {
             .into())
         }
     }
+
+    // the type the extension says the file is, without looking at the file
+    fn from_extension<P: AsRef<Path>>(path: P) -> Result<Self> {
+        match path
+            .as_ref()
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("ttc") | Some("otc") => Ok(FontFileType::Collection),
+            Some("ttf") | Some("otf") => Ok(FontFileType::Single),
+            Some(ext) => Err(FontError::FileExtension(
+                path.as_ref().to_string_lossy().into_owned(),
+                ext.into(),
+            )
+            .into()),
+            None => Err(FontError::FileExtension(
+                path.as_ref().to_string_lossy().into_owned(),
+                "no extension".to_string(),
+            )
+            .into()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -621,13 +2286,23 @@ impl NamedInstanceInfo {
 
 #[derive(Clone)]
 pub struct FontRef<'a> {
-    font_cache: &'a FontCache,
     cache_index: usize,
     font_data: &'a FontCacheData,
     lazy_font_data: &'a LazyFontCacheData,
+    shape_cache: &'a Mutex<ShapeCache>,
 }
 
 impl<'a> FontRef<'a> {
+    /// Where the font is in its [`FontCache`], see [`FontCache::get_font`].
+    pub fn cache_index(&self) -> usize {
+        self.cache_index
+    }
+
+    /// Which font of its file this is, 0 unless the file is a collection (`.ttc`/`.otc`).
+    pub fn collection_index(&self) -> u32 {
+        self.font_data.font_ref_idx
+    }
+
     pub fn _full_name(&self) -> String {
         format!(
             "{}{}",
@@ -656,30 +2331,32 @@ impl<'a> FontRef<'a> {
         &self.font_data.named_instances
     }
 
+    /// The named instance called `name` (e.g. "Light" or "Bold"), ignoring case.
+    pub fn named_instance(&self, name: &str) -> Option<&NamedInstanceInfo> {
+        self.named_instances()
+            .iter()
+            .find(|named_instance| named_instance.name.eq_ignore_ascii_case(name))
+    }
+
     pub fn features(&self) -> &[String] {
         &self.font_data.features
     }
 
-    fn revision(&self) -> &skrifa::raw::types::Fixed {
-        &self.font_cache.font_datas[self.cache_index].revision
+    /// The `head` table's font revision, the newer of two duplicate fonts is kept.
+    pub fn revision(&self) -> &skrifa::raw::types::Fixed {
+        &self.font_data.revision
     }
 
-    pub fn ext_font_ref(&self) -> &ExtFontRef<'static> {
-        &self
-            .lazy_font_data
-            .ext_font_ref(self.font_cache, self.cache_index)
+    pub fn ext_font_ref(&self) -> &ExtFontRef<'a> {
+        self.lazy_font_data.ext_font_ref(self.font_data)
     }
 
     fn shaper_data(&self) -> &ShaperData {
-        &self
-            .lazy_font_data
-            .shaper_data(self.font_cache, self.cache_index)
+        self.lazy_font_data.shaper_data(self.font_data)
     }
 
-    pub fn outline_glyph_collection(&self) -> &OutlineGlyphCollection<'static> {
-        &self
-            .lazy_font_data
-            .outline_glyph_collection(self.font_cache, self.cache_index)
+    pub fn outline_glyph_collection(&self) -> &OutlineGlyphCollection<'a> {
+        self.lazy_font_data.outline_glyph_collection(self.font_data)
     }
 
     pub fn shaper(&'a self, settings: ShaperSettings) -> FontShaper<'a> {
@@ -736,6 +2413,7 @@ impl<'a> std::fmt::Debug for FontRef<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("FontCacheRef")
             .field("cache_index", &self.cache_index)
+            .field("collection_index()", &self.collection_index())
             .field("family_name()", &self.family_name())
             .field("subfamily_name()", &self.subfamily_name())
             .field(
@@ -795,7 +2473,8 @@ impl<'a> std::hash::Hash for FontRef<'a> {
 }
 
 struct FontCacheData {
-    raw_data_ref: &'static [u8],
+    // the whole file, shared by its fonts
+    raw_data: Arc<[u8]>,
     font_ref_idx: u32,
     family_name: String,
     subfamily_name: Option<String>,
@@ -805,6 +2484,33 @@ struct FontCacheData {
     features: SmallVec<[String; 32]>,
 }
 
+impl FontCacheData {
+    // of two fonts with the same names, the one with more variation axes, then more features,
+    // then more named instances, then the later revision is better
+    fn is_better_than(&self, other: &FontCacheData) -> bool {
+        (
+            self.variation_axes.len(),
+            self.features.len(),
+            self.named_instances.len(),
+        )
+            .cmp(&(
+                other.variation_axes.len(),
+                other.features.len(),
+                other.named_instances.len(),
+            ))
+            .then(self.revision.cmp(&other.revision))
+            .is_gt()
+    }
+
+    fn has_same_names(&self, other: &FontCacheData) -> bool {
+        self.family_name.eq_ignore_ascii_case(&other.family_name)
+            && match (&self.subfamily_name, &other.subfamily_name) {
+                (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+                (a, b) => a == b,
+            }
+    }
+}
+
 impl std::fmt::Debug for FontCacheData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("FontCacheData")
@@ -850,39 +2556,32 @@ impl LazyFontCacheData {
         }
     }
 
-    fn ext_font_ref(
-        &self,
-        font_cache: &FontCache,
-        font_cache_index: usize,
-    ) -> &ExtFontRef<'static> {
-        let font_data = &font_cache.font_datas[font_cache_index];
+    // font_data must be the font this is the lazy data of
+    fn ext_font_ref(&self, font_data: &FontCacheData) -> &ExtFontRef<'static> {
         self.ext_font_ref.get_or_init(|| {
+            // SAFETY: the references into the data don't outlive it. The cache drops a font's
+            // lazy data before its FontCacheData (see FontCache::set_font and the order of
+            // FontCache's fields), and FontRef only hands them out for as long as it borrows
+            // the cache.
+            let raw_data: &'static [u8] = unsafe { &*Arc::as_ptr(&font_data.raw_data) };
             Box::new(
-                ExtFontRef::from_index(font_data.raw_data_ref, font_data.font_ref_idx)
+                ExtFontRef::from_index(raw_data, font_data.font_ref_idx)
                     .expect("Unable to create FontRef<'static> for cached font"),
             )
         })
     }
 
-    fn shaper_data(&self, font_cache: &FontCache, font_cache_index: usize) -> &ShaperData {
-        self.shaper_data.get_or_init(|| {
-            Box::new(ShaperData::new(
-                self.ext_font_ref(font_cache, font_cache_index),
-            ))
-        })
+    fn shaper_data(&self, font_data: &FontCacheData) -> &ShaperData {
+        self.shaper_data
+            .get_or_init(|| Box::new(ShaperData::new(self.ext_font_ref(font_data))))
     }
 
     fn outline_glyph_collection(
         &self,
-        font_cache: &FontCache,
-        font_cache_index: usize,
+        font_data: &FontCacheData,
     ) -> &OutlineGlyphCollection<'static> {
-        self.outline_glyphs_ref.get_or_init(|| {
-            Box::new(
-                self.ext_font_ref(font_cache, font_cache_index)
-                    .outline_glyphs(),
-            )
-        })
+        self.outline_glyphs_ref
+            .get_or_init(|| Box::new(self.ext_font_ref(font_data).outline_glyphs()))
     }
 }
 
@@ -902,7 +2601,7 @@ enum RawCacheResult {
     },
     New {
         path: PathBuf,
-        raw_data_ref: &'static [u8],
+        raw_data: Arc<[u8]>,
         raw_data_hash: u64,
         font_file_type: FontFileType,
         font_datas: Vec<RawFontCacheData>,
@@ -927,15 +2626,97 @@ enum CacheResult {
     },
 }
 
+impl CacheResult {
+    // the cache indexes of the file's fonts, whether they were cached, replaced or skipped
+    fn into_idxs(self) -> SmallVec<[usize; 16]> {
+        match self {
+            CacheResult::New {
+                newly_cached,
+                replaced,
+                skipped,
+                ..
+            } => newly_cached
+                .into_iter()
+                .chain(replaced)
+                .chain(skipped)
+                .collect(),
+            CacheResult::AlreadyCached { idxs, .. } => idxs,
+            CacheResult::NoNewData { existing_idxs, .. } => existing_idxs,
+        }
+    }
+}
+
 pub struct FontCache {
     paths: Vec<PathBuf>,
     font_file_types: Vec<FontFileType>,
     paths_to_font_idxs: HashMap<PathBuf, SmallVec<[usize; 16]>>,
-    paths_to_data_refs: HashMap<PathBuf, &'static [u8]>,
+    // the data is freed once none of the file's fonts are cached anymore
+    paths_to_data_refs: HashMap<PathBuf, Weak<[u8]>>,
     raw_data_hashes_to_paths: HashMap<u64, PathBuf>,
 
-    font_datas: Vec<FontCacheData>,
+    // the lazy datas refer to the font datas, so they have to be dropped first. Unloaded
+    // fonts leave their cache index empty, so the indexes of the others don't change.
     lazy_font_datas: Vec<LazyFontCacheData>,
+    font_datas: Vec<Option<FontCacheData>>,
+
+    watcher: Option<FontWatcher>,
+    // the files loaded through the asset store, with the version of each that's cached (see
+    // FontCache::load_font_asset)
+    font_assets: Vec<(Handle<FontData>, u32)>,
+    background_load: Option<BackgroundLoad>,
+    shape_cache: Mutex<ShapeCache>,
+}
+
+/// Progress of loading fonts in the background, see
+/// [`FontCache::load_system_fonts_in_background`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FontLoadEvent {
+    /// `loaded` of the `total` font files were read, their fonts are in the cache
+    Progress { loaded: usize, total: usize },
+    /// All the files were read, adding `fonts` fonts to the cache. The fonts at the
+    /// `replaced` cache indexes were replaced by better versions, anything cached for them
+    /// (like glyphs) is outdated.
+    Finished { fonts: usize, replaced: Vec<usize> },
+}
+
+// a font file loaded by the background thread, or the number of files it's going to load
+enum BackgroundLoadMessage {
+    Total(usize),
+    Loaded(PathBuf, Result<RawCacheResult>),
+}
+
+// fonts being loaded on another thread (the receiver is locked so the cache stays Sync)
+struct BackgroundLoad {
+    messages: Mutex<Receiver<BackgroundLoadMessage>>,
+    total: Option<usize>,
+    loaded: usize,
+    fonts_before: usize,
+    replaced: Vec<usize>,
+}
+
+// the directories of FontCache::watch_directory (the receiver is locked so the cache stays
+// Sync, fonts are loaded in parallel)
+struct FontWatcher {
+    watcher: RecommendedWatcher,
+    events: Mutex<Receiver<notify::Result<notify::Event>>>,
+}
+
+/// A small Latin font embedded in the binary (Cantarell, under the SIL Open Font License, see
+/// `src/gfx/fonts/OFL.txt`), so there's text even when none of the game's font files is
+/// found. See [`FontCache::load_fallback_font`].
+pub const FALLBACK_FONT: &[u8] = include_bytes!("fonts/Cantarell-Regular.ttf");
+
+// how the cache recognizes a file it has loaded before, under any path
+fn hash_raw_data(raw_data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    raw_data.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Default for FontCache {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[allow(unused)]
@@ -948,36 +2729,44 @@ impl FontCache {
             paths_to_data_refs: HashMap::new(),
             font_file_types: Vec::new(),
 
-            font_datas: Vec::new(),
             lazy_font_datas: Vec::new(),
+            font_datas: Vec::new(),
+
+            watcher: None,
+            font_assets: Vec::new(),
+            background_load: None,
+            shape_cache: Mutex::default(),
         }
     }
 
-    fn ext_font_ref(&self, font_index: usize) -> &ExtFontRef<'static> {
-        self.lazy_font_datas[font_index].ext_font_ref(self, font_index)
+    // the loaded fonts with their cache indexes
+    fn loaded_font_datas(&self) -> impl Iterator<Item = (usize, &FontCacheData)> {
+        self.font_datas
+            .iter()
+            .enumerate()
+            .filter_map(|(i, fd)| Some((i, fd.as_ref()?)))
     }
 
-    fn shaper_data(&self, font_index: usize) -> &ShaperData {
-        self.lazy_font_datas[font_index].shaper_data(self, font_index)
+    // puts a font at a cache index (None unloads it), dropping what was derived from the
+    // previous one
+    fn set_font(&mut self, idx: usize, font_data: Option<FontCacheData>) {
+        self.lazy_font_datas[idx] = LazyFontCacheData::new();
+        self.font_datas[idx] = font_data;
+        self.shape_cache.get_mut().forget_font(idx);
     }
 
-    fn outline_glyph_collection(&self, font_index: usize) -> &OutlineGlyphCollection<'static> {
-        self.lazy_font_datas[font_index].outline_glyph_collection(self, font_index)
+    /// Bytes the lines shaped with the cache's fonts take (see [`layout_line`]), for the
+    /// memory report.
+    pub fn shape_cache_size(&self) -> usize {
+        self.shape_cache.lock().bytes
     }
 
     pub fn list_fonts(&self, show_path: bool) {
         let mut fonts: Vec<String> = self
-            .font_datas
-            .iter()
-            .enumerate()
+            .loaded_font_datas()
             .map(|(i, font)| {
                 if show_path {
-                    let path = self
-                        .paths_to_font_idxs
-                        .iter()
-                        .find(|(_, v)| v.contains(&i))
-                        .unwrap()
-                        .0;
+                    let path = self.font_path(i).unwrap();
                     format!(
                         "{} - {} -- [{}]",
                         font.family_name,
@@ -996,17 +2785,53 @@ impl FontCache {
 
         fonts.sort();
 
-        for font in fonts {
-            eprintln!("{}", font);
-        }
+        log::info!(target: logging::FONT_CACHE, "{} fonts:\n{}", fonts.len(), fonts.join("\n"));
+    }
+
+    /// The file the font with the index `idx` was loaded from.
+    pub fn font_path(&self, idx: usize) -> Option<&Path> {
+        self.paths_to_font_idxs
+            .iter()
+            .find(|(_, idxs)| idxs.contains(&idx))
+            .map(|(path, _)| path.as_path())
+    }
+
+    /// The cached fonts of the file at `path`, by collection index. A font of the file that
+    /// was skipped for a better duplicate from another file isn't one of them.
+    pub fn fonts_in_file(&self, path: impl AsRef<Path>) -> Vec<FontRef<'_>> {
+        let mut fonts: Vec<FontRef<'_>> = self
+            .paths_to_font_idxs
+            .get(path.as_ref())
+            .into_iter()
+            .flatten()
+            .filter_map(|&idx| self.get_font(idx))
+            .collect();
+        fonts.sort_by_key(FontRef::collection_index);
+        fonts
+    }
+
+    /// The font at `collection_index` in the file at `path`, e.g. one face of a `.ttc` when
+    /// [`FontCache::find_font`] would pick another one with the same names.
+    pub fn font_in_file<'a>(
+        &'a self,
+        path: impl AsRef<Path>,
+        collection_index: u32,
+    ) -> Result<FontRef<'a>> {
+        let path = path.as_ref();
+        self.fonts_in_file(path)
+            .into_iter()
+            .find(|font| font.collection_index() == collection_index)
+            .ok_or_else(|| {
+                FontError::NotCachedInFile(path.display().to_string(), collection_index).into()
+            })
     }
 
     pub fn get_font<'a>(&'a self, idx: usize) -> Option<FontRef<'a>> {
-        self.font_datas.get(idx).map(|fd| FontRef {
-            font_cache: self,
+        self.font_datas.get(idx)?.as_ref().map(|fd| FontRef {
             cache_index: idx,
             font_data: fd,
             lazy_font_data: &self.lazy_font_datas[idx],
+            shape_cache: &self.shape_cache,
         })
     }
 
@@ -1033,9 +2858,7 @@ impl FontCache {
         let subfam_name: Option<String> = subfamily_name.map(|s| s.into());
 
         let family_idxs: Vec<usize> = self
-            .font_datas
-            .iter()
-            .enumerate()
+            .loaded_font_datas()
             .filter_map(|(i, fd)| {
                 is_match(
                     &fd.family_name,
@@ -1047,13 +2870,10 @@ impl FontCache {
             })
             .collect();
 
-        if family_idxs.len() == 1 || (subfam_name.is_none() && family_idxs.len() > 0) {
-            Ok(FontRef {
-                font_cache: &self,
-                cache_index: family_idxs[0],
-                font_data: &self.font_datas[family_idxs[0]],
-                lazy_font_data: &self.lazy_font_datas[family_idxs[0]],
-            })
+        if (family_idxs.len() == 1 || (subfam_name.is_none() && family_idxs.len() > 0))
+            && let Some(font) = self.get_font(family_idxs[0])
+        {
+            Ok(font)
         } else {
             Err(FontError::NotCached {
                 family_name: fam_name,
@@ -1092,9 +2912,7 @@ impl FontCache {
             let two = terms[i..tlen].join(" ");
 
             results.extend(
-                self.font_datas
-                    .iter()
-                    .enumerate()
+                self.loaded_font_datas()
                     .filter(|(_, fd)| {
                         !one.is_empty()
                             && is_match(
@@ -1111,12 +2929,7 @@ impl FontCache {
                                     if one.is_empty() { None } else { Some(&one) },
                                 )
                     })
-                    .map(|(idx, _)| FontRef {
-                        font_cache: &self,
-                        cache_index: idx,
-                        font_data: &self.font_datas[idx],
-                        lazy_font_data: &self.lazy_font_datas[idx],
-                    }),
+                    .filter_map(|(idx, _)| self.get_font(idx)),
             );
         }
 
@@ -1131,6 +2944,123 @@ impl FontCache {
         self.load_multiple_font_files(system_font_paths)
     }
 
+    /// Loads the fonts installed on the system on another thread, so the game can start with
+    /// its own fonts while the system has many. [`FontCache::poll_background_load`] adds them
+    /// to the cache as they come in. Files that can't be loaded are logged and skipped.
+    pub fn load_system_fonts_in_background(&mut self) -> Result<()> {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("font loader".into())
+            .spawn(move || {
+                let start = std::time::Instant::now();
+                let paths = match font_util::load_system_font_paths() {
+                    Ok(paths) => {
+                        log::debug!(
+                            target: logging::FONT_CACHE,
+                            "found {} system font files in {:?}",
+                            paths.len(),
+                            start.elapsed()
+                        );
+                        paths
+                    }
+                    Err(e) => {
+                        log::warn!(
+                            target: logging::FONT_CACHE,
+                            "unable to list the system fonts: {}",
+                            e
+                        );
+                        Vec::new()
+                    }
+                };
+                if sender
+                    .send(BackgroundLoadMessage::Total(paths.len()))
+                    .is_err()
+                {
+                    return;
+                }
+                // duplicates of fonts already in the cache are skipped when they're stored
+                let no_hashes = HashMap::new();
+                paths.into_par_iter().for_each_with(sender, |sender, path| {
+                    let raw_data = Self::load_raw_data(&path, Arc::new(&no_hashes));
+                    // the cache is gone, nothing left to do
+                    let _ = sender.send(BackgroundLoadMessage::Loaded(path, raw_data));
+                });
+            })?;
+        self.background_load = Some(BackgroundLoad {
+            messages: Mutex::new(receiver),
+            total: None,
+            loaded: 0,
+            fonts_before: self.font_datas.len(),
+            replaced: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// True while fonts are being loaded in the background.
+    pub fn is_loading(&self) -> bool {
+        self.background_load.is_some()
+    }
+
+    /// Adds the fonts that were loaded in the background since the last call to the cache.
+    /// Returns the progress, if there was any.
+    pub fn poll_background_load(&mut self) -> Option<FontLoadEvent> {
+        let mut background_load = self.background_load.take()?;
+        let messages: Vec<BackgroundLoadMessage> =
+            background_load.messages.lock().try_iter().collect();
+        let progressed = !messages.is_empty();
+        for message in messages {
+            let (path, raw_data) = match message {
+                BackgroundLoadMessage::Total(total) => {
+                    background_load.total = Some(total);
+                    continue;
+                }
+                BackgroundLoadMessage::Loaded(path, raw_data) => (path, raw_data),
+            };
+            background_load.loaded += 1;
+            let raw_data = match raw_data {
+                Ok(RawCacheResult::New { raw_data_hash, .. })
+                    if self.raw_data_hashes_to_paths.contains_key(&raw_data_hash)
+                        || self.paths_to_font_idxs.contains_key(&path) =>
+                {
+                    continue;
+                }
+                raw_data => raw_data,
+            };
+            match self.store_raw_data(raw_data, &[]) {
+                Ok(CacheResult::New { replaced, .. }) => background_load.replaced.extend(replaced),
+                Ok(_) => {}
+                Err(e) => {
+                    log::warn!(
+                        target: logging::FONT_CACHE,
+                        "unable to load font file {}: {}",
+                        path.display(),
+                        e
+                    );
+                }
+            }
+        }
+
+        match background_load.total {
+            Some(total) if background_load.loaded >= total => {
+                let mut replaced = background_load.replaced;
+                replaced.sort();
+                replaced.dedup();
+                Some(FontLoadEvent::Finished {
+                    fonts: self.font_datas.len() - background_load.fonts_before,
+                    replaced,
+                })
+            }
+            total => {
+                let event = (progressed && total.is_some()).then(|| FontLoadEvent::Progress {
+                    loaded: background_load.loaded,
+                    total: total.unwrap_or(0),
+                });
+                self.background_load = Some(background_load);
+                event
+            }
+        }
+    }
+
     pub fn load_multiple_font_files(&mut self, paths: Vec<impl Into<PathBuf>>) -> Result<usize> {
         let result_count_heuristic = 2 * paths.len();
 
@@ -1141,13 +3071,13 @@ impl FontCache {
             .map(|path| path.into())
             .collect::<Vec<PathBuf>>()
             .into_par_iter()
-            .map(|path| self.load_raw_data(path, raw_data_hashes_to_paths.clone()))
+            .map(|path| Self::load_raw_data(path, raw_data_hashes_to_paths.clone()))
             .collect();
 
         let mut result_idxs: Vec<usize> = Vec::with_capacity(result_count_heuristic);
 
         for raw_data in raw_datas.into_iter() {
-            let idxs = match self.store_raw_data(raw_data)? {
+            let idxs = match self.store_raw_data(raw_data, &[])? {
                 CacheResult::New {
                     newly_cached,
                     replaced,
@@ -1174,39 +3104,451 @@ impl FontCache {
     pub fn load_font_file(&mut self, path: impl Into<PathBuf>) -> Result<SmallVec<[usize; 16]>> {
         let path: PathBuf = path.into();
         let raw_data_hashes_to_paths = Arc::new(&self.raw_data_hashes_to_paths);
-        let cache_result: CacheResult =
-            self.store_raw_data(self.load_raw_data(&path, raw_data_hashes_to_paths.clone()))?;
+        let cache_result: CacheResult = self.store_raw_data(
+            Self::load_raw_data(&path, raw_data_hashes_to_paths.clone()),
+            &[],
+        )?;
 
-        let results: SmallVec<[usize; 16]> = match cache_result {
-            CacheResult::New {
-                newly_cached,
-                replaced,
-                skipped,
-                ..
-            } => newly_cached
-                .into_iter()
-                .chain(replaced.into_iter())
-                .chain(skipped.into_iter())
-                .collect(),
-            CacheResult::AlreadyCached { idxs, .. } => idxs,
-            CacheResult::NoNewData { existing_idxs, .. } => existing_idxs,
+        Ok(cache_result.into_idxs())
+    }
+
+    /// Loads a font file that's in memory, e.g. one embedded in the binary with
+    /// `include_bytes!`. The `name` stands in for its path (see [`FontCache::font_path`]) and
+    /// needs a font file's extension, like `"fallback/Cantarell-Regular.ttf"`. The data is
+    /// copied, the cache keeps it like a file's.
+    pub fn load_font_bytes(
+        &mut self,
+        name: &str,
+        data: &'static [u8],
+    ) -> Result<SmallVec<[usize; 16]>> {
+        let path = PathBuf::from(name);
+        let raw_data = FontFileType::from_extension(&path).and_then(|font_file_type| {
+            Self::parse_raw_data(
+                &path,
+                font_file_type,
+                data.to_vec(),
+                &self.raw_data_hashes_to_paths,
+            )
+        });
+        Ok(self.store_raw_data(raw_data, &[])?.into_idxs())
+    }
+
+    /// Loads the fonts of a file the asset store loaded (see [`crate::assets`]), sharing its
+    /// data. The cache keeps the handle, and when the store reloads the file (with hot
+    /// reloading on) [`FontCache::poll_changes`] reloads the fonts in place, like
+    /// [`FontCache::reload_font_file`]. The file has to be loaded already, see
+    /// [`AssetStore::load_blocking`](crate::assets::AssetStore::load_blocking).
+    pub fn load_font_asset(&mut self, handle: &Handle<FontData>) -> Result<SmallVec<[usize; 16]>> {
+        let version = handle.version();
+        let raw_data = self.font_asset_raw_data(handle);
+        let idxs = self.store_raw_data(raw_data, &[])?.into_idxs();
+        if !self
+            .font_assets
+            .iter()
+            .any(|(font_asset, _)| font_asset == handle)
+        {
+            self.font_assets.push((handle.clone(), version));
+        }
+        Ok(idxs)
+    }
+
+    // reloads the fonts of a file the asset store reloaded
+    fn reload_font_asset(&mut self, handle: &Handle<FontData>) -> Result<SmallVec<[usize; 16]>> {
+        let version = handle.version();
+        let raw_data = self.font_asset_raw_data(handle);
+        let idxs = self.reload_raw_data(handle.path(), raw_data)?;
+        // forgotten with the file's previous data
+        self.font_assets
+            .retain(|(font_asset, _)| font_asset != handle);
+        self.font_assets.push((handle.clone(), version));
+        Ok(idxs)
+    }
+
+    fn font_asset_raw_data(&self, handle: &Handle<FontData>) -> Result<RawCacheResult> {
+        let font = handle.get().ok_or_else(|| {
+            anyhow::anyhow!(
+                "font file {} isn't loaded: {}",
+                handle.path().display(),
+                handle.error().unwrap_or_else(|| "still loading".into())
+            )
+        })?;
+        let font_file_type = FontFileType::from_extension(handle.path())?;
+        Self::parse_raw_data(
+            handle.path(),
+            font_file_type,
+            font.data().clone(),
+            &self.raw_data_hashes_to_paths,
+        )
+    }
+
+    /// Downloads the font file at `url` and loads it through the asset store `fonts`, like
+    /// [`FontCache::load_font_asset`]. The file is kept in the game's data directory
+    /// (`fonts/downloads`), named after its contents, so a font that's cached already (from
+    /// any file or URL) is only downloaded again, not parsed or stored twice. The URL has to
+    /// end in a font file's extension.
+    #[cfg(feature = "remote-fonts")]
+    pub fn load_font_url(
+        &mut self,
+        url: &str,
+        fonts: &mut crate::assets::AssetStore<FontData>,
+    ) -> Result<SmallVec<[usize; 16]>> {
+        // large CJK fonts and collections are tens of megabytes
+        const MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+        let file_name = url
+            .split(['?', '#'])
+            .next()
+            .and_then(|url| url.rsplit('/').next())
+            .unwrap_or_default();
+        let extension = Path::new(file_name)
+            .extension()
+            .and_then(OsStr::to_str)
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if !["ttf", "otf", "ttc", "otc"].contains(&extension.as_str()) {
+            return Err(FontError::FileExtension(url.to_string(), extension).into());
+        }
+
+        let raw_bytes = ureq::get(url)
+            .call()
+            .and_then(|mut response| {
+                response
+                    .body_mut()
+                    .with_config()
+                    .limit(MAX_SIZE)
+                    .read_to_vec()
+            })
+            .with_context(|| format!("unable to download font file {}", url))?;
+        let raw_data_hash = hash_raw_data(&raw_bytes);
+        // the same data from another file or URL, nothing to store
+        if let Some(path) = self.raw_data_hashes_to_paths.get(&raw_data_hash) {
+            return self.paths_to_font_idxs.get(path).cloned().ok_or_else(|| {
+                FontError::Inconsistent(format!("{} is hashed but has no fonts", path.display()))
+                    .into()
+            });
+        }
+
+        let directory = crate::paths::data_dir(crate::paths::APP_NAME)
+            .unwrap_or_else(std::env::temp_dir)
+            .join("fonts")
+            .join("downloads");
+        let path = directory.join(format!("{:016x}.{}", raw_data_hash, extension));
+        if !path.exists() {
+            std::fs::create_dir_all(&directory)
+                .and_then(|_| std::fs::write(&path, &raw_bytes))
+                .with_context(|| format!("unable to store {} at {}", url, path.display()))?;
+        }
+        log::info!(target: logging::FONT_CACHE, "downloaded {} to {}", url, path.display());
+        self.load_font_asset(&fonts.load_blocking(path))
+    }
+
+    /// Loads [`FALLBACK_FONT`] and returns its cache index.
+    pub fn load_fallback_font(&mut self) -> Result<usize> {
+        self.load_font_bytes("fallback/Cantarell-Regular.ttf", FALLBACK_FONT)?
+            .first()
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("the fallback font has no fonts"))
+    }
+
+    /// Loads a font file again after it changed. The fonts it had before are replaced in
+    /// place (keeping their cache indexes), fonts it didn't have yet are added. Returns the
+    /// cache indexes of both, anything cached for them (like glyphs) is outdated.
+    ///
+    /// The file's previous data is freed, unless the cache keeps fonts it no longer has.
+    pub fn reload_font_file(&mut self, path: impl Into<PathBuf>) -> Result<SmallVec<[usize; 16]>> {
+        let path: PathBuf = path.into();
+        let raw_cache_data = Self::load_raw_data(&path, Arc::new(&self.raw_data_hashes_to_paths));
+        self.reload_raw_data(&path, raw_cache_data)
+    }
+
+    // puts the new data of the file at `path` in place of its previous data, see
+    // FontCache::reload_font_file
+    fn reload_raw_data(
+        &mut self,
+        path: &Path,
+        raw_cache_data: Result<RawCacheResult>,
+    ) -> Result<SmallVec<[usize; 16]>> {
+        let Some(previous_idxs) = self.paths_to_font_idxs.get(path).cloned() else {
+            return Ok(self.store_raw_data(raw_cache_data, &[])?.into_idxs());
         };
+        let raw_cache_data = raw_cache_data?;
+        if let RawCacheResult::AlreadyCached { .. } = raw_cache_data {
+            // unchanged (or now a copy of another file)
+            return Ok(SmallVec::new());
+        }
+        self.forget_path(path);
+        Ok(
+            match self.store_raw_data(Ok(raw_cache_data), &previous_idxs)? {
+                CacheResult::New {
+                    newly_cached,
+                    replaced,
+                    ..
+                } => newly_cached.into_iter().chain(replaced).collect(),
+                CacheResult::AlreadyCached { .. } | CacheResult::NoNewData { .. } => {
+                    SmallVec::new()
+                }
+            },
+        )
+    }
+
+    /// Unloads the fonts of a font file, e.g. one that was deleted. Their cache indexes are
+    /// left empty, the indexes of other fonts don't change. Returns the unloaded indexes,
+    /// anything cached for them (like glyphs) should be dropped.
+    pub fn unload_path(&mut self, path: impl AsRef<Path>) -> SmallVec<[usize; 16]> {
+        let path = path.as_ref();
+        let idxs = self
+            .paths_to_font_idxs
+            .get(path)
+            .cloned()
+            .unwrap_or_default();
+        for &idx in idxs.iter() {
+            self.set_font(idx, None);
+        }
+        self.forget_path(path);
+        idxs
+    }
+
+    /// Unloads every font but the ones in `keep`, e.g. the system fonts the game doesn't
+    /// use. Files without cached fonts are forgotten, loading them again parses them again.
+    /// Returns the unloaded indexes, see [`FontCache::unload_path`].
+    pub fn retain_only(&mut self, keep: &[usize]) -> SmallVec<[usize; 16]> {
+        let unloaded: SmallVec<[usize; 16]> = self
+            .loaded_font_datas()
+            .map(|(idx, _)| idx)
+            .filter(|idx| !keep.contains(idx))
+            .collect();
+        for &idx in unloaded.iter() {
+            self.set_font(idx, None);
+        }
+        for idxs in self.paths_to_font_idxs.values_mut() {
+            idxs.retain(|idx| !unloaded.contains(idx));
+        }
+        let unused_paths: Vec<PathBuf> = self
+            .paths_to_font_idxs
+            .iter()
+            .filter(|(_, idxs)| idxs.is_empty())
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in unused_paths {
+            self.forget_path(&path);
+        }
+        unloaded
+    }
+
+    /// The loaded fonts, by cache index.
+    pub fn fonts(&self) -> impl Iterator<Item = FontRef<'_>> {
+        self.loaded_font_datas()
+            .filter_map(|(idx, _)| self.get_font(idx))
+    }
+
+    /// Checks the bookkeeping of which file has which fonts: every loaded font belongs to
+    /// exactly one file, every font a file has is loaded, and the cache knows each file's
+    /// type, hash and data. Loading fonts checks this too and fails instead of caching more
+    /// fonts on top of a mistake, so an error here is a bug in the cache.
+    pub fn check_consistency(&self) -> Result<()> {
+        self.check_bookkeeping(&[])
+    }
+
+    // like check_consistency, with the fonts of a file that's being reloaded belonging to no
+    // file
+    fn check_bookkeeping(&self, reloaded: &[usize]) -> Result<()> {
+        let inconsistent = |detail: String| Err(FontError::Inconsistent(detail).into());
+        if self.font_datas.len() != self.lazy_font_datas.len() {
+            return inconsistent(format!(
+                "{} fonts but {} lazy font datas",
+                self.font_datas.len(),
+                self.lazy_font_datas.len()
+            ));
+        }
+        let paths = self.paths.len();
+        for (name, len) in [
+            ("file types", self.font_file_types.len()),
+            ("paths with fonts", self.paths_to_font_idxs.len()),
+            ("hashes", self.raw_data_hashes_to_paths.len()),
+            ("data references", self.paths_to_data_refs.len()),
+        ] {
+            if len != paths {
+                return inconsistent(format!("{} paths but {} {}", paths, len, name));
+            }
+        }
 
-        Ok(results)
+        let mut owners: HashMap<usize, &Path> = HashMap::new();
+        for path in &self.paths {
+            let Some(idxs) = self.paths_to_font_idxs.get(path) else {
+                return inconsistent(format!("{} has no fonts", path.display()));
+            };
+            for &idx in idxs {
+                if self.font_datas.get(idx).is_none_or(Option::is_none) {
+                    return inconsistent(format!(
+                        "{} has the font at cache index {}, which isn't loaded",
+                        path.display(),
+                        idx
+                    ));
+                }
+                if let Some(other) = owners.insert(idx, path) {
+                    return inconsistent(format!(
+                        "the font at cache index {} belongs to both {} and {}",
+                        idx,
+                        other.display(),
+                        path.display()
+                    ));
+                }
+            }
+        }
+        for (idx, _) in self.loaded_font_datas() {
+            if !owners.contains_key(&idx) && !reloaded.contains(&idx) {
+                return inconsistent(format!(
+                    "the font at cache index {} belongs to no file",
+                    idx
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // removes everything the cache knows about a file, except its fonts
+    fn forget_path(&mut self, path: &Path) {
+        if let Some(i) = self.paths.iter().position(|p| p == path) {
+            self.paths.remove(i);
+            self.font_file_types.remove(i);
+        }
+        self.raw_data_hashes_to_paths.retain(|_, p| p != path);
+        self.paths_to_data_refs.remove(path);
+        self.paths_to_font_idxs.remove(path);
+        self.font_assets
+            .retain(|(font_asset, _)| font_asset.path() != path);
+    }
+
+    /// Loads the font files in `directory` and watches it for changes: fonts that are added
+    /// or changed there are (re)loaded by [`FontCache::poll_changes`], see
+    /// [`FontCache::reload_font_file`]. Files that fail to load are logged and skipped.
+    /// Returns the number of files loaded.
+    pub fn watch_directory(&mut self, directory: impl AsRef<Path>) -> Result<usize> {
+        // the watcher reports absolute paths
+        let directory = &directory.as_ref().canonicalize().with_context(|| {
+            format!(
+                "unable to find font directory {}",
+                directory.as_ref().display()
+            )
+        })?;
+        let mut loaded = 0;
+        for entry in std::fs::read_dir(directory)
+            .with_context(|| format!("unable to read font directory {}", directory.display()))?
+        {
+            let path = entry?.path();
+            if FontFileType::from_path(&path).is_err() {
+                continue;
+            }
+            match self.load_font_file(&path) {
+                Ok(_) => loaded += 1,
+                Err(e) => {
+                    log::warn!(
+                        target: logging::FONT_CACHE,
+                        "unable to load font file {}: {}",
+                        path.display(),
+                        e
+                    );
+                }
+            }
+        }
+        if self.watcher.is_none() {
+            let (sender, events) = mpsc::channel();
+            self.watcher = Some(FontWatcher {
+                watcher: notify::recommended_watcher(sender)?,
+                events: Mutex::new(events),
+            });
+        }
+        if let Some(watcher) = &mut self.watcher {
+            watcher
+                .watcher
+                .watch(directory, RecursiveMode::NonRecursive)?;
+        }
+        Ok(loaded)
     }
 
-    fn raw_data(&self) -> &Mutex<Arena<&'static [u8]>> {
-        static DATA: LazyLock<Mutex<Arena<&'static [u8]>>> =
-            LazyLock::new(|| Mutex::new(Arena::new()));
-        &DATA
+    /// (Re)loads the font files that were added or changed in the watched directories since
+    /// the last call, and the fonts of the files the asset store reloaded (see
+    /// [`FontCache::load_font_asset`]). Returns the cache indexes of the fonts that changed,
+    /// see [`FontCache::reload_font_file`].
+    pub fn poll_changes(&mut self) -> SmallVec<[usize; 16]> {
+        let mut changed_paths: Vec<PathBuf> = Vec::new();
+        if let Some(watcher) = &self.watcher {
+            for event in watcher.events.lock().try_iter() {
+                match event {
+                    Ok(event)
+                        if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) =>
+                    {
+                        for path in event.paths {
+                            if !changed_paths.contains(&path)
+                                && FontFileType::from_path(&path).is_ok()
+                            {
+                                changed_paths.push(path);
+                            }
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::warn!(target: logging::FONT_CACHE, "font watcher error: {}", e),
+                }
+            }
+        }
+        let mut changed = SmallVec::new();
+        for path in changed_paths {
+            // editors may still be writing the file, it's loaded again on the next change
+            match self.reload_font_file(&path) {
+                Ok(idxs) if idxs.is_empty() => {}
+                Ok(idxs) => {
+                    log::info!(
+                        target: logging::FONT_CACHE,
+                        "reloaded font file {}",
+                        path.display()
+                    );
+                    changed.extend(idxs);
+                }
+                Err(e) => {
+                    log::warn!(
+                        target: logging::FONT_CACHE,
+                        "unable to reload font file {}: {}",
+                        path.display(),
+                        e
+                    );
+                }
+            }
+        }
+        let reloaded: Vec<Handle<FontData>> = self
+            .font_assets
+            .iter()
+            .filter(|(handle, version)| handle.version() != *version)
+            .map(|(handle, _)| handle.clone())
+            .collect();
+        for handle in reloaded {
+            match self.reload_font_asset(&handle) {
+                Ok(idxs) => changed.extend(idxs),
+                Err(e) => {
+                    log::warn!(
+                        target: logging::FONT_CACHE,
+                        "unable to reload the fonts of {}: {}",
+                        handle.path().display(),
+                        e
+                    );
+                }
+            }
+        }
+        changed
     }
 
+    /// Bytes of font file data in memory. A file's data is freed once none of its fonts are
+    /// cached anymore: when they were unloaded, or replaced by better or reloaded versions.
     pub fn raw_data_size(&self) -> usize {
-        self.raw_data().lock().iter_mut().map(|d| d.len()).sum()
+        let mut raw_datas: Vec<&Arc<[u8]>> = self
+            .loaded_font_datas()
+            .map(|(_, fd)| &fd.raw_data)
+            .collect();
+        raw_datas.sort_by_key(|raw_data| Arc::as_ptr(raw_data) as *const u8);
+        raw_datas.dedup_by(|a, b| Arc::ptr_eq(a, b));
+        raw_datas.iter().map(|raw_data| raw_data.len()).sum()
     }
 
     fn load_raw_data(
-        &self,
         path: impl AsRef<Path>,
         raw_data_hashes_to_paths: Arc<&HashMap<u64, PathBuf>>,
     ) -> Result<RawCacheResult> {
@@ -1217,28 +3559,26 @@ impl FontCache {
                 path.as_ref().display()
             )
         })?;
+        Self::parse_raw_data(path, font_file_type, raw_bytes, &raw_data_hashes_to_paths)
+    }
 
-        // Hash the raw bytes
-        let mut hasher = DefaultHasher::new();
-        raw_bytes.hash(&mut hasher);
-        let raw_data_hash = hasher.finish();
+    // the fonts of a file's data, `path` is where it came from
+    fn parse_raw_data(
+        path: impl AsRef<Path>,
+        font_file_type: FontFileType,
+        raw_data: impl Into<Arc<[u8]>>,
+        raw_data_hashes_to_paths: &HashMap<u64, PathBuf>,
+    ) -> Result<RawCacheResult> {
+        let raw_data: Arc<[u8]> = raw_data.into();
+        let raw_data_hash = hash_raw_data(&raw_data);
 
         // Check if an already parsed file contained identical data
         if let Some(p) = raw_data_hashes_to_paths.get(&raw_data_hash) {
             return Ok(RawCacheResult::AlreadyCached { path: p.clone() });
         }
 
-        let raw_data_ref: &'static [u8] = {
-            let data = self.raw_data();
-            let _lock = data.lock();
-            // SAFETY: We hold the lock, and this is the only place
-            // that modifies the static DATA
-            let raw = unsafe { &*data.data_ptr() };
-            raw.alloc(raw_bytes.leak())
-        };
-
         // Load the data with skrifa
-        let file_ref: skrifa::raw::FileRef = skrifa::raw::FileRef::new(raw_data_ref)?;
+        let file_ref: skrifa::raw::FileRef = skrifa::raw::FileRef::new(&raw_data)?;
 
         // the font_ref_idx (used by FontRef::from_index). Will be incremented at the start of the loop,
         // so init to -1
@@ -1315,48 +3655,75 @@ impl FontCache {
 
         Ok(RawCacheResult::New {
             path: path.as_ref().into(),
-            raw_data_ref,
+            raw_data,
             raw_data_hash,
             font_file_type,
             font_datas,
         })
     }
 
-    fn store_raw_data(&mut self, raw_cache_data: Result<RawCacheResult>) -> Result<CacheResult> {
+    // `reloaded` are the fonts the file had before, when it is loaded again after a change
+    // (see reload_font_file). Those are always replaced, and kept if the file no longer has
+    // them.
+    fn store_raw_data(
+        &mut self,
+        raw_cache_data: Result<RawCacheResult>,
+        reloaded: &[usize],
+    ) -> Result<CacheResult> {
         if raw_cache_data.is_err() {
             return Err(raw_cache_data.err().unwrap());
         }
-        let raw_cache_data = raw_cache_data.unwrap();
-        let (path, raw_data_ref, raw_data_hash, font_file_type, font_datas) = match raw_cache_data {
+        let raw_cache_data = match raw_cache_data.unwrap() {
+            // a file with the same data was stored since this one was read, e.g. the same
+            // path twice in load_multiple_font_files
+            RawCacheResult::New { raw_data_hash, .. }
+                if self.raw_data_hashes_to_paths.contains_key(&raw_data_hash) =>
+            {
+                RawCacheResult::AlreadyCached {
+                    path: self.raw_data_hashes_to_paths[&raw_data_hash].clone(),
+                }
+            }
+            raw_cache_data => raw_cache_data,
+        };
+        let (path, raw_data, raw_data_hash, font_file_type, font_datas) = match raw_cache_data {
             RawCacheResult::New {
                 path,
-                raw_data_ref,
+                raw_data,
                 raw_data_hash,
                 font_file_type,
                 font_datas,
-            } => (
-                path,
-                raw_data_ref,
-                raw_data_hash,
-                font_file_type,
-                font_datas,
-            ),
+            } => (path, raw_data, raw_data_hash, font_file_type, font_datas),
             RawCacheResult::AlreadyCached { path } => {
-                let idxs = self.paths_to_font_idxs.get(&path).unwrap().clone();
+                let idxs = self.paths_to_font_idxs.get(&path).cloned().ok_or_else(|| {
+                    FontError::Inconsistent(format!(
+                        "{} is hashed but has no fonts",
+                        path.display()
+                    ))
+                })?;
                 return Ok(CacheResult::AlreadyCached { path, idxs });
             }
         };
+        if self.paths_to_font_idxs.contains_key(&path) {
+            return Err(FontError::ChangedSinceLoaded(path.to_string_lossy().into_owned()).into());
+        }
+        // whatever went wrong before, nothing more is cached on top of it
+        self.check_bookkeeping(reloaded)
+            .with_context(|| path.display().to_string())?;
 
-        // new_font_datas.len() + replace_font_datas.len() + skipped_font_datas should equal the number
-        // of fonts in the file_ref
+        // new_font_datas.len() + replace_font_datas.len() + skipped_font_datas + duplicates_in_file
+        // should equal the number of fonts in the file_ref
         let font_datas_length = font_datas.len();
         let mut new_font_datas: Vec<FontCacheData> = Vec::new();
         let mut replace_font_datas: Vec<(usize, FontCacheData)> = Vec::new();
         let mut skipped_font_idxs: SmallVec<[usize; 16]> = SmallVec::new();
 
+        // a collection can have the same font twice, only the better one is cached (or the
+        // two would both be new, or both replace the same font)
+        let mut file_font_datas: Vec<FontCacheData> = Vec::with_capacity(font_datas_length);
+        let mut duplicates_in_file = 0;
         for raw_font_cache_data in font_datas {
             let fd = FontCacheData {
-                raw_data_ref,
+                raw_data: raw_data.clone(),
                 font_ref_idx: raw_font_cache_data.font_ref_idx,
                 family_name: raw_font_cache_data.family_name,
                 subfamily_name: raw_font_cache_data.subfamily_name,
@@ -1365,19 +3732,25 @@ impl FontCache {
                 named_instances: raw_font_cache_data.named_instances,
                 features: raw_font_cache_data.features,
             };
+            match file_font_datas
+                .iter_mut()
+                .find(|earlier| earlier.has_same_names(&fd))
+            {
+                Some(earlier) => {
+                    if fd.is_better_than(earlier) {
+                        *earlier = fd;
+                    }
+                    duplicates_in_file += 1;
+                }
+                None => file_font_datas.push(fd),
+            }
+        }
+
+        for fd in file_font_datas {
             // Check if an this font is the same family + subfamily, but with "better"
             // properties
             if let Ok(existing) = self.find_font(&fd.family_name, fd.subfamily_name.as_ref()) {
-                if fd.variation_axes.len() > existing.variation_axes().len()
-                    || (fd.variation_axes.len() == existing.variation_axes().len()
-                        && fd.features.len() > existing.features().len())
-                    || (fd.variation_axes.len() == existing.variation_axes().len()
-                        && fd.features.len() == existing.features().len()
-                        && fd.named_instances.len() > existing.named_instances().len())
-                    || (fd.variation_axes.len() == existing.variation_axes().len()
-                        && fd.features.len() == existing.features().len()
-                        && fd.named_instances.len() == existing.named_instances().len()
-                        && fd.revision > *existing.revision())
+                if reloaded.contains(&existing.cache_index) || fd.is_better_than(existing.font_data)
                 {
                     // if a duplicate font exists and the new one is better, replace the font
                     // at the existing index
@@ -1395,12 +3768,17 @@ impl FontCache {
         }
 
         // all fonts in the file should be processed
-        debug_assert_eq!(
-            new_font_datas.len() + replace_font_datas.len() + skipped_font_idxs.len(),
-            // lengths start at 1, font_ref_index starts at 0
-            font_datas_length,
-            "{}",
-            path.to_string_lossy()
+        anyhow::ensure!(
+            new_font_datas.len()
+                + replace_font_datas.len()
+                + skipped_font_idxs.len()
+                + duplicates_in_file
+                == font_datas_length,
+            FontError::Inconsistent(format!(
+                "{}: not all of its {} fonts were added, replaced or skipped",
+                path.display(),
+                font_datas_length
+            ))
         );
 
         // if all fonts were skipped, only save the path and hashed data values
@@ -1413,8 +3791,9 @@ impl FontCache {
             self.font_file_types.push(font_file_type);
 
             self.paths_to_font_idxs
-                .insert(path.clone(), SmallVec::default());
-            self.paths_to_data_refs.insert(path.clone(), raw_data_ref);
+                .insert(path.clone(), reloaded.iter().copied().collect());
+            self.paths_to_data_refs
+                .insert(path.clone(), Arc::downgrade(&raw_data));
 
             return Ok(CacheResult::NoNewData {
                 path,
@@ -1431,48 +3810,29 @@ impl FontCache {
         let new_font_idxs: SmallVec<[usize; 16]> =
             (new_fonts_start_index..new_fonts_end_index).collect();
 
-        let font_datas_extended_len = self.font_datas.len() + new_font_datas.len();
-
         let replaced_font_idxs: SmallVec<[usize; 16]> =
             replace_font_datas.iter().map(|r| r.0).collect();
 
-        // sanity checks *before* modifications
-
-        // failing these three means something went wrong on a previous
-        // load, but there is no more sensible check
-        // luckily the operations involved with these vecs are pretty much infallible
-        debug_assert_eq!(
-            self.paths.len(),
-            self.font_file_types.len(),
-            "{}",
-            path.to_string_lossy()
-        );
-        debug_assert_eq!(
-            self.paths.len(),
-            self.paths_to_font_idxs.len(),
-            "{}",
-            path.to_string_lossy()
-        );
-        debug_assert_eq!(
-            self.paths.len(),
-            self.raw_data_hashes_to_paths.len(),
-            "{}",
-            path.to_string_lossy()
-        );
-
-        // all fonts should be referred to by only a single path, therefore the
-        // length of all the indexes mapped to by all the paths + the new indexes
-        // should equal all the font datas + the new datas
-        debug_assert_eq!(
-            font_datas_extended_len,
-            self.paths_to_font_idxs
-                .values()
-                .map(SmallVec::len)
-                .sum::<usize>()
-                + new_font_idxs.len(),
-            "{}",
-            path.to_string_lossy()
-        );
+        // the path each replaced font belonged to, found before anything changes (a reloaded
+        // file's fonts belong to no path right now, see reload_font_file)
+        let mut old_paths: Vec<(usize, PathBuf)> = Vec::new();
+        for &(idx, _) in &replace_font_datas {
+            if reloaded.contains(&idx) {
+                continue;
+            }
+            let old_path = self
+                .paths_to_font_idxs
+                .iter()
+                .find_map(|(p, idxs)| idxs.contains(&idx).then(|| p.clone()))
+                .ok_or_else(|| {
+                    FontError::Inconsistent(format!(
+                        "{}: font at cache index {} has no file path",
+                        path.display(),
+                        idx
+                    ))
+                })?;
+            old_paths.push((idx, old_path));
+        }
 
         // add path and hash related stuff
         // need to do this now because we need this data to properly process
@@ -1484,74 +3844,41 @@ impl FontCache {
 
         let mut path_to_font_idxs = new_font_idxs.clone();
         path_to_font_idxs.extend(replace_font_datas.iter().map(|fd| fd.0));
+        path_to_font_idxs.extend(
+            reloaded
+                .iter()
+                .filter(|idx| !replaced_font_idxs.contains(idx))
+                .copied(),
+        );
 
         self.paths_to_font_idxs
             .insert(path.clone(), path_to_font_idxs.clone());
 
-        self.paths_to_data_refs.insert(path.clone(), raw_data_ref);
+        self.paths_to_data_refs
+            .insert(path.clone(), Arc::downgrade(&raw_data));
 
         for font_data in replace_font_datas {
-            let old_paths: Vec<&PathBuf> = self
-                .paths_to_font_idxs
+            if reloaded.contains(&font_data.0) {
+                // no other path refers to the fonts of a reloaded file
+                self.set_font(font_data.0, Some(font_data.1));
+                continue;
+            }
+            let old_path = old_paths
                 .iter()
-                // (we already added the new path to indexes, so ignore the current path in our search)
-                .filter_map(|(p, is)| (*p != path && is.contains(&font_data.0)).then_some(p))
-                .collect();
-
-            // sanity check: ignoring the new path, the cache index should have beeen referenced by exactly
-            // one other path
-            debug_assert_eq!(
-                1,
-                old_paths.len(),
-                "font at cache index {} is linked to multiple (or no) file paths: [{}]",
-                &font_data.0,
-                old_paths
-                    .iter()
-                    .map(|p| p.to_string_lossy())
-                    .reduce(|acc, el| format!("{}; \"{}\"", acc, el).into())
-                    .unwrap_or("NO PATHS".into())
-            );
-
-            let old_path = old_paths[0].clone();
-
-            let old_path_idxs = self.paths_to_font_idxs.get_mut(&old_path).unwrap();
-
+                .find_map(|(idx, p)| (*idx == font_data.0).then_some(p))
+                .unwrap();
             // remove cache index from old path
-            old_path_idxs.remove(
-                old_path_idxs
-                    .iter()
-                    .position(|i| i == &font_data.0)
-                    .unwrap(),
-            );
+            if let Some(old_path_idxs) = self.paths_to_font_idxs.get_mut(old_path) {
+                old_path_idxs.retain(|idx| *idx != font_data.0);
+            }
 
-            self.font_datas[font_data.0] = font_data.1;
-            self.lazy_font_datas[font_data.0] = LazyFontCacheData::new();
+            self.set_font(font_data.0, Some(font_data.1));
         }
 
-        // double check that the sum of all cache indexes referenced by paths is exactly equal
-        // to all the fonts (to be) in the cache
-        debug_assert_eq!(
-            font_datas_extended_len,
-            self.paths_to_font_idxs
-                .values()
-                .map(SmallVec::len)
-                .sum::<usize>(),
-            "{}",
-            path.to_string_lossy()
-        );
-
         self.lazy_font_datas.extend(Vec::from_iter(
             std::iter::repeat_with(|| LazyFontCacheData::new()).take(new_font_datas.len()),
         ));
-        self.font_datas.extend(new_font_datas);
-
-        // font_datas and lazy_font_datas must be equal in length
-        debug_assert_eq!(
-            self.font_datas.len(),
-            self.lazy_font_datas.len(),
-            "{}",
-            path.to_string_lossy()
-        );
+        self.font_datas.extend(new_font_datas.into_iter().map(Some));
 
         Ok(CacheResult::New {
             path,
@@ -1563,7 +3890,6 @@ impl FontCache {
 }
 
 #[derive(Debug, Clone, PartialEq)]
-#[allow(dead_code)]
 enum ShaperInstanceSettings {
     Variations(Vec<Variation>),
     NamedInstance(NamedInstanceInfo),
@@ -1586,7 +3912,15 @@ impl std::fmt::Display for ShaperInstanceSettings {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// How text is shaped: the instance of a variable font, and the OpenType features that are
+/// turned on or off.
+///
+/// Features only change which glyphs the shaper picks (e.g. the glyph of a ligature instead
+/// of its letters, or a tabular digit), not how a glyph is drawn. The glyph cache is keyed on
+/// the glyph id, so text with different features shares the glyphs it has in common. The
+/// instance does change the outlines, its coordinates are part of the key (see
+/// [`ShaperSettings::coords`]).
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct ShaperSettings {
     instance_settings: Option<ShaperInstanceSettings>,
     shape_features: Option<Vec<Feature>>,
@@ -1594,33 +3928,40 @@ pub struct ShaperSettings {
 
 impl ShaperSettings {
     pub fn new() -> Self {
-        Self {
-            instance_settings: None,
-            shape_features: None,
-        }
+        Self::default()
     }
 
-    pub fn _with_variations(
-        mut self,
-        variations: impl IntoIterator<Item: Into<Variation>>,
-    ) -> Self {
+    pub fn with_variations(mut self, variations: impl IntoIterator<Item: Into<Variation>>) -> Self {
         self.instance_settings = Some(ShaperInstanceSettings::Variations(
             variations.into_iter().map(|v| v.into()).collect(),
         ));
         self
     }
 
-    pub fn _with_named_instance(mut self, named_instance: NamedInstanceInfo) -> Self {
+    pub fn with_named_instance(mut self, named_instance: NamedInstanceInfo) -> Self {
         self.instance_settings = Some(ShaperInstanceSettings::NamedInstance(named_instance));
         self
     }
 
-    pub fn _with_features(mut self, features: impl IntoIterator<Item: Into<Feature>>) -> Self {
+    pub fn with_features(mut self, features: impl IntoIterator<Item: Into<Feature>>) -> Self {
         self.shape_features = Some(features.into_iter().map(|f| f.into()).collect());
         self
     }
 
-    pub fn _coords<'a>(&self, font: &'a FontRef<'a>) -> skrifa::instance::Location {
+    /// Turns the feature `tag` on or off for the whole text, replacing what was set for it
+    /// before. Features that aren't set are up to the shaper: the ones every text gets (like
+    /// `liga` and `calt`) are on, optional ones (like `tnum` and `ss01`) are off. Fonts
+    /// without the feature are shaped as if it wasn't set.
+    pub fn with_feature(mut self, tag: Tag, enabled: bool) -> Self {
+        let features = self.shape_features.get_or_insert_with(Vec::new);
+        features.retain(|feature| feature.tag != tag);
+        features.push(Feature::new(tag, enabled as u32, ..));
+        self
+    }
+
+    /// Where in the font's design space glyphs are drawn with these settings. The default
+    /// location without variations or a named instance.
+    pub fn coords(&self, font: &FontRef<'_>) -> skrifa::instance::Location {
         match &self.instance_settings {
             Some(si) => match si {
                 ShaperInstanceSettings::Variations(variations) => {
@@ -1777,19 +4118,28 @@ impl<'a> FontShaper<'a> {
 
 pub struct Rasterizer {
     path: Vec<zeno::Command>,
-    draw_buffer: Vec<u8>,
     scratch: zeno::Scratch,
+    /// For [`TextAntialiasing::Lcd`]
+    pub lcd_filter: LcdFilter,
+}
+
+impl Default for Rasterizer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Rasterizer {
     pub fn new() -> Self {
         Self {
             path: Vec::new(),
-            draw_buffer: Vec::new(),
             scratch: zeno::Scratch::new(),
+            lcd_filter: LcdFilter::DEFAULT,
         }
     }
 
+    /// Rasterizes a glyph into `buffer` from `start`, as rows of `width * 4` bytes of
+    /// subpixel coverage (the same in every channel if it's not [`TextAntialiasing::Lcd`]).
     pub fn render_mask(
         &mut self,
         font: &FontRef<'_>,
@@ -1798,39 +4148,109 @@ impl Rasterizer {
         coords: &skrifa::instance::Location,
         buffer: &mut [u8],
         start: usize,
-        _row_size: usize,
-    ) -> zeno::Placement {
+        antialiasing: TextAntialiasing,
+    ) -> Result<zeno::Placement, GameError> {
+        let (mask, placement) =
+            self.render_owned_mask(font, glyph_id, size, coords, antialiasing)?;
+        buffer[start..start + mask.len()].copy_from_slice(&mask);
+        Ok(placement)
+    }
+
+    /// Like [`Rasterizer::render_mask`], into a buffer of its own, e.g. to rasterize on
+    /// another thread than the one that owns the atlas.
+    pub fn render_owned_mask(
+        &mut self,
+        font: &FontRef<'_>,
+        glyph_id: GlyphId,
+        size: skrifa::instance::Size,
+        coords: &skrifa::instance::Location,
+        antialiasing: TextAntialiasing,
+    ) -> Result<(Vec<u8>, zeno::Placement), GameError> {
+        self.draw_outline(font, glyph_id, size, coords, antialiasing)?;
+        let format = match antialiasing {
+            TextAntialiasing::Lcd if self.lcd_filter.bgr => zeno::Format::subpixel_bgra(),
+            TextAntialiasing::Lcd => zeno::Format::Subpixel,
+            TextAntialiasing::Grayscale | TextAntialiasing::None => zeno::Format::Alpha,
+        };
+        let (mut mask, placement) = zeno::Mask::with_scratch(&self.path, &mut self.scratch)
+            .origin(zeno::Origin::BottomLeft)
+            .format(format)
+            .render();
+        match antialiasing {
+            TextAntialiasing::Lcd => self.lcd_filter.apply(&mut mask, placement.width as usize),
+            // the atlas is RGBA, so the one coverage value goes into every channel
+            TextAntialiasing::Grayscale => {
+                mask = mask.iter().flat_map(|coverage| [*coverage; 4]).collect()
+            }
+            TextAntialiasing::None => {
+                mask = mask
+                    .iter()
+                    .flat_map(|coverage| [if *coverage >= 128 { 255 } else { 0 }; 4])
+                    .collect()
+            }
+        }
+        Ok((mask, placement))
+    }
+
+    // the hinted outline of the glyph into self.path, fails for glyphs without an outline
+    // (e.g. bitmap-only color glyphs) and fonts whose hinting program errors out
+    fn draw_outline(
+        &mut self,
+        font: &FontRef<'_>,
+        glyph_id: GlyphId,
+        size: skrifa::instance::Size,
+        coords: &skrifa::instance::Location,
+        antialiasing: TextAntialiasing,
+    ) -> Result<(), GameError> {
         self.path.clear();
-        self.draw_buffer.clear();
 
+        let smooth = |mode| skrifa::outline::Target::Smooth {
+            mode,
+            symmetric_rendering: false,
+            preserve_linear_metrics: true,
+        };
         let hinting_instance = skrifa::outline::HintingInstance::new(
             font.outline_glyph_collection(),
             size,
             coords,
             skrifa::outline::HintingOptions {
                 engine: skrifa::outline::Engine::AutoFallback,
-                target: skrifa::outline::Target::Smooth {
-                    mode: skrifa::outline::SmoothMode::Lcd,
-                    symmetric_rendering: false,
-                    preserve_linear_metrics: true,
+                target: match antialiasing {
+                    TextAntialiasing::Lcd => smooth(skrifa::outline::SmoothMode::Lcd),
+                    TextAntialiasing::Grayscale => smooth(skrifa::outline::SmoothMode::Normal),
+                    TextAntialiasing::None => skrifa::outline::Target::Mono,
                 },
             },
         )
-        .expect("Could not create HintingInstance");
+        .map_err(|e| {
+            GameError::FontError(format!(
+                "unable to hint {} at {:?}: {}",
+                font.family_name(),
+                size,
+                e
+            ))
+        })?;
         let draw_settings = skrifa::outline::DrawSettings::hinted(&hinting_instance, true);
 
-        let glyph_outline = font.outline_glyph_collection().get(glyph_id).unwrap();
-        glyph_outline.draw(draw_settings, self).unwrap();
-
-        let placement = zeno::Mask::with_scratch(&self.path, &mut self.scratch)
-            .origin(zeno::Origin::BottomLeft)
-            .format(zeno::Format::Subpixel)
-            .inspect(|format, width, height| {
-                self.draw_buffer
-                    .resize(format.buffer_size(width, height), 0);
-            })
-            .render_into(&mut buffer[start..], None);
-        placement
+        let glyph_outline = font
+            .outline_glyph_collection()
+            .get(glyph_id)
+            .ok_or_else(|| {
+                GameError::FontError(format!(
+                    "{} has no outline for {:?}",
+                    font.family_name(),
+                    glyph_id
+                ))
+            })?;
+        glyph_outline.draw(draw_settings, self).map_err(|e| {
+            GameError::FontError(format!(
+                "unable to draw {:?} of {}: {}",
+                glyph_id,
+                font.family_name(),
+                e
+            ))
+        })?;
+        Ok(())
     }
 }
 
@@ -1856,99 +4276,345 @@ impl skrifa::outline::OutlinePen for Rasterizer {
     }
 }
 
-#[derive(Eq, Hash, PartialEq)]
+// in texels, see GlyphCache::solid_uv_bounds
+const SOLID_BLOCK_SIZE: u32 = 4;
+// separate atlas uploads per frame, more are merged into one
+const MAX_DIRTY_RECTS: usize = 32;
+
+// see TextRenderer::set_atlas_overlay, sizes in logical pixels
+const ATLAS_OVERLAY_MARGIN: f32 = 16.0;
+const ATLAS_OVERLAY_TEXT_SIZE: f32 = 14.0;
+const ATLAS_OVERLAY_BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.85];
+const ATLAS_OVERLAY_ALLOCATION_COLOR: [f32; 4] = [0.2, 1.0, 0.4, 0.6];
+
+/// How full the glyph atlas is, see [`GlyphCache::stats`]. The atlas is a single texture,
+/// one page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasStats {
+    /// Width and height of the atlas texture in pixels
+    pub size: [u32; 2],
+    /// Glyphs cached in the atlas
+    pub glyphs: usize,
+    /// Glyphs that aren't in it: empty ones, and ones that failed to rasterize or to fit
+    pub undrawable_glyphs: usize,
+    /// The cached glyphs plus the block for solid rectangles (see
+    /// [`GlyphCache::solid_uv_bounds`])
+    pub allocations: usize,
+    /// The share of the atlas that's allocated, from 0 to 1
+    pub occupancy: f32,
+}
+
+/// The printable ASCII characters, e.g. for [`GlyphCache::prewarm`].
+pub const PRINTABLE_ASCII: std::ops::RangeInclusive<char> = ' '..='~';
+
+/// A glyph for [`GlyphCache::rasterize_glyphs`], as it's drawn.
+pub struct GlyphRequest<'a> {
+    pub font: &'a FontRef<'a>,
+    pub glyph_id: GlyphId,
+    pub size: skrifa::instance::Size,
+    pub coords: skrifa::instance::Location,
+    pub antialiasing: TextAntialiasing,
+}
+
+#[derive(Clone, Eq, Hash, PartialEq)]
 struct GlyphCacheKey {
     font_cache_index: usize,
     glyph_id: GlyphId,
+    // the bits of the ppem, glyphs are rasterized at fractional sizes too
     ppem: u32,
     coords: skrifa::instance::Location,
+    antialiasing: TextAntialiasing,
+}
+
+impl GlyphCacheKey {
+    fn new(
+        font: &FontRef<'_>,
+        glyph_id: GlyphId,
+        size: skrifa::instance::Size,
+        coords: skrifa::instance::Location,
+        antialiasing: TextAntialiasing,
+    ) -> Self {
+        Self {
+            font_cache_index: font.cache_index,
+            glyph_id,
+            // unscaled outlines are keyed as 0 ppem
+            ppem: size.ppem().unwrap_or_default().to_bits(),
+            // the default instance, however it's spelled, so it's cached once
+            coords: if skrifa::instance::LocationRef::from(&coords).is_default() {
+                skrifa::instance::Location::default()
+            } else {
+                coords
+            },
+            antialiasing,
+        }
+    }
 }
 
+/// A cached glyph's placement relative to the caret, and where it is in the atlas
+pub type GlyphBounds = (
+    zeno::Placement,
+    etagere::euclid::Box2D<u32, etagere::euclid::UnknownUnit>,
+);
+
 pub struct GlyphCache {
     texture_row_size: usize,
-    _texture_rows: usize,
+    texture_rows: usize,
     atlas: etagere::AtlasAllocator,
     draw_texture: Vec<u8>,
     pub texture: Vec<u8>,
-    texture_data_dirty: bool,
+    // the parts of `texture` changed since it was last uploaded, in bytes across and rows
+    // (see TextRenderer::queue_write_texture_if_changed)
+    dirty_rects: Vec<etagere::euclid::Box2D<usize, etagere::euclid::UnknownUnit>>,
     rasterizer: Rasterizer,
     glyph_map: HashMap<GlyphCacheKey, (etagere::AllocId, zeno::Placement)>,
+    // glyphs with nothing to draw: empty ones, and the ones that failed to rasterize or to
+    // fit in the atlas (reported the first time), so they aren't tried again every frame
+    undrawable_glyphs: HashSet<GlyphCacheKey>,
+    // see solid_uv_bounds
+    solid: Option<etagere::euclid::Box2D<u32, etagere::euclid::UnknownUnit>>,
 }
 
 impl GlyphCache {
     pub fn new(texture_row_size: usize, texture_rows: usize) -> Self {
         Self {
             texture_row_size,
-            _texture_rows: texture_rows,
+            texture_rows,
             atlas: etagere::AtlasAllocator::new(etagere::size2(
                 texture_row_size as i32,
                 texture_rows as i32,
             )),
             draw_texture: vec![0u8; texture_row_size * texture_rows],
             texture: vec![0u8; texture_row_size * texture_rows],
-            texture_data_dirty: false,
+            dirty_rects: Vec::new(),
             rasterizer: Rasterizer::new(),
             glyph_map: HashMap::new(),
+            undrawable_glyphs: HashSet::new(),
+            solid: None,
         }
     }
 
+    /// Where the glyph is in the atlas, rasterizing and adding it first if it isn't yet. None
+    /// if there's nothing to draw: the glyph is empty, or it failed before. A glyph that
+    /// can't be rasterized or doesn't fit in the atlas fails with a [`GameError::FontError`]
+    /// or a [`GameError::AtlasError`] the first time, after that it's None.
     pub fn get_glyph_texture_bounds(
         &mut self,
         font: &FontRef<'_>,
         glyph_id: GlyphId,
         size: skrifa::instance::Size,
         coords: skrifa::instance::Location,
-    ) -> (
-        zeno::Placement,
-        etagere::euclid::Box2D<u32, etagere::euclid::UnknownUnit>,
-    ) {
-        fn result_uv_bounds(
-            alloc_box: etagere::euclid::Box2D<i32, etagere::euclid::UnknownUnit>,
-            raster_placement: &zeno::Placement,
-        ) -> etagere::euclid::Box2D<u32, etagere::euclid::UnknownUnit> {
-            etagere::euclid::Box2D::from_origin_and_size(
-                alloc_box.to_f32().scale(0.25, 1.0).round().to_u32().min,
-                etagere::euclid::Size2D::new(raster_placement.width, raster_placement.height),
-            )
-        }
-
-        let rounded_size = size.ppem().unwrap().floor() as u32;
-
-        let key = GlyphCacheKey {
-            font_cache_index: font.cache_index,
-            glyph_id,
-            ppem: rounded_size,
-            coords: coords.clone(),
-        };
+        antialiasing: TextAntialiasing,
+    ) -> Result<Option<GlyphBounds>, GameError> {
+        let key = GlyphCacheKey::new(font, glyph_id, size, coords, antialiasing);
 
         if let Some((alloc_id, placement)) = self.glyph_map.get(&key) {
-            return (
+            return Ok(Some((
                 *placement,
-                result_uv_bounds(self.atlas.get(*alloc_id), placement),
-            );
+                Self::uv_bounds(self.atlas.get(*alloc_id), placement),
+            )));
+        }
+        if self.undrawable_glyphs.contains(&key) {
+            return Ok(None);
         }
 
         for v in &mut self.draw_texture {
             *v = 0
         }
 
-        let placement = self.rasterizer.render_mask(
+        let placement = match self.rasterizer.render_mask(
             font,
             glyph_id,
             size,
             &key.coords,
             &mut self.draw_texture,
             0,
-            self.texture_row_size,
-        );
+            antialiasing,
+        ) {
+            Ok(placement) => placement,
+            Err(e) => {
+                self.undrawable_glyphs.insert(key);
+                return Err(e);
+            }
+        };
+        if placement.width == 0 || placement.height == 0 {
+            self.undrawable_glyphs.insert(key);
+            return Ok(None);
+        }
 
-        let allocation = self
-            .atlas
-            .allocate(etagere::size2(
-                (placement.width * 4) as i32,
-                placement.height as i32,
-            ))
-            .unwrap();
+        // taken out, store_glyph borrows the cache mutably
+        let draw_texture = std::mem::take(&mut self.draw_texture);
+        let uv_bounds = self.store_glyph(key, &draw_texture, placement);
+        self.draw_texture = draw_texture;
+        Ok(Some((placement, uv_bounds?)))
+    }
+
+    /// Rasterizes the glyphs of `characters` in `font` at each of `sizes` ahead of time, in
+    /// parallel, so the first frame that shows them doesn't stall rasterizing them one after
+    /// the other. Glyphs that are cached already are skipped, and so is whitespace. Glyphs that
+    /// fail are logged and skipped, and it stops when the atlas is full. Returns how many
+    /// glyphs were added.
+    pub fn prewarm(
+        &mut self,
+        font: &FontRef<'_>,
+        sizes: &[skrifa::instance::Size],
+        characters: impl IntoIterator<Item = char>,
+        antialiasing: TextAntialiasing,
+    ) -> usize {
+        let charmap = font.ext_font_ref().charmap();
+        let mut glyph_ids: Vec<GlyphId> = characters
+            .into_iter()
+            .filter(|character| !character.is_whitespace())
+            .filter_map(|character| charmap.map(character))
+            .collect();
+        glyph_ids.sort();
+        glyph_ids.dedup();
+        let coords = skrifa::instance::Location::default();
+        let glyphs = sizes.iter().flat_map(|size| {
+            glyph_ids.iter().map(|glyph_id| GlyphRequest {
+                font,
+                glyph_id: *glyph_id,
+                size: *size,
+                coords: coords.clone(),
+                antialiasing,
+            })
+        });
+        let mut count = 0;
+        for stored in self.rasterize_missing(glyphs) {
+            match stored {
+                Ok(true) => count += 1,
+                Ok(false) => {}
+                Err(e @ GameError::AtlasError(_)) => {
+                    log::warn!(target: logging::GFX, "stopped prewarming glyphs: {}", e);
+                }
+                Err(e) => {
+                    log::warn!(target: logging::GFX, "skipped prewarming a glyph: {}", e);
+                }
+            }
+        }
+        count
+    }
+
+    /// Rasterizes the glyphs that aren't in the atlas yet in parallel, each thread with its
+    /// own rasterizer, then adds them to the atlas. Returns how many were added. Like
+    /// [`GlyphCache::get_glyph_texture_bounds`] a glyph that fails is undrawable from then
+    /// on, the first failure is returned and the glyphs after a full atlas are left out.
+    pub fn rasterize_glyphs<'a>(
+        &mut self,
+        glyphs: impl IntoIterator<Item = GlyphRequest<'a>>,
+    ) -> Result<usize, GameError> {
+        let mut count = 0;
+        let mut result = Ok(());
+        for stored in self.rasterize_missing(glyphs) {
+            match stored {
+                Ok(true) => count += 1,
+                Ok(false) => {}
+                Err(e) => result = result.and(Err(e)),
+            }
+        }
+        result.map(|_| count)
+    }
+
+    // rasterizes the glyphs that aren't cached or undrawable, each once. Ok(true) for every
+    // glyph stored, Ok(false) for the empty ones, and it stops at the first that doesn't fit
+    fn rasterize_missing<'a>(
+        &mut self,
+        glyphs: impl IntoIterator<Item = GlyphRequest<'a>>,
+    ) -> Vec<Result<bool, GameError>> {
+        let mut keys = HashSet::new();
+        let missing: Vec<(GlyphCacheKey, GlyphRequest<'a>)> = glyphs
+            .into_iter()
+            .filter_map(|glyph| {
+                let key = GlyphCacheKey::new(
+                    glyph.font,
+                    glyph.glyph_id,
+                    glyph.size,
+                    glyph.coords.clone(),
+                    glyph.antialiasing,
+                );
+                (!self.glyph_map.contains_key(&key)
+                    && !self.undrawable_glyphs.contains(&key)
+                    && keys.insert(key.clone()))
+                .then_some((key, glyph))
+            })
+            .collect();
+        if missing.is_empty() {
+            return Vec::new();
+        }
+
+        let lcd_filter = self.rasterizer.lcd_filter;
+        let masks: Vec<Result<(Vec<u8>, zeno::Placement), GameError>> = missing
+            .par_iter()
+            .map_init(
+                || Rasterizer {
+                    lcd_filter,
+                    ..Rasterizer::new()
+                },
+                |rasterizer, (key, glyph)| {
+                    rasterizer.render_owned_mask(
+                        glyph.font,
+                        key.glyph_id,
+                        glyph.size,
+                        &key.coords,
+                        key.antialiasing,
+                    )
+                },
+            )
+            .collect();
+        // the atlas is filled in on this thread, in the same order every time
+        let mut stored = Vec::with_capacity(missing.len());
+        for ((key, _), mask) in missing.into_iter().zip(masks) {
+            match mask {
+                Ok((mask, placement)) if placement.width > 0 && placement.height > 0 => {
+                    if let Err(e) = self.store_glyph(key, &mask, placement) {
+                        stored.push(Err(e));
+                        break;
+                    }
+                    stored.push(Ok(true));
+                }
+                Ok(_) => {
+                    self.undrawable_glyphs.insert(key);
+                    stored.push(Ok(false));
+                }
+                Err(e) => {
+                    self.undrawable_glyphs.insert(key);
+                    stored.push(Err(e));
+                }
+            }
+        }
+        stored
+    }
+
+    // the texture coordinates of a glyph in the atlas, from its allocation
+    fn uv_bounds(
+        alloc_box: etagere::euclid::Box2D<i32, etagere::euclid::UnknownUnit>,
+        raster_placement: &zeno::Placement,
+    ) -> etagere::euclid::Box2D<u32, etagere::euclid::UnknownUnit> {
+        etagere::euclid::Box2D::from_origin_and_size(
+            alloc_box.to_f32().scale(0.25, 1.0).round().to_u32().min,
+            etagere::euclid::Size2D::new(raster_placement.width, raster_placement.height),
+        )
+    }
+
+    // copies a rasterized glyph (rows of `placement.width * 4` bytes) into the atlas, fails
+    // (and marks the glyph undrawable) when there's no room left for it
+    fn store_glyph(
+        &mut self,
+        key: GlyphCacheKey,
+        mask: &[u8],
+        placement: zeno::Placement,
+    ) -> Result<etagere::euclid::Box2D<u32, etagere::euclid::UnknownUnit>, GameError> {
+        let Some(allocation) = self.atlas.allocate(etagere::size2(
+            (placement.width * 4) as i32,
+            placement.height as i32,
+        )) else {
+            self.undrawable_glyphs.insert(key);
+            return Err(GameError::AtlasError(format!(
+                "no room for a {}x{} px glyph ({} glyphs cached)",
+                placement.width,
+                placement.height,
+                self.glyph_map.len()
+            )));
+        };
 
         let start = (allocation.rectangle.min.y as usize) * self.texture_row_size
             + (allocation.rectangle.min.x) as usize;
@@ -1958,9 +4624,9 @@ impl GlyphCache {
 
         for row in 0..height {
             for value in 0..width {
-                let r = self.draw_texture[(row * width * 4) + value * 4];
-                let g = self.draw_texture[(row * width * 4) + value * 4 + 1];
-                let b = self.draw_texture[(row * width * 4) + value * 4 + 2];
+                let r = mask[(row * width * 4) + value * 4];
+                let g = mask[(row * width * 4) + value * 4 + 1];
+                let b = mask[(row * width * 4) + value * 4 + 2];
                 let alpha = r.saturating_add(g).saturating_add(b);
                 self.texture[start + (row * self.texture_row_size) + value * 4] = r;
                 self.texture[start + (row * self.texture_row_size) + value * 4 + 1] = g;
@@ -1969,7 +4635,7 @@ impl GlyphCache {
             }
         }
 
-        let uv_bounds = result_uv_bounds(allocation.rectangle, &placement);
+        let uv_bounds = Self::uv_bounds(allocation.rectangle, &placement);
 
         // debug draw border
         /*for value in uv_bounds.min.x as usize * 4..=uv_bounds.max.x as usize * 4 {
@@ -2000,21 +4666,168 @@ impl GlyphCache {
 
         self.glyph_map.insert(key, (allocation.id, placement));
 
-        self.texture_data_dirty = true;
+        self.mark_dirty(allocation.rectangle);
+
+        Ok(uv_bounds)
+    }
+
+    // queues an allocation's rectangle for the next upload. Rows of texture copies are padded
+    // to COPY_BYTES_PER_ROW_ALIGNMENT anyway, so the rectangle is widened to it: that costs
+    // nothing and lets the glyphs next to each other on a shelf merge into one copy.
+    fn mark_dirty(&mut self, rect: etagere::euclid::Box2D<i32, etagere::euclid::UnknownUnit>) {
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize;
+        let mut rect = rect.to_usize();
+        rect.min.x = rect.min.x / alignment * alignment;
+        rect.max.x = (rect.max.x.div_ceil(alignment) * alignment).min(self.texture_row_size);
+        rect.max.y = rect.max.y.min(self.texture_rows);
+        // touching or overlapping, e.g. the next glyph on the same shelf
+        if let Some(dirty) = self.dirty_rects.iter_mut().find(|dirty| {
+            dirty.min.x <= rect.max.x
+                && rect.min.x <= dirty.max.x
+                && dirty.min.y <= rect.max.y
+                && rect.min.y <= dirty.max.y
+        }) {
+            *dirty = dirty.union(&rect);
+        } else if self.dirty_rects.len() < MAX_DIRTY_RECTS {
+            self.dirty_rects.push(rect);
+        } else {
+            // too many copies, one bigger one is cheaper
+            let union = self
+                .dirty_rects
+                .iter()
+                .fold(rect, |union, dirty| union.union(dirty));
+            self.dirty_rects = vec![union];
+        }
+    }
+
+    // the whole texture has to be uploaded, e.g. to a new device
+    fn mark_all_dirty(&mut self) {
+        self.dirty_rects = vec![etagere::euclid::Box2D::new(
+            etagere::euclid::point2(0, 0),
+            etagere::euclid::point2(self.texture_row_size, self.texture_rows),
+        )];
+    }
+
+    /// Drops every cached glyph, e.g. after the scale factor changed and all the text is
+    /// rasterized at other sizes.
+    pub fn clear(&mut self) {
+        self.glyph_map.clear();
+        self.undrawable_glyphs.clear();
+        self.atlas.clear();
+        self.solid = None;
+    }
+
+    /// Where a block of full coverage is in the atlas, for drawing solid rectangles (e.g. text
+    /// backgrounds) with the glyphs. The bounds are inside the block, so it can be stretched
+    /// over any size without sampling what's around it.
+    pub fn solid_uv_bounds(
+        &mut self,
+    ) -> Result<etagere::euclid::Box2D<u32, etagere::euclid::UnknownUnit>, GameError> {
+        if let Some(uv_bounds) = self.solid {
+            return Ok(uv_bounds);
+        }
+        let Some(allocation) = self.atlas.allocate(etagere::size2(
+            (SOLID_BLOCK_SIZE * 4) as i32,
+            SOLID_BLOCK_SIZE as i32,
+        )) else {
+            return Err(GameError::AtlasError(
+                "no room for the block that text backgrounds are drawn with".to_string(),
+            ));
+        };
+        let start = (allocation.rectangle.min.y as usize) * self.texture_row_size
+            + (allocation.rectangle.min.x) as usize;
+        for row in 0..SOLID_BLOCK_SIZE as usize {
+            let row_start = start + row * self.texture_row_size;
+            self.texture[row_start..row_start + SOLID_BLOCK_SIZE as usize * 4].fill(255);
+        }
+        self.mark_dirty(allocation.rectangle);
+        let block = Self::uv_bounds(
+            allocation.rectangle,
+            &zeno::Placement {
+                left: 0,
+                top: 0,
+                width: SOLID_BLOCK_SIZE,
+                height: SOLID_BLOCK_SIZE,
+            },
+        );
+        let uv_bounds = etagere::euclid::Box2D::new(
+            block.min + etagere::euclid::vec2(1, 1),
+            block.max - etagere::euclid::vec2(1, 1),
+        );
+        self.solid = Some(uv_bounds);
+        Ok(uv_bounds)
+    }
+
+    /// How full the atlas is.
+    pub fn stats(&self) -> AtlasStats {
+        let area = (self.texture_row_size * self.texture_rows) as f32;
+        AtlasStats {
+            size: [(self.texture_row_size / 4) as u32, self.texture_rows as u32],
+            glyphs: self.glyph_map.len(),
+            undrawable_glyphs: self.undrawable_glyphs.len(),
+            allocations: self.atlas.iter().count(),
+            occupancy: self.atlas.allocated_space() as f32 / area,
+        }
+    }
+
+    /// The left, top, right and bottom edges of everything allocated in the atlas, in pixels of
+    /// the atlas texture.
+    pub fn allocated_rects(&self) -> Vec<[u32; 4]> {
+        self.atlas
+            .iter()
+            .map(|allocation| {
+                // the allocator works in bytes across, 4 per pixel
+                let rect = allocation.rectangle.to_u32();
+                [rect.min.x / 4, rect.min.y, rect.max.x / 4, rect.max.y]
+            })
+            .collect()
+    }
+
+    /// Rasterizes LCD glyphs with `filter` from now on, dropping the glyphs that were
+    /// rasterized with the previous filter.
+    pub fn set_lcd_filter(&mut self, filter: LcdFilter) {
+        if filter == self.rasterizer.lcd_filter {
+            return;
+        }
+        self.rasterizer.lcd_filter = filter;
+        // glyphs that didn't fit may now
+        self.undrawable_glyphs.clear();
+        let atlas = &mut self.atlas;
+        self.glyph_map.retain(|key, (alloc_id, _)| {
+            if key.antialiasing == TextAntialiasing::Lcd {
+                atlas.deallocate(*alloc_id);
+                false
+            } else {
+                true
+            }
+        });
+    }
 
-        (placement, uv_bounds)
+    /// Drops the cached glyphs of a font, e.g. after it was reloaded.
+    pub fn invalidate_font(&mut self, font_cache_index: usize) {
+        self.undrawable_glyphs.clear();
+        let atlas = &mut self.atlas;
+        self.glyph_map.retain(|key, (alloc_id, _)| {
+            if key.font_cache_index == font_cache_index {
+                atlas.deallocate(*alloc_id);
+                false
+            } else {
+                true
+            }
+        });
     }
 
     pub fn prepare_draw_for_glyph(
         &self,
         vertices: &mut Vec<GlyphVertex>,
-        indices: &mut Vec<u16>,
+        indices: &mut Vec<u32>,
         glyph: RenderGlyphData,
+        color: [f32; 4],
         caret_x: i32,
         caret_y: i32,
     ) {
-        let (glyph_vertices, glyph_indices) = glyph.to_indexed_vertices(caret_x, caret_y);
-        let previous_vertices_len = vertices.len() as u16;
+        let (glyph_vertices, glyph_indices) = glyph.to_indexed_vertices(caret_x, caret_y, color);
+        let previous_vertices_len = vertices.len() as u32;
         for v in glyph_vertices {
             vertices.push(v);
         }
@@ -2024,6 +4837,17 @@ impl GlyphCache {
     }
 }
 
+impl HeapSize for GlyphCache {
+    // the cpu copies of the atlas texture plus the glyph map's entries
+    fn heap_size(&self) -> usize {
+        vec_size(&self.draw_texture)
+            + vec_size(&self.texture)
+            + self.glyph_map.capacity()
+                * std::mem::size_of::<(GlyphCacheKey, (etagere::AllocId, zeno::Placement))>()
+            + self.undrawable_glyphs.capacity() * std::mem::size_of::<GlyphCacheKey>()
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct RenderGlyphData {
     px_bounds: etagere::euclid::Box2D<i32, etagere::euclid::UnknownUnit>,
@@ -2031,34 +4855,48 @@ pub struct RenderGlyphData {
 }
 
 impl RenderGlyphData {
-    pub fn to_indexed_vertices(&self, caret_x: i32, caret_y: i32) -> ([GlyphVertex; 4], [u16; 6]) {
+    pub fn to_indexed_vertices(
+        &self,
+        caret_x: i32,
+        caret_y: i32,
+        color: [f32; 4],
+    ) -> ([GlyphVertex; 4], [u32; 6]) {
         let left = self.px_bounds.min.x;
         let right = self.px_bounds.max.x;
         let top = self.px_bounds.max.y;
         let bottom = self.px_bounds.min.y;
+        // the depth is set to the text's layer by TextRenderer::prepare_draw_for_text
         let vertices: [GlyphVertex; 4] = [
             GlyphVertex {
                 caret_position: [caret_x, caret_y],
                 px_bounds_offset: [left, top],
                 tex_coords: [self.uv_bounds.min.x, self.uv_bounds.min.y],
+                color,
+                depth: Layer::Hud.depth(),
             },
             GlyphVertex {
                 caret_position: [caret_x, caret_y],
                 px_bounds_offset: [left, bottom],
                 tex_coords: [self.uv_bounds.min.x, self.uv_bounds.max.y],
+                color,
+                depth: Layer::Hud.depth(),
             },
             GlyphVertex {
                 caret_position: [caret_x, caret_y],
                 px_bounds_offset: [right, bottom],
                 tex_coords: [self.uv_bounds.max.x, self.uv_bounds.max.y],
+                color,
+                depth: Layer::Hud.depth(),
             },
             GlyphVertex {
                 caret_position: [caret_x, caret_y],
                 px_bounds_offset: [right, top],
                 tex_coords: [self.uv_bounds.max.x, self.uv_bounds.min.y],
+                color,
+                depth: Layer::Hud.depth(),
             },
         ];
-        let indices: [u16; 6] = [0, 1, 2, 2, 3, 0];
+        let indices: [u32; 6] = [0, 1, 2, 2, 3, 0];
 
         (vertices, indices)
     }
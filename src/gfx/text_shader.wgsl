@@ -12,12 +12,16 @@ var<uniform> surface_dimensions: SurfaceDimensionsUniform;
 struct GlyphVertexInput {
     @location(0) caret_position: vec2<i32>,
     @location(1) px_bounds_offset: vec2<i32>,
-    @location(2) tex_coords: vec2<u32>
+    @location(2) tex_coords: vec2<u32>,
+    @location(3) color: vec4<f32>,
+    // layer, see render_graph::Layer
+    @location(4) depth: f32
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) tex_coords: vec2<f32>
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>
 }
 
 fn to_clip_coords(px: vec2<i32>) -> vec2<f32> {
@@ -33,7 +37,8 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.tex_coords = vec2<f32>(model.tex_coords);
-    out.clip_position = vec4<f32>(to_clip_coords(model.caret_position + model.px_bounds_offset), 0.0, 1.0);
+    out.color = model.color;
+    out.clip_position = vec4<f32>(to_clip_coords(model.caret_position + model.px_bounds_offset), model.depth, 1.0);
     return out;
 }
 
@@ -43,6 +48,33 @@ var t_diffuse: texture_2d<f32>;
 @group(1) @binding(1)
 var s_diffuse: sampler;
 
+// the glyph's coverage per subpixel, scaled by the text's opacity
+fn coverage(in: VertexOutput) -> vec4<f32> {
+    let dimensions = textureDimensions(t_diffuse);
+
+    let x: f32 = f32(in.tex_coords.x) / f32(dimensions.x);
+    let y: f32 = f32(in.tex_coords.y) / f32(dimensions.y);
+
+    let tex_coords = vec2<f32>(x, y);
+
+    return textureSample(t_diffuse, s_diffuse, tex_coords) * in.color.a;
+}
+
+// without dual source blending the glyphs are grayscale, the same coverage in every channel
+@fragment
+fn fs_grayscale(
+    in: VertexOutput,
+) -> @location(0) vec4<f32> {
+    let alpha = coverage(in).g;
+    // outside the glyph, it mustn't write the layer's depth over what's behind it
+    if alpha <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(in.color.rgb, alpha);
+}
+
+// dual source blending from here on, left out without it (see gfx::text::single_source_shader)
+
 struct FragmentOutput {
     @location(0) @blend_src(0) color : vec4f,
     @location(0) @blend_src(1) blend : vec4f,
@@ -52,17 +84,14 @@ struct FragmentOutput {
 fn fs_main(
     in: VertexOutput,
 ) -> FragmentOutput {
-    let dimensions = textureDimensions(t_diffuse);
-
-    let x: f32 = f32(in.tex_coords.x) / f32(dimensions.x);
-    let y: f32 = f32(in.tex_coords.y) / f32(dimensions.y);
-
-    let tex_coords = vec2<f32>(x, y);
-
     var output : FragmentOutput;
     // text color
-    output.color = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    output.color = in.color;
     // subpixel rgb mask
-    output.blend = textureSample(t_diffuse, s_diffuse, tex_coords);
+    output.blend = coverage(in);
+    // outside the glyph, it mustn't write the layer's depth over what's behind it
+    if max(output.blend.r, max(output.blend.g, output.blend.b)) <= 0.0 {
+        discard;
+    }
     return output;
-}
\ No newline at end of file
+}
@@ -1,13 +1,14 @@
 use std::mem::size_of;
 
+/// A corner of a tessellated thick line (see gfx::lines).
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct LineVertex {
-    pub position: [f32; 3],
-    pub previous_point: [f32; 3],
-    pub next_point: [f32; 3],
-    pub miter_dir: f32,
-    pub thickness: f32,
+    /// Physical pixels from the top left of the surface
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+    /// See [`super::Layer::depth`]
+    pub depth: f32,
 }
 
 impl LineVertex {
@@ -17,30 +18,238 @@ impl LineVertex {
             step_mode: wgpu::VertexStepMode::Vertex,
             attributes: &[
                 wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x3,
+                    format: wgpu::VertexFormat::Float32x2,
                     offset: 0,
                     shader_location: 0,
                 },
                 wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x3,
-                    offset: size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: size_of::<[f32; 2]>() as wgpu::BufferAddress,
                     shader_location: 1,
                 },
                 wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x3,
-                    offset: 2 * size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    format: wgpu::VertexFormat::Float32,
+                    offset: (size_of::<[f32; 2]>() + size_of::<[f32; 4]>()) as wgpu::BufferAddress,
+                    shader_location: 2,
+                },
+            ],
+        }
+    }
+}
+
+/// A flat colored vertex in clip space, for overlays.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ColorVertex {
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl ColorVertex {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<ColorVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: 0,
+                    shader_location: 0,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                },
+            ],
+        }
+    }
+}
+
+/// One particle, drawn as a quad (six vertices generated in the shader) per instance.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ParticleInstance {
+    /// Clip space
+    pub center: [f32; 2],
+    /// Clip space
+    pub half_size: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl ParticleInstance {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<ParticleInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: 0,
+                    shader_location: 0,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 2 * size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                },
+            ],
+        }
+    }
+}
+
+/// A rounded rectangle, drawn as a quad (six vertices generated in the shader) per instance.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShapeInstance {
+    /// Left, top, right and bottom edges in physical pixels from the top left of the surface
+    pub rect: [f32; 4],
+    pub fill_color: [f32; 4],
+    pub border_color: [f32; 4],
+    /// Physical pixels
+    pub corner_radius: f32,
+    /// Physical pixels
+    pub border_width: f32,
+    /// See [`super::Layer::depth`]
+    pub depth: f32,
+}
+
+impl ShapeInstance {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<ShapeInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 0,
+                    shader_location: 0,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 2 * size_of::<[f32; 4]>() as wgpu::BufferAddress,
                     shader_location: 2,
                 },
                 wgpu::VertexAttribute {
                     format: wgpu::VertexFormat::Float32,
-                    offset: 3 * size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    offset: 3 * size_of::<[f32; 4]>() as wgpu::BufferAddress,
                     shader_location: 3,
                 },
                 wgpu::VertexAttribute {
                     format: wgpu::VertexFormat::Float32,
-                    offset: (size_of::<f32>() + 3 * size_of::<[f32; 3]>()) as wgpu::BufferAddress,
+                    offset: (3 * size_of::<[f32; 4]>() + size_of::<f32>()) as wgpu::BufferAddress,
                     shader_location: 4,
                 },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32,
+                    offset: (3 * size_of::<[f32; 4]>() + 2 * size_of::<f32>())
+                        as wgpu::BufferAddress,
+                    shader_location: 5,
+                },
+            ],
+        }
+    }
+}
+
+/// A corner of a mesh that's drawn once per [`OutlineInstance`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MeshVertex {
+    /// Mesh space, scaled by the instance's radius for `corner`
+    pub position: [f32; 2],
+    /// Which of the instance's radii the position is scaled by
+    pub corner: u32,
+}
+
+impl MeshVertex {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<MeshVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: 0,
+                    shader_location: 0,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Uint32,
+                    offset: size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                },
+            ],
+        }
+    }
+}
+
+/// Where and how a mesh of [`MeshVertex`]es is drawn, one per world object.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct OutlineInstance {
+    /// Clip space
+    pub center: [f32; 2],
+    /// Where the mesh's x and y axes point, in clip space per mesh unit (so rotated and
+    /// scaled)
+    pub x_axis: [f32; 2],
+    pub y_axis: [f32; 2],
+    pub color: [f32; 4],
+    /// Scale of the mesh's corners, by their [`MeshVertex::corner`]
+    pub radii: [f32; 12],
+}
+
+impl OutlineInstance {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const VEC2: wgpu::BufferAddress = size_of::<[f32; 2]>() as wgpu::BufferAddress;
+        const VEC4: wgpu::BufferAddress = size_of::<[f32; 4]>() as wgpu::BufferAddress;
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<OutlineInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: 0,
+                    shader_location: 2,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: VEC2,
+                    shader_location: 3,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: 2 * VEC2,
+                    shader_location: 4,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 3 * VEC2,
+                    shader_location: 5,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 3 * VEC2 + VEC4,
+                    shader_location: 6,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 3 * VEC2 + 2 * VEC4,
+                    shader_location: 7,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 3 * VEC2 + 3 * VEC4,
+                    shader_location: 8,
+                },
             ],
         }
     }
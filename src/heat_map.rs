@@ -0,0 +1,74 @@
+use crate::memory::{HeapSize, vec_size};
+use crate::world::{WORLD_HEIGHT, WORLD_WIDTH, WorldEventKind};
+
+/// Size of a heat map cell in world units
+const CELL_SIZE: f32 = 60.0;
+
+/// Counts of world events (collisions, deaths, spawns) per coarse grid cell, accumulated over
+/// the whole session. Drawn as a translucent overlay (Ctrl+H) to see where the action is
+/// when balancing spawns and arena layout.
+pub struct HeatMap {
+    columns: usize,
+    rows: usize,
+    // one count per event kind, see WorldEventKind::index
+    cells: Vec<[u32; WorldEventKind::COUNT]>,
+    max_total: u32,
+}
+
+impl Default for HeatMap {
+    fn default() -> Self {
+        let columns = (WORLD_WIDTH / CELL_SIZE).ceil() as usize;
+        let rows = (WORLD_HEIGHT / CELL_SIZE).ceil() as usize;
+        Self {
+            columns,
+            rows,
+            cells: vec![[0; WorldEventKind::COUNT]; columns * rows],
+            max_total: 0,
+        }
+    }
+}
+
+impl HeatMap {
+    pub fn record(&mut self, kind: WorldEventKind, position: [f32; 2]) {
+        let column = ((position[0] / CELL_SIZE) as usize).min(self.columns - 1);
+        let row = ((position[1] / CELL_SIZE) as usize).min(self.rows - 1);
+        let cell = &mut self.cells[row * self.columns + column];
+        cell[kind.index()] += 1;
+        self.max_total = self.max_total.max(cell.iter().sum());
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.fill([0; WorldEventKind::COUNT]);
+        self.max_total = 0;
+    }
+
+    pub fn count(&self, column: usize, row: usize, kind: WorldEventKind) -> u32 {
+        self.cells[row * self.columns + column][kind.index()]
+    }
+
+    /// Non-empty cells as (min corner in world units, cell size, heat in 0..=1 relative to the
+    /// busiest cell).
+    pub fn hot_cells(&self) -> impl Iterator<Item = ([f32; 2], f32, f32)> + '_ {
+        self.cells.iter().enumerate().filter_map(|(i, cell)| {
+            let total: u32 = cell.iter().sum();
+            (total > 0).then(|| {
+                let (column, row) = (i % self.columns, i / self.columns);
+                (
+                    [column as f32 * CELL_SIZE, row as f32 * CELL_SIZE],
+                    CELL_SIZE,
+                    total as f32 / self.max_total as f32,
+                )
+            })
+        })
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+impl HeapSize for HeatMap {
+    fn heap_size(&self) -> usize {
+        vec_size(&self.cells)
+    }
+}
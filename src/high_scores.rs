@@ -0,0 +1,101 @@
+//! The top 10 scores, kept in `high-scores.json` in the user's data directory. The game over
+//! screen shows the table and asks for a name when the score makes it in (see crate::screen).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::paths;
+
+pub const MAX_ENTRIES: usize = 10;
+/// Longest name that can be entered
+pub const MAX_NAME_LENGTH: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+    /// The wave the game ended in
+    pub wave: u32,
+    /// Unix time in seconds
+    pub date: u64,
+}
+
+impl HighScore {
+    pub fn new(name: impl Into<String>, score: u32, wave: u32) -> Self {
+        Self {
+            name: name.into(),
+            score,
+            wave,
+            date: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs()),
+        }
+    }
+}
+
+/// Best first. Equal scores keep the order they were reached in.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighScores {
+    entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    /// `high-scores.json` in the platform's data directory, see [`paths::data_dir`].
+    pub fn default_path() -> Option<PathBuf> {
+        paths::data_dir(paths::APP_NAME).map(|dir| dir.join("high-scores.json"))
+    }
+
+    /// An empty table if the file doesn't exist yet.
+    pub fn load(path: &Path) -> Result<HighScores> {
+        if !path.exists() {
+            return Ok(HighScores::default());
+        }
+        let text = fs::read_to_string(path)?;
+        let mut high_scores: HighScores = serde_json::from_str(&text)
+            .map_err(|e| anyhow!("invalid high score file {}: {}", path.display(), e))?;
+        // in case someone edited the file
+        high_scores
+            .entries
+            .sort_by_key(|e| std::cmp::Reverse(e.score));
+        high_scores.entries.truncate(MAX_ENTRIES);
+        Ok(high_scores)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[HighScore] {
+        &self.entries
+    }
+
+    /// True if the score would make it into the table.
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.entries.len() < MAX_ENTRIES
+                || self.entries.last().is_some_and(|last| score > last.score))
+    }
+
+    /// Adds the entry if it qualifies, returning its rank (0 = best).
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        if !self.qualifies(entry.score) {
+            return None;
+        }
+        let rank = self
+            .entries
+            .iter()
+            .position(|other| entry.score > other.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+}
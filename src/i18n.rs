@@ -0,0 +1,200 @@
+//! Translations of the UI's text. The screens look up every string they show by key with
+//! [`t!`], in the string table of the current language. Keys missing from it fall back to
+//! English, which is built in, and then to the key itself.
+//!
+//! A language's table is a TOML file of keys and strings named after the language, e.g.
+//! `lang/de.toml` in a directory added with [`add_directory`] (see
+//! [`EngineBuilder::language_directory`](crate::engine::EngineBuilder::language_directory)):
+//!
+//! ```toml
+//! language_name = "DEUTSCH"
+//! play = "SPIELEN"
+//! score_and_wave = "PUNKTE {score}   WELLE {wave}"
+//! ```
+//!
+//! `{name}` placeholders are filled in by name, `t!("score_and_wave", score = 10, wave = 2)`.
+//! Placeholders that aren't given stay as they are (the controls prompt's `{action:fire}`
+//! tags are for [`InputMap::prompt`](crate::input::InputMap::prompt)).
+//!
+//! [`set_language`] switches the language while the game runs. The screens build their text
+//! every update, so the next update lays everything out again in the new language, shaped
+//! for its script and direction by the text renderer.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use anyhow::{Result, anyhow};
+use parking_lot::RwLock;
+
+/// The built in language, and the fallback for keys the others are missing
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+const ENGLISH: &[(&str, &str)] = &[
+    ("language_name", "ENGLISH"),
+    ("title", "SPACESHIP"),
+    ("play", "PLAY"),
+    ("fonts", "FONTS"),
+    ("paused", "PAUSED"),
+    ("resume", "RESUME"),
+    ("settings", "SETTINGS"),
+    ("quit_to_menu", "QUIT TO MENU"),
+    ("on", "ON"),
+    ("off", "OFF"),
+    ("vsync", "VSYNC {state}"),
+    ("fullscreen", "FULLSCREEN {state}"),
+    ("radar", "RADAR {state}"),
+    ("language", "LANGUAGE {language}"),
+    ("ui_scale", "UI SCALE {percent}%"),
+    ("colors", "COLORS {vision}"),
+    ("normal", "NORMAL"),
+    ("deuteranopia", "DEUTERANOPIA"),
+    ("protanopia", "PROTANOPIA"),
+    ("tritanopia", "TRITANOPIA"),
+    ("back", "BACK"),
+    (
+        "controls",
+        "{action:thrust} THRUST   {action:turn_left} {action:turn_right} TURN   {action:fire} FIRE",
+    ),
+    ("unbound", "UNBOUND"),
+    ("score_and_wave", "SCORE {score}   WAVE {wave}"),
+    ("new_high_score", "NEW HIGH SCORE! ENTER YOUR NAME: "),
+    ("default_player_name", "PLAYER"),
+    ("continue", "CONTINUE"),
+    ("high_scores", "HIGH SCORES"),
+    ("high_score_wave", "WAVE {wave}"),
+    ("kills_and_deaths", "{kills} KILLS   {deaths} DEATHS"),
+    ("connecting", "CONNECTING TO {server}"),
+    ("refused", "{server} REFUSED: {reason}"),
+    ("timed_out", "{server} STOPPED ANSWERING"),
+    ("chat_prompt", "SAY: "),
+    (
+        "chat_hints",
+        "ENTER CHAT   PAGE UP PAGE DOWN SCROLL   ESC LEAVE",
+    ),
+    ("search_prompt", "SEARCH: "),
+    (
+        "font_browser_hints",
+        "TYPE TO SEARCH   UP DOWN SELECT   PAGE UP PAGE DOWN TURN PAGES   ESC BACK",
+    ),
+    ("font_pages", "{faces} FACES   PAGE {page} OF {pages}"),
+];
+
+#[derive(Default)]
+struct Translations {
+    language: Option<String>,
+    // the current language's table, empty for English
+    strings: HashMap<String, String>,
+    directories: Vec<PathBuf>,
+}
+
+static TRANSLATIONS: LazyLock<RwLock<Translations>> = LazyLock::new(Default::default);
+
+/// The string for `key` in the current language, with `args` filled in. See the module
+/// docs, [`t!`] is shorter.
+pub fn translate(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let translations = TRANSLATIONS.read();
+    let text = translations
+        .strings
+        .get(key)
+        .map(String::as_str)
+        .or_else(|| english(key))
+        .unwrap_or(key);
+    if args.is_empty() {
+        return text.to_string();
+    }
+    let mut text = text.to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    text
+}
+
+/// Looks up a UI string, see [`crate::i18n`]: `t!("play")`, `t!("high_score_wave", wave = 3)`.
+#[macro_export]
+macro_rules! t {
+    ($key:expr) => {
+        $crate::i18n::translate($key, &[])
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::translate(
+            $key,
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),+],
+        )
+    };
+}
+
+fn english(key: &str) -> Option<&'static str> {
+    ENGLISH
+        .iter()
+        .find(|(english_key, _)| *english_key == key)
+        .map(|(_, text)| *text)
+}
+
+/// Adds a directory with string tables, searched in the order they were added.
+pub fn add_directory(path: impl Into<PathBuf>) {
+    TRANSLATIONS.write().directories.push(path.into());
+}
+
+/// The current language, e.g. `"de"`.
+pub fn language() -> String {
+    TRANSLATIONS
+        .read()
+        .language
+        .clone()
+        .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string())
+}
+
+/// Switches to `language`, loading its table from the first directory that has it. The
+/// current language stays if it can't be loaded.
+pub fn set_language(language: &str) -> Result<()> {
+    let strings = if language == DEFAULT_LANGUAGE {
+        HashMap::new()
+    } else {
+        let file_name = format!("{}.toml", language);
+        let path = TRANSLATIONS
+            .read()
+            .directories
+            .iter()
+            .map(|directory| directory.join(&file_name))
+            .find(|path| path.exists())
+            .ok_or_else(|| anyhow!("there's no string table for the language {:?}", language))?;
+        load_table(&path)?
+    };
+    let mut translations = TRANSLATIONS.write();
+    translations.language = Some(language.to_string());
+    translations.strings = strings;
+    log::info!("switched the language to {}", language);
+    Ok(())
+}
+
+/// The languages there are tables for (and English), sorted.
+pub fn languages() -> Vec<String> {
+    let mut languages = vec![DEFAULT_LANGUAGE.to_string()];
+    for directory in TRANSLATIONS.read().directories.iter() {
+        let Ok(entries) = fs::read_dir(directory) else {
+            continue;
+        };
+        languages.extend(entries.flatten().filter_map(|entry| {
+            let path = entry.path();
+            (path.extension()? == "toml")
+                .then(|| path.file_stem()?.to_str().map(str::to_string))
+                .flatten()
+        }));
+    }
+    languages.sort();
+    languages.dedup();
+    languages
+}
+
+fn load_table(path: &Path) -> Result<HashMap<String, String>> {
+    let text = fs::read_to_string(path)?;
+    let strings: HashMap<String, String> = toml::from_str(&text)
+        .map_err(|e| anyhow!("invalid string table {}: {}", path.display(), e))?;
+    for key in strings.keys().filter(|key| english(key).is_none()) {
+        log::warn!("{} has the unknown key {:?}", path.display(), key);
+    }
+    Ok(strings)
+}
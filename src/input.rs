@@ -1,116 +1,636 @@
+use std::ops::Range;
+use std::time::Duration;
+
 use device_query::{DeviceQuery, DeviceState, Keycode};
 
+use crate::gfx::text::{LinkId, LinkRegion};
+use crate::t;
+
+/// Game actions that can be bound to keys, see InputMap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Thrust,
+    TurnLeft,
+    TurnRight,
+    Fire,
+}
+
+impl Action {
+    pub const ALL: [Action; 4] = [
+        Action::Thrust,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Fire,
+    ];
+
+    /// The action's name in prompts, see [`InputMap::prompt`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Thrust => "thrust",
+            Action::TurnLeft => "turn_left",
+            Action::TurnRight => "turn_right",
+            Action::Fire => "fire",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// What a key is called in prompts, e.g. "SPACE" or "["
+pub fn key_name(key_code: Keycode) -> String {
+    match key_code {
+        Keycode::LeftBracket => "[".to_string(),
+        Keycode::RightBracket => "]".to_string(),
+        Keycode::LControl => "LEFT CTRL".to_string(),
+        Keycode::RControl => "RIGHT CTRL".to_string(),
+        Keycode::LShift => "LEFT SHIFT".to_string(),
+        Keycode::RShift => "RIGHT SHIFT".to_string(),
+        Keycode::LAlt => "LEFT ALT".to_string(),
+        Keycode::RAlt => "RIGHT ALT".to_string(),
+        Keycode::PageUp => "PAGE UP".to_string(),
+        Keycode::PageDown => "PAGE DOWN".to_string(),
+        // Key0-Key9
+        _ => {
+            let name = key_code.to_string();
+            name.strip_prefix("Key").unwrap_or(&name).to_uppercase()
+        }
+    }
+}
+
+/// Maps actions to keys, so game code asks "is the player thrusting" instead of "is W down".
+/// Any key can be bound, KeyboardState tracks them all.
+#[derive(Debug, Clone)]
+pub struct InputMap {
+    bindings: Vec<(Action, Keycode)>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self {
+            bindings: vec![
+                (Action::Thrust, Keycode::W),
+                (Action::TurnLeft, Keycode::A),
+                (Action::TurnRight, Keycode::D),
+                (Action::Fire, Keycode::Space),
+            ],
+        }
+    }
+}
+
+impl InputMap {
+    /// Adds a key for the action. An action can have multiple keys.
+    pub fn bind(&mut self, action: Action, key_code: Keycode) {
+        if !self.bindings.contains(&(action, key_code)) {
+            self.bindings.push((action, key_code));
+        }
+    }
+
+    pub fn unbind_all(&mut self, action: Action) {
+        self.bindings.retain(|(a, _)| *a != action);
+    }
+
+    /// The keys bound to the action, in the order they were bound.
+    pub fn keys(&self, action: Action) -> impl Iterator<Item = Keycode> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(a, _)| *a == action)
+            .map(|(_, key_code)| *key_code)
+    }
+
+    /// Replaces the `{action:<name>}` tags in `text` (e.g. `{action:fire}`, see
+    /// [`Action::name`]) with the names of the keys bound to the action, so prompts show the
+    /// current bindings. Prompts are made every frame, so they follow rebinding right away.
+    /// Tags of unknown actions are left as they are.
+    pub fn prompt(&self, text: &str) -> String {
+        const TAG: &str = "{action:";
+        let mut prompt = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(TAG) {
+            prompt.push_str(&rest[..start]);
+            let after_tag = &rest[start + TAG.len()..];
+            let action = after_tag
+                .find('}')
+                .and_then(|end| Action::from_name(&after_tag[..end]).map(|action| (action, end)));
+            match action {
+                Some((action, end)) => {
+                    let keys: Vec<String> = self.keys(action).map(key_name).collect();
+                    if keys.is_empty() {
+                        prompt.push_str(&t!("unbound"));
+                    } else {
+                        prompt.push_str(&keys.join("/"));
+                    }
+                    rest = &after_tag[end + 1..];
+                }
+                None => {
+                    prompt.push_str(TAG);
+                    rest = after_tag;
+                }
+            }
+        }
+        prompt.push_str(rest);
+        prompt
+    }
+
+    /// True if any key bound to the action is down.
+    pub fn is_down(&self, action: Action, keyboard_state: &KeyboardState) -> bool {
+        self.bindings
+            .iter()
+            .filter(|(a, _)| *a == action)
+            .any(|(_, key_code)| keyboard_state.is_down(*key_code))
+    }
+}
+
+/// The mouse, from the window's events (see the engine). Positions are in physical pixels
+/// from the top left of the window.
+#[derive(Debug, Clone, Default)]
+pub struct MouseState {
+    position: Option<[f32; 2]>,
+    left_down: bool,
+    // a press since the last update, so a click shorter than an update isn't lost
+    left_press_pending: bool,
+    left_pressed: bool,
+    // wheel and touchpad deltas since the last update, and the ones of the last update
+    scroll_lines_pending: f32,
+    scroll_pixels_pending: f32,
+    scroll_lines: f32,
+    scroll_pixels: f32,
+}
+
+impl MouseState {
+    /// None while the cursor is outside the window
+    pub fn position(&self) -> Option<[f32; 2]> {
+        self.position
+    }
+
+    pub fn is_left_down(&self) -> bool {
+        self.left_down
+    }
+
+    /// True if the left button was pressed since the previous update.
+    pub fn is_left_pressed(&self) -> bool {
+        self.left_pressed
+    }
+
+    /// Mouse wheel notches since the previous update, positive when scrolling up (towards
+    /// the top of the content).
+    pub fn scroll_lines(&self) -> f32 {
+        self.scroll_lines
+    }
+
+    /// Precise scrolling (e.g. touchpads) since the previous update, in physical pixels,
+    /// positive when scrolling up.
+    pub fn scroll_pixels(&self) -> f32 {
+        self.scroll_pixels
+    }
+
+    pub(crate) fn cursor_moved(&mut self, position: [f32; 2]) {
+        self.position = Some(position);
+    }
+
+    pub(crate) fn cursor_left(&mut self) {
+        self.position = None;
+    }
+
+    pub(crate) fn left_button(&mut self, down: bool) {
+        if down && !self.left_down {
+            self.left_press_pending = true;
+        }
+        self.left_down = down;
+    }
+
+    pub(crate) fn scrolled_lines(&mut self, lines: f32) {
+        self.scroll_lines_pending += lines;
+    }
+
+    pub(crate) fn scrolled_pixels(&mut self, pixels: f32) {
+        self.scroll_pixels_pending += pixels;
+    }
+
+    pub(crate) fn update(&mut self) {
+        self.left_pressed = std::mem::take(&mut self.left_press_pending);
+        self.scroll_lines = std::mem::take(&mut self.scroll_lines_pending);
+        self.scroll_pixels = std::mem::take(&mut self.scroll_pixels_pending);
+    }
+}
+
+/// Text from the input method (IME), e.g. for typing Chinese, Japanese or Korean: the text
+/// it committed since the last update, and the pre-edit string it's composing until then.
+/// From the window's events (see the engine), which only come while a text field is shown
+/// (see [`crate::screen::ScreenOverlay::text_input`]).
+#[derive(Debug, Clone, Default)]
+pub struct ImeState {
+    committed_pending: String,
+    committed: String,
+    preedit: String,
+    // byte range of the pre-edit string the input method's cursor is on
+    preedit_cursor: Option<(usize, usize)>,
+    // composed since the last update, even if it was cancelled before the update
+    composed_pending: bool,
+    composed: bool,
+}
+
+impl ImeState {
+    /// Text the input method committed since the previous update.
+    pub fn committed(&self) -> &str {
+        &self.committed
+    }
+
+    /// What the input method is composing, drawn at the caret until it's committed (empty
+    /// while it isn't composing).
+    pub fn preedit(&self) -> &str {
+        &self.preedit
+    }
+
+    /// The byte range of the pre-edit string the input method's cursor is on.
+    pub fn preedit_cursor(&self) -> Option<Range<usize>> {
+        self.preedit_cursor.map(|(start, end)| start..end)
+    }
+
+    /// True while the keys that are pressed go to the input method: it's composing, or it
+    /// committed or cancelled what it composed since the previous update. Text fields don't
+    /// take them as typing (or enter and escape) of their own then.
+    pub fn is_composing(&self) -> bool {
+        self.composed || !self.preedit.is_empty() || !self.committed.is_empty()
+    }
+
+    pub(crate) fn set_preedit(&mut self, text: String, cursor: Option<(usize, usize)>) {
+        self.composed_pending |= !text.is_empty() || !self.preedit.is_empty();
+        self.preedit = text;
+        self.preedit_cursor = cursor;
+    }
+
+    pub(crate) fn commit(&mut self, text: &str) {
+        self.committed_pending.push_str(text);
+        self.composed_pending = true;
+        self.preedit.clear();
+        self.preedit_cursor = None;
+    }
+
+    /// The input method was turned off, what it was composing is gone.
+    pub(crate) fn disabled(&mut self) {
+        self.set_preedit(String::new(), None);
+    }
+
+    pub(crate) fn update(&mut self) {
+        self.committed = std::mem::take(&mut self.committed_pending);
+        self.composed = std::mem::take(&mut self.composed_pending);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkEvent {
+    /// The cursor moved onto the link
+    HoverStart(LinkId),
+    /// The cursor moved off the link
+    HoverEnd(LinkId),
+    Click(LinkId),
+}
+
+/// Hover and click events for the links in the text on screen (see
+/// [`TextDraw::link`](crate::gfx::text::TextDraw::link)), hit tested once per update against
+/// the regions of the text drawn after the previous one.
+#[derive(Debug, Clone, Default)]
+pub struct Links {
+    regions: Vec<LinkRegion>,
+    hovered: Option<LinkId>,
+    events: Vec<LinkEvent>,
+}
+
+impl Links {
+    /// The link under the cursor
+    pub fn hovered(&self) -> Option<LinkId> {
+        self.hovered
+    }
+
+    /// The events of this update
+    pub fn events(&self) -> &[LinkEvent] {
+        &self.events
+    }
+
+    /// True if the link was clicked this update.
+    pub fn clicked(&self, id: LinkId) -> bool {
+        self.events.contains(&LinkEvent::Click(id))
+    }
+
+    pub(crate) fn set_regions(&mut self, regions: Vec<LinkRegion>) {
+        self.regions = regions;
+    }
+
+    pub(crate) fn update(&mut self, mouse: &MouseState) {
+        self.events.clear();
+        // text drawn later is on top
+        let hovered = mouse.position().and_then(|position| {
+            self.regions
+                .iter()
+                .rev()
+                .find(|region| region.contains(position))
+                .map(|region| region.id)
+        });
+        if hovered != self.hovered {
+            self.events.extend(self.hovered.map(LinkEvent::HoverEnd));
+            self.events.extend(hovered.map(LinkEvent::HoverStart));
+            self.hovered = hovered;
+        }
+        if let Some(id) = hovered
+            && mouse.is_left_pressed()
+        {
+            self.events.push(LinkEvent::Click(id));
+        }
+    }
+}
+
+/// How held keys repeat, see [`KeyState::is_repeat_pressed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyRepeat {
+    /// From the press to the first repeat
+    pub delay: Duration,
+    /// Between repeats after that
+    pub interval: Duration,
+}
+
+impl Default for KeyRepeat {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_millis(400),
+            interval: Duration::from_millis(40),
+        }
+    }
+}
+
 pub struct KeyboardState {
     device_state: DeviceState,
-    character_keys: [KeyState; 38],
+    key_repeat: KeyRepeat,
+    // updates a second, the key states count in updates
+    update_rate: u32,
+    // of the last update
+    frame_number: u64,
+    character_keys: [KeyState; 52],
+    function_keys: [KeyState; 12],
+    navigation_keys: [KeyState; 10],
+    numpad_keys: [KeyState; 14],
+    caps_lock: KeyState,
+    // the left and right keys are one
     shift: KeyState,
     control: KeyState,
     alt: KeyState,
+    meta: KeyState,
 }
 
 impl KeyboardState {
     pub fn new(device_state: DeviceState) -> Self {
-        Self {
+        let mut keyboard_state = Self {
             device_state,
+            key_repeat: KeyRepeat::default(),
+            update_rate: 60,
+            frame_number: 0,
             character_keys: [
-                KeyState::new(Keycode::Key0),
-                KeyState::new(Keycode::Key1),
-                KeyState::new(Keycode::Key2),
-                KeyState::new(Keycode::Key3),
-                KeyState::new(Keycode::Key4),
-                KeyState::new(Keycode::Key5),
-                KeyState::new(Keycode::Key6),
-                KeyState::new(Keycode::Key7),
-                KeyState::new(Keycode::Key8),
-                KeyState::new(Keycode::Key9),
-                KeyState::new(Keycode::A),
-                KeyState::new(Keycode::B),
-                KeyState::new(Keycode::C),
-                KeyState::new(Keycode::D),
-                KeyState::new(Keycode::E),
-                KeyState::new(Keycode::F),
-                KeyState::new(Keycode::G),
-                KeyState::new(Keycode::H),
-                KeyState::new(Keycode::I),
-                KeyState::new(Keycode::J),
-                KeyState::new(Keycode::K),
-                KeyState::new(Keycode::L),
-                KeyState::new(Keycode::M),
-                KeyState::new(Keycode::N),
-                KeyState::new(Keycode::O),
-                KeyState::new(Keycode::P),
-                KeyState::new(Keycode::Q),
-                KeyState::new(Keycode::R),
-                KeyState::new(Keycode::S),
-                KeyState::new(Keycode::T),
-                KeyState::new(Keycode::U),
-                KeyState::new(Keycode::V),
-                KeyState::new(Keycode::W),
-                KeyState::new(Keycode::X),
-                KeyState::new(Keycode::Y),
-                KeyState::new(Keycode::Z),
-                KeyState::new(Keycode::LeftBracket),
-                KeyState::new(Keycode::RightBracket),
-            ],
+                Keycode::Key0,
+                Keycode::Key1,
+                Keycode::Key2,
+                Keycode::Key3,
+                Keycode::Key4,
+                Keycode::Key5,
+                Keycode::Key6,
+                Keycode::Key7,
+                Keycode::Key8,
+                Keycode::Key9,
+                Keycode::A,
+                Keycode::B,
+                Keycode::C,
+                Keycode::D,
+                Keycode::E,
+                Keycode::F,
+                Keycode::G,
+                Keycode::H,
+                Keycode::I,
+                Keycode::J,
+                Keycode::K,
+                Keycode::L,
+                Keycode::M,
+                Keycode::N,
+                Keycode::O,
+                Keycode::P,
+                Keycode::Q,
+                Keycode::R,
+                Keycode::S,
+                Keycode::T,
+                Keycode::U,
+                Keycode::V,
+                Keycode::W,
+                Keycode::X,
+                Keycode::Y,
+                Keycode::Z,
+                Keycode::LeftBracket,
+                Keycode::RightBracket,
+                Keycode::Grave,
+                Keycode::Minus,
+                Keycode::Equal,
+                Keycode::BackSlash,
+                Keycode::Semicolon,
+                Keycode::Apostrophe,
+                Keycode::Comma,
+                Keycode::Dot,
+                Keycode::Slash,
+                Keycode::Space,
+                Keycode::Tab,
+                Keycode::Enter,
+                Keycode::Escape,
+                Keycode::Backspace,
+            ]
+            .map(KeyState::new),
+            function_keys: [
+                Keycode::F1,
+                Keycode::F2,
+                Keycode::F3,
+                Keycode::F4,
+                Keycode::F5,
+                Keycode::F6,
+                Keycode::F7,
+                Keycode::F8,
+                Keycode::F9,
+                Keycode::F10,
+                Keycode::F11,
+                Keycode::F12,
+            ]
+            .map(KeyState::new),
+            navigation_keys: [
+                Keycode::PageUp,
+                Keycode::PageDown,
+                Keycode::Home,
+                Keycode::End,
+                Keycode::Insert,
+                Keycode::Delete,
+                Keycode::Up,
+                Keycode::Down,
+                Keycode::Left,
+                Keycode::Right,
+            ]
+            .map(KeyState::new),
+            numpad_keys: [
+                Keycode::Numpad0,
+                Keycode::Numpad1,
+                Keycode::Numpad2,
+                Keycode::Numpad3,
+                Keycode::Numpad4,
+                Keycode::Numpad5,
+                Keycode::Numpad6,
+                Keycode::Numpad7,
+                Keycode::Numpad8,
+                Keycode::Numpad9,
+                Keycode::NumpadAdd,
+                Keycode::NumpadSubtract,
+                Keycode::NumpadMultiply,
+                Keycode::NumpadDivide,
+            ]
+            .map(KeyState::new),
+            caps_lock: KeyState::new(Keycode::CapsLock),
             shift: KeyState::new(Keycode::LShift),
             control: KeyState::new(Keycode::LControl),
             alt: KeyState::new(Keycode::LAlt),
-        }
+            meta: KeyState::new(Keycode::Meta),
+        };
+        keyboard_state.apply_key_repeat();
+        keyboard_state
     }
 
-    pub fn get_key_state(&self, key_code: Keycode) -> &KeyState {
-        match key_code {
-            Keycode::LShift | Keycode::RShift => &self.shift,
-            Keycode::LControl | Keycode::RControl => &self.control,
-            Keycode::LAlt | Keycode::RAlt => &self.alt,
-            _ => {
-                for key_state in &self.character_keys {
-                    if key_state.key_code == key_code {
-                        return &key_state;
-                    }
-                }
-                panic!(
-                    "KeyboardState does not have a key state for keycode: {:?}",
-                    key_code
-                );
-            }
+    /// Held keys repeat like this from now on.
+    pub fn set_key_repeat(&mut self, key_repeat: KeyRepeat) {
+        self.key_repeat = key_repeat;
+        self.apply_key_repeat();
+    }
+
+    /// The keyboard is updated `hz` times a second from now on, see
+    /// [`KeyboardState::update`].
+    pub(crate) fn set_update_rate(&mut self, hz: u32) {
+        self.update_rate = hz;
+        self.apply_key_repeat();
+    }
+
+    // the repeat in updates, for every key
+    fn apply_key_repeat(&mut self) {
+        let updates =
+            |duration: Duration| (duration.as_secs_f64() * self.update_rate as f64).round() as u64;
+        let delay = updates(self.key_repeat.delay);
+        let interval = updates(self.key_repeat.interval);
+        for key_state in self.key_states_mut() {
+            key_state.set_repeat(delay, interval);
         }
     }
 
-    pub fn _get_pressed_characters(&self) -> Vec<char> {
-        return vec![];
-        /*let intermediate_iter = self
-            .character_keys
+    fn key_states(&self) -> impl Iterator<Item = &KeyState> {
+        self.character_keys
+            .iter()
+            .chain(&self.function_keys)
+            .chain(&self.navigation_keys)
+            .chain(&self.numpad_keys)
+            .chain([
+                &self.caps_lock,
+                &self.shift,
+                &self.control,
+                &self.alt,
+                &self.meta,
+            ])
+    }
+
+    fn key_states_mut(&mut self) -> impl Iterator<Item = &mut KeyState> {
+        self.character_keys
+            .iter_mut()
+            .chain(&mut self.function_keys)
+            .chain(&mut self.navigation_keys)
+            .chain(&mut self.numpad_keys)
+            .chain([
+                &mut self.caps_lock,
+                &mut self.shift,
+                &mut self.control,
+                &mut self.alt,
+                &mut self.meta,
+            ])
+    }
+
+    /// The key's state, None for a key that isn't tracked. The right shift, control and alt
+    /// keys are the same as the left ones.
+    pub fn get_key_state(&self, key_code: Keycode) -> Option<&KeyState> {
+        let key_code = match key_code {
+            Keycode::RShift => Keycode::LShift,
+            Keycode::RControl => Keycode::LControl,
+            Keycode::RAlt => Keycode::LAlt,
+            key_code => key_code,
+        };
+        self.key_states()
+            .find(|key_state| key_state.key_code == key_code)
+    }
+
+    /// True if the key is down.
+    pub fn is_down(&self, key_code: Keycode) -> bool {
+        self.get_key_state(key_code).is_some_and(KeyState::is_down)
+    }
+
+    /// True if the key was pressed since the previous update.
+    pub fn is_pressed(&self, key_code: Keycode) -> bool {
+        self.get_key_state(key_code)
+            .is_some_and(KeyState::is_pressed)
+    }
+
+    /// True if the key was pressed since the previous update or it repeats, see
+    /// [`KeyState::is_repeat_pressed`].
+    pub fn is_repeat_pressed(&self, key_code: Keycode) -> bool {
+        self.get_key_state(key_code)
+            .is_some_and(|key_state| key_state.is_repeat_pressed(self.frame_number))
+    }
+
+    /// Text typed since the last update: the letters (upper case with shift), digits (the
+    /// numpad's too) and spaces whose keys were pressed, in key order.
+    pub fn pressed_characters(&self) -> Vec<char> {
+        self.character_keys
             .iter()
-            .filter(|(_, key_state)| key_state.is_pressed());
-        if self.shift.is_down() {
-            intermediate_iter
-                .map(|(c, _)| c.clone().to_ascii_uppercase())
-                .collect()
-        } else {
-            intermediate_iter.map(|(c, _)| c.clone()).collect()
-        }*/
+            .chain(&self.numpad_keys)
+            .filter(|key_state| key_state.is_pressed())
+            .filter_map(|key_state| key_char(key_state.key_code))
+            .map(|c| {
+                if self.shift.is_down() {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                }
+            })
+            .collect()
     }
 
     pub fn update(&mut self, frame_number: u64) {
         let mut keys: Vec<Keycode> = self.device_state.get_keys();
-        for i in 0..keys.len() {
-            match &keys[i] {
-                &Keycode::RShift => keys[i] = Keycode::LShift,
-                &Keycode::RControl => keys[i] = Keycode::LControl,
-                &Keycode::RAlt => keys[i] = Keycode::LAlt,
+        for key in &mut keys {
+            match key {
+                Keycode::RShift => *key = Keycode::LShift,
+                Keycode::RControl => *key = Keycode::LControl,
+                Keycode::RAlt => *key = Keycode::LAlt,
                 _ => continue,
             }
         }
-
-        for i in 0..self.character_keys.len() {
-            let key_state = &mut self.character_keys[i];
+        self.frame_number = frame_number;
+        for key_state in self.key_states_mut() {
             key_state.update(&keys, frame_number);
         }
+    }
+}
 
-        self.shift.update(&keys, frame_number);
-        self.control.update(&keys, frame_number);
-        self.alt.update(&keys, frame_number);
+// the character a key types, if any (letters in lower case)
+fn key_char(key_code: Keycode) -> Option<char> {
+    if key_code == Keycode::Space {
+        return Some(' ');
+    }
+    // letter keys are named after their letter, digit keys Key0 to Key9 (and Numpad0 to
+    // Numpad9)
+    let name = key_code.to_string();
+    let name = name
+        .strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Numpad"))
+        .unwrap_or(&name);
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => Some(c.to_ascii_lowercase()),
+        _ => None,
     }
 }
 
@@ -123,6 +643,9 @@ pub struct KeyState {
     last_released_frame: Option<u64>,
     held: bool,
     held_since_frame: Option<u64>,
+    // in updates, see is_repeat_pressed
+    repeat_delay: u64,
+    repeat_interval: u64,
 }
 
 impl KeyState {
@@ -136,17 +659,41 @@ impl KeyState {
             last_released_frame: None,
             held: false,
             held_since_frame: None,
+            // doesn't repeat until set_repeat
+            repeat_delay: u64::MAX,
+            repeat_interval: 1,
         }
     }
 
+    /// The key repeats `delay` frames after it was pressed, and every `interval` frames after
+    /// that while it's held. Usually set for all keys with [`KeyboardState::set_key_repeat`].
+    pub fn set_repeat(&mut self, delay: u64, interval: u64) {
+        self.repeat_delay = delay;
+        self.repeat_interval = interval.max(1);
+    }
+
     pub fn is_down(&self) -> bool {
         self.down
     }
 
-    pub fn _is_pressed(&self) -> bool {
+    pub fn is_pressed(&self) -> bool {
         self.pressed
     }
 
+    /// True if the key was pressed, or it repeats, at `frame_number` (the frame number of the
+    /// last update): like a key held down in a text field, for moving through menus or
+    /// deleting with backspace held down.
+    pub fn is_repeat_pressed(&self, frame_number: u64) -> bool {
+        if self.pressed {
+            return true;
+        }
+        let Some(pressed_frame) = self.last_pressed_frame.filter(|_| self.down) else {
+            return false;
+        };
+        let held = frame_number.saturating_sub(pressed_frame);
+        held >= self.repeat_delay && (held - self.repeat_delay).is_multiple_of(self.repeat_interval)
+    }
+
     pub fn _is_released(&self) -> bool {
         self.released
     }
@@ -0,0 +1,84 @@
+//! Drawing between ticks. The simulation only moves in whole ticks, but frames don't line up
+//! with them (30 Hz ticks on a 144 Hz display, or a frame halfway through a tick), so
+//! everything drawn from simulation state is drawn `alpha` of the way from the previous tick
+//! to the current one instead of snapping to the last tick.
+//!
+//! Renderers get the frame's alpha in `FrameContext::render_alpha` (see
+//! [`GameState::render_alpha`](crate::GameState::render_alpha)). They, and anything else that
+//! presents simulation state (a camera or an audio listener), should use these helpers rather
+//! than their own math, so they all agree on where things are.
+
+use std::collections::HashMap;
+
+use crate::math::{Real, Scalar, SimVec2};
+use crate::world::{WORLD_HEIGHT, WORLD_WIDTH, World};
+
+pub fn lerp(a: f32, b: f32, alpha: f32) -> f32 {
+    a + (b - a) * alpha
+}
+
+pub fn lerp_vec2(a: [f32; 2], b: [f32; 2], alpha: f32) -> [f32; 2] {
+    [lerp(a[0], b[0], alpha), lerp(a[1], b[1], alpha)]
+}
+
+/// Interpolates angles (in radians) the short way around.
+pub fn lerp_angle(a: f32, b: f32, alpha: f32) -> f32 {
+    use std::f32::consts::{PI, TAU};
+    let delta = (b - a + PI).rem_euclid(TAU) - PI;
+    a + delta * alpha
+}
+
+/// Interpolates positions in the world, which wraps around its edges: something that flew
+/// off the right edge moves on to the right rather than back across the whole world.
+pub fn lerp_world_position(a: [f32; 2], b: [f32; 2], alpha: f32) -> [f32; 2] {
+    let size = [WORLD_WIDTH, WORLD_HEIGHT];
+    std::array::from_fn(|i| {
+        let delta = (b[i] - a[i] + size[i] / 2.0).rem_euclid(size[i]) - size[i] / 2.0;
+        (a[i] + delta * alpha).rem_euclid(size[i])
+    })
+}
+
+fn lerp_sim_position(a: SimVec2, b: SimVec2, alpha: f32) -> SimVec2 {
+    let [x, y] = lerp_world_position(a.to_f32(), b.to_f32(), alpha);
+    SimVec2::new(Scalar::from_f32(x), Scalar::from_f32(y))
+}
+
+fn lerp_sim_angle(a: Scalar, b: Scalar, alpha: f32) -> Scalar {
+    Scalar::from_f32(lerp_angle(a.to_f32(), b.to_f32(), alpha))
+}
+
+/// The world `alpha` of the way from `previous` to `current` (the tick before it), for
+/// drawing only. Things that didn't exist in `previous` are where they are in `current`, and
+/// a ship that was dead in `previous` isn't moved back from its respawn point.
+pub fn interpolate_world(previous: &World, current: &World, alpha: f32) -> World {
+    let mut world = current.clone();
+    if previous.ship_alive() && current.ship_alive() {
+        world.ship.position =
+            lerp_sim_position(previous.ship.position, current.ship.position, alpha);
+        world.ship.angle = lerp_sim_angle(previous.ship.angle, current.ship.angle, alpha);
+    }
+
+    let asteroids: HashMap<u32, _> = previous
+        .asteroids
+        .iter()
+        .map(|asteroid| (asteroid.id, asteroid))
+        .collect();
+    for asteroid in world.asteroids.iter_mut() {
+        if let Some(before) = asteroids.get(&asteroid.id) {
+            asteroid.position = lerp_sim_position(before.position, asteroid.position, alpha);
+            asteroid.angle = lerp_sim_angle(before.angle, asteroid.angle, alpha);
+        }
+    }
+
+    let bullets: HashMap<u32, _> = previous
+        .bullets
+        .iter()
+        .map(|bullet| (bullet.id, bullet))
+        .collect();
+    for bullet in world.bullets.iter_mut() {
+        if let Some(before) = bullets.get(&bullet.id) {
+            bullet.position = lerp_sim_position(before.position, bullet.position, alpha);
+        }
+    }
+    world
+}
@@ -0,0 +1,279 @@
+//! Automatic layout for the UI, like a small flexbox: rows and columns of boxes with spacing,
+//! padding, stretch factors and min/max sizes. Screens describe what goes next to what (see
+//! crate::screen) and [`layout`] works out where everything goes for the window's size and
+//! scale, instead of every screen placing text at hand picked pixel coordinates.
+//!
+//! Sizes are in whatever unit the caller uses, the screens use physical pixels like the text
+//! renderer.
+
+/// Which way a container lays out its children.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Direction {
+    /// Left to right
+    #[default]
+    Row,
+    /// Top to bottom
+    Column,
+}
+
+/// Where a container puts its children across its direction (vertically in a row,
+/// horizontally in a column).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Start,
+    Center,
+    End,
+    /// As wide (or tall) as the container, within the child's own min and max
+    Stretch,
+}
+
+/// A box in a layout: a leaf with the size of its content (e.g. a line of text), or a row or
+/// column of boxes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutNode {
+    pub direction: Direction,
+    pub children: Vec<LayoutNode>,
+    /// Leaves get a rectangle from [`layout`], containers and spacers don't
+    pub leaf: bool,
+    /// Containers with a frame get a rectangle too (before their children's), e.g. for a
+    /// background behind them
+    pub framed: bool,
+    /// Size of a leaf's content. Containers are as large as their children.
+    pub size: [f32; 2],
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    /// Share of the space left over in the parent (along the parent's direction), relative
+    /// to the other children. 0 keeps the box at its own size.
+    pub stretch: f32,
+    /// Between the children of a container
+    pub spacing: f32,
+    /// Around the children of a container
+    pub padding: f32,
+    pub align: Align,
+}
+
+impl LayoutNode {
+    fn new(direction: Direction, children: Vec<LayoutNode>, leaf: bool) -> Self {
+        Self {
+            direction,
+            children,
+            leaf,
+            framed: false,
+            size: [0.0, 0.0],
+            min: [0.0, 0.0],
+            max: [f32::INFINITY, f32::INFINITY],
+            stretch: 0.0,
+            spacing: 0.0,
+            padding: 0.0,
+            align: Align::Start,
+        }
+    }
+
+    pub fn row(children: Vec<LayoutNode>) -> Self {
+        Self::new(Direction::Row, children, false)
+    }
+
+    pub fn column(children: Vec<LayoutNode>) -> Self {
+        Self::new(Direction::Column, children, false)
+    }
+
+    /// A box with content of `size`.
+    pub fn leaf(size: [f32; 2]) -> Self {
+        Self {
+            size,
+            ..Self::new(Direction::Row, Vec::new(), true)
+        }
+    }
+
+    /// Empty space that takes up the space left over, e.g. to push the boxes after it to the
+    /// end of a row.
+    pub fn spacer() -> Self {
+        Self::new(Direction::Row, Vec::new(), false).stretch(1.0)
+    }
+
+    /// Empty space of a fixed size.
+    pub fn gap(size: f32) -> Self {
+        Self {
+            size: [size, size],
+            ..Self::new(Direction::Row, Vec::new(), false)
+        }
+    }
+
+    pub fn stretch(mut self, stretch: f32) -> Self {
+        self.stretch = stretch;
+        self
+    }
+
+    pub fn spacing(mut self, spacing: f32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn padding(mut self, padding: f32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn min(mut self, min: [f32; 2]) -> Self {
+        self.min = min;
+        self
+    }
+
+    pub fn max(mut self, max: [f32; 2]) -> Self {
+        self.max = max;
+        self
+    }
+
+    /// Gives the container a rectangle, see [`LayoutNode::framed`].
+    pub fn framed(mut self) -> Self {
+        self.framed = true;
+        self
+    }
+
+    fn is_container(&self) -> bool {
+        !self.leaf && !self.children.is_empty()
+    }
+
+    /// The size the box wants: its content's, or its children's with the spacing and
+    /// padding, within its min and max.
+    pub fn preferred_size(&self) -> [f32; 2] {
+        let size = if self.is_container() {
+            let main = main_axis(self.direction);
+            let sizes: Vec<[f32; 2]> = self.children.iter().map(Self::preferred_size).collect();
+            let mut size = [0.0; 2];
+            size[main] = sizes.iter().map(|size| size[main]).sum::<f32>()
+                + self.spacing * (sizes.len() - 1) as f32;
+            size[1 - main] = sizes.iter().map(|size| size[1 - main]).fold(0.0, f32::max);
+            size.map(|size| size + 2.0 * self.padding)
+        } else {
+            self.size
+        };
+        std::array::from_fn(|i| size[i].clamp(self.min[i], self.max[i].max(self.min[i])))
+    }
+}
+
+fn main_axis(direction: Direction) -> usize {
+    match direction {
+        Direction::Row => 0,
+        Direction::Column => 1,
+    }
+}
+
+/// Lays `root` out in `rect` (left, top, right and bottom edges). Returns the rectangles of
+/// the leaves, depth first (in the order they were added).
+pub fn layout(root: &LayoutNode, rect: [f32; 4]) -> Vec<[f32; 4]> {
+    let mut rects = Vec::new();
+    arrange(root, rect, &mut rects);
+    rects
+}
+
+fn arrange(node: &LayoutNode, rect: [f32; 4], rects: &mut Vec<[f32; 4]>) {
+    if node.leaf {
+        rects.push(rect);
+        return;
+    }
+    if node.framed {
+        rects.push(rect);
+    }
+    if !node.is_container() {
+        return;
+    }
+    let main = main_axis(node.direction);
+    let cross = 1 - main;
+    let inner = [
+        rect[0] + node.padding,
+        rect[1] + node.padding,
+        rect[2] - node.padding,
+        rect[3] - node.padding,
+    ];
+    let inner_size = [inner[2] - inner[0], inner[3] - inner[1]];
+    let preferred: Vec<[f32; 2]> = node
+        .children
+        .iter()
+        .map(LayoutNode::preferred_size)
+        .collect();
+    let mut mains: Vec<f32> = preferred.iter().map(|size| size[main]).collect();
+    let available = inner_size[main]
+        - node.spacing * (node.children.len() - 1) as f32
+        - mains.iter().sum::<f32>();
+
+    if available > 0.0 {
+        // hand out the space by stretch factor, again and again as children reach their max
+        let mut left = available;
+        let mut growing: Vec<bool> = node
+            .children
+            .iter()
+            .map(|child| child.stretch > 0.0)
+            .collect();
+        while left > 0.01 {
+            let total: f32 = node
+                .children
+                .iter()
+                .zip(&growing)
+                .filter(|(_, growing)| **growing)
+                .map(|(child, _)| child.stretch)
+                .sum();
+            if total <= 0.0 {
+                break;
+            }
+            let share = left;
+            for (i, child) in node.children.iter().enumerate() {
+                if !growing[i] {
+                    continue;
+                }
+                let grown = (mains[i] + share * child.stretch / total).min(child.max[main]);
+                left -= grown - mains[i];
+                mains[i] = grown;
+                if grown >= child.max[main] {
+                    growing[i] = false;
+                }
+            }
+            if !growing.iter().any(|growing| *growing) || left >= share {
+                break;
+            }
+        }
+    } else if available < 0.0 {
+        // shrink what can shrink, in proportion to how far it can
+        let shrinkable: Vec<f32> = node
+            .children
+            .iter()
+            .zip(&mains)
+            .map(|(child, main_size)| (main_size - child.min[main]).max(0.0))
+            .collect();
+        let total: f32 = shrinkable.iter().sum();
+        if total > 0.0 {
+            let shrink = (-available).min(total);
+            for (main_size, shrinkable) in mains.iter_mut().zip(&shrinkable) {
+                *main_size -= shrink * shrinkable / total;
+            }
+        }
+    }
+
+    let mut position = inner[main];
+    for ((child, preferred), main_size) in node.children.iter().zip(&preferred).zip(&mains) {
+        let cross_size = match node.align {
+            Align::Stretch => {
+                inner_size[cross].clamp(child.min[cross], child.max[cross].max(child.min[cross]))
+            }
+            _ => preferred[cross],
+        };
+        let cross_position = match node.align {
+            Align::Start | Align::Stretch => inner[cross],
+            Align::Center => inner[cross] + (inner_size[cross] - cross_size) / 2.0,
+            Align::End => inner[cross + 2] - cross_size,
+        };
+        let mut child_rect = [0.0; 4];
+        child_rect[main] = position;
+        child_rect[main + 2] = position + main_size;
+        child_rect[cross] = cross_position;
+        child_rect[cross + 2] = cross_position + cross_size;
+        arrange(child, child_rect, rects);
+        position += main_size + node.spacing;
+    }
+}
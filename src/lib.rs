@@ -0,0 +1,55 @@
+//! A small 2D game engine: a wgpu renderer with a text/glyph pipeline and debug lines,
+//! polled keyboard input, and an [`Engine`] that ties them to a winit window.
+//!
+//! See [`engine`] for how to set up and run a game.
+
+pub mod assets;
+pub mod asteroid;
+pub mod benchmark;
+pub mod bug_report;
+pub mod camera;
+pub mod collision;
+pub mod config;
+pub mod crash;
+pub mod diagnose;
+pub mod engine;
+pub mod error;
+pub mod events;
+pub mod file_formats;
+pub mod font_browser;
+pub mod frame_pacing;
+pub mod game_state;
+pub mod gfx;
+pub mod heat_map;
+pub mod high_scores;
+pub mod i18n;
+pub mod input;
+pub mod interpolation;
+pub mod layout;
+pub mod log_panel;
+pub mod logging;
+pub mod math;
+pub mod memory;
+pub mod net;
+#[cfg_attr(windows, path = "os/windows/mod.rs")]
+#[cfg_attr(not(windows), path = "os/other/mod.rs")]
+mod os;
+pub mod palette;
+pub mod paths;
+pub mod radar;
+pub mod replay;
+pub mod rng;
+pub mod save;
+pub mod screen;
+pub mod scroll;
+pub mod ship;
+pub mod tick_rate;
+pub mod time_scale;
+pub mod time_travel;
+pub mod ui;
+pub mod window;
+pub mod world;
+
+pub use engine::{Engine, EngineBuilder, System};
+pub use game_state::GameState;
+pub use window::{FullscreenMode, WindowSettings};
@@ -0,0 +1,174 @@
+//! The log panel (F2): the end of the session log (see [`crate::logging::recent_lines`])
+//! over the bottom of the screen, with warnings and errors in color, to see what happened
+//! without leaving the game. The mouse
+//! wheel and touchpads (over the panel), Page Up/Down and Home/End scroll it smoothly (see
+//! crate::scroll), and it follows new lines while it's scrolled to the end.
+
+use device_query::Keycode;
+use log::Level;
+
+use crate::gfx::Layer;
+use crate::gfx::text::{FontRef, TextDraw, TextOverflow, line_height};
+use crate::input::{KeyboardState, MouseState};
+use crate::logging;
+use crate::palette::Palette;
+use crate::scroll::SmoothScroll;
+
+// font size in logical pixels per em
+const TEXT_SIZE: f32 = 14.0;
+// warnings and errors are in the palette's colors
+const TEXT_COLOR: [f32; 4] = [0.85, 0.85, 0.85, 1.0];
+// longer lines are cut off
+const MAX_LINE_CHARS: usize = 160;
+// lines scrolled per mouse wheel notch
+const WHEEL_LINES: f32 = 3.0;
+// in logical pixels
+const MARGIN: f32 = 16.0;
+const PADDING: f32 = 8.0;
+// of the window's height
+const HEIGHT: f32 = 0.4;
+
+#[derive(Debug, Clone, Default)]
+pub struct LogPanel {
+    pub visible: bool,
+    // with their record's level
+    lines: Vec<(String, Level)>,
+    // in physical pixels
+    rect: [f32; 4],
+    line_height: f32,
+    padding: f32,
+    scroll: SmoothScroll,
+}
+
+impl LogPanel {
+    /// Opening the panel shows the latest lines.
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.scroll = SmoothScroll::default();
+    }
+
+    /// Left, top, right and bottom edges of the panel in physical pixels from the top left of
+    /// the window, as of the last update.
+    pub fn rect(&self) -> [f32; 4] {
+        self.rect
+    }
+
+    pub fn scroll(&self) -> &SmoothScroll {
+        &self.scroll
+    }
+
+    /// Takes the new log lines and scrolls. `window_size` is in physical pixels, `dt` is the
+    /// time since the last update in seconds.
+    pub(crate) fn update(
+        &mut self,
+        font: Option<FontRef<'_>>,
+        keyboard: &KeyboardState,
+        mouse: &MouseState,
+        window_size: [f32; 2],
+        scale_factor: f32,
+        dt: f32,
+    ) {
+        if !self.visible {
+            return;
+        }
+        self.lines = logging::recent_lines()
+            .iter()
+            .flat_map(|record| {
+                let level = level(record);
+                record
+                    .lines()
+                    .map(move |line| (line.chars().take(MAX_LINE_CHARS).collect(), level))
+            })
+            .collect();
+        let margin = MARGIN * scale_factor;
+        self.padding = PADDING * scale_factor;
+        self.rect = [
+            margin,
+            window_size[1] * (1.0 - HEIGHT),
+            window_size[0] - margin,
+            window_size[1] - margin,
+        ];
+        let font_size = TEXT_SIZE * scale_factor;
+        self.line_height = font.map_or(font_size * 1.2, |font| {
+            line_height(&font, skrifa::instance::Size::new(font_size))
+        });
+        let view_height = (self.rect[3] - self.rect[1] - 2.0 * self.padding).max(0.0);
+        self.scroll
+            .set_extent(self.lines.len() as f32 * self.line_height, view_height);
+        self.scroll.update(dt);
+
+        let pressed = |key_code| keyboard.is_pressed(key_code);
+        let page = (view_height - self.line_height).max(self.line_height);
+        if pressed(Keycode::PageUp) {
+            self.scroll.scroll_to(self.scroll.destination() - page);
+        }
+        if pressed(Keycode::PageDown) {
+            self.scroll.scroll_to(self.scroll.destination() + page);
+        }
+        if pressed(Keycode::Home) {
+            self.scroll.scroll_to(0.0);
+        }
+        if pressed(Keycode::End) {
+            self.scroll.scroll_to(self.scroll.max_offset());
+        }
+        let [left, top, right, bottom] = self.rect;
+        if mouse
+            .position()
+            .is_some_and(|[x, y]| (left..right).contains(&x) && (top..bottom).contains(&y))
+        {
+            // scrolling up moves the view towards the top
+            if mouse.scroll_lines() != 0.0 {
+                self.scroll
+                    .fling(-mouse.scroll_lines() * WHEEL_LINES * self.line_height);
+            }
+            if mouse.scroll_pixels() != 0.0 {
+                self.scroll.drag(-mouse.scroll_pixels(), dt);
+            }
+        }
+    }
+
+    /// The lines in view, scrolled to where they are at `alpha` between the last two updates.
+    /// The text renderer can't clip, so lines that are partly out of view are left out.
+    pub fn text(&self, alpha: f32, palette: &Palette) -> Vec<TextDraw> {
+        if !self.visible || self.line_height <= 0.0 {
+            return Vec::new();
+        }
+        let offset = self.scroll.interpolated_offset(alpha);
+        let top = self.rect[1] + self.padding;
+        let bottom = self.rect[3] - self.padding;
+        let first = (offset / self.line_height).ceil().max(0.0) as usize;
+        self.lines
+            .iter()
+            .enumerate()
+            .skip(first)
+            .map(|(i, line)| (top + i as f32 * self.line_height - offset, line))
+            .take_while(|(y, _)| y + self.line_height <= bottom)
+            .map(|(y, (line, level))| {
+                let color = match level {
+                    Level::Error => palette.error,
+                    Level::Warn => palette.warning,
+                    _ => TEXT_COLOR,
+                };
+                TextDraw::new(line.as_str(), [self.rect[0] + self.padding, y], TEXT_SIZE)
+                    .color(color)
+                    .layer(Layer::Console)
+                    .truncate(
+                        self.rect[2] - self.rect[0] - 2.0 * self.padding,
+                        TextOverflow::Ellipsis,
+                    )
+            })
+            .collect()
+    }
+}
+
+// warnings and errors stand out, by the level in the record's prefix (see crate::logging)
+fn level(record: &str) -> Level {
+    let prefix = record.split(']').next().unwrap_or_default();
+    if prefix.split_whitespace().any(|word| word == "ERROR") {
+        Level::Error
+    } else if prefix.split_whitespace().any(|word| word == "WARN") {
+        Level::Warn
+    } else {
+        Level::Info
+    }
+}
@@ -0,0 +1,308 @@
+//! Logging to stderr and to a log file per session.
+//!
+//! stderr works like before (env_logger, configured with `RUST_LOG`). In addition every
+//! session writes to its own file in the platform's log directory:
+//!
+//! - `session-<unix time>.log` is the current file. When it grows past
+//!   `LogSettings::max_file_size` it is renamed to `session-<unix time>.1.log` (the older
+//!   ones shift to `.2`, `.3`...) and a new file is started.
+//! - Every file starts with a header with system info, plus whatever was added with
+//!   [`header_info`] (e.g. the graphics adapter), so each file is useful on its own.
+//! - Only the last `LogSettings::max_sessions` sessions are kept.
+//!
+//! [`session_log_path`] is the file to attach to crash reports (see crate::crash),
+//! [`recent_lines`] has the end of it in memory.
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use log::{LevelFilter, Log, Metadata, Record};
+use parking_lot::{Mutex, const_mutex};
+
+use crate::paths;
+
+// Log targets of the subsystems, used instead of the module path so one filter covers a
+// subsystem across modules, e.g. `RUST_LOG=font_cache=debug` (the log file and the log panel
+// show the target too)
+pub const GFX: &str = "gfx";
+pub const FONT_CACHE: &str = "font_cache";
+pub const INPUT: &str = "input";
+pub const ASSETS: &str = "assets";
+pub const NET: &str = "net";
+pub const FRAME_PACING: &str = "frame_pacing";
+
+#[derive(Debug, Clone)]
+pub struct LogSettings {
+    /// Name of the log directory (inside the platform's log directory)
+    pub app_name: String,
+    /// Overrides the platform's log directory
+    pub directory: Option<PathBuf>,
+    /// Most verbose level written to the file (stderr uses RUST_LOG)
+    pub file_level: LevelFilter,
+    /// Size in bytes after which the session's file is rotated
+    pub max_file_size: u64,
+    /// Number of rotated files kept per session, besides the current one
+    pub max_rotated_files: usize,
+    /// Number of sessions (including this one) whose log files are kept
+    pub max_sessions: usize,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            app_name: paths::APP_NAME.to_string(),
+            directory: None,
+            file_level: LevelFilter::Info,
+            max_file_size: 4 * 1024 * 1024,
+            max_rotated_files: 3,
+            max_sessions: 10,
+        }
+    }
+}
+
+struct SessionFile {
+    directory: PathBuf,
+    stem: String,
+    writer: BufWriter<File>,
+    size: u64,
+    header: Vec<String>,
+    max_file_size: u64,
+    max_rotated_files: usize,
+}
+
+impl SessionFile {
+    fn path(&self) -> PathBuf {
+        self.directory.join(format!("{}.log", self.stem))
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        self.directory.join(format!("{}.{}.log", self.stem, index))
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if self.size > 0
+            && self.size + line.len() as u64 + 1 > self.max_file_size
+            && let Err(error) = self.rotate()
+        {
+            eprintln!("failed to rotate the log file: {}", error);
+        }
+        if writeln!(self.writer, "{}", line).is_ok() {
+            self.size += line.len() as u64 + 1;
+        }
+    }
+
+    fn write_header(&mut self) {
+        for line in self.header.clone() {
+            self.write_line(&line);
+        }
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.writer.flush()?;
+        if self.max_rotated_files == 0 {
+            fs::remove_file(self.path())?;
+        } else {
+            let _ = fs::remove_file(self.rotated_path(self.max_rotated_files));
+            for index in (1..self.max_rotated_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(self.path(), self.rotated_path(1))?;
+        }
+        self.writer = BufWriter::new(File::create(self.path())?);
+        self.size = 0;
+        self.write_header();
+        Ok(())
+    }
+}
+
+struct Logger {
+    stderr: env_logger::Logger,
+    file_level: LevelFilter,
+    start: Instant,
+}
+
+static SESSION_FILE: OnceLock<Mutex<SessionFile>> = OnceLock::new();
+
+const RECENT_LINES: usize = 500;
+// the last lines that went to the file
+static RECENT: Mutex<VecDeque<String>> = const_mutex(VecDeque::new());
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.stderr.enabled(metadata) || metadata.level() <= self.file_level
+    }
+
+    fn log(&self, record: &Record) {
+        if self.stderr.matches(record) {
+            self.stderr.log(record);
+        }
+        if record.level() > self.file_level {
+            return;
+        }
+        let line = format!(
+            "[{:>9.3}s {:<5} {}] {}",
+            self.start.elapsed().as_secs_f64(),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        if let Some(file) = SESSION_FILE.get() {
+            file.lock().write_line(&line);
+        }
+        let mut recent = RECENT.lock();
+        if recent.len() == RECENT_LINES {
+            recent.pop_front();
+        }
+        recent.push_back(line);
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+        if let Some(file) = SESSION_FILE.get() {
+            let _ = file.lock().writer.flush();
+        }
+    }
+}
+
+// deletes the files of all but the newest `keep` sessions (file names sort by session start)
+fn remove_old_sessions(directory: &Path, keep: usize) -> std::io::Result<()> {
+    let mut sessions: Vec<(String, PathBuf)> = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if let Some(rest) = name.strip_prefix("session-")
+            && name.ends_with(".log")
+        {
+            let stem = rest.split('.').next().unwrap_or_default().to_string();
+            sessions.push((stem, path));
+        }
+    }
+    let mut stems: Vec<&String> = sessions.iter().map(|(stem, _)| stem).collect();
+    // unix times, so the longer ones are newer
+    stems.sort_by(|a, b| (a.len(), a).cmp(&(b.len(), b)));
+    stems.dedup();
+    let removed: Vec<String> = stems
+        .iter()
+        .take(stems.len().saturating_sub(keep))
+        .map(|stem| stem.to_string())
+        .collect();
+    for (stem, path) in sessions.iter() {
+        if removed.contains(stem) {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+fn system_header(settings: &LogSettings, unix_time: u64) -> Vec<String> {
+    vec![
+        format!(
+            "{} {} session started at unix time {}",
+            settings.app_name,
+            env!("CARGO_PKG_VERSION"),
+            unix_time
+        ),
+        format!(
+            "system: {} {} ({}), {} logical cpus",
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            std::env::consts::FAMILY,
+            std::thread::available_parallelism().map_or(0, |count| count.get())
+        ),
+    ]
+}
+
+fn open_session_file(settings: &LogSettings) -> Result<SessionFile> {
+    let directory = settings
+        .directory
+        .clone()
+        .or_else(|| paths::log_dir(&settings.app_name))
+        .ok_or_else(|| anyhow::anyhow!("no log directory for this platform"))?;
+    fs::create_dir_all(&directory)?;
+
+    let unix_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    // two sessions started in the same second get different files
+    let mut stem = format!("session-{}", unix_time);
+    let mut suffix = 1;
+    while directory.join(format!("{}.log", stem)).exists() {
+        stem = format!("session-{}_{}", unix_time, suffix);
+        suffix += 1;
+    }
+    let writer = BufWriter::new(File::create(directory.join(format!("{}.log", stem)))?);
+    remove_old_sessions(&directory, settings.max_sessions.max(1))?;
+
+    let mut file = SessionFile {
+        directory,
+        stem,
+        writer,
+        size: 0,
+        header: system_header(settings, unix_time),
+        max_file_size: settings.max_file_size,
+        max_rotated_files: settings.max_rotated_files,
+    };
+    file.write_header();
+    Ok(file)
+}
+
+/// Installs the logger. Fails if a logger is already installed. Not being able to create
+/// the log file only logs a warning, stderr logging works regardless.
+pub fn init(settings: LogSettings) -> Result<()> {
+    let stderr = env_logger::Builder::from_default_env().build();
+    let max_level = stderr.filter().max(settings.file_level);
+    let logger = Logger {
+        stderr,
+        file_level: settings.file_level,
+        start: Instant::now(),
+    };
+    log::set_boxed_logger(Box::new(logger))?;
+    log::set_max_level(max_level);
+
+    match open_session_file(&settings) {
+        Ok(file) => {
+            let path = file.path();
+            let _ = SESSION_FILE.set(Mutex::new(file));
+            log::info!("logging to {}", path.display());
+        }
+        Err(error) => log::warn!("not logging to a file: {}", error),
+    }
+    Ok(())
+}
+
+/// Logs `line` (at info level) and adds it to the header of the session's log files, so
+/// it's at the top of files that get started after rotating too.
+pub fn header_info(line: String) {
+    log::info!("{}", line);
+    if let Some(file) = SESSION_FILE.get() {
+        file.lock().header.push(line);
+    }
+}
+
+/// The current log file of this session, if logging to a file (e.g. to attach to crash
+/// reports).
+pub fn session_log_path() -> Option<PathBuf> {
+    SESSION_FILE.get().map(|file| file.lock().path())
+}
+
+/// The last (up to 500) lines logged at the file's level, oldest first. Empty if [`init`]
+/// wasn't called.
+pub fn recent_lines() -> Vec<String> {
+    RECENT.lock().iter().cloned().collect()
+}
+
+/// Like [`recent_lines`], but None instead of waiting while they're locked, e.g. for the
+/// panic hook when the panic happened while logging.
+pub fn try_recent_lines() -> Option<Vec<String>> {
+    Some(RECENT.try_lock()?.iter().cloned().collect())
+}
@@ -0,0 +1,409 @@
+//! Determinism-safe math for the simulation layer.
+//!
+//! Simulation code (anything that has to replay identically, or run in lockstep with
+//! another machine) uses [`Scalar`] and [`SimVec2`] instead of raw `f32`/cgmath:
+//!
+//! - by default `Scalar` is `f32`, restricted to operations that are exactly specified by
+//!   IEEE 754 (+, -, *, /, sqrt, floor). Rust never contracts these into fused
+//!   multiply-adds or reorders them (no fast-math), so they give the same bits everywhere.
+//!   `sin`/`cos` come from a lookup table that is computed at compile time instead of the
+//!   platform's libm, which differs between platforms.
+//! - with the `fixed-point` feature `Scalar` is [`Fixed`], a 32.32 fixed-point number that
+//!   only uses integer math.
+//!
+//! Rendering can keep using plain floats, convert with [`Real::to_f32`].
+
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "fixed-point")]
+pub type Scalar = Fixed;
+#[cfg(not(feature = "fixed-point"))]
+pub type Scalar = f32;
+
+pub type SimVec2 = Vec2<Scalar>;
+
+/// Scalar operations the simulation is allowed to use.
+pub trait Real:
+    Copy
+    + Debug
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn from_f32(value: f32) -> Self;
+    fn to_f32(self) -> f32;
+    fn from_int(value: i32) -> Self;
+    /// The raw bits, for hashing/comparing simulation state
+    fn to_bits(self) -> u64;
+
+    // (not called sin/cos, so f32's own (libm) methods can't be picked up by accident)
+    /// Sine of an angle in radians, from the lookup table
+    fn table_sin(self) -> Self;
+    /// Cosine of an angle in radians, from the lookup table
+    fn table_cos(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn abs(self) -> Self;
+    fn floor(self) -> Self;
+    /// A total order, for sorting. Unlike `partial_cmp` it has a place for NaN (f32) too.
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+// Number of table entries per full turn. The table has one extra entry (== the first) so
+// interpolation never has to wrap.
+const SIN_TABLE_BITS: u32 = 10;
+const SIN_TABLE_SIZE: usize = 1 << SIN_TABLE_BITS;
+
+const fn const_sin(x: f64) -> f64 {
+    // x is in [-pi, pi], where 30 terms of the taylor series are far more precise than f64
+    let mut term = x;
+    let mut sum = x;
+    let mut n = 1;
+    while n < 30 {
+        term = -term * x * x / ((2 * n) as f64 * (2 * n + 1) as f64);
+        sum += term;
+        n += 1;
+    }
+    sum
+}
+
+const fn sin_table_f64() -> [f64; SIN_TABLE_SIZE + 1] {
+    let mut table = [0.0; SIN_TABLE_SIZE + 1];
+    let mut i = 0;
+    while i <= SIN_TABLE_SIZE {
+        let mut angle = i as f64 / SIN_TABLE_SIZE as f64 * std::f64::consts::TAU;
+        if angle > std::f64::consts::PI {
+            angle -= std::f64::consts::TAU;
+        }
+        table[i] = const_sin(angle);
+        i += 1;
+    }
+    table
+}
+
+// Computed by the compiler, so the values don't depend on the target's libm
+const SIN_TABLE_F64: [f64; SIN_TABLE_SIZE + 1] = sin_table_f64();
+
+const SIN_TABLE_F32: [f32; SIN_TABLE_SIZE + 1] = {
+    let mut table = [0.0; SIN_TABLE_SIZE + 1];
+    let mut i = 0;
+    while i <= SIN_TABLE_SIZE {
+        table[i] = SIN_TABLE_F64[i] as f32;
+        i += 1;
+    }
+    table
+};
+
+const SIN_TABLE_FIXED: [i64; SIN_TABLE_SIZE + 1] = {
+    let mut table = [0; SIN_TABLE_SIZE + 1];
+    let mut i = 0;
+    while i <= SIN_TABLE_SIZE {
+        table[i] = (SIN_TABLE_F64[i] * Fixed::ONE_BITS as f64) as i64;
+        i += 1;
+    }
+    table
+};
+
+fn table_sin_f32(radians: f32) -> f32 {
+    let turns = radians * (1.0 / std::f32::consts::TAU);
+    let position = (turns - turns.floor()) * SIN_TABLE_SIZE as f32;
+    let index = (position as usize).min(SIN_TABLE_SIZE - 1);
+    let t = position - index as f32;
+    let a = SIN_TABLE_F32[index];
+    let b = SIN_TABLE_F32[index + 1];
+    a + (b - a) * t
+}
+
+impl Real for f32 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn from_int(value: i32) -> Self {
+        value as f32
+    }
+
+    fn to_bits(self) -> u64 {
+        f32::to_bits(self) as u64
+    }
+
+    fn table_sin(self) -> Self {
+        table_sin_f32(self)
+    }
+
+    fn table_cos(self) -> Self {
+        table_sin_f32(self + std::f32::consts::FRAC_PI_2)
+    }
+
+    fn sqrt(self) -> Self {
+        // correctly rounded per IEEE 754, unlike the trig functions
+        f32::sqrt(self)
+    }
+
+    fn abs(self) -> Self {
+        f32::abs(self)
+    }
+
+    fn floor(self) -> Self {
+        f32::floor(self)
+    }
+
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        f32::total_cmp(self, other)
+    }
+}
+
+/// Signed 32.32 fixed-point number.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Fixed(i64);
+
+impl Fixed {
+    const FRACTION_BITS: u32 = 32;
+    const ONE_BITS: i64 = 1 << Self::FRACTION_BITS;
+    const INV_TAU: Fixed = Fixed((Self::ONE_BITS as f64 / std::f64::consts::TAU) as i64);
+    const QUARTER_TURN: Fixed = Fixed((Self::ONE_BITS as f64 * std::f64::consts::FRAC_PI_2) as i64);
+
+    pub const fn from_bits(bits: i64) -> Self {
+        Self(bits)
+    }
+
+    fn lookup_sin(self) -> Self {
+        // the fractional part of the angle in turns is the position in the table, also for
+        // negative angles (two's complement)
+        let turns = self * Self::INV_TAU;
+        let fraction = turns.0 as u64 & (Self::ONE_BITS as u64 - 1);
+        let index_shift = Self::FRACTION_BITS - SIN_TABLE_BITS;
+        let index = (fraction >> index_shift) as usize;
+        let t = (fraction & ((1 << index_shift) - 1)) as i64;
+        let a = SIN_TABLE_FIXED[index];
+        let b = SIN_TABLE_FIXED[index + 1];
+        Self(a + (((b - a) as i128 * t as i128) >> index_shift) as i64)
+    }
+}
+
+impl Add for Fixed {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_add(rhs.0))
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_sub(rhs.0))
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self(((self.0 as i128 * rhs.0 as i128) >> Self::FRACTION_BITS) as i64)
+    }
+}
+
+/// Dividing by zero doesn't panic: like f32's infinities the result saturates to the largest
+/// number of the dividend's sign, and 0 / 0 (f32's NaN) is 0.
+impl Div for Fixed {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        if rhs.0 == 0 {
+            return Self(match self.0.signum() {
+                1 => i64::MAX,
+                -1 => i64::MIN,
+                _ => 0,
+            });
+        }
+        Self((((self.0 as i128) << Self::FRACTION_BITS) / rhs.0 as i128) as i64)
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self(self.0.wrapping_neg())
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign for Fixed {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl Real for Fixed {
+    const ZERO: Self = Self(0);
+    const ONE: Self = Self(Self::ONE_BITS);
+
+    fn from_f32(value: f32) -> Self {
+        Self((value as f64 * Self::ONE_BITS as f64) as i64)
+    }
+
+    fn to_f32(self) -> f32 {
+        (self.0 as f64 / Self::ONE_BITS as f64) as f32
+    }
+
+    fn from_int(value: i32) -> Self {
+        Self((value as i64) << Self::FRACTION_BITS)
+    }
+
+    fn to_bits(self) -> u64 {
+        self.0 as u64
+    }
+
+    fn table_sin(self) -> Self {
+        self.lookup_sin()
+    }
+
+    fn table_cos(self) -> Self {
+        (self + Self::QUARTER_TURN).lookup_sin()
+    }
+
+    fn sqrt(self) -> Self {
+        if self.0 <= 0 {
+            return Self::ZERO;
+        }
+        Self((((self.0 as u128) << Self::FRACTION_BITS).isqrt()) as i64)
+    }
+
+    fn abs(self) -> Self {
+        Self(self.0.wrapping_abs())
+    }
+
+    fn floor(self) -> Self {
+        Self(self.0 & !(Self::ONE_BITS - 1))
+    }
+
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        self.cmp(other)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vec2<T> {
+    pub x: T,
+    pub y: T,
+}
+
+impl<T: Real> Vec2<T> {
+    pub fn new(x: T, y: T) -> Self {
+        Self { x, y }
+    }
+
+    pub fn zero() -> Self {
+        Self::new(T::ZERO, T::ZERO)
+    }
+
+    /// Unit vector pointing at `radians` (0 = +x, counter-clockwise)
+    pub fn from_angle(radians: T) -> Self {
+        Self::new(radians.table_cos(), radians.table_sin())
+    }
+
+    pub fn scale(self, factor: T) -> Self {
+        Self::new(self.x * factor, self.y * factor)
+    }
+
+    pub fn dot(self, other: Self) -> T {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn length_squared(self) -> T {
+        self.dot(self)
+    }
+
+    pub fn length(self) -> T {
+        self.length_squared().sqrt()
+    }
+
+    pub fn to_f32(self) -> [f32; 2] {
+        [self.x.to_f32(), self.y.to_f32()]
+    }
+}
+
+impl<T: Real> Add for Vec2<T> {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl<T: Real> Sub for Vec2<T> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl<T: Real> AddAssign for Vec2<T> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<T: Real> SubAssign for Vec2<T> {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+/// Deterministic random numbers for the simulation (xorshift64*). The state is part of the
+/// simulated world, so replays and snapshots produce the same sequence.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimRng {
+    state: u64,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift gets stuck on zero
+        Self { state: seed.max(1) }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniformly distributed in [min, max)
+    pub fn range(&mut self, min: Scalar, max: Scalar) -> Scalar {
+        // 24 bits, so the conversion is exact for f32 as well
+        let unit = Scalar::from_int((self.next_u64() >> 40) as i32) / Scalar::from_int(1 << 24);
+        min + (max - min) * unit
+    }
+}
@@ -0,0 +1,159 @@
+//! Rough per-subsystem memory accounting, to spot caches and buffers that keep growing.
+//!
+//! Subsystems report the heap memory they own through [`HeapSize`] (allocated capacity, not
+//! just what's in use). [`MemoryReport`] collects those per subsystem; F3 shows it as a panel
+//! of bars (one per subsystem, the colors are listed in the log) and logs it regularly, see
+//! `GameState::memory_report` and `GfxState::add_memory_usage`.
+//!
+//! The numbers are estimates: allocator overhead and the internals of hash maps and
+//! third-party types are not counted.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Heap memory owned by a value, not including `size_of_val(self)`.
+pub trait HeapSize {
+    fn heap_size(&self) -> usize;
+}
+
+/// Allocated bytes of a Vec whose elements own no heap memory themselves.
+pub fn vec_size<T>(vec: &Vec<T>) -> usize {
+    vec.capacity() * std::mem::size_of::<T>()
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        vec_size(self) + self.iter().map(HeapSize::heap_size).sum::<usize>()
+    }
+}
+
+impl<T: HeapSize> HeapSize for VecDeque<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * std::mem::size_of::<T>()
+            + self.iter().map(HeapSize::heap_size).sum::<usize>()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryEntry {
+    pub name: &'static str,
+    pub bytes: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    pub entries: Vec<MemoryEntry>,
+}
+
+impl MemoryReport {
+    pub fn add(&mut self, name: &'static str, bytes: usize) {
+        self.entries.push(MemoryEntry { name, bytes });
+    }
+
+    pub fn total(&self) -> usize {
+        self.entries.iter().map(|entry| entry.bytes).sum()
+    }
+
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.bytes)
+    }
+}
+
+// e.g. 1.5 MiB
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.entries.iter().map(|entry| entry.name.len()).max();
+        for entry in self.entries.iter() {
+            writeln!(
+                f,
+                "{:<width$}  {:>10}",
+                entry.name,
+                format_bytes(entry.bytes),
+                width = width.unwrap_or(0)
+            )?;
+        }
+        write!(
+            f,
+            "{:<width$}  {:>10}",
+            "total",
+            format_bytes(self.total()),
+            width = width.unwrap_or(0)
+        )
+    }
+}
+
+/// Colors of the panel's bars, by entry index (with their names for the log).
+pub const PANEL_COLORS: [(&str, [f32; 4]); 9] = [
+    ("cyan", [0.2, 0.8, 0.9, 0.9]),
+    ("orange", [1.0, 0.6, 0.1, 0.9]),
+    ("green", [0.3, 0.9, 0.3, 0.9]),
+    ("magenta", [0.9, 0.3, 0.9, 0.9]),
+    ("yellow", [0.95, 0.9, 0.2, 0.9]),
+    ("blue", [0.3, 0.4, 1.0, 0.9]),
+    ("white", [0.9, 0.9, 0.9, 0.9]),
+    ("pink", [1.0, 0.5, 0.6, 0.9]),
+    ("red", [0.9, 0.2, 0.2, 0.9]),
+];
+
+// the panel is refreshed every second, logged every 10 seconds
+const LOG_INTERVAL: u64 = 10;
+
+/// State of the memory panel (F3): the latest report and the one before it, to show what
+/// grew.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryPanel {
+    pub visible: bool,
+    pub current: MemoryReport,
+    pub previous: MemoryReport,
+    refreshes: u64,
+}
+
+impl MemoryPanel {
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.refreshes = 0;
+        self.current = MemoryReport::default();
+        self.previous = MemoryReport::default();
+    }
+
+    /// Replaces the current report, logging it (with the bar colors) when the panel was just
+    /// opened and every 10 refreshes after that.
+    pub fn update(&mut self, report: MemoryReport) {
+        if self.refreshes.is_multiple_of(LOG_INTERVAL) {
+            let mut legend = String::new();
+            for (i, entry) in report.entries.iter().enumerate() {
+                let color = PANEL_COLORS[i % PANEL_COLORS.len()].0;
+                legend.push_str(&format!("\n  {} = {}", color, entry.name));
+            }
+            log::info!("memory usage:\n{}\npanel colors:{}", report, legend);
+        }
+        self.refreshes += 1;
+        self.previous = std::mem::replace(&mut self.current, report);
+    }
+
+    /// True if the entry got bigger since the previous report.
+    pub fn grew(&self, name: &str) -> bool {
+        match (self.current.get(name), self.previous.get(name)) {
+            (Some(current), Some(previous)) => current > previous,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,25 @@
+//! Multiplayer: ships dogfighting in an [`Arena`] over UDP. The [`Server`] (the `server`
+//! binary runs one headless) owns the arena and simulates it at a fixed tick rate. A
+//! [`Client`] sends its controls every tick and gets a [`Snapshot`] of the arena back.
+//!
+//! The server is authoritative, clients only predict their own ship: the ship from the last
+//! snapshot is moved on by the controls the server hasn't applied yet, so steering responds
+//! right away instead of a round trip later. Everything else (the other ships, the shots,
+//! hits) is drawn as the last snapshot has it.
+//!
+//! Messages are JSON (see [`protocol`]), a datagram each. Lost controls are sent again with
+//! the next ones until the server acknowledges them, lost snapshots are replaced by the next.
+
+pub mod arena;
+pub mod client;
+pub mod protocol;
+pub mod server;
+mod transport;
+
+pub use arena::{Arena, Fighter, PlayerId, Shot};
+pub use client::{Client, ConnectionState};
+pub use protocol::{ClientMessage, ServerMessage, Snapshot};
+pub use server::Server;
+
+/// The port servers listen on unless told otherwise
+pub const DEFAULT_PORT: u16 = 27450;
@@ -0,0 +1,191 @@
+//! The multiplayer simulation: a ship per player in the wrapping world, shooting each other.
+//! No asteroids, a hit destroys a ship and it respawns after a while. Like the
+//! [`World`](crate::world::World) it only uses the deterministic Scalar math.
+
+use serde::{Deserialize, Serialize};
+
+use crate::collision::{Collider, Shape};
+use crate::math::{Real, Scalar, SimVec2};
+use crate::ship::{BULLET_RADIUS, Bullet, Ship, ShipControls};
+
+const RESPAWN_SECONDS: f32 = 2.0;
+// where the players' ships (re)spawn, by player id, in world units
+const SPAWN_POINTS: [[f32; 2]; 4] = [
+    [360.0, 450.0],
+    [1080.0, 450.0],
+    [720.0, 225.0],
+    [720.0, 675.0],
+];
+
+/// Identifies a player for the lifetime of a server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PlayerId(pub u32);
+
+/// A player's ship and score.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fighter {
+    pub player: PlayerId,
+    pub name: String,
+    pub ship: Ship,
+    /// Ships of other players shot down
+    pub kills: u32,
+    pub deaths: u32,
+    /// Seconds until the ship respawns, None while it is alive
+    respawn_timer: Option<Scalar>,
+}
+
+/// A bullet and the player who fired it, who it can't hit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Shot {
+    pub owner: PlayerId,
+    pub bullet: Bullet,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Arena {
+    /// Number of ticks simulated so far
+    pub tick: u64,
+    /// Sorted by player, so they're updated in the same order everywhere
+    pub fighters: Vec<Fighter>,
+    pub shots: Vec<Shot>,
+    next_id: u32,
+}
+
+fn spawn_point(player: PlayerId) -> SimVec2 {
+    let [x, y] = SPAWN_POINTS[player.0 as usize % SPAWN_POINTS.len()];
+    SimVec2::new(Scalar::from_f32(x), Scalar::from_f32(y))
+}
+
+impl Fighter {
+    fn new(player: PlayerId, name: String) -> Self {
+        Self {
+            player,
+            name,
+            ship: Ship::new(spawn_point(player)),
+            kills: 0,
+            deaths: 0,
+            respawn_timer: None,
+        }
+    }
+
+    pub fn alive(&self) -> bool {
+        self.respawn_timer.is_none()
+    }
+}
+
+impl Arena {
+    /// Adds a ship for `player`, at its spawn point.
+    pub fn join(&mut self, player: PlayerId, name: String) {
+        if let Err(index) = self
+            .fighters
+            .binary_search_by_key(&player, |fighter| fighter.player)
+        {
+            self.fighters.insert(index, Fighter::new(player, name));
+        }
+    }
+
+    /// Removes the player's ship and its shots.
+    pub fn leave(&mut self, player: PlayerId) {
+        self.fighters.retain(|fighter| fighter.player != player);
+        self.shots.retain(|shot| shot.owner != player);
+    }
+
+    pub fn fighter(&self, player: PlayerId) -> Option<&Fighter> {
+        self.fighters
+            .iter()
+            .find(|fighter| fighter.player == player)
+    }
+
+    /// Advances the arena by one fixed tick of `dt` seconds. `controls` returns each player's
+    /// controls for the tick.
+    pub fn update(&mut self, controls: impl Fn(PlayerId) -> ShipControls, dt: Scalar) {
+        for fighter in self.fighters.iter_mut() {
+            let Some(timer) = fighter.respawn_timer.as_mut() else {
+                let controls = controls(fighter.player);
+                fighter.ship.update(controls, dt);
+                if controls.fire
+                    && let Some(bullet) = fighter.ship.fire(self.next_id)
+                {
+                    self.next_id += 1;
+                    self.shots.push(Shot {
+                        owner: fighter.player,
+                        bullet,
+                    });
+                }
+                continue;
+            };
+            *timer -= dt;
+            if *timer <= Scalar::ZERO {
+                fighter.ship = Ship::new(spawn_point(fighter.player));
+                fighter.respawn_timer = None;
+            }
+        }
+        self.shots.retain_mut(|shot| shot.bullet.update(dt));
+        self.collide();
+        self.tick += 1;
+    }
+
+    // shots hitting the ships of other players, in order, so the outcome only depends on the
+    // arena. A shot only hits one ship.
+    fn collide(&mut self) {
+        let ship_shape = Ship::collider_shape();
+        let bullet_shape = Shape::Circle {
+            radius: Scalar::from_f32(BULLET_RADIUS),
+        };
+        let mut hits: Vec<(usize, usize)> = Vec::new();
+        for (shot_index, shot) in self.shots.iter().enumerate() {
+            let bullet = Collider {
+                shape: &bullet_shape,
+                position: shot.bullet.position,
+                angle: Scalar::ZERO,
+            };
+            let hit = (0..self.fighters.len()).find(|index| {
+                let fighter = &self.fighters[*index];
+                fighter.alive()
+                    && fighter.player != shot.owner
+                    && !hits.iter().any(|(_, hit)| hit == index)
+                    && bullet.intersects(&Collider {
+                        shape: &ship_shape,
+                        position: fighter.ship.position,
+                        angle: fighter.ship.angle,
+                    })
+            });
+            if let Some(fighter) = hit {
+                hits.push((shot_index, fighter));
+            }
+        }
+
+        for (shot, fighter) in hits.iter().copied() {
+            let owner = self.shots[shot].owner;
+            if let Some(shooter) = self.fighters.iter_mut().find(|f| f.player == owner) {
+                shooter.kills += 1;
+            }
+            let fighter = &mut self.fighters[fighter];
+            fighter.deaths += 1;
+            fighter.respawn_timer = Some(Scalar::from_f32(RESPAWN_SECONDS));
+        }
+        let mut shot_index = 0..;
+        self.shots.retain(|_| {
+            let index = shot_index.next().unwrap_or_default();
+            !hits.iter().any(|(shot, _)| *shot == index)
+        });
+    }
+
+    /// Line segments (pairs of points, in world units) to draw the ships and shots with.
+    /// `ship` replaces a player's ship, e.g. with the one a client predicted.
+    pub fn line_segments(&self, ship: Option<(PlayerId, &Ship)>) -> Vec<[f32; 2]> {
+        let mut segments = Vec::new();
+        for fighter in self.fighters.iter().filter(|fighter| fighter.alive()) {
+            match ship {
+                Some((player, ship)) if player == fighter.player => {
+                    segments.extend(ship.line_segments())
+                }
+                _ => segments.extend(fighter.ship.line_segments()),
+            }
+        }
+        for shot in self.shots.iter() {
+            segments.extend(shot.bullet.line_segments());
+        }
+        segments
+    }
+}
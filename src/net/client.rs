@@ -0,0 +1,292 @@
+use std::collections::VecDeque;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+
+use super::arena::{Arena, PlayerId};
+use super::protocol::{
+    ChatLine, ClientMessage, MAX_CHAT_LENGTH, PROTOCOL_VERSION, ServerMessage, Snapshot, sanitize,
+};
+use super::transport::Transport;
+use crate::logging;
+use crate::ship::{Ship, ShipControls};
+use crate::tick_rate::TickRate;
+
+// how often the client asks to join until the server answers
+const HELLO_INTERVAL: Duration = Duration::from_millis(500);
+// a server that hasn't sent anything for this long is gone
+const SERVER_TIMEOUT: Duration = Duration::from_secs(5);
+// the most inputs sent again while the server doesn't acknowledge them, about a second
+const MAX_PENDING_INPUTS: usize = 64;
+// the chat lines kept to scroll back through
+const CHAT_SCROLLBACK: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Waiting for the server to answer
+    Connecting,
+    /// In the game as `player`, sending inputs at `tick_rate`
+    Connected {
+        player: PlayerId,
+        tick_rate: TickRate,
+    },
+    /// The server didn't let the client join
+    Refused(String),
+    /// The server stopped answering (or never did)
+    TimedOut,
+}
+
+/// A connection to a [`Server`](super::Server), see [`crate::net`]. Call [`Client::update`]
+/// once a tick, at the server's tick rate.
+pub struct Client {
+    transport: Transport,
+    server: SocketAddr,
+    name: String,
+    state: ConnectionState,
+    // the number of the next input
+    next_input: u64,
+    // sent, but not acknowledged yet
+    pending: VecDeque<(u64, ShipControls)>,
+    // the newest one
+    snapshot: Option<Snapshot>,
+    // the player's ship, with the pending inputs applied
+    predicted: Option<Ship>,
+    // the chat so far, and the player's messages the server hasn't acknowledged
+    chat: VecDeque<ChatLine>,
+    outgoing_chat: VecDeque<(u64, String)>,
+    next_chat_message: u64,
+    last_heard: Instant,
+    last_hello: Option<Instant>,
+}
+
+impl Client {
+    /// Starts joining the server at `address` (e.g. `127.0.0.1:27450`) as `name`.
+    pub fn connect(address: impl ToSocketAddrs, name: &str) -> Result<Self> {
+        let server = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow!("the server's address didn't resolve"))?;
+        let local: SocketAddr = if server.is_ipv4() {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        } else {
+            (Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        let transport = Transport::bind(local)?;
+        log::info!(target: logging::NET, "connecting to {}", server);
+        Ok(Self {
+            transport,
+            server,
+            name: name.to_string(),
+            state: ConnectionState::Connecting,
+            next_input: 0,
+            pending: VecDeque::new(),
+            snapshot: None,
+            predicted: None,
+            chat: VecDeque::new(),
+            outgoing_chat: VecDeque::new(),
+            next_chat_message: 0,
+            last_heard: Instant::now(),
+            last_hello: None,
+        })
+    }
+
+    pub fn server(&self) -> SocketAddr {
+        self.server
+    }
+
+    pub fn state(&self) -> &ConnectionState {
+        &self.state
+    }
+
+    /// The player the server let the client join as.
+    pub fn player(&self) -> Option<PlayerId> {
+        match self.state {
+            ConnectionState::Connected { player, .. } => Some(player),
+            _ => None,
+        }
+    }
+
+    /// The arena as of the newest snapshot.
+    pub fn arena(&self) -> Option<&Arena> {
+        self.snapshot.as_ref().map(|snapshot| &snapshot.arena)
+    }
+
+    /// The player's ship where the client predicts it is, None while it's destroyed.
+    pub fn predicted_ship(&self) -> Option<&Ship> {
+        self.predicted.as_ref()
+    }
+
+    /// The chat lines received so far (the last ones, to scroll back through), oldest first.
+    pub fn chat(&self) -> &VecDeque<ChatLine> {
+        &self.chat
+    }
+
+    /// Sends a chat message with the next updates, until the server gets it.
+    pub fn send_chat(&mut self, text: &str) {
+        let text = sanitize(text, MAX_CHAT_LENGTH);
+        if !text.is_empty() {
+            self.outgoing_chat.push_back((self.next_chat_message, text));
+            self.next_chat_message += 1;
+        }
+    }
+
+    /// Line segments (pairs of points, in world units) to draw the arena with, the player's
+    /// ship where it's predicted.
+    pub fn line_segments(&self) -> Vec<[f32; 2]> {
+        let ship = self.player().zip(self.predicted.as_ref());
+        self.arena()
+            .map(|arena| arena.line_segments(ship))
+            .unwrap_or_default()
+    }
+
+    /// Handles what the server sent, then sends it `controls` as the next input and predicts
+    /// the player's ship with them.
+    pub fn update(&mut self, controls: ShipControls, now: Instant) {
+        for (from, message) in self.transport.receive::<ServerMessage>() {
+            if from != self.server {
+                continue;
+            }
+            self.last_heard = now;
+            self.handle(message);
+        }
+        if now.saturating_duration_since(self.last_heard) > SERVER_TIMEOUT
+            && matches!(
+                self.state,
+                ConnectionState::Connecting | ConnectionState::Connected { .. }
+            )
+        {
+            log::warn!(target: logging::NET, "{} stopped answering", self.server);
+            self.state = ConnectionState::TimedOut;
+            self.predicted = None;
+        }
+
+        match self.state.clone() {
+            ConnectionState::Connecting => {
+                if self
+                    .last_hello
+                    .is_none_or(|last| now.saturating_duration_since(last) >= HELLO_INTERVAL)
+                {
+                    let hello = ClientMessage::Hello {
+                        version: PROTOCOL_VERSION,
+                        name: self.name.clone(),
+                    };
+                    self.transport.send(&hello, self.server);
+                    self.last_hello = Some(now);
+                }
+            }
+            ConnectionState::Connected { player, tick_rate } => {
+                self.pending.push_back((self.next_input, controls));
+                self.next_input += 1;
+                while self.pending.len() > MAX_PENDING_INPUTS {
+                    self.pending.pop_front();
+                }
+                let first = self.pending.front().map_or(0, |(input, _)| *input);
+                let input = ClientMessage::Input {
+                    first,
+                    controls: self
+                        .pending
+                        .iter()
+                        .map(|(_, controls)| controls.to_byte())
+                        .collect(),
+                    chat_received: self.chat.back().map(|line| line.number),
+                };
+                self.transport.send(&input, self.server);
+                if let Some((first, _)) = self.outgoing_chat.front() {
+                    let chat = ClientMessage::Chat {
+                        first: *first,
+                        texts: self
+                            .outgoing_chat
+                            .iter()
+                            .map(|(_, text)| text.clone())
+                            .collect(),
+                    };
+                    self.transport.send(&chat, self.server);
+                }
+                self.predict(player, tick_rate);
+            }
+            ConnectionState::Refused(_) | ConnectionState::TimedOut => {}
+        }
+    }
+
+    fn handle(&mut self, message: ServerMessage) {
+        match message {
+            ServerMessage::Welcome { player, tick_rate } => {
+                if self.state == ConnectionState::Connecting {
+                    log::info!(
+                        target: logging::NET,
+                        "joined {} as player {} at {}",
+                        self.server,
+                        player.0,
+                        tick_rate
+                    );
+                    self.state = ConnectionState::Connected { player, tick_rate };
+                }
+            }
+            ServerMessage::Refused { reason } => {
+                if self.state == ConnectionState::Connecting {
+                    log::warn!(target: logging::NET, "{} refused: {}", self.server, reason);
+                    self.state = ConnectionState::Refused(reason);
+                }
+            }
+            // snapshots can arrive out of order, the older ones are dropped (the chat lines
+            // after the ones the client has are taken from any)
+            ServerMessage::Snapshot(snapshot) => {
+                let received = self.chat.back().map(|line| line.number);
+                self.chat.extend(
+                    snapshot
+                        .chat
+                        .iter()
+                        .filter(|line| received.is_none_or(|last| line.number > last))
+                        .cloned(),
+                );
+                while self.chat.len() > CHAT_SCROLLBACK {
+                    self.chat.pop_front();
+                }
+                if let Some(acknowledged) = snapshot.chat_acknowledged {
+                    self.outgoing_chat
+                        .retain(|(number, _)| *number > acknowledged);
+                }
+                if self
+                    .snapshot
+                    .as_ref()
+                    .is_none_or(|newest| snapshot.arena.tick > newest.arena.tick)
+                {
+                    if let Some(acknowledged) = snapshot.acknowledged {
+                        self.pending.retain(|(input, _)| *input > acknowledged);
+                    }
+                    self.snapshot = Some(snapshot);
+                }
+            }
+        }
+    }
+
+    // the ship from the newest snapshot, moved on by the inputs the server hasn't applied
+    // yet the way the arena will apply them
+    fn predict(&mut self, player: PlayerId, tick_rate: TickRate) {
+        self.predicted = self
+            .arena()
+            .and_then(|arena| arena.fighter(player))
+            .filter(|fighter| fighter.alive())
+            .map(|fighter| {
+                let mut ship = fighter.ship.clone();
+                for (_, controls) in self.pending.iter() {
+                    ship.update(*controls, tick_rate.dt());
+                    if controls.fire {
+                        // only the reload matters, the server fires the actual shot
+                        let _ = ship.fire(0);
+                    }
+                }
+                ship
+            });
+    }
+}
+
+impl Drop for Client {
+    // so the server doesn't wait for the timeout
+    fn drop(&mut self) {
+        if let ConnectionState::Connected { .. } = self.state {
+            self.transport.send(&ClientMessage::Bye, self.server);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! What clients and servers send each other. A message is a datagram of JSON, like the saves
+//! it's serde all the way down, so the snapshots carry the simulation's exact Scalar values.
+//!
+//! Controls are numbered by the client (its input sequence, which isn't the server's tick):
+//! the server applies one per tick from a short queue and acknowledges the last one applied
+//! in the snapshots, which is where the client's prediction picks up.
+//!
+//! Chat works the same way in both directions: a client sends its messages (numbered by the
+//! client) until a snapshot acknowledges them, and the server sends the chat lines (numbered
+//! by the server) after the last one the client says it has.
+
+use serde::{Deserialize, Serialize};
+
+use super::arena::{Arena, PlayerId};
+use crate::tick_rate::TickRate;
+
+/// Clients and servers only talk to the same version
+pub const PROTOCOL_VERSION: u32 = 2;
+/// The largest datagram either side reads
+pub const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+/// Longer player names and chat messages are cut off by the server, in characters
+pub const MAX_PLAYER_NAME_LENGTH: usize = 16;
+pub const MAX_CHAT_LENGTH: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// Asks to join, sent until the server welcomes or refuses the client
+    Hello { version: u32, name: String },
+    /// Controls for the inputs from `first` on, a byte each (see
+    /// [`ShipControls::to_byte`](crate::ship::ShipControls::to_byte)). All the ones the
+    /// server hasn't acknowledged yet, so a lost message's are in the next. `chat_received`
+    /// is the last chat line the client has.
+    Input {
+        first: u64,
+        controls: Vec<u8>,
+        chat_received: Option<u64>,
+    },
+    /// Chat messages from `first` on, all the ones the server hasn't acknowledged yet
+    Chat { first: u64, texts: Vec<String> },
+    /// Leaves the game
+    Bye,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ServerMessage {
+    /// The client joined as `player`. Its inputs should come at `tick_rate`, one a tick.
+    Welcome {
+        player: PlayerId,
+        tick_rate: TickRate,
+    },
+    /// The client can't join, e.g. the server is full
+    Refused { reason: String },
+    /// The arena after a tick, sent to every client every tick
+    Snapshot(Snapshot),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub arena: Arena,
+    /// The last of the recipient's inputs the arena was simulated with, None before the
+    /// first
+    pub acknowledged: Option<u64>,
+    /// Chat lines after the last one the recipient has, oldest first
+    pub chat: Vec<ChatLine>,
+    /// The last of the recipient's chat messages the server got
+    pub chat_acknowledged: Option<u64>,
+}
+
+/// A chat message, or a notice from the server (someone joined or left).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatLine {
+    /// Counts up from 0 for the lifetime of the server
+    pub number: u64,
+    /// Who said it, None for the server's notices
+    pub player: Option<PlayerId>,
+    /// The player's name when they said it
+    pub name: String,
+    pub text: String,
+}
+
+/// `text` without control characters or the spaces around it, cut off after `max_length`
+/// characters.
+pub fn sanitize(text: &str, max_length: usize) -> String {
+    text.chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>()
+        .trim()
+        .chars()
+        .take(max_length)
+        .collect()
+}
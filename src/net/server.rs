@@ -0,0 +1,326 @@
+use std::collections::VecDeque;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use super::arena::{Arena, PlayerId};
+use super::protocol::{
+    ChatLine, ClientMessage, MAX_CHAT_LENGTH, MAX_PLAYER_NAME_LENGTH, PROTOCOL_VERSION,
+    ServerMessage, Snapshot, sanitize,
+};
+use super::transport::Transport;
+use crate::logging;
+use crate::ship::ShipControls;
+use crate::tick_rate::TickRate;
+
+pub const MAX_PLAYERS: usize = 4;
+// clients that haven't sent anything for this long left
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+// inputs queued beyond this are dropped, so a client that sent a burst doesn't stay behind
+// by that much
+const MAX_QUEUED_INPUTS: usize = 8;
+// a packet with inputs further past the newest one received is dropped. A client that lost
+// its packets for longer than this has timed out already, even at 120 Hz.
+const MAX_INPUTS_AHEAD: u64 = 1024;
+// the chat lines kept for the players who join later
+const CHAT_HISTORY: usize = 100;
+// the most chat lines in a snapshot, the rest follow in the next ones
+const MAX_CHAT_LINES: usize = 16;
+
+struct RemoteClient {
+    address: SocketAddr,
+    player: PlayerId,
+    name: String,
+    // received, not applied yet, in order
+    inputs: VecDeque<(u64, ShipControls)>,
+    // the newest input received
+    received: Option<u64>,
+    // the last input applied, and its controls, which are applied again while the queue is
+    // empty
+    acknowledged: Option<u64>,
+    controls: ShipControls,
+    // the last of the client's chat messages taken, and the last chat line it has
+    chat_received: Option<u64>,
+    chat_delivered: Option<u64>,
+    last_heard: Instant,
+}
+
+impl RemoteClient {
+    fn receive_inputs(&mut self, first: u64, controls: &[u8]) {
+        let Some(numbers) =
+            numbers_in_window(first, controls.len(), self.received, MAX_INPUTS_AHEAD)
+        else {
+            return;
+        };
+        for (input, byte) in numbers.zip(controls) {
+            if self.received.is_none_or(|received| input > received) {
+                self.inputs
+                    .push_back((input, ShipControls::from_byte(*byte)));
+                self.received = Some(input);
+            }
+        }
+        while self.inputs.len() > MAX_QUEUED_INPUTS {
+            self.inputs.pop_front();
+        }
+    }
+
+    // the controls for the next tick
+    fn next_controls(&mut self) -> ShipControls {
+        if let Some((input, controls)) = self.inputs.pop_front() {
+            self.acknowledged = Some(input);
+            self.controls = controls;
+        }
+        self.controls
+    }
+}
+
+// the numbers of `count` messages from `first` on, or None if the last of them is more than
+// `window` past `newest` (the newest one received so far, the first is 0). The numbers come
+// from the packet, so they can be anything.
+fn numbers_in_window(
+    first: u64,
+    count: usize,
+    newest: Option<u64>,
+    window: u64,
+) -> Option<RangeInclusive<u64>> {
+    let last = first.checked_add((count as u64).checked_sub(1)?)?;
+    let limit = newest.map_or(0, |newest| newest.saturating_add(1));
+    (last <= limit.saturating_add(window)).then_some(first..=last)
+}
+
+/// Runs an [`Arena`] for the clients that join it, see [`crate::net`]. Call
+/// [`Server::tick`] at the tick rate.
+pub struct Server {
+    transport: Transport,
+    tick_rate: TickRate,
+    arena: Arena,
+    clients: Vec<RemoteClient>,
+    next_player: u32,
+    chat: VecDeque<ChatLine>,
+    next_chat_line: u64,
+}
+
+impl Server {
+    /// A server listening on `address` (e.g. `0.0.0.0:27450`).
+    pub fn bind(address: impl ToSocketAddrs, tick_rate: TickRate) -> Result<Self> {
+        let transport = Transport::bind(address)?;
+        log::info!(
+            target: logging::NET,
+            "server listening on {} at {}",
+            transport.local_addr()?,
+            tick_rate
+        );
+        Ok(Self {
+            transport,
+            tick_rate,
+            arena: Arena::default(),
+            clients: Vec::new(),
+            next_player: 0,
+            chat: VecDeque::new(),
+            next_chat_line: 0,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.transport.local_addr()?)
+    }
+
+    pub fn tick_rate(&self) -> TickRate {
+        self.tick_rate
+    }
+
+    pub fn arena(&self) -> &Arena {
+        &self.arena
+    }
+
+    /// Number of clients in the game
+    pub fn players(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Handles what the clients sent, simulates a tick and sends everyone the snapshot.
+    pub fn tick(&mut self, now: Instant) {
+        for (address, message) in self.transport.receive::<ClientMessage>() {
+            self.handle(address, message, now);
+        }
+        let timed_out: Vec<PlayerId> = self
+            .clients
+            .iter()
+            .filter(|client| now.saturating_duration_since(client.last_heard) > CLIENT_TIMEOUT)
+            .map(|client| client.player)
+            .collect();
+        for player in timed_out {
+            log::info!(target: logging::NET, "player {} timed out", player.0);
+            self.remove(player);
+        }
+
+        let controls: Vec<(PlayerId, ShipControls)> = self
+            .clients
+            .iter_mut()
+            .map(|client| (client.player, client.next_controls()))
+            .collect();
+        self.arena.update(
+            |player| {
+                controls
+                    .iter()
+                    .find(|(p, _)| *p == player)
+                    .map_or_else(ShipControls::default, |(_, controls)| *controls)
+            },
+            self.tick_rate.dt(),
+        );
+
+        for client in self.clients.iter() {
+            let chat = self
+                .chat
+                .iter()
+                .filter(|line| client.chat_delivered.is_none_or(|last| line.number > last))
+                .take(MAX_CHAT_LINES)
+                .cloned()
+                .collect();
+            let snapshot = ServerMessage::Snapshot(Snapshot {
+                arena: self.arena.clone(),
+                acknowledged: client.acknowledged,
+                chat,
+                chat_acknowledged: client.chat_received,
+            });
+            self.transport.send(&snapshot, client.address);
+        }
+    }
+
+    fn handle(&mut self, address: SocketAddr, message: ClientMessage, now: Instant) {
+        let client = self
+            .clients
+            .iter_mut()
+            .find(|client| client.address == address);
+        match (message, client) {
+            (ClientMessage::Hello { version, name }, client) => {
+                let reply = if version != PROTOCOL_VERSION {
+                    ServerMessage::Refused {
+                        reason: format!(
+                            "the server speaks version {}, not {}",
+                            PROTOCOL_VERSION, version
+                        ),
+                    }
+                } else if let Some(client) = client {
+                    // the welcome got lost
+                    client.last_heard = now;
+                    ServerMessage::Welcome {
+                        player: client.player,
+                        tick_rate: self.tick_rate,
+                    }
+                } else if self.clients.len() >= MAX_PLAYERS {
+                    ServerMessage::Refused {
+                        reason: "the server is full".to_string(),
+                    }
+                } else {
+                    let player = PlayerId(self.next_player);
+                    self.next_player += 1;
+                    let mut name = sanitize(&name, MAX_PLAYER_NAME_LENGTH);
+                    if name.is_empty() {
+                        name = format!("PLAYER {}", player.0 + 1);
+                    }
+                    log::info!(
+                        target: logging::NET,
+                        "player {} ({}) joined from {}",
+                        player.0,
+                        name,
+                        address
+                    );
+                    self.clients.push(RemoteClient {
+                        address,
+                        player,
+                        name: name.clone(),
+                        inputs: VecDeque::new(),
+                        received: None,
+                        acknowledged: None,
+                        controls: ShipControls::default(),
+                        chat_received: None,
+                        chat_delivered: None,
+                        last_heard: now,
+                    });
+                    self.arena.join(player, name.clone());
+                    self.say(None, name, "joined the game".to_string());
+                    ServerMessage::Welcome {
+                        player,
+                        tick_rate: self.tick_rate,
+                    }
+                };
+                self.transport.send(&reply, address);
+            }
+            (
+                ClientMessage::Input {
+                    first,
+                    controls,
+                    chat_received,
+                },
+                Some(client),
+            ) => {
+                client.last_heard = now;
+                client.receive_inputs(first, &controls);
+                client.chat_delivered = chat_received;
+            }
+            (ClientMessage::Chat { first, texts }, Some(client)) => {
+                client.last_heard = now;
+                let mut said = Vec::new();
+                let numbers = numbers_in_window(
+                    first,
+                    texts.len(),
+                    client.chat_received,
+                    CHAT_HISTORY as u64,
+                );
+                for (number, text) in numbers.into_iter().flatten().zip(texts) {
+                    if client
+                        .chat_received
+                        .is_none_or(|received| number > received)
+                    {
+                        client.chat_received = Some(number);
+                        said.push(sanitize(&text, MAX_CHAT_LENGTH));
+                    }
+                }
+                let (player, name) = (client.player, client.name.clone());
+                for text in said.into_iter().filter(|text| !text.is_empty()) {
+                    self.say(Some(player), name.clone(), text);
+                }
+            }
+            (ClientMessage::Bye, Some(client)) => {
+                let player = client.player;
+                log::info!(target: logging::NET, "player {} left", player.0);
+                self.remove(player);
+            }
+            // from someone who isn't in the game (any more)
+            (
+                ClientMessage::Input { .. } | ClientMessage::Chat { .. } | ClientMessage::Bye,
+                None,
+            ) => {}
+        }
+    }
+
+    fn remove(&mut self, player: PlayerId) {
+        if let Some(index) = self
+            .clients
+            .iter()
+            .position(|client| client.player == player)
+        {
+            let client = self.clients.remove(index);
+            self.say(None, client.name, "left the game".to_string());
+        }
+        self.arena.leave(player);
+    }
+
+    // adds a line to the chat, None is the server's notice about `name`
+    fn say(&mut self, player: Option<PlayerId>, name: String, text: String) {
+        log::info!(target: logging::NET, "chat: {} {}", name, text);
+        self.chat.push_back(ChatLine {
+            number: self.next_chat_line,
+            player,
+            name,
+            text,
+        });
+        self.next_chat_line += 1;
+        while self.chat.len() > CHAT_HISTORY {
+            self.chat.pop_front();
+        }
+    }
+}
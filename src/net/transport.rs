@@ -0,0 +1,63 @@
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use super::protocol::MAX_MESSAGE_SIZE;
+use crate::logging;
+
+// a non-blocking UDP socket that sends and receives messages, a datagram each
+pub(crate) struct Transport {
+    socket: UdpSocket,
+    buffer: Vec<u8>,
+}
+
+impl Transport {
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            buffer: vec![0; MAX_MESSAGE_SIZE],
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    // lost like any datagram if it can't be sent
+    pub fn send(&self, message: &impl Serialize, to: SocketAddr) {
+        let result = serde_json::to_vec(message)
+            .map_err(io::Error::from)
+            .and_then(|bytes| self.socket.send_to(&bytes, to));
+        if let Err(e) = result {
+            log::debug!(target: logging::NET, "unable to send to {}: {}", to, e);
+        }
+    }
+
+    // everything that arrived since the last call, without waiting. What isn't a message is
+    // dropped.
+    pub fn receive<M: DeserializeOwned>(&mut self) -> Vec<(SocketAddr, M)> {
+        let mut messages = Vec::new();
+        loop {
+            match self.socket.recv_from(&mut self.buffer) {
+                Ok((length, from)) => match serde_json::from_slice(&self.buffer[..length]) {
+                    Ok(message) => messages.push((from, message)),
+                    Err(e) => {
+                        log::debug!(target: logging::NET, "dropped a datagram from {}: {}", from, e)
+                    }
+                },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                // Windows reports a datagram sent to a closed port on the next receive
+                Err(e) if e.kind() == io::ErrorKind::ConnectionReset => continue,
+                Err(e) => {
+                    log::warn!(target: logging::NET, "unable to receive: {}", e);
+                    break;
+                }
+            }
+        }
+        messages
+    }
+}
@@ -0,0 +1,58 @@
+//! Without a font API to ask (DirectWrite on Windows), the system fonts are the font files in
+//! the usual font directories of Linux, the BSDs and macOS. A browser has none of them.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+// relative ones are in the home directory
+const FONT_DIRECTORIES: &[&str] = &[
+    "/usr/share/fonts",
+    "/usr/local/share/fonts",
+    ".local/share/fonts",
+    ".fonts",
+    "/System/Library/Fonts",
+    "/Library/Fonts",
+    "Library/Fonts",
+];
+
+// the files the FontCache loads, the directories also have bitmap and Type 1 fonts
+const FONT_EXTENSIONS: &[&str] = &["ttf", "otf", "ttc", "otc"];
+
+pub fn load_system_font_paths() -> Result<Vec<PathBuf>> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let mut paths = Vec::new();
+    for directory in FONT_DIRECTORIES.iter().map(Path::new) {
+        if directory.is_absolute() {
+            add_font_files(directory, &mut paths);
+        } else if let Some(home) = &home {
+            add_font_files(&home.join(directory), &mut paths);
+        }
+    }
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+// the font files in `directory` and the directories in it. Directories that can't be read
+// (or don't exist) are skipped, and symlinked ones aren't followed so there are no loops.
+fn add_font_files(directory: &Path, paths: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            add_font_files(&path, paths);
+        } else if path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                FONT_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+            })
+        {
+            paths.push(path);
+        }
+    }
+}
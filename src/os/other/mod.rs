@@ -0,0 +1 @@
+pub mod font_util;
@@ -0,0 +1,14 @@
+use windows::Win32::UI::WindowsAndMessaging::{MB_ICONERROR, MB_OK, MessageBoxW};
+use windows::core::HSTRING;
+
+/// Shows `text` in a message box with an error icon, and waits until it's closed.
+pub fn show_error(title: &str, text: &str) {
+    unsafe {
+        MessageBoxW(
+            None,
+            &HSTRING::from(text),
+            &HSTRING::from(title),
+            MB_OK | MB_ICONERROR,
+        );
+    }
+}
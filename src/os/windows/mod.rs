@@ -1 +1,2 @@
+pub mod dialog;
 pub mod font_util;
@@ -0,0 +1,161 @@
+//! The colors that carry meaning: the world's lines, the UI's text and highlights, friend and
+//! foe on the radar, the players' names, warnings and errors, the heat map and the particles.
+//! The renderers and screens take them from the game state's [`Palette`] instead of their own
+//! constants, so picking a [`ColorVision`] preset (the `[interface]` section of the settings
+//! file, or the settings screen) recolors everything at once.
+//!
+//! The presets for color vision deficiencies keep the pairs that have to stay apart (friend
+//! and foe, highlighted and plain text, the players) on the axis the player can still tell
+//! apart, based on the Okabe-Ito palette. Neutral colors (panel backgrounds, shadows) aren't
+//! part of the palette.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorVision {
+    #[default]
+    Normal,
+    /// Red-green, green weak
+    Deuteranopia,
+    /// Red-green, red weak (reds look dark)
+    Protanopia,
+    /// Blue-yellow
+    Tritanopia,
+}
+
+impl ColorVision {
+    pub const ALL: [ColorVision; 4] = [
+        ColorVision::Normal,
+        ColorVision::Deuteranopia,
+        ColorVision::Protanopia,
+        ColorVision::Tritanopia,
+    ];
+
+    /// The name in the settings file, and the key of its name in the string tables (see
+    /// crate::i18n)
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorVision::Normal => "normal",
+            ColorVision::Deuteranopia => "deuteranopia",
+            ColorVision::Protanopia => "protanopia",
+            ColorVision::Tritanopia => "tritanopia",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    /// The ships, asteroids and shots
+    pub world: [f32; 4],
+    pub text: [f32; 4],
+    /// Picked menu items, text fields being typed in, the player's new high score
+    pub highlight: [f32; 4],
+    /// Hints and the server's notices, less prominent than the text
+    pub hint: [f32; 4],
+    pub warning: [f32; 4],
+    pub error: [f32; 4],
+    /// The player's ship and shots on the radar
+    pub friend: [f32; 4],
+    /// Asteroids and the other players on the radar
+    pub foe: [f32; 4],
+    /// The names in the multiplayer scores and chat, by player
+    pub players: [[f32; 4]; 4],
+    /// The heat map, cold, warm and hot (the heat sets the alpha)
+    pub heat: [[f32; 3]; 3],
+    /// Particles at the start and at the end of their life
+    pub explosion: ([f32; 4], [f32; 4]),
+    pub debris: ([f32; 4], [f32; 4]),
+    pub sparks: ([f32; 4], [f32; 4]),
+    pub thruster: ([f32; 4], [f32; 4]),
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            world: [0.0, 1.0, 0.0, 1.0],
+            text: [1.0, 1.0, 1.0, 1.0],
+            highlight: [1.0, 0.85, 0.2, 1.0],
+            hint: [0.75, 0.75, 0.75, 1.0],
+            warning: [1.0, 0.8, 0.35, 1.0],
+            error: [1.0, 0.45, 0.4, 1.0],
+            friend: [0.4, 1.0, 0.6, 1.0],
+            foe: [1.0, 0.35, 0.3, 1.0],
+            players: [
+                [0.4, 0.85, 1.0, 1.0],
+                [1.0, 0.55, 0.3, 1.0],
+                [0.5, 1.0, 0.45, 1.0],
+                [1.0, 0.5, 0.9, 1.0],
+            ],
+            heat: [[0.0, 0.0, 1.0], [1.0, 1.0, 0.0], [1.0, 0.0, 0.0]],
+            explosion: ([1.0, 0.55, 0.1, 1.0], [0.25, 0.1, 0.05, 0.0]),
+            debris: ([0.35, 0.35, 0.35, 0.9], [0.6, 0.6, 0.6, 0.0]),
+            sparks: ([1.0, 0.9, 0.4, 1.0], [1.0, 0.3, 0.0, 0.0]),
+            thruster: ([1.0, 0.75, 0.2, 0.9], [0.9, 0.15, 0.05, 0.0]),
+        }
+    }
+}
+
+impl Palette {
+    pub fn new(vision: ColorVision) -> Self {
+        match vision {
+            ColorVision::Normal => Self::default(),
+            // blue against orange and yellow instead of green against red
+            ColorVision::Deuteranopia => Self {
+                world: [0.0, 0.45, 0.7, 1.0],
+                warning: [0.95, 0.9, 0.25, 1.0],
+                error: [0.9, 0.6, 0.0, 1.0],
+                friend: [0.35, 0.7, 0.9, 1.0],
+                foe: [0.9, 0.6, 0.0, 1.0],
+                players: [
+                    [0.35, 0.7, 0.9, 1.0],
+                    [0.9, 0.6, 0.0, 1.0],
+                    [0.95, 0.9, 0.25, 1.0],
+                    [0.8, 0.6, 0.7, 1.0],
+                ],
+                heat: [[0.27, 0.0, 0.33], [0.13, 0.57, 0.55], [0.99, 0.91, 0.14]],
+                ..Self::default()
+            },
+            // like deuteranopia, but the reds brighter (they look dark)
+            ColorVision::Protanopia => Self {
+                error: [1.0, 0.7, 0.1, 1.0],
+                foe: [1.0, 0.7, 0.1, 1.0],
+                players: [
+                    [0.35, 0.7, 0.9, 1.0],
+                    [1.0, 0.7, 0.1, 1.0],
+                    [0.95, 0.9, 0.25, 1.0],
+                    [0.85, 0.65, 0.8, 1.0],
+                ],
+                explosion: ([1.0, 0.75, 0.2, 1.0], [0.4, 0.3, 0.1, 0.0]),
+                ..Self::new(ColorVision::Deuteranopia)
+            },
+            // cyan against red and pink instead of blue against yellow
+            ColorVision::Tritanopia => Self {
+                highlight: [1.0, 0.45, 0.6, 1.0],
+                warning: [1.0, 0.45, 0.6, 1.0],
+                error: [0.9, 0.2, 0.2, 1.0],
+                friend: [0.3, 0.85, 0.9, 1.0],
+                foe: [0.9, 0.2, 0.2, 1.0],
+                players: [
+                    [0.3, 0.85, 0.9, 1.0],
+                    [0.9, 0.2, 0.2, 1.0],
+                    [1.0, 1.0, 1.0, 1.0],
+                    [1.0, 0.45, 0.6, 1.0],
+                ],
+                heat: [[0.0, 0.6, 0.7], [0.9, 0.5, 0.6], [0.85, 0.0, 0.15]],
+                sparks: ([1.0, 0.7, 0.7, 1.0], [0.9, 0.2, 0.2, 0.0]),
+                ..Self::default()
+            },
+        }
+    }
+
+    /// The color of a player's name, see [`Palette::players`]
+    pub fn player(&self, index: usize) -> [f32; 4] {
+        self.players[index % self.players.len()]
+    }
+}
+
+/// `color` with another alpha, e.g. a translucent fill in the highlight color
+pub fn with_alpha([r, g, b, _]: [f32; 4], alpha: f32) -> [f32; 4] {
+    [r, g, b, alpha]
+}
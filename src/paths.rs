@@ -0,0 +1,166 @@
+//! Platform directories for files the game reads and writes (settings, logs, bug reports,
+//! saves...), and the [`AssetRoots`] the game's own files are looked up in.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+
+/// Name of the game's directories inside the platform directories
+pub const APP_NAME: &str = "game";
+
+/// Environment variable with a directory that relative asset paths are looked up in before
+/// any other, e.g. `GAME_ASSET_DIR=/opt/game`
+pub const ASSET_DIR_VAR: &str = "GAME_ASSET_DIR";
+
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The platform's directory for the logs of `app_name`:
+///
+/// - Windows: `%LOCALAPPDATA%\<app_name>\logs`
+/// - macOS: `~/Library/Logs/<app_name>`
+/// - others: `$XDG_STATE_HOME/<app_name>/logs` (default `~/.local/state/<app_name>/logs`)
+pub fn log_dir(app_name: &str) -> Option<PathBuf> {
+    if cfg!(windows) {
+        env_dir("LOCALAPPDATA").map(|dir| dir.join(app_name).join("logs"))
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library").join("Logs").join(app_name))
+    } else {
+        env_dir("XDG_STATE_HOME")
+            .or_else(|| env_dir("HOME").map(|home| home.join(".local").join("state")))
+            .map(|dir| dir.join(app_name).join("logs"))
+    }
+}
+
+/// The platform's directory for user data of `app_name`:
+///
+/// - Windows: `%APPDATA%\<app_name>`
+/// - macOS: `~/Library/Application Support/<app_name>`
+/// - others: `$XDG_DATA_HOME/<app_name>` (default `~/.local/share/<app_name>`)
+pub fn data_dir(app_name: &str) -> Option<PathBuf> {
+    if cfg!(windows) {
+        env_dir("APPDATA").map(|dir| dir.join(app_name))
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| {
+            home.join("Library")
+                .join("Application Support")
+                .join(app_name)
+        })
+    } else {
+        env_dir("XDG_DATA_HOME")
+            .or_else(|| env_dir("HOME").map(|home| home.join(".local").join("share")))
+            .map(|dir| dir.join(app_name))
+    }
+}
+
+/// The platform's directory for the settings of `app_name`:
+///
+/// - Windows: `%APPDATA%\<app_name>`
+/// - macOS: `~/Library/Application Support/<app_name>`
+/// - others: `$XDG_CONFIG_HOME/<app_name>` (default `~/.config/<app_name>`)
+pub fn config_dir(app_name: &str) -> Option<PathBuf> {
+    if cfg!(windows) {
+        env_dir("APPDATA").map(|dir| dir.join(app_name))
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| {
+            home.join("Library")
+                .join("Application Support")
+                .join(app_name)
+        })
+    } else {
+        env_dir("XDG_CONFIG_HOME")
+            .or_else(|| env_dir("HOME").map(|home| home.join(".config")))
+            .map(|dir| dir.join(app_name))
+    }
+}
+
+/// The directories relative asset paths are looked up in, in order:
+///
+/// - the directory in [`ASSET_DIR_VAR`]
+/// - the executable's directory
+/// - in debug builds, the crate's directory (like the shader sources, see
+///   [`Shader::source_path`](crate::gfx::shaders::Shader::source_path))
+/// - `<data_dir>/assets` (see [`data_dir`])
+/// - the working directory
+pub fn asset_base_dirs(app_name: &str) -> Vec<PathBuf> {
+    let executable_dir = std::env::current_exe()
+        .ok()
+        .and_then(|executable| Some(executable.parent()?.to_path_buf()));
+    let crate_dir = cfg!(debug_assertions).then(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for dir in env_dir(ASSET_DIR_VAR)
+        .into_iter()
+        .chain(executable_dir)
+        .chain(crate_dir)
+        .chain(data_dir(app_name).map(|dir| dir.join("assets")))
+        .chain(std::env::current_dir().ok())
+    {
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+/// Where relative asset paths (fonts, language files, textures...) are looked up, so the game
+/// finds its files whatever directory it was started from. A relative root is looked up in
+/// each of the base directories (usually the [`asset_base_dirs`]), and without any roots the
+/// base directories are the roots. Without base directories either (the default), paths are
+/// used as they are, i.e. relative to the working directory.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AssetRoots {
+    base_dirs: Vec<PathBuf>,
+    roots: Vec<PathBuf>,
+}
+
+impl AssetRoots {
+    /// No roots yet, in the [`asset_base_dirs`] of `app_name`.
+    pub fn new(app_name: &str) -> Self {
+        Self::with_base_dirs(asset_base_dirs(app_name))
+    }
+
+    pub fn with_base_dirs(base_dirs: Vec<PathBuf>) -> Self {
+        Self {
+            base_dirs,
+            roots: Vec::new(),
+        }
+    }
+
+    /// Adds a root, searched after the ones added before.
+    pub fn add_root(&mut self, root: impl Into<PathBuf>) {
+        self.roots.push(root.into());
+    }
+
+    /// The directories that are searched, in order.
+    pub fn dirs(&self) -> Vec<PathBuf> {
+        if self.roots.is_empty() {
+            return self.base_dirs.clone();
+        }
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        for root in &self.roots {
+            if root.is_absolute() || self.base_dirs.is_empty() {
+                dirs.push(root.clone());
+            } else {
+                dirs.extend(self.base_dirs.iter().map(|base_dir| base_dir.join(root)));
+            }
+        }
+        dirs
+    }
+
+    /// The first file or directory at `path` in the searched directories. Absolute paths are
+    /// as they are, and so are all paths when there's nothing to search.
+    pub fn resolve(&self, path: impl AsRef<Path>) -> Result<PathBuf> {
+        let path = path.as_ref();
+        let dirs = self.dirs();
+        if path.is_absolute() || dirs.is_empty() {
+            return Ok(path.to_path_buf());
+        }
+        dirs.iter()
+            .map(|dir| dir.join(path))
+            .find(|candidate| candidate.exists())
+            .ok_or_else(|| anyhow!("asset {} not found in {:?}", path.display(), dirs))
+    }
+}
@@ -0,0 +1,186 @@
+//! The radar in the corner of the HUD: what's within range of the player's ship, projected
+//! into a circle around it. Screens fill a [`RadarView`] with the contacts every frame and
+//! add it as a [`Widget::Radar`](crate::ui::Widget::Radar). The disc and the blips are drawn
+//! by the shape renderer, the ship's marker by the line renderer.
+//!
+//! North is up, like the view. The world wraps around its edges, so something just across
+//! the edge from the ship is a contact too.
+
+use std::f32::consts::PI;
+
+use crate::gfx::Layer;
+use crate::gfx::lines::Polyline;
+use crate::gfx::shapes::RoundedRect;
+use crate::palette::{Palette, with_alpha};
+use crate::world::{WORLD_HEIGHT, WORLD_WIDTH};
+
+// in logical pixels
+const BLIP_RADIUS: f32 = 3.0;
+const MARKER_SIZE: f32 = 7.0;
+const RING_WIDTH: f32 = 1.0;
+const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.08, 0.04, 0.55];
+// the rings are in the friend color
+const RING_ALPHA: f32 = 0.35;
+
+/// What the radar shows and how, see the `[hud]` section of the settings file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RadarSettings {
+    pub enabled: bool,
+    /// World units from the ship to the radar's edge
+    pub range: f32,
+    /// The radar's diameter, in logical pixels
+    pub size: f32,
+    /// The player's ship and shots, None for the palette's (see crate::palette)
+    pub friend_color: Option<[f32; 4]>,
+    /// Asteroids and the other players, None for the palette's
+    pub foe_color: Option<[f32; 4]>,
+}
+
+impl Default for RadarSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            range: 450.0,
+            size: 160.0,
+            friend_color: None,
+            foe_color: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Allegiance {
+    Friend,
+    Foe,
+}
+
+/// A contact on the radar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Blip {
+    /// From the center, in radii (so within the unit circle), y up
+    pub offset: [f32; 2],
+    pub allegiance: Allegiance,
+}
+
+/// The contacts around the player's ship, for a frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RadarView {
+    pub settings: RadarSettings,
+    /// Where the ship is, in world units
+    pub center: [f32; 2],
+    /// The ship's angle (radians, counterclockwise from the x axis), None while it's
+    /// destroyed
+    pub heading: Option<f32>,
+    pub blips: Vec<Blip>,
+}
+
+impl RadarView {
+    pub fn new(settings: RadarSettings, center: [f32; 2], heading: Option<f32>) -> Self {
+        Self {
+            settings,
+            center,
+            heading,
+            blips: Vec::new(),
+        }
+    }
+
+    /// Adds a contact at `position` (world units) if it's in range.
+    pub fn add(&mut self, position: [f32; 2], allegiance: Allegiance) {
+        let [x, y] = wrapped_offset(self.center, position);
+        let range = self.settings.range.max(1.0);
+        let offset = [x / range, y / range];
+        if offset[0] * offset[0] + offset[1] * offset[1] <= 1.0 {
+            self.blips.push(Blip { offset, allegiance });
+        }
+    }
+
+    /// The radar drawn in `rect` (physical pixels), as shapes and lines. The shapes are on
+    /// the world layer: the line pass comes before the shape pass, so the marker stays on
+    /// top of the disc only if that's farther back.
+    pub(crate) fn draw(
+        &self,
+        rect: [f32; 4],
+        scale_factor: f32,
+        palette: &Palette,
+    ) -> (Vec<RoundedRect>, Vec<Polyline>) {
+        let friend_color = self.settings.friend_color.unwrap_or(palette.friend);
+        let foe_color = self.settings.foe_color.unwrap_or(palette.foe);
+        let ring_color = with_alpha(friend_color, RING_ALPHA);
+        let [left, top, right, bottom] = rect;
+        let radius = (right - left).min(bottom - top) / 2.0;
+        let center = [(left + right) / 2.0, (top + bottom) / 2.0];
+        let circle = |radius: f32| {
+            [
+                center[0] - radius,
+                center[1] - radius,
+                center[0] + radius,
+                center[1] + radius,
+            ]
+        };
+
+        let mut shapes = vec![
+            RoundedRect::new(circle(radius), BACKGROUND_COLOR)
+                .border(RING_WIDTH * scale_factor, ring_color)
+                .corner_radius(radius)
+                .layer(Layer::World),
+            RoundedRect::outline(circle(radius / 2.0), RING_WIDTH * scale_factor, ring_color)
+                .corner_radius(radius / 2.0)
+                .layer(Layer::World),
+        ];
+        let blip_radius = BLIP_RADIUS * scale_factor;
+        for blip in self.blips.iter() {
+            let color = match blip.allegiance {
+                Allegiance::Friend => friend_color,
+                Allegiance::Foe => foe_color,
+            };
+            // screen y is down
+            let x = center[0] + blip.offset[0] * (radius - blip_radius);
+            let y = center[1] - blip.offset[1] * (radius - blip_radius);
+            shapes.push(
+                RoundedRect::new(
+                    [
+                        x - blip_radius,
+                        y - blip_radius,
+                        x + blip_radius,
+                        y + blip_radius,
+                    ],
+                    color,
+                )
+                .corner_radius(blip_radius)
+                .layer(Layer::World),
+            );
+        }
+
+        let mut lines = Vec::new();
+        if let Some(heading) = self.heading {
+            // an arrowhead pointing where the ship is headed
+            let size = MARKER_SIZE * scale_factor;
+            let point = |angle: f32, length: f32| {
+                [
+                    center[0] + angle.cos() * length,
+                    center[1] - angle.sin() * length,
+                ]
+            };
+            lines.push(
+                Polyline::new(
+                    vec![
+                        point(heading, size),
+                        point(heading + PI * 0.8, size),
+                        point(heading - PI * 0.8, size),
+                    ],
+                    friend_color,
+                )
+                .width(1.5)
+                .closed()
+                .layer(Layer::Hud),
+            );
+        }
+        (shapes, lines)
+    }
+}
+
+// the shortest way from `from` to `to` in the world, which wraps around its edges
+fn wrapped_offset(from: [f32; 2], to: [f32; 2]) -> [f32; 2] {
+    let size = [WORLD_WIDTH, WORLD_HEIGHT];
+    std::array::from_fn(|i| (to[i] - from[i] + size[i] / 2.0).rem_euclid(size[i]) - size[i] / 2.0)
+}
@@ -0,0 +1,130 @@
+//! Input recordings. The World is deterministic, so its seed plus the controls of every tick
+//! are enough to play a game back exactly (e.g. to reproduce a bug report). The tick rate
+//! is recorded too, since it decides how far each tick advances the world.
+
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::memory::{HeapSize, vec_size};
+use crate::ship::ShipControls;
+use crate::tick_rate::TickRate;
+use crate::world::World;
+
+/// The seed of a game and its controls for every tick since it started.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    // one byte per tick, see ShipControls::to_byte
+    inputs: Vec<u8>,
+    // (first tick, rate) whenever the tick rate changed. Saves from before tick rates could
+    // change don't have any, they ran at 60 Hz.
+    #[serde(default)]
+    tick_rates: Vec<(u64, TickRate)>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, tick_rate: TickRate) -> Self {
+        Self {
+            seed,
+            inputs: Vec::new(),
+            tick_rates: vec![(0, tick_rate)],
+        }
+    }
+
+    /// Number of recorded ticks
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    /// Records the controls (and tick rate) for simulating tick `tick`. Recording an earlier
+    /// tick again (e.g. after resuming from a time-travel snapshot) drops everything after it.
+    pub fn record(&mut self, tick: u64, controls: ShipControls, tick_rate: TickRate) {
+        self.inputs.truncate(tick as usize);
+        if self.inputs.len() == tick as usize {
+            self.inputs.push(controls.to_byte());
+        }
+        self.tick_rates.retain(|(first_tick, _)| *first_tick < tick);
+        if self.tick_rates.last().map(|(_, rate)| *rate) != Some(tick_rate) {
+            self.tick_rates.push((tick, tick_rate));
+        }
+    }
+
+    pub fn controls(&self, tick: u64) -> Option<ShipControls> {
+        self.inputs
+            .get(tick as usize)
+            .copied()
+            .map(ShipControls::from_byte)
+    }
+
+    /// The tick rate tick `tick` was simulated at.
+    pub fn tick_rate(&self, tick: u64) -> TickRate {
+        self.tick_rates
+            .iter()
+            .rev()
+            .find(|(first_tick, _)| *first_tick <= tick)
+            .map_or(TickRate::Hz60, |(_, rate)| *rate)
+    }
+
+    /// Simulates the first `ticks` recorded ticks from a new world.
+    pub fn simulate(&self, ticks: u64) -> World {
+        let mut world = World::new(self.seed);
+        for tick in 0..ticks.min(self.inputs.len() as u64) {
+            world.update(
+                ShipControls::from_byte(self.inputs[tick as usize]),
+                self.tick_rate(tick).dt(),
+            );
+        }
+        world
+    }
+
+    /// Text format: a header with the seed, the tick rates (`first tick:Hz`) and all inputs
+    /// (hex, one byte per tick), then a readable line per tick from `from_tick` on, with the
+    /// controls as `T` (thrust), `F` (fire) and `<`/`>` (turn left or right).
+    pub fn to_text(&self, from_tick: u64) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "seed {}", self.seed);
+        let tick_rates: Vec<String> = self
+            .tick_rates
+            .iter()
+            .map(|(first_tick, rate)| format!("{}:{}", first_tick, rate.hz()))
+            .collect();
+        let _ = writeln!(text, "tick_rates {}", tick_rates.join(" "));
+        let _ = writeln!(text, "ticks {}", self.inputs.len());
+        let _ = writeln!(text, "inputs {}", encode_hex(&self.inputs));
+        for tick in from_tick..self.inputs.len() as u64 {
+            let controls = ShipControls::from_byte(self.inputs[tick as usize]);
+            let _ = writeln!(
+                text,
+                "{} {}{}{}",
+                tick,
+                if controls.thrust { 'T' } else { '-' },
+                if controls.fire { 'F' } else { '-' },
+                match controls.turn {
+                    1 => '<',
+                    -1 => '>',
+                    _ => '-',
+                }
+            );
+        }
+        text
+    }
+}
+
+impl HeapSize for Replay {
+    fn heap_size(&self) -> usize {
+        vec_size(&self.inputs)
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    hex
+}
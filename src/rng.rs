@@ -0,0 +1,56 @@
+//! Where the random numbers come from. Every game starts from a master seed, and each system
+//! that needs random numbers gets its own stream, a [`SimRng`] derived from the master seed
+//! and the [`RngStream`]. So one system drawing more numbers (a bigger explosion) doesn't
+//! change what the others get (the next asteroid wave).
+//!
+//! The seed is new every game unless it's fixed, with `seed` in the `[simulation]` section of
+//! the settings file or `--seed <seed>` on the command line. It's logged when a game starts
+//! and it's part of saved games, replays and bug reports, so an interesting run can be played
+//! again exactly.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::math::SimRng;
+
+/// The systems with their own stream of random numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RngStream {
+    /// Where the asteroids spawn, their shapes and how they move. Part of the world.
+    Asteroids,
+    /// The particle effects, cosmetic
+    Particles,
+}
+
+impl RngStream {
+    // never change these, the streams of a seed (and so replays) would change
+    fn id(self) -> u64 {
+        match self {
+            RngStream::Asteroids => 1,
+            RngStream::Particles => 2,
+        }
+    }
+}
+
+/// The random numbers of `stream` in the game with the master seed `seed`.
+pub fn stream(seed: u64, stream: RngStream) -> SimRng {
+    SimRng::new(splitmix64(
+        seed ^ stream.id().wrapping_mul(0x9e37_79b9_7f4a_7c15),
+    ))
+}
+
+/// A master seed that's different every time, for games without a fixed seed. 32 bits, so
+/// it's short enough to type in (and fits the settings file's integers).
+pub fn random_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64);
+    splitmix64(nanos ^ ((std::process::id() as u64) << 32)) >> 32
+}
+
+// scrambles the bits, so seeds next to each other give unrelated streams
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,146 @@
+//! Saved games: the whole World (score, ship, seed, every asteroid and bullet, and the rng
+//! state, so the game continues exactly as it would have) and the replay so far, as JSON
+//! files in the `saves` directory of the user's data directory.
+//!
+//! F5 saves to the quicksave slot and F9 loads it (see `GameState::update`), a slot can be
+//! loaded on start with [`EngineBuilder::load_game`](crate::engine::EngineBuilder::load_game).
+//! Saves only load in builds with the same simulation math (see the `fixed-point` feature).
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::paths;
+use crate::replay::Replay;
+use crate::world::World;
+
+/// Bumped when a change to the World makes older saves unreadable
+pub const SAVE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveSlot {
+    /// The slot F5 and F9 use
+    Quick,
+    Numbered(u32),
+}
+
+impl SaveSlot {
+    fn file_name(self) -> String {
+        match self {
+            SaveSlot::Quick => "quicksave.json".to_string(),
+            SaveSlot::Numbered(number) => format!("slot-{}.json", number),
+        }
+    }
+
+    /// Where the slot is saved, see [`paths::data_dir`].
+    pub fn path(self) -> Option<PathBuf> {
+        paths::data_dir(paths::APP_NAME).map(|dir| dir.join("saves").join(self.file_name()))
+    }
+}
+
+/// `quick` or a slot number
+impl FromStr for SaveSlot {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        if name == "quick" {
+            return Ok(SaveSlot::Quick);
+        }
+        name.parse().map(SaveSlot::Numbered).map_err(|_| {
+            anyhow!(
+                "invalid save slot {:?}, expected \"quick\" or a number",
+                name
+            )
+        })
+    }
+}
+
+impl fmt::Display for SaveSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveSlot::Quick => write!(f, "quick"),
+            SaveSlot::Numbered(number) => write!(f, "{}", number),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    /// Whether the saving build used fixed-point simulation math
+    pub fixed_point: bool,
+    /// Unix time in seconds
+    pub saved_at: u64,
+    pub world: World,
+    /// The inputs up to the save, so bug reports of a loaded game still replay from the start
+    pub replay: Replay,
+}
+
+impl SaveGame {
+    pub fn new(world: World, replay: Replay) -> Self {
+        Self {
+            version: SAVE_VERSION,
+            fixed_point: cfg!(feature = "fixed-point"),
+            saved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs()),
+            world,
+            replay,
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<SaveGame> {
+        let text = fs::read_to_string(path)?;
+        let save: SaveGame = serde_json::from_str(&text)
+            .map_err(|e| anyhow!("invalid save file {}: {}", path.display(), e))?;
+        if save.version != SAVE_VERSION {
+            return Err(anyhow!(
+                "{} is from an incompatible version (save version {}, expected {})",
+                path.display(),
+                save.version,
+                SAVE_VERSION
+            ));
+        }
+        if save.fixed_point != cfg!(feature = "fixed-point") {
+            return Err(anyhow!(
+                "{} was saved by a build with different simulation math (fixed point: {})",
+                path.display(),
+                save.fixed_point
+            ));
+        }
+        Ok(save)
+    }
+
+    /// Writes the save next to `path` first, so a crash while saving doesn't destroy the
+    /// previous save.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_string(self)?)?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+
+    pub fn load(slot: SaveSlot) -> Result<SaveGame> {
+        let path = slot
+            .path()
+            .ok_or_else(|| anyhow!("no data directory for this platform"))?;
+        Self::read(&path)
+    }
+
+    /// Saves to the slot and returns the file's path.
+    pub fn save(&self, slot: SaveSlot) -> Result<PathBuf> {
+        let path = slot
+            .path()
+            .ok_or_else(|| anyhow!("no data directory for this platform"))?;
+        self.write(&path)?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,1009 @@
+//! The screens the game can be on (main menu, playing, paused, game over, multiplayer, and the
+//! font browser of crate::font_browser). Each screen has
+//! its own input handling and overlay, with hooks that run when it is entered and exited.
+
+use device_query::Keycode;
+
+use crate::events::EntityDestroyed;
+use crate::game_state::GameState;
+use crate::gfx::lines::Polyline;
+use crate::gfx::shapes::RoundedRect;
+use crate::gfx::text::{LinkId, LinkRegion, TextAlign, TextDraw, line_height};
+use crate::high_scores::MAX_NAME_LENGTH;
+use crate::i18n;
+use crate::input::{Action, ImeState, LinkEvent};
+use crate::layout::{self, Align, LayoutNode};
+use crate::math::Real;
+use crate::net::ConnectionState;
+use crate::net::protocol::MAX_CHAT_LENGTH;
+use crate::palette::ColorVision;
+use crate::radar::{Allegiance, RadarView};
+use crate::ship::ShipControls;
+use crate::t;
+use crate::ui::{self, Anchor, Ui, Widget, place_text};
+use crate::world::WorldEventKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    /// Enter (or clicking PLAY) starts a new game
+    MainMenu,
+    /// Esc pauses
+    Playing,
+    /// Esc resumes, Q goes back to the main menu. The arrow keys and Enter (or clicking) pick
+    /// RESUME, SETTINGS or QUIT TO MENU
+    Paused,
+    /// Opened from the pause menu, the game stays paused: vsync, fullscreen and the radar on
+    /// or off, the language, the UI scale and the colors. Esc (or clicking BACK) goes back
+    /// to the pause menu
+    Settings,
+    /// Shows the high scores, asking for a name first if the score made it in. Enter (or
+    /// clicking CONTINUE) goes back to the main menu
+    GameOver,
+    /// Clicking FONTS on the main menu lists the loaded fonts, see crate::font_browser. Esc
+    /// goes back to the main menu
+    FontBrowser,
+    /// Playing on a multiplayer server (see crate::net), joined with GameState::connect. Enter
+    /// opens the chat (Enter sends, Esc closes it), Page Up/Down scroll it back. Esc leaves
+    /// the server for the main menu
+    Multiplayer,
+}
+
+/// Symbols the overlay renderer can draw in the middle of the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayIcon {
+    Play,
+    Pause,
+    GameOver,
+}
+
+/// What gets drawn on top of the scene for the current screen.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScreenOverlay {
+    /// Translucent color over the whole screen
+    pub tint: Option<[f32; 4]>,
+    pub icon: Option<OverlayIcon>,
+    /// Backgrounds of widgets (see crate::ui), drawn by the shape renderer under the text
+    pub shapes: Vec<RoundedRect>,
+    /// Lines of widgets (the radar's), drawn by the line renderer
+    pub polylines: Vec<Polyline>,
+    /// Drawn by the text renderer, on top of the rest
+    pub text: Vec<TextDraw>,
+    /// Where buttons can be clicked, besides the links in the text
+    pub regions: Vec<LinkRegion>,
+    /// Where the caret of the text field being typed in is (left, top, right and bottom in
+    /// physical pixels), see [`text_input_area`]. The input method is on while there's one
+    /// and shows what it suggests next to it.
+    pub text_input: Option<[f32; 4]>,
+}
+
+const TEXT_SIZE: f32 = 24.0;
+// the menus' titles, spaced out (in ems)
+const TITLE_SIZE: f32 = 40.0;
+const TITLE_LETTER_SPACING: f32 = 0.3;
+const TITLE_WORD_SPACING: f32 = 0.5;
+const HINT_SIZE: f32 = 16.0;
+// under the hints, which are over the game
+const HINT_SHADOW_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.8];
+// text layout in logical pixels (see lay_out_text): the margin around the text, the space
+// between lines and between the columns of the high score table
+const MARGIN: f32 = 64.0;
+const LINE_SPACING: f32 = TEXT_SIZE * 0.25;
+const COLUMN_SPACING: f32 = TEXT_SIZE * 0.5;
+// the links and buttons on the screens, see link_text and crate::ui
+const PLAY_LINK: LinkId = LinkId(0);
+const RESUME_LINK: LinkId = LinkId(1);
+const QUIT_LINK: LinkId = LinkId(2);
+const CONTINUE_LINK: LinkId = LinkId(3);
+const FONTS_LINK: LinkId = LinkId(4);
+const SETTINGS_LINK: LinkId = LinkId(5);
+const VSYNC_LINK: LinkId = LinkId(6);
+const FULLSCREEN_LINK: LinkId = LinkId(7);
+const RADAR_LINK: LinkId = LinkId(8);
+const BACK_LINK: LinkId = LinkId(9);
+const LANGUAGE_LINK: LinkId = LinkId(10);
+const UI_SCALE_LINK: LinkId = LinkId(11);
+const COLORS_LINK: LinkId = LinkId(12);
+// what the settings screen's UI SCALE button adds
+const UI_SCALE_STEP: f32 = 0.25;
+// the multiplayer chat: the lines shown at a time, and how wide they get before they wrap, in
+// logical pixels
+const CHAT_LINES: usize = 8;
+const CHAT_WIDTH: f32 = 520.0;
+
+impl Screen {
+    /// The game stands still on this screen: the simulation, the particles and
+    /// `GameState::run_time`.
+    pub fn is_paused(self) -> bool {
+        matches!(self, Screen::Paused | Screen::Settings)
+    }
+
+    pub(crate) fn enter(self, game_state: &mut GameState) {
+        match self {
+            // the menu shows the fresh world of the next game in the background
+            Screen::MainMenu => game_state.new_game(),
+            Screen::GameOver => {
+                let world = &game_state.world;
+                log::info!(
+                    "game over in wave {} after {} ticks with {} points (seed {})",
+                    world.wave,
+                    world.tick,
+                    world.score,
+                    world.seed
+                );
+                game_state.new_high_score = None;
+                if game_state.high_scores.qualifies(world.score) {
+                    game_state.name_entry = Some(String::new());
+                }
+            }
+            Screen::Playing
+            | Screen::Paused
+            | Screen::Settings
+            | Screen::FontBrowser
+            | Screen::Multiplayer => {}
+        }
+    }
+
+    /// Handles the screen's input (and simulation). Returns the screen to switch to, if any.
+    pub(crate) fn update(self, game_state: &mut GameState) -> Option<Screen> {
+        let keyboard = &game_state.keyboard_state;
+        let ctrl = keyboard.is_down(Keycode::LControl);
+        let pressed = |key_code| !ctrl && keyboard.is_pressed(key_code);
+        let links = &game_state.links;
+        match self {
+            Screen::MainMenu => match menu_choice(game_state, &[PLAY_LINK, FONTS_LINK]) {
+                Some(PLAY_LINK) => {
+                    // to play the run again, see crate::rng
+                    log::info!("starting a game with seed {}", game_state.seed());
+                    Some(Screen::Playing)
+                }
+                Some(FONTS_LINK) => Some(Screen::FontBrowser),
+                _ => None,
+            },
+            Screen::Playing => {
+                if pressed(Keycode::Escape) {
+                    return Some(Screen::Paused);
+                }
+                update_playing(game_state);
+                game_state.world.game_over().then_some(Screen::GameOver)
+            }
+            Screen::Paused => {
+                if pressed(Keycode::Escape) {
+                    return Some(Screen::Playing);
+                } else if pressed(Keycode::Q) {
+                    return Some(Screen::MainMenu);
+                } else if pressed(Keycode::F7) {
+                    step_tick(game_state);
+                }
+                match menu_choice(game_state, &[RESUME_LINK, SETTINGS_LINK, QUIT_LINK]) {
+                    Some(RESUME_LINK) => Some(Screen::Playing),
+                    Some(SETTINGS_LINK) => Some(Screen::Settings),
+                    Some(QUIT_LINK) => Some(Screen::MainMenu),
+                    _ => None,
+                }
+            }
+            Screen::Settings => {
+                if pressed(Keycode::Escape) {
+                    return Some(Screen::Paused);
+                }
+                let buttons = [
+                    VSYNC_LINK,
+                    FULLSCREEN_LINK,
+                    RADAR_LINK,
+                    LANGUAGE_LINK,
+                    UI_SCALE_LINK,
+                    COLORS_LINK,
+                    BACK_LINK,
+                ];
+                match menu_choice(game_state, &buttons) {
+                    Some(VSYNC_LINK) => {
+                        let vsync = game_state.gfx_settings.vsync();
+                        game_state.gfx_settings.set_vsync(!vsync);
+                    }
+                    Some(FULLSCREEN_LINK) => game_state.window_settings.toggle_fullscreen(),
+                    Some(RADAR_LINK) => game_state.radar.enabled = !game_state.radar.enabled,
+                    Some(LANGUAGE_LINK) => next_language(),
+                    Some(UI_SCALE_LINK) => {
+                        // up a step, around from the largest to the smallest
+                        let step = (game_state.ui_scale() / UI_SCALE_STEP).round() + 1.0;
+                        let next = step * UI_SCALE_STEP;
+                        game_state.set_ui_scale(if next > ui::MAX_UI_SCALE {
+                            ui::MIN_UI_SCALE
+                        } else {
+                            next
+                        });
+                    }
+                    Some(COLORS_LINK) => {
+                        let all = ColorVision::ALL;
+                        let current = all
+                            .iter()
+                            .position(|vision| *vision == game_state.color_vision());
+                        let next = current.map_or(0, |index| (index + 1) % all.len());
+                        game_state.set_color_vision(all[next]);
+                    }
+                    Some(BACK_LINK) => return Some(Screen::Paused),
+                    _ => {}
+                }
+                None
+            }
+            Screen::GameOver => {
+                if game_state.name_entry.is_some() {
+                    update_name_entry(game_state);
+                    None
+                } else {
+                    (pressed(Keycode::Enter) || links.clicked(CONTINUE_LINK))
+                        .then_some(Screen::MainMenu)
+                }
+            }
+            Screen::FontBrowser => {
+                // escape cancels what the input method composes first
+                if pressed(Keycode::Escape) && !game_state.ime.is_composing() {
+                    return Some(Screen::MainMenu);
+                }
+                // taken out, it reads the game state
+                let mut font_browser = std::mem::take(&mut game_state.font_browser);
+                font_browser.update(game_state);
+                game_state.font_browser = font_browser;
+                None
+            }
+            Screen::Multiplayer => {
+                // escape closes the chat first
+                if pressed(Keycode::Escape) && game_state.chat_entry.is_none() {
+                    return Some(Screen::MainMenu);
+                }
+                update_multiplayer(game_state);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn render(self, game_state: &GameState) -> ScreenOverlay {
+        match self {
+            Screen::MainMenu => ScreenOverlay {
+                tint: Some([0.0, 0.0, 0.0, 0.55]),
+                icon: Some(OverlayIcon::Play),
+                ..menu(
+                    game_state,
+                    &t!("title"),
+                    vec![
+                        Widget::button(t!("play"), TEXT_SIZE, PLAY_LINK),
+                        Widget::button(t!("fonts"), TEXT_SIZE, FONTS_LINK),
+                    ],
+                )
+            },
+            Screen::Playing => playing_overlay(game_state),
+            Screen::Paused => ScreenOverlay {
+                tint: Some([0.0, 0.0, 0.0, 0.35]),
+                icon: Some(OverlayIcon::Pause),
+                ..menu(
+                    game_state,
+                    &t!("paused"),
+                    vec![
+                        Widget::button(t!("resume"), TEXT_SIZE, RESUME_LINK),
+                        Widget::button(t!("settings"), TEXT_SIZE, SETTINGS_LINK),
+                        Widget::button(t!("quit_to_menu"), TEXT_SIZE, QUIT_LINK),
+                    ],
+                )
+            },
+            Screen::Settings => {
+                let on_off = |on: bool| if on { t!("on") } else { t!("off") };
+                ScreenOverlay {
+                    tint: Some([0.0, 0.0, 0.0, 0.35]),
+                    icon: Some(OverlayIcon::Pause),
+                    ..menu(
+                        game_state,
+                        &t!("settings"),
+                        vec![
+                            Widget::button(
+                                t!("vsync", state = on_off(game_state.gfx_settings.vsync())),
+                                TEXT_SIZE,
+                                VSYNC_LINK,
+                            ),
+                            Widget::button(
+                                t!(
+                                    "fullscreen",
+                                    state = on_off(game_state.window_settings.fullscreen.is_some())
+                                ),
+                                TEXT_SIZE,
+                                FULLSCREEN_LINK,
+                            ),
+                            Widget::button(
+                                t!("radar", state = on_off(game_state.radar.enabled)),
+                                TEXT_SIZE,
+                                RADAR_LINK,
+                            ),
+                            Widget::button(
+                                t!("language", language = t!("language_name")),
+                                TEXT_SIZE,
+                                LANGUAGE_LINK,
+                            ),
+                            Widget::button(
+                                t!(
+                                    "ui_scale",
+                                    percent = (game_state.ui_scale() * 100.0).round()
+                                ),
+                                TEXT_SIZE,
+                                UI_SCALE_LINK,
+                            ),
+                            Widget::button(
+                                t!("colors", vision = t!(game_state.color_vision().name())),
+                                TEXT_SIZE,
+                                COLORS_LINK,
+                            ),
+                            Widget::button(t!("back"), TEXT_SIZE, BACK_LINK),
+                        ],
+                    )
+                }
+            }
+            Screen::GameOver => {
+                let (text, text_input) = high_score_text(game_state);
+                ScreenOverlay {
+                    tint: Some([0.4, 0.0, 0.0, 0.5]),
+                    icon: Some(OverlayIcon::GameOver),
+                    text,
+                    text_input,
+                    ..ScreenOverlay::default()
+                }
+            }
+            Screen::FontBrowser => game_state.font_browser.render(game_state),
+            Screen::Multiplayer => multiplayer_overlay(game_state),
+        }
+    }
+
+    pub(crate) fn exit(self, game_state: &mut GameState) {
+        match self {
+            // nothing that happened in the last tick should outlive the pause
+            Screen::Playing => game_state.events.clear(),
+            // dropping the client tells the server
+            Screen::Multiplayer => {
+                game_state.net_client = None;
+                game_state.chat_entry = None;
+                game_state.chat_scroll = 0;
+            }
+            Screen::MainMenu
+            | Screen::Paused
+            | Screen::Settings
+            | Screen::GameOver
+            | Screen::FontBrowser => {}
+        }
+    }
+}
+
+// a menu's buttons by keyboard: up and down move the focus (around from the last to the
+// first), Enter picks the focused button, or the first if none is. The focus follows the
+// cursor onto a button. Returns the button picked, that way or by clicking.
+fn menu_choice(game_state: &mut GameState, buttons: &[LinkId]) -> Option<LinkId> {
+    let keyboard = &game_state.keyboard_state;
+    let links = &game_state.links;
+    let ctrl = keyboard.is_down(Keycode::LControl);
+    let step = keyboard.is_repeat_pressed(Keycode::Down) as isize
+        - keyboard.is_repeat_pressed(Keycode::Up) as isize;
+    for event in links.events() {
+        if let LinkEvent::HoverStart(id) = *event
+            && buttons.contains(&id)
+        {
+            game_state.menu_focus = Some(id);
+        }
+    }
+    if step != 0 && !buttons.is_empty() {
+        let count = buttons.len() as isize;
+        let next = match game_state
+            .menu_focus
+            .and_then(|focus| buttons.iter().position(|id| *id == focus))
+        {
+            Some(index) => (index as isize + step).rem_euclid(count),
+            // the first going down, the last going up
+            None if step > 0 => 0,
+            None => count - 1,
+        };
+        game_state.menu_focus = Some(buttons[next as usize]);
+    }
+    if let Some(id) = buttons.iter().copied().find(|id| links.clicked(*id)) {
+        return Some(id);
+    }
+    if !ctrl && keyboard.is_pressed(Keycode::Enter) {
+        return game_state.menu_focus.or(buttons.first().copied());
+    }
+    None
+}
+
+// switches to the language after the current one, around to the first
+fn next_language() {
+    let languages = i18n::languages();
+    let current = i18n::language();
+    let next = languages
+        .iter()
+        .position(|language| *language == current)
+        .map_or(0, |index| (index + 1) % languages.len());
+    if let Err(e) = i18n::set_language(&languages[next]) {
+        log::warn!("{}", e);
+    }
+}
+
+// the title over the buttons in a panel in the top left corner, with the controls in the
+// bottom left
+fn menu(game_state: &GameState, title: &str, buttons: Vec<Widget>) -> ScreenOverlay {
+    let title = TextDraw::new(title, [0.0, 0.0], TITLE_SIZE)
+        .color(game_state.palette().highlight)
+        .letter_spacing(TITLE_LETTER_SPACING)
+        .word_spacing(TITLE_WORD_SPACING);
+    let mut column = vec![Widget::Label(title)];
+    column.extend(buttons);
+    let mut ui = Ui::new(game_state);
+    ui.add(Anchor::TopLeft, Widget::panel(Widget::column(column)));
+    ui.add(Anchor::BottomLeft, Widget::Label(controls_text(game_state)));
+    ui.finish()
+}
+
+// places the text in the window: `root` has a leaf for each of the draws (see text_leaf), in
+// the same order (see ui::place_text)
+fn lay_out_text(game_state: &GameState, root: LayoutNode, draws: Vec<TextDraw>) -> Vec<TextDraw> {
+    let [width, height] = game_state.window_size;
+    let rects = layout::layout(&root, [0.0, 0.0, width, height]);
+    debug_assert_eq!(rects.len(), draws.len());
+    draws
+        .into_iter()
+        .zip(rects)
+        .map(|(draw, rect)| place_text(draw, rect))
+        .collect()
+}
+
+// the layout box of a draw, the size of its text (empty without a font, nothing is drawn then)
+fn text_leaf(game_state: &GameState, draw: &TextDraw) -> LayoutNode {
+    LayoutNode::leaf(game_state.font_for(draw).map_or([0.0, 0.0], |font| {
+        draw.measure(&font, game_state.scale_factor)
+    }))
+}
+
+// the lines of a screen, down the left side
+fn text_column(game_state: &GameState, children: Vec<LayoutNode>) -> LayoutNode {
+    let scale = game_state.scale_factor;
+    LayoutNode::column(children)
+        .padding(MARGIN * scale)
+        .spacing(LINE_SPACING * scale)
+}
+
+// bold, if the UI font is a variable font with a bold instance
+fn heading_text(game_state: &GameState, text: &str) -> TextDraw {
+    let draw = TextDraw::new(text, [0.0, 0.0], TEXT_SIZE);
+    match game_state
+        .ui_font()
+        .and_then(|font| font.named_instance("Bold").cloned())
+    {
+        Some(bold) => draw.named_instance(bold),
+        None => draw,
+    }
+}
+
+fn controls_text(game_state: &GameState) -> TextDraw {
+    TextDraw::new(
+        game_state.input_map.prompt(&t!("controls")),
+        [0.0, 0.0],
+        HINT_SIZE,
+    )
+    .color(game_state.palette().hint)
+    .shadow([0.0, 2.0 * game_state.scale_factor], HINT_SHADOW_COLOR)
+}
+
+// text that is a link as a whole, highlighted while the cursor is on it (placed by
+// lay_out_text)
+fn link_text(game_state: &GameState, text: &str, id: LinkId) -> TextDraw {
+    let color = if game_state.links.hovered() == Some(id) {
+        game_state.palette().highlight
+    } else {
+        game_state.palette().text
+    };
+    TextDraw::new(text, [0.0, 0.0], TEXT_SIZE)
+        .color(color)
+        .link(0..text.len(), id)
+}
+
+// typing a name for the high score table: letters, digits and spaces (or what the input
+// method commits), backspace deletes, enter adds the score and escape skips it
+fn update_name_entry(game_state: &mut GameState) {
+    let keyboard = &game_state.keyboard_state;
+    let ime = &game_state.ime;
+    // the keys go to the input method while it composes
+    let pressed = |key_code| !ime.is_composing() && keyboard.is_pressed(key_code);
+    if pressed(Keycode::Escape) {
+        game_state.name_entry = None;
+        return;
+    }
+    let confirm = pressed(Keycode::Enter);
+    // held down, it keeps deleting
+    let backspace = !ime.is_composing() && keyboard.is_repeat_pressed(Keycode::Backspace);
+    let typed: Vec<char> = if ime.is_composing() {
+        ime.committed().chars().collect()
+    } else {
+        keyboard.pressed_characters()
+    };
+    // the whole last cluster
+    let backspace_to = game_state
+        .name_entry
+        .as_deref()
+        .filter(|_| backspace)
+        .map(|name| game_state.previous_caret(name, name.len()));
+    let Some(name) = game_state.name_entry.as_mut() else {
+        return;
+    };
+    if let Some(backspace_to) = backspace_to {
+        name.truncate(backspace_to);
+    }
+    for c in typed {
+        if name.chars().count() < MAX_NAME_LENGTH {
+            name.push(c.to_ascii_uppercase());
+        }
+    }
+    if confirm {
+        let name = name.trim().to_string();
+        game_state.name_entry = None;
+        game_state.add_high_score(if name.is_empty() {
+            t!("default_player_name")
+        } else {
+            name
+        });
+    }
+}
+
+// the score, the name being typed and the table, down the left side. Also returns where
+// the name's caret is while it's typed.
+fn high_score_text(game_state: &GameState) -> (Vec<TextDraw>, Option<[f32; 4]>) {
+    let world = &game_state.world;
+    let palette = game_state.palette();
+    let mut text = vec![TextDraw::new(
+        t!("score_and_wave", score = world.score, wave = world.wave),
+        [0.0, 0.0],
+        TEXT_SIZE,
+    )];
+    let mut name_caret = None;
+    text.push(match &game_state.name_entry {
+        Some(name) => {
+            let (draw, caret) = text_field(&t!("new_high_score"), name, &game_state.ime, TEXT_SIZE);
+            name_caret = Some(caret);
+            draw.color(palette.highlight)
+        }
+        None => link_text(game_state, &t!("continue"), CONTINUE_LINK),
+    });
+    text.push(heading_text(game_state, &t!("high_scores")));
+
+    // the table, a column each for the ranks, names, scores and waves. The numbers are right
+    // aligned, so their digits line up.
+    let mut columns: [Vec<TextDraw>; 4] = Default::default();
+    for (rank, entry) in game_state.high_scores.entries().iter().enumerate() {
+        let color = if game_state.new_high_score == Some(rank) {
+            palette.highlight
+        } else {
+            palette.text
+        };
+        let row = [
+            TextDraw::new(format!("{}.", rank + 1), [0.0, 0.0], TEXT_SIZE).align(TextAlign::Right),
+            TextDraw::new(entry.name.clone(), [0.0, 0.0], TEXT_SIZE),
+            TextDraw::new(entry.score.to_string(), [0.0, 0.0], TEXT_SIZE).align(TextAlign::Right),
+            TextDraw::new(
+                t!("high_score_wave", wave = entry.wave),
+                [0.0, 0.0],
+                TEXT_SIZE,
+            ),
+        ];
+        for (column, draw) in columns.iter_mut().zip(row) {
+            column.push(draw.color(color));
+        }
+    }
+
+    let scale = game_state.scale_factor;
+    let leaf = |draw: &TextDraw| text_leaf(game_state, draw);
+    // names get the room of the longest one there can be, so the table doesn't move around
+    // while one is typed
+    let name_width = leaf(&TextDraw::new(
+        "W".repeat(MAX_NAME_LENGTH),
+        [0.0, 0.0],
+        TEXT_SIZE,
+    ))
+    .size[0];
+    let table = LayoutNode::row(
+        columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let column_node = LayoutNode::column(column.iter().map(leaf).collect())
+                    .spacing(LINE_SPACING * scale);
+                match i {
+                    0 | 2 => column_node.align(Align::End),
+                    1 => column_node.min([name_width, 0.0]),
+                    _ => column_node,
+                }
+            })
+            .collect(),
+    )
+    .spacing(COLUMN_SPACING * scale);
+    let root = text_column(
+        game_state,
+        vec![
+            leaf(&text[0]),
+            leaf(&text[1]),
+            LayoutNode::gap(TEXT_SIZE * scale),
+            leaf(&text[2]),
+            table,
+        ],
+    );
+    text.extend(columns.into_iter().flatten());
+    let text = lay_out_text(game_state, root, text);
+    let text_input = name_caret.and_then(|caret| text_input_area(game_state, &text[1], caret));
+    (text, text_input)
+}
+
+/// A text field's draw: `label`, what's typed and what the input method is composing
+/// (underlined), and an underscore for the caret. Also returns the byte index of the caret
+/// in the text, for [`text_input_area`].
+pub(crate) fn text_field(label: &str, typed: &str, ime: &ImeState, size: f32) -> (TextDraw, usize) {
+    let preedit = ime.preedit();
+    let preedit_start = label.len() + typed.len();
+    let caret = preedit_start
+        + ime
+            .preedit_cursor()
+            .map_or(preedit.len(), |cursor| cursor.start);
+    let mut draw = TextDraw::new(format!("{}{}{}_", label, typed, preedit), [0.0, 0.0], size);
+    if !preedit.is_empty() {
+        draw = draw.underline(preedit_start..preedit_start + preedit.len());
+    }
+    (draw, caret)
+}
+
+/// Where the caret at the byte `caret` of `draw` is, once it's placed (see
+/// [`ScreenOverlay::text_input`]).
+pub(crate) fn text_input_area(
+    game_state: &GameState,
+    draw: &TextDraw,
+    caret: usize,
+) -> Option<[f32; 4]> {
+    let font = game_state.font_for(draw)?;
+    let scale = game_state.scale_factor;
+    let [x, y] = draw.caret_position(&font, scale, caret);
+    let height = line_height(&font, skrifa::instance::Size::new(draw.size * scale));
+    Some([x, y, x + 1.0, y + height])
+}
+
+// the ship's controls from the input map. The ship ignores them while ctrl is down, so the
+// debug hotkeys don't steer it.
+fn ship_controls(game_state: &GameState) -> ShipControls {
+    let keyboard = &game_state.keyboard_state;
+    let steering = !keyboard.is_down(Keycode::LControl);
+    let is_down = |action| steering && game_state.input_map.is_down(action, keyboard);
+    ShipControls {
+        thrust: is_down(Action::Thrust),
+        turn: is_down(Action::TurnLeft) as i32 - is_down(Action::TurnRight) as i32,
+        fire: is_down(Action::Fire),
+    }
+}
+
+// ship controls, time travel and one tick of the simulation
+fn update_playing(game_state: &mut GameState) {
+    let controls = ship_controls(game_state);
+    let keyboard = &game_state.keyboard_state;
+    let steering = !keyboard.is_down(Keycode::LControl);
+
+    // time travel: hold [ to rewind, ] to fast-forward (shift for 4x), F8 to resume
+    // playing from the current point. The simulation is paused while rewound.
+    let time_travel_speed = if keyboard.is_down(Keycode::LShift) {
+        4
+    } else {
+        1
+    };
+    let rewind = steering && keyboard.is_down(Keycode::LeftBracket);
+    let fast_forward = steering && keyboard.is_down(Keycode::RightBracket);
+    let resume = keyboard.is_pressed(Keycode::F8);
+
+    let scrubbed = if rewind {
+        game_state.time_travel.rewind(time_travel_speed)
+    } else if fast_forward {
+        game_state.time_travel.fast_forward(time_travel_speed)
+    } else {
+        None
+    };
+    if let Some(snapshot) = scrubbed {
+        game_state.world = snapshot.clone();
+    }
+    if resume {
+        game_state.time_travel.resume_here();
+    }
+
+    if !game_state.time_travel.is_live() {
+        game_state.previous_world = None;
+        return;
+    }
+    // slowed down, the world ticks every few updates, and the previous tick stays around to
+    // interpolate from in between
+    for _ in 0..game_state.time_scale.ticks() {
+        game_state.previous_world = Some(game_state.world.clone());
+        simulate_tick(game_state, controls);
+    }
+}
+
+// F7 on the pause screen: one tick, to look at what happens frame by frame
+fn step_tick(game_state: &mut GameState) {
+    if game_state.time_travel.is_live() {
+        let controls = ship_controls(game_state);
+        simulate_tick(game_state, controls);
+    }
+}
+
+fn simulate_tick(game_state: &mut GameState, controls: ShipControls) {
+    game_state
+        .replay
+        .record(game_state.world.tick, controls, game_state.tick_rate);
+    // the simulation always advances by a fixed step, so it stays deterministic
+    game_state.world.update(controls, game_state.dt());
+    // drained before the snapshot, so rewinding doesn't deliver events twice
+    let start = game_state.events.len();
+    game_state.events.append(&mut game_state.world.events);
+    for event in game_state.events[start..].iter() {
+        game_state
+            .heat_map
+            .record(event.kind, event.position.to_f32());
+        if event.kind == WorldEventKind::Death {
+            game_state.event_bus.publish(EntityDestroyed {
+                entity: event.entity,
+                position: event.position,
+            });
+        }
+    }
+    game_state.time_travel.record(game_state.world.clone());
+}
+
+// the radar in the bottom right corner: the asteroids are foes, the ship's shots friends
+fn playing_overlay(game_state: &GameState) -> ScreenOverlay {
+    if !game_state.radar.enabled {
+        return ScreenOverlay::default();
+    }
+    let world = &game_state.world;
+    let ship = &world.ship;
+    let mut radar = RadarView::new(
+        game_state.radar,
+        ship.position.to_f32(),
+        world.ship_alive().then(|| ship.angle.to_f32()),
+    );
+    for asteroid in world.asteroids.iter() {
+        radar.add(asteroid.position.to_f32(), Allegiance::Foe);
+    }
+    for bullet in world.bullets.iter() {
+        radar.add(bullet.position.to_f32(), Allegiance::Friend);
+    }
+    let mut ui = Ui::new(game_state);
+    ui.add(Anchor::BottomRight, Widget::radar(radar));
+    ui.finish()
+}
+
+// the controls go to the server, once a tick. The game ticks at the server's rate, so its
+// inputs arrive as fast as it applies them. The ship coasts while the chat is open.
+fn update_multiplayer(game_state: &mut GameState) {
+    let keyboard = &game_state.keyboard_state;
+    if game_state.chat_entry.is_some() {
+        update_chat_entry(game_state);
+    } else if keyboard.is_pressed(Keycode::Enter) {
+        game_state.chat_entry = Some(String::new());
+    }
+    let keyboard = &game_state.keyboard_state;
+    let scroll = keyboard.is_repeat_pressed(Keycode::PageUp) as isize
+        - keyboard.is_repeat_pressed(Keycode::PageDown) as isize;
+    let controls = if game_state.chat_entry.is_some() {
+        ShipControls::default()
+    } else {
+        ship_controls(game_state)
+    };
+    let now = game_state.now;
+    let Some(client) = game_state.net_client.as_mut() else {
+        return;
+    };
+    client.update(controls, now);
+    let max_scroll = client.chat().len().saturating_sub(CHAT_LINES);
+    game_state.chat_scroll = game_state
+        .chat_scroll
+        .saturating_add_signed(scroll * CHAT_LINES as isize / 2)
+        .min(max_scroll);
+    if let ConnectionState::Connected { tick_rate, .. } = *client.state() {
+        game_state.set_tick_rate(tick_rate);
+    }
+}
+
+// like the high score's name, but the message is sent as typed
+fn update_chat_entry(game_state: &mut GameState) {
+    let keyboard = &game_state.keyboard_state;
+    let ime = &game_state.ime;
+    // the keys go to the input method while it composes
+    let pressed = |key_code| !ime.is_composing() && keyboard.is_pressed(key_code);
+    if pressed(Keycode::Escape) {
+        game_state.chat_entry = None;
+        return;
+    }
+    let send = pressed(Keycode::Enter);
+    // held down, it keeps deleting
+    let backspace = !ime.is_composing() && keyboard.is_repeat_pressed(Keycode::Backspace);
+    let typed: Vec<char> = if ime.is_composing() {
+        ime.committed().chars().collect()
+    } else {
+        keyboard.pressed_characters()
+    };
+    // the whole last cluster
+    let backspace_to = game_state
+        .chat_entry
+        .as_deref()
+        .filter(|_| backspace)
+        .map(|text| game_state.previous_caret(text, text.len()));
+    let Some(text) = game_state.chat_entry.as_mut() else {
+        return;
+    };
+    if let Some(backspace_to) = backspace_to {
+        text.truncate(backspace_to);
+    }
+    for c in typed {
+        if text.chars().count() < MAX_CHAT_LENGTH {
+            text.push(c);
+        }
+    }
+    if send {
+        if let Some(client) = game_state.net_client.as_mut() {
+            client.send_chat(text);
+        }
+        game_state.chat_entry = None;
+        // back to the newest, where the message shows up
+        game_state.chat_scroll = 0;
+    }
+}
+
+// the connection, or the players' scores (in their colors, the player's marked) in the top
+// left corner, and the chat in the bottom left
+fn multiplayer_overlay(game_state: &GameState) -> ScreenOverlay {
+    let Some(client) = &game_state.net_client else {
+        return ScreenOverlay::default();
+    };
+    let palette = game_state.palette();
+    let mut column = Vec::new();
+    match (client.state(), client.arena()) {
+        (ConnectionState::Connected { player, .. }, Some(arena)) => {
+            for fighter in arena.fighters.iter() {
+                let marker = if fighter.player == *player { "> " } else { "" };
+                column.push(Widget::Label(
+                    TextDraw::new(
+                        format!(
+                            "{}{}   {}",
+                            marker,
+                            fighter.name,
+                            t!(
+                                "kills_and_deaths",
+                                kills = fighter.kills,
+                                deaths = fighter.deaths
+                            )
+                        ),
+                        [0.0, 0.0],
+                        HINT_SIZE,
+                    )
+                    .color(palette.text)
+                    .color_range(
+                        marker.len()..marker.len() + fighter.name.len(),
+                        palette.player(fighter.player.0 as usize),
+                    )
+                    .tabular_figures(),
+                ));
+            }
+        }
+        (ConnectionState::Connecting | ConnectionState::Connected { .. }, _) => {
+            column.push(Widget::label(
+                t!("connecting", server = client.server()),
+                TEXT_SIZE,
+            ));
+        }
+        (ConnectionState::Refused(reason), _) => {
+            column.push(Widget::label(
+                t!(
+                    "refused",
+                    server = client.server(),
+                    reason = reason.to_uppercase()
+                ),
+                TEXT_SIZE,
+            ));
+        }
+        (ConnectionState::TimedOut, _) => {
+            column.push(Widget::label(
+                t!("timed_out", server = client.server()),
+                TEXT_SIZE,
+            ));
+        }
+    }
+
+    // the last lines before the ones scrolled past, each wrapped on its own so the name's
+    // color stays with it
+    let scale = game_state.scale_factor;
+    let font = game_state.ui_font();
+    let chat = client.chat();
+    let end = chat.len().saturating_sub(game_state.chat_scroll);
+    let mut chat_column: Vec<Widget> = chat
+        .range(end.saturating_sub(CHAT_LINES)..end)
+        .map(|line| {
+            // the server's notices are dimmed
+            let draw = match line.player {
+                Some(player) => TextDraw::new(
+                    format!("{}: {}", line.name, line.text),
+                    [0.0, 0.0],
+                    HINT_SIZE,
+                )
+                .color(palette.text)
+                .color_range(0..line.name.len(), palette.player(player.0 as usize)),
+                None => TextDraw::new(
+                    format!("{} {}", line.name, line.text),
+                    [0.0, 0.0],
+                    HINT_SIZE,
+                )
+                .color(palette.hint),
+            };
+            let draw = match &font {
+                Some(font) => draw.wrapped(font, scale, CHAT_WIDTH * scale),
+                None => draw,
+            };
+            Widget::Label(draw)
+        })
+        .collect();
+    let mut text_input = None;
+    if let Some(entry) = &game_state.chat_entry {
+        let (field, caret) = text_field(&t!("chat_prompt"), entry, &game_state.ime, HINT_SIZE);
+        text_input = Some((field.text.clone(), caret));
+        chat_column.push(Widget::Label(field.color(palette.highlight)));
+    } else {
+        chat_column.push(Widget::Label(
+            TextDraw::new(t!("chat_hints"), [0.0, 0.0], HINT_SIZE).color(palette.hint),
+        ));
+    }
+
+    let mut ui = Ui::new(game_state);
+    ui.add(Anchor::TopLeft, Widget::panel(Widget::column(column)));
+    ui.add(
+        Anchor::BottomLeft,
+        Widget::panel(Widget::column(chat_column)),
+    );
+    if let Some(radar) = multiplayer_radar(game_state) {
+        ui.add(Anchor::BottomRight, Widget::radar(radar));
+    }
+    let overlay = ui.finish();
+    // the chat field, where the ui placed it
+    let text_input = text_input.and_then(|(field, caret)| {
+        overlay
+            .text
+            .iter()
+            .find(|draw| draw.text == field)
+            .and_then(|draw| text_input_area(game_state, draw, caret))
+    });
+    ScreenOverlay {
+        text_input,
+        ..overlay
+    }
+}
+
+// the other players and their shots are foes, the player's shots friends. Around the ship
+// where it's predicted, or where it was destroyed.
+fn multiplayer_radar(game_state: &GameState) -> Option<RadarView> {
+    let client = game_state.net_client.as_ref()?;
+    let (player, arena) = (client.player()?, client.arena()?);
+    if !game_state.radar.enabled {
+        return None;
+    }
+    let ship = client
+        .predicted_ship()
+        .or_else(|| arena.fighter(player).map(|fighter| &fighter.ship))?;
+    let mut radar = RadarView::new(
+        game_state.radar,
+        ship.position.to_f32(),
+        client.predicted_ship().map(|ship| ship.angle.to_f32()),
+    );
+    for fighter in arena.fighters.iter() {
+        if fighter.player != player && fighter.alive() {
+            radar.add(fighter.ship.position.to_f32(), Allegiance::Foe);
+        }
+    }
+    for shot in arena.shots.iter() {
+        let allegiance = if shot.owner == player {
+            Allegiance::Friend
+        } else {
+            Allegiance::Foe
+        };
+        radar.add(shot.bullet.position.to_f32(), allegiance);
+    }
+    Some(radar)
+}
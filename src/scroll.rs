@@ -0,0 +1,134 @@
+//! Smooth scrolling for text panels (see crate::log_panel). The offset moves in pixels rather
+//! than whole lines, and it has inertia:
+//!
+//! - Mouse wheel notches and Page Up/Down give it a velocity that decays, so it glides to a
+//!   stop instead of jumping.
+//! - Touchpads move it directly by their precise deltas, and it keeps going at the speed they
+//!   were moving when they stop.
+//! - It can go past either end, with more resistance the further it goes, and springs back
+//!   (rubber-banding).
+//!
+//! [`SmoothScroll::update`] advances it once per game update, and panels are drawn at
+//! [`SmoothScroll::interpolated_offset`] so the motion is smooth between updates too.
+
+use crate::interpolation::lerp;
+
+// the velocity decays by e^-FRICTION per second
+const FRICTION: f32 = 6.0;
+// how fast it springs back from past the ends, in the same way
+const SPRING: f32 = 14.0;
+// how far past the ends (in pixels) the resistance doubles
+const RUBBER_BAND_RANGE: f32 = 120.0;
+// slower than this (pixels per second) it stops
+const STOP_SPEED: f32 = 4.0;
+
+#[derive(Debug, Clone, Default)]
+pub struct SmoothScroll {
+    // pixels from the top of the content to the top of the view
+    offset: f32,
+    previous_offset: f32,
+    // pixels per second
+    velocity: f32,
+    content_height: f32,
+    view_height: f32,
+    // a touchpad moved the offset since the last update
+    dragged: bool,
+}
+
+impl SmoothScroll {
+    /// Pixels from the top of the content to the top of the view. Negative or past
+    /// [`SmoothScroll::max_offset`] while rubber-banding.
+    pub fn offset(&self) -> f32 {
+        self.offset
+    }
+
+    /// The offset to draw at `alpha` between the previous update and the last one, see
+    /// [`crate::interpolation`].
+    pub fn interpolated_offset(&self, alpha: f32) -> f32 {
+        lerp(self.previous_offset, self.offset, alpha)
+    }
+
+    pub fn max_offset(&self) -> f32 {
+        (self.content_height - self.view_height).max(0.0)
+    }
+
+    /// Sets the height of the content and of the view it's scrolled in. A view at the end of
+    /// the content stays there when the content grows, so it follows e.g. new log lines.
+    pub fn set_extent(&mut self, content_height: f32, view_height: f32) {
+        let at_end = self.velocity == 0.0 && (self.offset - self.max_offset()).abs() < 1.0;
+        self.content_height = content_height;
+        self.view_height = view_height;
+        if at_end {
+            self.offset = self.max_offset();
+        }
+    }
+
+    /// Where it comes to a stop if nothing else happens (ignoring the ends).
+    pub fn destination(&self) -> f32 {
+        self.offset + self.velocity / FRICTION
+    }
+
+    /// Glides `distance` pixels further than it was going to (down for positive distances),
+    /// e.g. for mouse wheel notches.
+    pub fn fling(&mut self, distance: f32) {
+        self.velocity += distance * FRICTION;
+    }
+
+    /// Glides to `offset`, clamped to the content.
+    pub fn scroll_to(&mut self, offset: f32) {
+        let target = offset.clamp(0.0, self.max_offset());
+        self.velocity = (target - self.offset) * FRICTION;
+    }
+
+    /// Moves the offset right away, e.g. by a touchpad's delta since the last update (`dt`
+    /// seconds ago). Past the ends it only moves part of the way.
+    pub fn drag(&mut self, delta: f32, dt: f32) {
+        let overscroll = self.overscroll();
+        let delta = if overscroll * delta > 0.0 {
+            delta / (1.0 + overscroll.abs() / RUBBER_BAND_RANGE)
+        } else {
+            delta
+        };
+        self.offset += delta;
+        if dt > 0.0 {
+            self.velocity = delta / dt;
+        }
+        self.dragged = true;
+    }
+
+    /// Advances the glide and the spring back by `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        self.previous_offset = self.offset;
+        // a touchpad moves it itself, it only glides once that stops
+        if std::mem::take(&mut self.dragged) {
+            return;
+        }
+        let decay = (-FRICTION * dt).exp();
+        // the distance covered while the velocity decays, so a fling goes exactly as far as
+        // asked regardless of the tick rate
+        self.offset += self.velocity * (1.0 - decay) / FRICTION;
+        self.velocity *= decay;
+
+        let overscroll = self.overscroll();
+        if overscroll != 0.0 {
+            let spring = (-SPRING * dt).exp();
+            self.offset -= overscroll * (1.0 - spring);
+            self.velocity *= spring;
+            if self.overscroll().abs() < 0.5 {
+                self.offset = self.offset.clamp(0.0, self.max_offset());
+            }
+        }
+        if self.velocity.abs() < STOP_SPEED {
+            self.velocity = 0.0;
+        }
+    }
+
+    // how far past the top (negative) or the end (positive) it is
+    fn overscroll(&self) -> f32 {
+        if self.offset < 0.0 {
+            self.offset
+        } else {
+            (self.offset - self.max_offset()).max(0.0)
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+
+use crate::collision::Shape;
+use crate::math::{Real, Scalar, SimVec2};
+use crate::world::wrap_position;
+
+/// What the ship is asked to do this tick, usually filled in from the InputMap.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ShipControls {
+    pub thrust: bool,
+    /// -1 = turn right (clockwise), 1 = turn left, 0 = don't turn
+    pub turn: i32,
+    pub fire: bool,
+}
+
+impl ShipControls {
+    /// The controls in a byte, for replays and the network protocol: thrust in bit 0, fire in
+    /// bit 1 and the turn in bits 2-3 (1 = left, 2 = right).
+    pub fn to_byte(self) -> u8 {
+        let turn = match self.turn {
+            0 => 0,
+            turn if turn > 0 => 1,
+            _ => 2,
+        };
+        self.thrust as u8 | (self.fire as u8) << 1 | turn << 2
+    }
+
+    pub fn from_byte(byte: u8) -> Self {
+        Self {
+            thrust: byte & 1 != 0,
+            fire: byte & 2 != 0,
+            turn: match (byte >> 2) & 3 {
+                1 => 1,
+                2 => -1,
+                _ => 0,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ShipParams {
+    /// Acceleration while thrusting, in units/s²
+    pub thrust: Scalar,
+    /// Angular acceleration while turning, in rad/s²
+    pub angular_acceleration: Scalar,
+    /// Fraction of the velocity lost per second
+    pub damping: Scalar,
+    /// Fraction of the angular velocity lost per second (higher, so turning stops quickly)
+    pub angular_damping: Scalar,
+    /// Seconds between shots while fire is held
+    pub reload_time: Scalar,
+    /// Bullet speed relative to the ship, in units/s
+    pub bullet_speed: Scalar,
+    /// Seconds until a bullet disappears
+    pub bullet_lifetime: Scalar,
+}
+
+impl Default for ShipParams {
+    fn default() -> Self {
+        Self {
+            thrust: Scalar::from_int(320),
+            angular_acceleration: Scalar::from_int(24),
+            damping: Scalar::from_f32(0.4),
+            angular_damping: Scalar::from_int(6),
+            reload_time: Scalar::from_f32(0.2),
+            bullet_speed: Scalar::from_int(600),
+            bullet_lifetime: Scalar::from_f32(1.2),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ship {
+    pub position: SimVec2,
+    pub velocity: SimVec2,
+    /// Heading in radians, 0 = pointing right, counter-clockwise (world y points up)
+    pub angle: Scalar,
+    pub angular_velocity: Scalar,
+    pub params: ShipParams,
+    thrusting: bool,
+    reload: Scalar,
+}
+
+/// A shot fired by the ship. Collides with asteroids as a small circle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bullet {
+    pub id: u32,
+    pub position: SimVec2,
+    pub velocity: SimVec2,
+    /// Seconds left until the bullet disappears
+    pub lifetime: Scalar,
+}
+
+pub const BULLET_RADIUS: f32 = 2.0;
+/// Length of the streak a bullet is drawn as, behind it
+pub const BULLET_STREAK: f32 = 4.0;
+
+/// The outline in ship space (nose pointing along +x), as a closed loop
+pub const HULL: [[f32; 2]; 4] = [[16.0, 0.0], [-10.0, 9.0], [-5.0, 0.0], [-10.0, -9.0]];
+// The collider is the convex hull of the outline (i.e. without the notch at the back)
+const COLLIDER: [[f32; 2]; 3] = [[16.0, 0.0], [-10.0, 9.0], [-10.0, -9.0]];
+/// The engine's flame while thrusting, in ship space, as an open line
+pub const FLAME: [[f32; 2]; 3] = [[-7.0, 4.0], [-17.0, 0.0], [-7.0, -4.0]];
+
+impl Ship {
+    pub fn new(position: SimVec2) -> Self {
+        Self {
+            position,
+            velocity: SimVec2::zero(),
+            angle: Scalar::from_f32(std::f32::consts::FRAC_PI_2),
+            angular_velocity: Scalar::ZERO,
+            params: ShipParams::default(),
+            thrusting: false,
+            reload: Scalar::ZERO,
+        }
+    }
+
+    /// Advances the ship by one fixed tick of `dt` seconds and wraps it around the edges of
+    /// the world.
+    pub fn update(&mut self, controls: ShipControls, dt: Scalar) {
+        let params = self.params;
+
+        self.angular_velocity += params.angular_acceleration * Scalar::from_int(controls.turn) * dt;
+        self.angular_velocity -= self.angular_velocity * clamp_to_one(params.angular_damping * dt);
+        self.angle += self.angular_velocity * dt;
+        // keep the angle small, so precision doesn't degrade after spinning for a while
+        let turn = Scalar::from_f32(std::f32::consts::TAU);
+        self.angle -= (self.angle / turn).floor() * turn;
+
+        self.thrusting = controls.thrust;
+        if controls.thrust {
+            self.velocity += SimVec2::from_angle(self.angle).scale(params.thrust * dt);
+        }
+        self.velocity -= self.velocity.scale(clamp_to_one(params.damping * dt));
+        self.position = wrap_position(self.position + self.velocity.scale(dt));
+
+        if self.reload > Scalar::ZERO {
+            self.reload -= dt;
+        }
+    }
+
+    /// True if the engine fired during the last update
+    pub fn thrusting(&self) -> bool {
+        self.thrusting
+    }
+
+    /// Fires a bullet from the nose of the ship, unless it is still reloading. `id` is the
+    /// bullet's entity id.
+    pub fn fire(&mut self, id: u32) -> Option<Bullet> {
+        if self.reload > Scalar::ZERO {
+            return None;
+        }
+        self.reload = self.params.reload_time;
+        let direction = SimVec2::from_angle(self.angle);
+        Some(Bullet {
+            id,
+            position: wrap_position(self.position + direction.scale(Scalar::from_int(16))),
+            velocity: self.velocity + direction.scale(self.params.bullet_speed),
+            lifetime: self.params.bullet_lifetime,
+        })
+    }
+
+    /// Collision shape in ship space (see Collider for placing it in the world).
+    pub fn collider_shape() -> Shape {
+        Shape::Polygon {
+            points: COLLIDER
+                .iter()
+                .map(|[x, y]| SimVec2::new(Scalar::from_f32(*x), Scalar::from_f32(*y)))
+                .collect(),
+        }
+    }
+
+    /// Line segments (pairs of points, in world units) to draw the ship with.
+    pub fn line_segments(&self) -> Vec<[f32; 2]> {
+        let [x, y] = self.position.to_f32();
+        let (sin, cos) = (
+            self.angle.table_sin().to_f32(),
+            self.angle.table_cos().to_f32(),
+        );
+        let transform = |[px, py]: [f32; 2]| [x + px * cos - py * sin, y + px * sin + py * cos];
+
+        let mut segments = Vec::with_capacity((HULL.len() + FLAME.len()) * 2);
+        for i in 0..HULL.len() {
+            segments.push(transform(HULL[i]));
+            segments.push(transform(HULL[(i + 1) % HULL.len()]));
+        }
+        if self.thrusting {
+            for pair in FLAME.windows(2) {
+                segments.push(transform(pair[0]));
+                segments.push(transform(pair[1]));
+            }
+        }
+        segments
+    }
+}
+
+impl Bullet {
+    /// Moves the bullet and counts down its lifetime. Returns false once it has expired.
+    pub fn update(&mut self, dt: Scalar) -> bool {
+        self.position = wrap_position(self.position + self.velocity.scale(dt));
+        self.lifetime -= dt;
+        self.lifetime > Scalar::ZERO
+    }
+
+    /// A short streak behind the bullet, as a line segment in world units.
+    pub fn line_segments(&self) -> [[f32; 2]; 2] {
+        let [x, y] = self.position.to_f32();
+        let [vx, vy] = self.velocity.to_f32();
+        let speed = (vx * vx + vy * vy).sqrt().max(1.0);
+        let streak = BULLET_STREAK / speed;
+        [[x - vx * streak, y - vy * streak], [x, y]]
+    }
+}
+
+// damping * dt above 1 would flip the velocity around instead of stopping it
+fn clamp_to_one(value: Scalar) -> Scalar {
+    if value > Scalar::ONE {
+        Scalar::ONE
+    } else {
+        value
+    }
+}
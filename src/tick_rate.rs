@@ -0,0 +1,79 @@
+//! The simulation's fixed tick rate. The engine updates the game state (and simulates one
+//! tick) at this rate, and everything that advances with the ticks should use
+//! `GameState::dt` rather than assume 60 Hz.
+
+use std::fmt;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::math::{Real, Scalar};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u32", into = "u32")]
+pub enum TickRate {
+    Hz30,
+    #[default]
+    Hz60,
+    Hz120,
+}
+
+impl TickRate {
+    pub const ALL: [TickRate; 3] = [TickRate::Hz30, TickRate::Hz60, TickRate::Hz120];
+
+    pub fn hz(self) -> u32 {
+        match self {
+            TickRate::Hz30 => 30,
+            TickRate::Hz60 => 60,
+            TickRate::Hz120 => 120,
+        }
+    }
+
+    pub fn from_hz(hz: u32) -> Option<TickRate> {
+        Self::ALL.into_iter().find(|rate| rate.hz() == hz)
+    }
+
+    /// The length of a tick in seconds
+    pub fn seconds(self) -> f32 {
+        1.0 / self.hz() as f32
+    }
+
+    /// The length of a tick for the simulation
+    pub fn dt(self) -> Scalar {
+        Scalar::from_f32(self.seconds())
+    }
+
+    /// The next rate in 30 -> 60 -> 120 -> 30
+    pub fn next(self) -> TickRate {
+        match self {
+            TickRate::Hz30 => TickRate::Hz60,
+            TickRate::Hz60 => TickRate::Hz120,
+            TickRate::Hz120 => TickRate::Hz30,
+        }
+    }
+
+    /// The time between updates (or the server's ticks, see crate::net)
+    pub fn update_interval(self) -> Duration {
+        Duration::from_secs(1) / self.hz()
+    }
+}
+
+impl TryFrom<u32> for TickRate {
+    type Error = String;
+
+    fn try_from(hz: u32) -> Result<Self, Self::Error> {
+        Self::from_hz(hz).ok_or_else(|| format!("unsupported tick rate {} (30, 60 or 120)", hz))
+    }
+}
+
+impl From<TickRate> for u32 {
+    fn from(rate: TickRate) -> u32 {
+        rate.hz()
+    }
+}
+
+impl fmt::Display for TickRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} Hz", self.hz())
+    }
+}
@@ -0,0 +1,119 @@
+//! How fast gameplay runs compared to real time, e.g. the slow motion when the ship is
+//! destroyed (see [`on_entity_destroyed`], subscribed to the event bus). The UI always runs
+//! in real time.
+//!
+//! The simulation still only moves in fixed ticks, so it stays deterministic: at half speed
+//! it ticks every other update, at double speed twice an update. The renderers interpolate
+//! between the ticks (see [`GameState::render_alpha`](crate::GameState::render_alpha)), so
+//! slow motion moves smoothly rather than in steps. The speed eases toward its target instead
+//! of jumping to it.
+
+use crate::events::EntityDestroyed;
+use crate::game_state::GameState;
+use crate::world::EntityId;
+
+pub const MIN_TIME_SCALE: f32 = 0.05;
+pub const MAX_TIME_SCALE: f32 = 4.0;
+
+// the most ticks an update simulates, so a high time scale can't stall the game
+const MAX_TICKS_PER_UPDATE: u32 = 4;
+// how quickly the speed catches up with its target, the difference shrinks by e every 1/x
+// seconds
+const EASING_RATE: f32 = 6.0;
+
+const DEATH_SLOW_MOTION: f32 = 0.25;
+// seconds of real time
+const DEATH_SLOW_MOTION_DURATION: f32 = 1.5;
+
+#[derive(Debug, Clone)]
+pub struct TimeScale {
+    // the speed set by the game, the target when there's no slow motion
+    scale: f32,
+    slow_motion: f32,
+    // seconds of real time the slow motion lasts for
+    slow_motion_left: f32,
+    current: f32,
+    // how far the simulation is toward its next tick, in ticks
+    progress: f32,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            slow_motion: 1.0,
+            slow_motion_left: 0.0,
+            current: 1.0,
+            progress: 0.0,
+        }
+    }
+}
+
+impl TimeScale {
+    /// Sets the speed gameplay runs at, 1 for real time.
+    pub fn set(&mut self, scale: f32) {
+        self.scale = scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+    }
+
+    /// Slows gameplay down to `scale` for `duration` seconds (of real time). The slower slow
+    /// motion wins.
+    pub fn slow_motion(&mut self, scale: f32, duration: f32) {
+        let scale = scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+        if self.slow_motion_left == 0.0 || scale <= self.slow_motion {
+            self.slow_motion = scale;
+        }
+        self.slow_motion_left = self.slow_motion_left.max(duration);
+    }
+
+    /// The speed gameplay runs at right now.
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    /// How far the simulation is from its last tick toward the next one, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+
+    fn target(&self) -> f32 {
+        if self.slow_motion_left > 0.0 {
+            self.slow_motion.min(self.scale)
+        } else {
+            self.scale
+        }
+    }
+
+    /// How many ticks the simulation advances by this update.
+    pub(crate) fn ticks(&mut self) -> u32 {
+        self.progress += self.current;
+        let ticks = (self.progress.floor() as u32).min(MAX_TICKS_PER_UPDATE);
+        self.progress = (self.progress - ticks as f32).clamp(0.0, 1.0 - f32::EPSILON);
+        ticks
+    }
+
+    /// Eases the speed toward its target, by `dt` seconds of real time.
+    pub(crate) fn update(&mut self, dt: f32) {
+        self.slow_motion_left = (self.slow_motion_left - dt).max(0.0);
+        let target = self.target();
+        self.current += (target - self.current) * (1.0 - (-EASING_RATE * dt).exp());
+        if (target - self.current).abs() < 0.001 {
+            self.current = target;
+        }
+    }
+
+    /// Back to the set speed at once, e.g. for a new game.
+    pub(crate) fn reset(&mut self) {
+        self.slow_motion_left = 0.0;
+        self.current = self.scale;
+        self.progress = 0.0;
+    }
+}
+
+/// Slows gameplay down for a moment when the ship is destroyed.
+pub fn on_entity_destroyed(game_state: &mut GameState, event: &EntityDestroyed) {
+    if event.entity == EntityId::Ship {
+        game_state
+            .time_scale
+            .slow_motion(DEATH_SLOW_MOTION, DEATH_SLOW_MOTION_DURATION);
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::VecDeque;
+
+use crate::memory::HeapSize;
+
+/// Ring buffer of the last `capacity` per-tick snapshots, that can be scrubbed back and forth
+/// while the game is running. Used as a debug tool: rewind to just before a physics bug
+/// happened and step through it again.
+///
+/// While the cursor is somewhere in the past the buffer is "not live": the caller should
+/// pause the simulation and show the snapshot at the cursor instead.
+pub struct TimeTravel<T> {
+    snapshots: VecDeque<T>,
+    capacity: usize,
+    // index into snapshots, None = live
+    cursor: Option<usize>,
+}
+
+impl<T> TimeTravel<T> {
+    /// A capacity of 0 disables recording.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+            cursor: None,
+        }
+    }
+
+    pub fn is_live(&self) -> bool {
+        self.cursor.is_none()
+    }
+
+    /// Records the snapshot of the latest tick. Ignored while not live.
+    pub fn record(&mut self, snapshot: T) {
+        if self.capacity == 0 || !self.is_live() {
+            return;
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// Moves the cursor `ticks` back (stopping at the oldest snapshot) and returns the
+    /// snapshot there.
+    pub fn rewind(&mut self, ticks: usize) -> Option<&T> {
+        let newest = self.snapshots.len().checked_sub(1)?;
+        let cursor = self.cursor.unwrap_or(newest).saturating_sub(ticks);
+        self.cursor = Some(cursor);
+        self.snapshots.get(cursor)
+    }
+
+    /// Moves the cursor `ticks` forward and returns the snapshot there. Reaching the newest
+    /// snapshot makes the buffer live again.
+    pub fn fast_forward(&mut self, ticks: usize) -> Option<&T> {
+        let cursor = self.cursor?;
+        let newest = self.snapshots.len() - 1;
+        let cursor = (cursor + ticks).min(newest);
+        self.cursor = if cursor == newest { None } else { Some(cursor) };
+        self.snapshots.get(cursor)
+    }
+
+    /// Drops everything after the cursor and goes live again, so the simulation continues
+    /// from the snapshot at the cursor.
+    pub fn resume_here(&mut self) {
+        if let Some(cursor) = self.cursor.take() {
+            self.snapshots.truncate(cursor + 1);
+        }
+    }
+
+    /// Forgets all snapshots (e.g. when a new game starts) and goes live.
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.cursor = None;
+    }
+}
+
+impl<T: HeapSize> HeapSize for TimeTravel<T> {
+    fn heap_size(&self) -> usize {
+        self.snapshots.heap_size()
+    }
+}
@@ -0,0 +1,327 @@
+//! Immediate-mode UI widgets: labels, buttons, sliders and panels, arranged by crate::layout. Screens
+//! build their widgets every frame (see crate::screen), [`Ui::add`] places them against an edge
+//! or corner of the window, and [`Ui::finish`] turns them into the shapes, text and click
+//! regions of the screen's overlay. Buttons and sliders are hovered and clicked through
+//! [`crate::input::Links`], like the links in text.
+//!
+//! Everything is in physical pixels, like the text renderer. Sizes are scaled from logical
+//! pixels with the window's scale factor times the player's UI scale (see
+//! `GameState::set_ui_scale`).
+
+use crate::game_state::GameState;
+use crate::gfx::lines::Polyline;
+use crate::gfx::shapes::RoundedRect;
+use crate::gfx::text::{LinkId, LinkRegion, TextAlign, TextDraw};
+use crate::layout::{self, Align, LayoutNode};
+use crate::palette::with_alpha;
+use crate::radar::RadarView;
+use crate::screen::ScreenOverlay;
+
+/// The UI scales players can pick, on top of the window's scale factor
+pub const MIN_UI_SCALE: f32 = 0.75;
+pub const MAX_UI_SCALE: f32 = 2.0;
+
+// in logical pixels
+const MARGIN: f32 = 64.0;
+const SPACING: f32 = 8.0;
+const PANEL_PADDING: f32 = 16.0;
+const BUTTON_PADDING: f32 = 8.0;
+const PANEL_CORNER_RADIUS: f32 = 8.0;
+const BUTTON_CORNER_RADIUS: f32 = 6.0;
+const BUTTON_BORDER_WIDTH: f32 = 1.0;
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
+const BUTTON_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.12];
+const BUTTON_HOVER_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.25];
+// the highlight color, see crate::palette
+const BUTTON_PRESSED_ALPHA: f32 = 0.4;
+const BUTTON_BORDER_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.6];
+const SLIDER_WIDTH: f32 = 240.0;
+const SLIDER_HEIGHT: f32 = 12.0;
+const SLIDER_FILL_ALPHA: f32 = 0.8;
+
+/// Where in the window [`Ui::add`] puts widgets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    // where a box of `size` goes in `area` (left, top, right and bottom edges)
+    fn place(self, size: [f32; 2], area: [f32; 4]) -> [f32; 4] {
+        let [left, top, right, bottom] = area;
+        let x = match self {
+            Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => left,
+            Anchor::Top | Anchor::Center | Anchor::Bottom => (left + right - size[0]) / 2.0,
+            Anchor::TopRight | Anchor::Right | Anchor::BottomRight => right - size[0],
+        };
+        let y = match self {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => top,
+            Anchor::Left | Anchor::Center | Anchor::Right => (top + bottom - size[1]) / 2.0,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => bottom - size[1],
+        };
+        [x, y, x + size[0], y + size[1]]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Widget {
+    Label(TextDraw),
+    /// Text on a background that lights up while hovered (or chosen with the keyboard, see
+    /// `GameState::menu_focus`). Clicks are reported by [`crate::input::Links`] with its id.
+    Button {
+        text: TextDraw,
+        id: LinkId,
+    },
+    /// A background behind its child
+    Panel {
+        child: Box<Widget>,
+        color: [f32; 4],
+    },
+    /// Side by side, centered vertically
+    Row(Vec<Widget>),
+    /// One under the other, all as wide as the widest
+    Column(Vec<Widget>),
+    /// A track filled up to `value` (0 to 1). Clicks are reported by
+    /// [`crate::input::Links`] with its id, the screen works out the value from where the
+    /// cursor is in [`ScreenOverlay::regions`].
+    Slider {
+        id: LinkId,
+        value: f32,
+    },
+    /// The contacts around the player's ship, see [`crate::radar`]
+    Radar(Box<RadarView>),
+}
+
+impl Widget {
+    pub fn label(text: impl Into<String>, size: f32) -> Self {
+        Widget::Label(TextDraw::new(text, [0.0, 0.0], size))
+    }
+
+    pub fn button(text: impl Into<String>, size: f32, id: LinkId) -> Self {
+        Widget::Button {
+            text: TextDraw::new(text, [0.0, 0.0], size),
+            id,
+        }
+    }
+
+    pub fn panel(child: Widget) -> Self {
+        Widget::Panel {
+            child: Box::new(child),
+            color: PANEL_COLOR,
+        }
+    }
+
+    pub fn row(children: Vec<Widget>) -> Self {
+        Widget::Row(children)
+    }
+
+    pub fn column(children: Vec<Widget>) -> Self {
+        Widget::Column(children)
+    }
+
+    pub fn slider(value: f32, id: LinkId) -> Self {
+        Widget::Slider { id, value }
+    }
+
+    pub fn radar(view: RadarView) -> Self {
+        Widget::Radar(Box::new(view))
+    }
+}
+
+// what goes in the rectangles of a widget's layout, in the same (depth first) order
+enum Item {
+    Text(Box<TextDraw>),
+    Button(LinkId),
+    Panel([f32; 4]),
+    Slider(LinkId, f32),
+    Radar(Box<RadarView>),
+}
+
+/// Places text in a rectangle of a layout: right aligned text at its right edge, the rest at
+/// its left edge.
+pub fn place_text(mut draw: TextDraw, rect: [f32; 4]) -> TextDraw {
+    let [left, top, right, _] = rect;
+    draw.position = match draw.align {
+        TextAlign::Right => [right, top],
+        TextAlign::Left | TextAlign::Decimal => [left, top],
+    };
+    draw
+}
+
+pub struct Ui<'a> {
+    game_state: &'a GameState,
+    scale_factor: f32,
+    window_size: [f32; 2],
+    hovered: Option<LinkId>,
+    // the button that's lit up: the one chosen with the keyboard, or the hovered one
+    highlighted: Option<LinkId>,
+    left_down: bool,
+    shapes: Vec<RoundedRect>,
+    polylines: Vec<Polyline>,
+    text: Vec<TextDraw>,
+    regions: Vec<LinkRegion>,
+}
+
+impl<'a> Ui<'a> {
+    /// A UI for the game state's window, with the hovers of the last update and the menu's
+    /// focus.
+    pub fn new(game_state: &'a GameState) -> Self {
+        Self {
+            game_state,
+            scale_factor: game_state.scale_factor,
+            window_size: game_state.window_size,
+            hovered: game_state.links().hovered(),
+            highlighted: game_state.menu_focus.or(game_state.links().hovered()),
+            left_down: game_state.mouse_state().is_left_down(),
+            shapes: Vec::new(),
+            polylines: Vec::new(),
+            text: Vec::new(),
+            regions: Vec::new(),
+        }
+    }
+
+    /// Lays `widget` out at its preferred size and puts it at `anchor`, inside the window's
+    /// margin.
+    pub fn add(&mut self, anchor: Anchor, widget: Widget) {
+        let mut items = Vec::new();
+        let node = self.build(widget, &mut items);
+        let margin = MARGIN * self.scale_factor;
+        let [width, height] = self.window_size;
+        let area = [margin, margin, width - margin, height - margin];
+        let rect = anchor.place(node.preferred_size(), area);
+        let rects = layout::layout(&node, rect);
+        debug_assert_eq!(rects.len(), items.len());
+        for (item, rect) in items.into_iter().zip(rects) {
+            match item {
+                Item::Text(draw) => self.text.push(place_text(*draw, rect)),
+                Item::Button(id) => {
+                    let highlighted = self.highlighted == Some(id);
+                    let color = if self.left_down && self.hovered == Some(id) {
+                        with_alpha(self.game_state.palette().highlight, BUTTON_PRESSED_ALPHA)
+                    } else if highlighted {
+                        BUTTON_HOVER_COLOR
+                    } else {
+                        BUTTON_COLOR
+                    };
+                    let mut shape = RoundedRect::new(rect, color)
+                        .corner_radius(BUTTON_CORNER_RADIUS * self.scale_factor);
+                    if highlighted {
+                        shape = shape
+                            .border(BUTTON_BORDER_WIDTH * self.scale_factor, BUTTON_BORDER_COLOR);
+                    }
+                    self.shapes.push(shape);
+                    self.regions.push(LinkRegion { id, rect });
+                }
+                Item::Panel(color) => self.shapes.push(
+                    RoundedRect::new(rect, color)
+                        .corner_radius(PANEL_CORNER_RADIUS * self.scale_factor),
+                ),
+                Item::Slider(id, value) => {
+                    let [left, top, right, bottom] = rect;
+                    let radius = (bottom - top) / 2.0;
+                    let color = if self.hovered == Some(id) {
+                        BUTTON_HOVER_COLOR
+                    } else {
+                        BUTTON_COLOR
+                    };
+                    self.shapes
+                        .push(RoundedRect::new(rect, color).corner_radius(radius));
+                    let fill_right = left + (right - left) * value.clamp(0.0, 1.0);
+                    self.shapes.push(
+                        RoundedRect::new(
+                            [left, top, fill_right, bottom],
+                            with_alpha(self.game_state.palette().highlight, SLIDER_FILL_ALPHA),
+                        )
+                        .corner_radius(radius),
+                    );
+                    self.regions.push(LinkRegion { id, rect });
+                }
+                Item::Radar(view) => {
+                    let (shapes, polylines) =
+                        view.draw(rect, self.scale_factor, self.game_state.palette());
+                    self.shapes.extend(shapes);
+                    self.polylines.extend(polylines);
+                }
+            }
+        }
+    }
+
+    /// The overlay with the widgets, to fill in the rest of (like the tint).
+    pub fn finish(self) -> ScreenOverlay {
+        ScreenOverlay {
+            shapes: self.shapes,
+            polylines: self.polylines,
+            text: self.text,
+            regions: self.regions,
+            ..ScreenOverlay::default()
+        }
+    }
+
+    // the widget's layout, with the items for its rectangles
+    fn build(&self, widget: Widget, items: &mut Vec<Item>) -> LayoutNode {
+        let scale = self.scale_factor;
+        match widget {
+            Widget::Label(draw) => {
+                let node = LayoutNode::leaf(self.measure(&draw));
+                items.push(Item::Text(Box::new(draw)));
+                node
+            }
+            Widget::Button { text, id } => {
+                // the background, with the text in the middle
+                items.push(Item::Button(id));
+                let text_node = LayoutNode::leaf(self.measure(&text));
+                items.push(Item::Text(Box::new(text)));
+                LayoutNode::row(vec![LayoutNode::spacer(), text_node, LayoutNode::spacer()])
+                    .padding(BUTTON_PADDING * scale)
+                    .framed()
+            }
+            Widget::Panel { child, color } => {
+                items.push(Item::Panel(color));
+                LayoutNode::column(vec![self.build(*child, items)])
+                    .padding(PANEL_PADDING * scale)
+                    .align(Align::Stretch)
+                    .framed()
+            }
+            Widget::Row(children) => LayoutNode::row(
+                children
+                    .into_iter()
+                    .map(|child| self.build(child, items))
+                    .collect(),
+            )
+            .spacing(SPACING * scale)
+            .align(Align::Center),
+            Widget::Column(children) => LayoutNode::column(
+                children
+                    .into_iter()
+                    .map(|child| self.build(child, items))
+                    .collect(),
+            )
+            .spacing(SPACING * scale)
+            .align(Align::Stretch),
+            Widget::Slider { id, value } => {
+                items.push(Item::Slider(id, value));
+                LayoutNode::leaf([SLIDER_WIDTH * scale, SLIDER_HEIGHT * scale])
+            }
+            Widget::Radar(view) => {
+                let size = view.settings.size * scale;
+                items.push(Item::Radar(view));
+                LayoutNode::leaf([size, size])
+            }
+        }
+    }
+
+    // the size of the text, empty without a font (nothing is drawn then)
+    fn measure(&self, draw: &TextDraw) -> [f32; 2] {
+        self.game_state
+            .font_for(draw)
+            .map_or([0.0, 0.0], |font| draw.measure(&font, self.scale_factor))
+    }
+}
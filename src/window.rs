@@ -0,0 +1,144 @@
+use winit::{
+    dpi::{LogicalSize, PhysicalPosition},
+    monitor::MonitorHandle,
+    window::{Fullscreen, Window, WindowAttributes},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FullscreenMode {
+    /// A borderless window covering the current monitor. Fast to switch in and out of.
+    Borderless,
+    /// Exclusive fullscreen using the monitor's largest video mode (highest refresh rate).
+    /// Falls back to borderless if the monitor doesn't report any video modes.
+    Exclusive,
+}
+
+/// User-facing window settings that can be changed while the game is running.
+///
+/// `width`, `height` and `position` describe the window while it is windowed. They are
+/// kept up to date when the window is moved or resized, so leaving fullscreen restores the
+/// window to where it was.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowSettings {
+    pub title: String,
+    /// Logical (i.e. before scaling) inner width of the window
+    pub width: f64,
+    /// Logical (i.e. before scaling) inner height of the window
+    pub height: f64,
+    /// Logical minimum inner size (width, height) of the window
+    pub min_size: Option<(f64, f64)>,
+    /// Physical outer position of the window. None lets the platform decide.
+    pub position: Option<(i32, i32)>,
+    /// None = windowed
+    pub fullscreen: Option<FullscreenMode>,
+    /// The mode `toggle_fullscreen` switches to
+    pub preferred_fullscreen: FullscreenMode,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            title: "Game".to_string(),
+            width: 1440.0,
+            height: 900.0,
+            min_size: Some((640.0, 400.0)),
+            position: None,
+            fullscreen: None,
+            preferred_fullscreen: FullscreenMode::Borderless,
+        }
+    }
+}
+
+impl WindowSettings {
+    pub fn toggle_fullscreen(&mut self) {
+        self.fullscreen = match self.fullscreen {
+            Some(_) => None,
+            None => Some(self.preferred_fullscreen),
+        };
+    }
+
+    pub(crate) fn window_attributes(&self) -> WindowAttributes {
+        let mut attributes = Window::default_attributes()
+            .with_title(self.title.as_str())
+            .with_inner_size(LogicalSize::new(self.width, self.height));
+        if let Some((min_width, min_height)) = self.min_size {
+            attributes = attributes.with_min_inner_size(LogicalSize::new(min_width, min_height));
+        }
+        if let Some((x, y)) = self.position {
+            attributes = attributes.with_position(PhysicalPosition::new(x, y));
+        }
+        // fullscreen needs a monitor handle, so it gets applied after the window is created
+        attributes
+    }
+
+    fn fullscreen_for(window: &Window, mode: FullscreenMode) -> Fullscreen {
+        let monitor: Option<MonitorHandle> = window.current_monitor();
+        match mode {
+            FullscreenMode::Borderless => Fullscreen::Borderless(monitor),
+            FullscreenMode::Exclusive => monitor
+                .and_then(|monitor| {
+                    monitor.video_modes().max_by_key(|video_mode| {
+                        (
+                            video_mode.size().width * video_mode.size().height,
+                            video_mode.refresh_rate_millihertz(),
+                        )
+                    })
+                })
+                .map(Fullscreen::Exclusive)
+                .unwrap_or_else(|| {
+                    log::warn!("no video modes available, falling back to borderless fullscreen");
+                    Fullscreen::Borderless(window.current_monitor())
+                }),
+        }
+    }
+
+    /// Applies whatever differs from `previous` to the window. Returns true if the
+    /// fullscreen mode changed (i.e. the swapchain needs to be recreated).
+    pub(crate) fn apply(&self, window: &Window, previous: &WindowSettings) -> bool {
+        if self.title != previous.title {
+            window.set_title(&self.title);
+        }
+        if self.min_size != previous.min_size {
+            window.set_min_inner_size(
+                self.min_size
+                    .map(|(width, height)| LogicalSize::new(width, height)),
+            );
+        }
+
+        if self.fullscreen != previous.fullscreen {
+            log::info!("switching fullscreen mode to {:?}", self.fullscreen);
+            window.set_fullscreen(
+                self.fullscreen
+                    .map(|mode| Self::fullscreen_for(window, mode)),
+            );
+            if self.fullscreen.is_none() {
+                // restore the windowed size and position from before going fullscreen
+                let _ = window.request_inner_size(LogicalSize::new(self.width, self.height));
+                if let Some((x, y)) = self.position {
+                    window.set_outer_position(PhysicalPosition::new(x, y));
+                }
+            }
+            return true;
+        }
+
+        if self.fullscreen.is_none() {
+            if (self.width, self.height) != (previous.width, previous.height) {
+                let _ = window.request_inner_size(LogicalSize::new(self.width, self.height));
+            }
+            if self.position != previous.position
+                && let Some((x, y)) = self.position
+            {
+                window.set_outer_position(PhysicalPosition::new(x, y));
+            }
+        }
+        false
+    }
+
+    /// Applies the initial fullscreen mode (which needs the created window's monitor).
+    pub(crate) fn apply_initial_fullscreen(&self, window: &Window) {
+        if let Some(mode) = self.fullscreen {
+            window.set_fullscreen(Some(Self::fullscreen_for(window, mode)));
+        }
+    }
+}
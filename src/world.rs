@@ -0,0 +1,397 @@
+use serde::{Deserialize, Serialize};
+
+use crate::asteroid::{Asteroid, AsteroidSize};
+use crate::collision::{Collider, Shape, SpatialGrid};
+use crate::math::{Real, Scalar, SimRng, SimVec2};
+use crate::memory::{HeapSize, vec_size};
+use crate::rng::{self, RngStream};
+use crate::ship::{BULLET_RADIUS, Bullet, Ship, ShipControls};
+
+/// Size of the playfield in world units. The renderer scales it uniformly to fit the surface,
+/// so the simulation doesn't depend on the window size.
+pub const WORLD_WIDTH: f32 = 1440.0;
+pub const WORLD_HEIGHT: f32 = 900.0;
+
+const DEFAULT_SEED: u64 = 0x5eed;
+// cell size of the collision broad phase, about the size of the largest asteroid
+const COLLISION_CELL_SIZE: f32 = 90.0;
+const STARTING_LIVES: u32 = 3;
+const RESPAWN_SECONDS: f32 = 2.0;
+// the ship only respawns once no asteroid is this close to the spawn point
+const RESPAWN_CLEARANCE: f32 = 120.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorldEventKind {
+    Spawn,
+    Death,
+    Collision,
+}
+
+impl WorldEventKind {
+    pub const COUNT: usize = 3;
+
+    pub fn index(self) -> usize {
+        match self {
+            WorldEventKind::Spawn => 0,
+            WorldEventKind::Death => 1,
+            WorldEventKind::Collision => 2,
+        }
+    }
+}
+
+/// Identifies what was involved in a WorldEvent. Asteroids and bullets have ids that are
+/// unique for the lifetime of the world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntityId {
+    Ship,
+    Asteroid(u32),
+    Bullet(u32),
+}
+
+/// Something that happened in the world during a tick, for systems that react to gameplay
+/// (debug overlays, sounds, particles...).
+///
+/// - Spawn: the ship or an asteroid appeared (bullets don't get spawn events)
+/// - Death: `entity` was destroyed
+/// - Collision: `entity` hit `other` (ship↔asteroid or bullet↔asteroid)
+#[derive(Debug, Clone, Copy)]
+pub struct WorldEvent {
+    pub kind: WorldEventKind,
+    pub position: SimVec2,
+    pub entity: EntityId,
+    pub other: Option<EntityId>,
+}
+
+/// The simulated part of the game state. Everything in here has to update deterministically
+/// (see crate::math), and is cheap enough to clone that it can be snapshotted every tick.
+/// Serializes to everything needed to continue the game (see crate::save), except the events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct World {
+    /// The master seed the world was created with, see crate::rng
+    pub seed: u64,
+    /// Number of ticks simulated so far
+    pub tick: u64,
+    pub ship: Ship,
+    pub asteroids: Vec<Asteroid>,
+    pub bullets: Vec<Bullet>,
+    /// Events since they were last drained
+    #[serde(skip)]
+    pub events: Vec<WorldEvent>,
+    /// Number of the current asteroid wave, starting at 1
+    pub wave: u32,
+    /// Ships left, including the current one
+    pub lives: u32,
+    /// Points for the asteroids shot so far, see AsteroidSize::points
+    pub score: u32,
+    /// Seconds until the ship respawns, None while it is alive
+    respawn_timer: Option<Scalar>,
+    next_id: u32,
+    rng: SimRng,
+}
+
+impl HeapSize for World {
+    fn heap_size(&self) -> usize {
+        self.asteroids.heap_size() + vec_size(&self.bullets) + vec_size(&self.events)
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+/// Wraps a position around the edges of the world.
+pub fn wrap_position(position: SimVec2) -> SimVec2 {
+    let width = Scalar::from_f32(WORLD_WIDTH);
+    let height = Scalar::from_f32(WORLD_HEIGHT);
+    SimVec2::new(
+        position.x - (position.x / width).floor() * width,
+        position.y - (position.y / height).floor() * height,
+    )
+}
+
+fn center() -> SimVec2 {
+    SimVec2::new(
+        Scalar::from_f32(WORLD_WIDTH / 2.0),
+        Scalar::from_f32(WORLD_HEIGHT / 2.0),
+    )
+}
+
+impl World {
+    /// A new world with the ship in the middle and the first asteroid wave. Worlds with the
+    /// same seed given the same controls play out identically.
+    pub fn new(seed: u64) -> Self {
+        let ship = Ship::new(center());
+        let mut world = Self {
+            seed,
+            tick: 0,
+            events: vec![WorldEvent {
+                kind: WorldEventKind::Spawn,
+                position: ship.position,
+                entity: EntityId::Ship,
+                other: None,
+            }],
+            ship,
+            asteroids: Vec::new(),
+            bullets: Vec::new(),
+            wave: 0,
+            lives: STARTING_LIVES,
+            score: 0,
+            respawn_timer: None,
+            next_id: 0,
+            rng: rng::stream(seed, RngStream::Asteroids),
+        };
+        world.spawn_wave();
+        world
+    }
+
+    pub fn ship_alive(&self) -> bool {
+        self.respawn_timer.is_none()
+    }
+
+    /// True once the last ship was destroyed (and its explosion had time to play out).
+    pub fn game_over(&self) -> bool {
+        self.lives == 0
+            && self
+                .respawn_timer
+                .is_some_and(|timer| timer <= Scalar::ZERO)
+    }
+
+    /// Adds `count` asteroids of `size` anywhere in the world, on top of the wave's (e.g. for
+    /// the benchmark scene, see crate::benchmark).
+    pub fn spawn_asteroids(&mut self, size: AsteroidSize, count: usize) {
+        for _ in 0..count {
+            let position = SimVec2::new(
+                self.rng.range(Scalar::ZERO, Scalar::from_f32(WORLD_WIDTH)),
+                self.rng.range(Scalar::ZERO, Scalar::from_f32(WORLD_HEIGHT)),
+            );
+            self.spawn_asteroid(size, position);
+        }
+    }
+
+    /// Advances the simulation by one fixed tick of `dt` seconds.
+    pub fn update(&mut self, controls: ShipControls, dt: Scalar) {
+        if self.ship_alive() {
+            self.ship.update(controls, dt);
+            if controls.fire {
+                let id = self.next_id;
+                if let Some(bullet) = self.ship.fire(id) {
+                    self.next_id += 1;
+                    self.bullets.push(bullet);
+                }
+            }
+        } else {
+            self.update_respawn(dt);
+        }
+
+        for asteroid in self.asteroids.iter_mut() {
+            asteroid.update(dt);
+        }
+        self.bullets.retain_mut(|bullet| bullet.update(dt));
+
+        self.collide();
+        if self.asteroids.is_empty() {
+            self.spawn_wave();
+        }
+        self.tick += 1;
+    }
+
+    /// Line segments (pairs of points, in world units) to draw everything with.
+    pub fn line_segments(&self) -> Vec<[f32; 2]> {
+        let mut segments = Vec::new();
+        if self.ship_alive() {
+            segments.extend(self.ship.line_segments());
+        }
+        for asteroid in self.asteroids.iter() {
+            segments.extend(asteroid.line_segments());
+        }
+        for bullet in self.bullets.iter() {
+            segments.extend(bullet.line_segments());
+        }
+        segments
+    }
+
+    fn next_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    fn push_event(
+        &mut self,
+        kind: WorldEventKind,
+        position: SimVec2,
+        entity: EntityId,
+        other: Option<EntityId>,
+    ) {
+        self.events.push(WorldEvent {
+            kind,
+            position,
+            entity,
+            other,
+        });
+    }
+
+    fn spawn_asteroid(&mut self, size: AsteroidSize, position: SimVec2) {
+        let id = self.next_id();
+        let asteroid = Asteroid::new(id, size, position, &mut self.rng);
+        self.push_event(
+            WorldEventKind::Spawn,
+            position,
+            EntityId::Asteroid(id),
+            None,
+        );
+        self.asteroids.push(asteroid);
+    }
+
+    // large asteroids around (but not too close to) the ship, one more every wave
+    fn spawn_wave(&mut self) {
+        self.wave += 1;
+        let turn = Scalar::from_f32(std::f32::consts::TAU);
+        for _ in 0..self.wave + 3 {
+            let direction = SimVec2::from_angle(self.rng.range(Scalar::ZERO, turn));
+            let distance = self.rng.range(Scalar::from_int(250), Scalar::from_int(400));
+            let position = wrap_position(self.ship.position + direction.scale(distance));
+            self.spawn_asteroid(AsteroidSize::Large, position);
+        }
+    }
+
+    fn update_respawn(&mut self, dt: Scalar) {
+        let Some(timer) = self.respawn_timer.as_mut() else {
+            return;
+        };
+        if *timer > Scalar::ZERO {
+            *timer -= dt;
+        }
+        if *timer > Scalar::ZERO || self.lives == 0 {
+            return;
+        }
+        let spawn_point = center();
+        let clearance = Scalar::from_f32(RESPAWN_CLEARANCE);
+        let blocked = self.asteroids.iter().any(|asteroid| {
+            let distance = clearance + asteroid.size.radius();
+            (asteroid.position - spawn_point).length_squared() < distance * distance
+        });
+        if blocked {
+            return;
+        }
+        self.ship = Ship::new(spawn_point);
+        self.respawn_timer = None;
+        self.push_event(WorldEventKind::Spawn, spawn_point, EntityId::Ship, None);
+    }
+
+    fn collide(&mut self) {
+        let ship_shape = Ship::collider_shape();
+        let bullet_shape = Shape::Circle {
+            radius: Scalar::from_f32(BULLET_RADIUS),
+        };
+
+        let mut entities: Vec<EntityId> = Vec::new();
+        let mut colliders: Vec<Collider> = Vec::new();
+        if self.ship_alive() {
+            entities.push(EntityId::Ship);
+            colliders.push(Collider {
+                shape: &ship_shape,
+                position: self.ship.position,
+                angle: self.ship.angle,
+            });
+        }
+        for asteroid in self.asteroids.iter() {
+            entities.push(EntityId::Asteroid(asteroid.id));
+            colliders.push(Collider {
+                shape: &asteroid.shape,
+                position: asteroid.position,
+                angle: asteroid.angle,
+            });
+        }
+        for bullet in self.bullets.iter() {
+            entities.push(EntityId::Bullet(bullet.id));
+            colliders.push(Collider {
+                shape: &bullet_shape,
+                position: bullet.position,
+                angle: Scalar::ZERO,
+            });
+        }
+
+        // only things hitting asteroids matter
+        let mut grid = SpatialGrid::new(
+            Scalar::from_f32(WORLD_WIDTH),
+            Scalar::from_f32(WORLD_HEIGHT),
+            Scalar::from_f32(COLLISION_CELL_SIZE),
+        );
+        let pairs = grid.find_collisions(&colliders, |a, b| {
+            matches!(entities[a], EntityId::Asteroid(_))
+                != matches!(entities[b], EntityId::Asteroid(_))
+        });
+        let positions: Vec<SimVec2> = colliders.iter().map(|c| c.position).collect();
+
+        // the pairs are sorted, so the outcome doesn't depend on anything but the world.
+        // Everything can only be destroyed once.
+        let mut destroyed = vec![false; entities.len()];
+        let mut broken: Vec<(AsteroidSize, SimVec2)> = Vec::new();
+        for (a, b) in pairs {
+            if destroyed[a] || destroyed[b] {
+                continue;
+            }
+            let (asteroid, other) = if matches!(entities[a], EntityId::Asteroid(_)) {
+                (a, b)
+            } else {
+                (b, a)
+            };
+            destroyed[asteroid] = true;
+            destroyed[other] = true;
+
+            self.push_event(
+                WorldEventKind::Collision,
+                positions[other],
+                entities[other],
+                Some(entities[asteroid]),
+            );
+            self.push_event(
+                WorldEventKind::Death,
+                positions[asteroid],
+                entities[asteroid],
+                None,
+            );
+            if let Some(size) = self
+                .asteroids
+                .iter()
+                .find(|a| EntityId::Asteroid(a.id) == entities[asteroid])
+                .map(|a| a.size)
+            {
+                broken.push((size, positions[asteroid]));
+                if matches!(entities[other], EntityId::Bullet(_)) {
+                    self.score += size.points();
+                }
+            }
+            if entities[other] == EntityId::Ship {
+                self.push_event(
+                    WorldEventKind::Death,
+                    positions[other],
+                    EntityId::Ship,
+                    None,
+                );
+                self.lives = self.lives.saturating_sub(1);
+                self.respawn_timer = Some(Scalar::from_f32(RESPAWN_SECONDS));
+            }
+        }
+
+        let destroyed: Vec<EntityId> = entities
+            .iter()
+            .zip(destroyed)
+            .filter(|(_, destroyed)| *destroyed)
+            .map(|(entity, _)| *entity)
+            .collect();
+        self.asteroids
+            .retain(|asteroid| !destroyed.contains(&EntityId::Asteroid(asteroid.id)));
+        self.bullets
+            .retain(|bullet| !destroyed.contains(&EntityId::Bullet(bullet.id)));
+
+        for (size, position) in broken {
+            if let Some(smaller) = size.smaller() {
+                self.spawn_asteroid(smaller, position);
+                self.spawn_asteroid(smaller, position);
+            }
+        }
+    }
+}
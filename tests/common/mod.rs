@@ -0,0 +1,274 @@
+//! Font files built in memory, for tests of the FontCache that shouldn't depend on the fonts
+//! of the machine. The fonts have only what the cache reads when it loads a file: the names,
+//! the revision and how many fonts there are. With [`FixtureFont::outlines`] they also have a
+//! square glyph for 'o' to rasterize, and with [`FixtureFont::weight_axis`] they're variable.
+//!
+//! ```
+//! let regular = FixtureFont::new("Fixture Sans").subfamily("Regular");
+//! let single = regular.to_file();
+//! let collection = FixtureFont::collection(&[regular, FixtureFont::new("Fixture Serif")]);
+//! ```
+
+// every test file has its own copy of this module and uses only some of it
+#![allow(dead_code)]
+
+/// A font to build a file of, see the module docs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixtureFont {
+    pub family: String,
+    pub subfamily: Option<String>,
+    /// The `head` table's font revision. A later revision of the same font replaces the
+    /// cached one.
+    pub revision: f32,
+    pub outlines: bool,
+    /// The `wght` axis' minimum, default and maximum
+    pub weight_axis: Option<[f32; 3]>,
+}
+
+impl FixtureFont {
+    pub fn new(family: impl Into<String>) -> Self {
+        Self {
+            family: family.into(),
+            subfamily: None,
+            revision: 1.0,
+            outlines: false,
+            weight_axis: None,
+        }
+    }
+
+    pub fn subfamily(mut self, subfamily: impl Into<String>) -> Self {
+        self.subfamily = Some(subfamily.into());
+        self
+    }
+
+    pub fn revision(mut self, revision: f32) -> Self {
+        self.revision = revision;
+        self
+    }
+
+    /// Gives the font glyph 1, a square mapped to 'o', so there's something to rasterize.
+    pub fn outlines(mut self) -> Self {
+        self.outlines = true;
+        self
+    }
+
+    /// Makes the font variable along a `wght` axis. Without `gvar` deltas the glyphs look the
+    /// same everywhere, but they're at other locations of the design space.
+    pub fn weight_axis(mut self, min: f32, default: f32, max: f32) -> Self {
+        self.weight_axis = Some([min, default, max]);
+        self
+    }
+
+    /// The font as a `.ttf` file.
+    pub fn to_file(&self) -> Vec<u8> {
+        write_fonts(std::slice::from_ref(self), false)
+    }
+
+    /// The fonts as a `.ttc` collection file.
+    pub fn collection(fonts: &[FixtureFont]) -> Vec<u8> {
+        write_fonts(fonts, true)
+    }
+
+    // sorted by tag
+    fn tables(&self) -> Vec<([u8; 4], Vec<u8>)> {
+        let mut tables = vec![(*b"head", self.head()), (*b"name", self.name())];
+        if self.outlines {
+            tables.extend([
+                (*b"cmap", cmap()),
+                (*b"glyf", GLYF.to_vec()),
+                (*b"hhea", hhea()),
+                (*b"hmtx", hmtx()),
+                (*b"loca", loca()),
+                (*b"maxp", maxp()),
+            ]);
+        }
+        if let Some(axis) = self.weight_axis {
+            tables.push((*b"fvar", fvar(axis)));
+        }
+        tables.sort_by_key(|(tag, _)| *tag);
+        tables
+    }
+
+    fn head(&self) -> Vec<u8> {
+        let mut head = Vec::with_capacity(54);
+        // version 1.0, then the revision as 16.16 fixed point
+        head.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        head.extend_from_slice(&((self.revision * 65536.0) as i32).to_be_bytes());
+        // checksum adjustment, magic number, flags, units per em
+        head.extend_from_slice(&0u32.to_be_bytes());
+        head.extend_from_slice(&0x5f0f_3cf5u32.to_be_bytes());
+        head.extend_from_slice(&0u16.to_be_bytes());
+        head.extend_from_slice(&1000u16.to_be_bytes());
+        // created and modified dates, the bounding box, mac style, smallest readable size,
+        // direction hint, loca and glyf formats
+        head.extend_from_slice(&[0; 16]);
+        head.extend_from_slice(&[0; 8]);
+        head.extend_from_slice(&[0, 0, 0, 8, 0, 2, 0, 0, 0, 0]);
+        head
+    }
+
+    // the names for Windows, in English (UTF-16)
+    fn name(&self) -> Vec<u8> {
+        let mut names = vec![(1u16, self.family.as_str())];
+        if let Some(subfamily) = &self.subfamily {
+            names.push((2, subfamily));
+        }
+        if self.weight_axis.is_some() {
+            names.push((AXIS_NAME_ID, "Weight"));
+        }
+        let header_size = 6 + names.len() * 12;
+        let mut records = Vec::new();
+        let mut strings = Vec::new();
+        for (name_id, text) in names {
+            let utf16: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+            // platform, encoding and language: Windows, Unicode BMP, en-US
+            for value in [3u16, 1, 0x0409, name_id] {
+                records.extend_from_slice(&value.to_be_bytes());
+            }
+            records.extend_from_slice(&(utf16.len() as u16).to_be_bytes());
+            records.extend_from_slice(&(strings.len() as u16).to_be_bytes());
+            strings.extend_from_slice(&utf16);
+        }
+        let mut name = Vec::with_capacity(header_size + strings.len());
+        name.extend_from_slice(&0u16.to_be_bytes());
+        name.extend_from_slice(&((records.len() / 12) as u16).to_be_bytes());
+        name.extend_from_slice(&(header_size as u16).to_be_bytes());
+        name.extend_from_slice(&records);
+        name.extend_from_slice(&strings);
+        name
+    }
+}
+
+// the name of the weight axis
+const AXIS_NAME_ID: u16 = 256;
+
+// .notdef is empty, glyph 1 is a square from (100, 0) to (500, 500): one contour of four
+// points on the curve, the coordinates as 16 bit deltas
+const GLYF: [u8; 34] = [
+    0, 1, 0, 100, 0, 0, 1, 244, 1, 244, // contours, bounding box
+    0, 3, 0, 0, // the contour's last point, no instructions
+    1, 1, 1, 1, // flags
+    0, 100, 0, 0, 1, 144, 0, 0, // x: 100, 0, 400, 0
+    0, 0, 1, 244, 0, 0, 254, 12, // y: 0, 500, 0, -500
+];
+
+fn u16s(values: &[u16]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect()
+}
+
+// format 4, 'o' to glyph 1 and the final segment every format 4 table ends with
+fn cmap() -> Vec<u8> {
+    let mut cmap = u16s(&[0, 1, 3, 1]);
+    cmap.extend_from_slice(&12u32.to_be_bytes());
+    let delta = 1u16.wrapping_sub('o' as u16);
+    cmap.extend(u16s(&[
+        4, 32, 0, // format, length, language
+        4, 4, 1, 0, // two segments, and their binary search hints
+        'o' as u16, 0xffff, 0, // end codes, padding
+        'o' as u16, 0xffff, // start codes
+        delta, 1, 0, 0, // deltas, range offsets
+    ]));
+    cmap
+}
+
+fn hhea() -> Vec<u8> {
+    let mut hhea = 0x0001_0000u32.to_be_bytes().to_vec();
+    // ascender, descender, line gap, largest advance, min side bearings, max extent, caret
+    // slope and offset, reserved, metric data format, number of metrics
+    hhea.extend(u16s(&[800, (-200i16) as u16, 0, 600, 0, 100, 500, 1, 0, 0]));
+    hhea.extend(u16s(&[0, 0, 0, 0, 0, 2]));
+    hhea
+}
+
+// the advance and left side bearing of each glyph
+fn hmtx() -> Vec<u8> {
+    u16s(&[600, 0, 600, 100])
+}
+
+// short offsets, halved
+fn loca() -> Vec<u8> {
+    u16s(&[0, 0, GLYF.len() as u16 / 2])
+}
+
+// version 0.5, only the number of glyphs
+fn maxp() -> Vec<u8> {
+    let mut maxp = 0x0000_5000u32.to_be_bytes().to_vec();
+    maxp.extend(u16s(&[2]));
+    maxp
+}
+
+// one axis and no named instances
+fn fvar([min, default, max]: [f32; 3]) -> Vec<u8> {
+    // version, offset of the axes, reserved, axis count and size, instance count and size
+    let mut fvar = u16s(&[1, 0, 16, 2, 1, 20, 0, 8]);
+    fvar.extend_from_slice(b"wght");
+    for value in [min, default, max] {
+        fvar.extend_from_slice(&((value * 65536.0) as i32).to_be_bytes());
+    }
+    fvar.extend(u16s(&[0, AXIS_NAME_ID]));
+    fvar
+}
+
+fn table_checksum(table: &[u8]) -> u32 {
+    table.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+// the fonts' table directories (after the collection header), then all their tables. Table
+// offsets are from the start of the file, in a collection too.
+fn write_fonts(fonts: &[FixtureFont], collection: bool) -> Vec<u8> {
+    let tables: Vec<_> = fonts.iter().map(FixtureFont::tables).collect();
+    let collection_header = if collection { 12 + 4 * fonts.len() } else { 0 };
+    let directory_size = |tables: &[([u8; 4], Vec<u8>)]| 12 + 16 * tables.len();
+    let mut directory_offsets = Vec::new();
+    let mut offset = collection_header;
+    for font_tables in &tables {
+        directory_offsets.push(offset);
+        offset += directory_size(font_tables);
+    }
+
+    let mut file = Vec::new();
+    if collection {
+        file.extend_from_slice(b"ttcf");
+        file.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        file.extend_from_slice(&(fonts.len() as u32).to_be_bytes());
+        for directory_offset in &directory_offsets {
+            file.extend_from_slice(&(*directory_offset as u32).to_be_bytes());
+        }
+    }
+    let mut data = Vec::new();
+    let data_start = offset;
+    for font_tables in &tables {
+        let count = font_tables.len() as u16;
+        // the binary search hints: the largest power of two that fits, times 16
+        let entry_selector = count.ilog2() as u16;
+        let search_range = (1 << entry_selector) * 16;
+        file.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        for value in [
+            count,
+            search_range,
+            entry_selector,
+            count * 16 - search_range,
+        ] {
+            file.extend_from_slice(&value.to_be_bytes());
+        }
+        // FixtureFont::tables sorts them by tag
+        for (tag, table) in font_tables {
+            file.extend_from_slice(tag);
+            file.extend_from_slice(&table_checksum(table).to_be_bytes());
+            file.extend_from_slice(&((data_start + data.len()) as u32).to_be_bytes());
+            file.extend_from_slice(&(table.len() as u32).to_be_bytes());
+            data.extend_from_slice(table);
+            // tables start on 4 byte boundaries
+            data.resize(data.len().next_multiple_of(4), 0);
+        }
+    }
+    file.extend_from_slice(&data);
+    file
+}
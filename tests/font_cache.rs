@@ -0,0 +1,380 @@
+//! The font cache keeps track of which file has which fonts while files are loaded, reloaded
+//! and unloaded, and a duplicate font is either skipped or replaces the cached one in place.
+//! These load fixture fonts (see tests/common) and check that bookkeeping with
+//! FontCache::check_consistency after every step, also for random sequences of steps.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use common::FixtureFont;
+use game::assets::{AssetStore, FontData};
+use game::gfx::text::{FontCache, ShaperSettings, layout_line};
+use game::math::SimRng;
+use skrifa::MetadataProvider;
+use skrifa::instance::Size;
+
+// a directory of its own for every test, they run in parallel
+fn directory(test: &str) -> PathBuf {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("font_cache")
+        .join(test);
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+fn write(directory: &Path, name: &str, data: &[u8]) -> PathBuf {
+    let path = directory.join(name);
+    std::fs::write(&path, data).unwrap();
+    path
+}
+
+fn revision(font_cache: &FontCache, family: &str) -> f32 {
+    font_cache
+        .find_font(family, None::<&str>)
+        .unwrap()
+        .revision()
+        .to_f32()
+}
+
+#[test]
+fn the_same_file_twice_is_cached_once() {
+    let directory = directory("same_file");
+    let data = FixtureFont::new("Fixture Sans").to_file();
+    let path = write(&directory, "sans.ttf", &data);
+    let copy = write(&directory, "sans-copy.ttf", &data);
+
+    let mut font_cache = FontCache::new();
+    let idxs = font_cache.load_font_file(&path).unwrap();
+    assert_eq!(idxs.len(), 1);
+    assert_eq!(font_cache.load_font_file(&path).unwrap(), idxs);
+    // the same data under another name is the same file
+    assert_eq!(font_cache.load_font_file(&copy).unwrap(), idxs);
+    assert_eq!(font_cache.fonts().count(), 1);
+    font_cache.check_consistency().unwrap();
+
+    // both at once too, parsed in parallel
+    let mut font_cache = FontCache::new();
+    assert_eq!(
+        font_cache
+            .load_multiple_font_files(vec![&path, &path, &copy])
+            .unwrap(),
+        1
+    );
+    assert_eq!(font_cache.fonts().count(), 1);
+    font_cache.check_consistency().unwrap();
+}
+
+#[test]
+fn a_changed_file_has_to_be_reloaded() {
+    let directory = directory("changed_file");
+    let path = write(
+        &directory,
+        "sans.ttf",
+        &FixtureFont::new("Fixture Sans").to_file(),
+    );
+    let mut font_cache = FontCache::new();
+    let idxs = font_cache.load_font_file(&path).unwrap();
+
+    write(
+        &directory,
+        "sans.ttf",
+        &FixtureFont::new("Fixture Sans").revision(2.0).to_file(),
+    );
+    assert!(font_cache.load_font_file(&path).is_err());
+    font_cache.check_consistency().unwrap();
+
+    assert_eq!(font_cache.reload_font_file(&path).unwrap(), idxs);
+    assert_eq!(revision(&font_cache, "Fixture Sans"), 2.0);
+    font_cache.check_consistency().unwrap();
+}
+
+#[test]
+fn a_font_asset_is_reloaded_with_its_file() {
+    let directory = directory("font_asset");
+    let path = write(
+        &directory,
+        "sans.ttf",
+        &FixtureFont::new("Fixture Sans").to_file(),
+    );
+    let mut fonts = AssetStore::<FontData>::default();
+    fonts.set_hot_reload(true);
+    let mut font_cache = FontCache::new();
+    let idxs = font_cache
+        .load_font_asset(&fonts.load_blocking(&path))
+        .unwrap();
+    assert_eq!(
+        font_cache
+            .load_font_asset(&fonts.load_blocking(&path))
+            .unwrap(),
+        idxs
+    );
+    font_cache.check_consistency().unwrap();
+
+    write(
+        &directory,
+        "sans.ttf",
+        &FixtureFont::new("Fixture Sans").revision(2.0).to_file(),
+    );
+    // the store reloads the file in the background once the watcher saw the change
+    let start = Instant::now();
+    let mut changed = Vec::new();
+    while changed.is_empty() && start.elapsed() < Duration::from_secs(10) {
+        std::thread::sleep(Duration::from_millis(10));
+        fonts.poll_changes();
+        changed = font_cache.poll_changes().to_vec();
+    }
+    assert_eq!(changed, idxs.to_vec());
+    assert_eq!(revision(&font_cache, "Fixture Sans"), 2.0);
+    font_cache.check_consistency().unwrap();
+}
+
+#[test]
+fn better_duplicates_replace_worse_ones_are_skipped() {
+    let directory = directory("duplicates");
+    let old = write(
+        &directory,
+        "old.ttf",
+        &FixtureFont::new("Fixture Sans").revision(1.0).to_file(),
+    );
+    let new = write(
+        &directory,
+        "new.ttf",
+        &FixtureFont::new("Fixture Sans").revision(2.0).to_file(),
+    );
+
+    // the newer revision replaces the older one at its cache index
+    let mut font_cache = FontCache::new();
+    let idxs = font_cache.load_font_file(&old).unwrap();
+    assert_eq!(font_cache.load_font_file(&new).unwrap(), idxs);
+    assert_eq!(revision(&font_cache, "Fixture Sans"), 2.0);
+    assert_eq!(font_cache.fonts().count(), 1);
+    font_cache.check_consistency().unwrap();
+    // and belongs to its file now, the old file has nothing left to unload
+    assert!(font_cache.unload_path(&old).is_empty());
+    assert_eq!(font_cache.fonts().count(), 1);
+    assert_eq!(font_cache.unload_path(&new), idxs);
+    assert_eq!(font_cache.fonts().count(), 0);
+    font_cache.check_consistency().unwrap();
+
+    // the other way around the older revision is skipped
+    let mut font_cache = FontCache::new();
+    let idxs = font_cache.load_font_file(&new).unwrap();
+    assert_eq!(font_cache.load_font_file(&old).unwrap(), idxs);
+    assert_eq!(revision(&font_cache, "Fixture Sans"), 2.0);
+    font_cache.check_consistency().unwrap();
+}
+
+#[test]
+fn collections_cache_every_font() {
+    let directory = directory("collections");
+    let sans = write(
+        &directory,
+        "sans.ttf",
+        &FixtureFont::new("Fixture Sans").to_file(),
+    );
+    let collection = write(
+        &directory,
+        "family.ttc",
+        &FixtureFont::collection(&[
+            FixtureFont::new("Fixture Sans").revision(2.0),
+            FixtureFont::new("Fixture Sans").subfamily("Bold"),
+            FixtureFont::new("Fixture Serif"),
+        ]),
+    );
+
+    let mut font_cache = FontCache::new();
+    let sans_idxs = font_cache.load_font_file(&sans).unwrap();
+    let idxs = font_cache.load_font_file(&collection).unwrap();
+    assert_eq!(idxs.len(), 3);
+    // the better Fixture Sans is in its old place
+    assert!(idxs.contains(&sans_idxs[0]));
+    assert_eq!(font_cache.fonts().count(), 3);
+    font_cache.check_consistency().unwrap();
+
+    assert_eq!(font_cache.unload_path(&collection).len(), 3);
+    assert_eq!(font_cache.fonts().count(), 0);
+    font_cache.check_consistency().unwrap();
+
+    // a font that's in a collection twice is cached once, the better one
+    let twice = write(
+        &directory,
+        "twice.ttc",
+        &FixtureFont::collection(&[
+            FixtureFont::new("Fixture Mono"),
+            FixtureFont::new("Fixture Mono").revision(3.0),
+        ]),
+    );
+    assert_eq!(font_cache.load_font_file(&twice).unwrap().len(), 1);
+    assert_eq!(revision(&font_cache, "Fixture Mono"), 3.0);
+    font_cache.check_consistency().unwrap();
+}
+
+#[test]
+fn a_collection_font_by_its_index() {
+    let directory = directory("collection_index");
+    let bold = write(
+        &directory,
+        "bold.ttf",
+        &FixtureFont::new("Fixture Sans")
+            .subfamily("Bold")
+            .revision(2.0)
+            .to_file(),
+    );
+    let collection = write(
+        &directory,
+        "family.ttc",
+        &FixtureFont::collection(&[
+            FixtureFont::new("Fixture Sans").subfamily("Regular"),
+            FixtureFont::new("Fixture Sans").subfamily("Bold"),
+            FixtureFont::new("Fixture Serif"),
+        ]),
+    );
+
+    let mut font_cache = FontCache::new();
+    font_cache.load_font_file(&bold).unwrap();
+    font_cache.load_font_file(&collection).unwrap();
+    let fonts = font_cache.fonts_in_file(&collection);
+    let indexes: Vec<u32> = fonts.iter().map(|font| font.collection_index()).collect();
+    // the bold font is skipped, the other file's is better
+    assert_eq!(indexes, [0, 2]);
+    assert_eq!(fonts[1].family_name(), "Fixture Serif");
+
+    let regular = font_cache.font_in_file(&collection, 0).unwrap();
+    assert_eq!(regular.subfamily_name(), Some("Regular"));
+    assert!(format!("{:?}", regular).contains("collection_index()"));
+    assert!(font_cache.font_in_file(&collection, 1).is_err());
+    assert!(font_cache.font_in_file(&collection, 3).is_err());
+    assert_eq!(
+        font_cache.font_in_file(&bold, 0).unwrap().subfamily_name(),
+        Some("Bold")
+    );
+    assert!(
+        font_cache
+            .fonts_in_file(directory.join("none.ttc"))
+            .is_empty()
+    );
+}
+
+#[test]
+fn random_loads_and_unloads_stay_consistent() {
+    let directory = directory("random");
+    let families = ["Fixture Sans", "Fixture Serif", "Fixture Mono"];
+    let mut rng = SimRng::new(0x5eed_f0a7);
+    let mut pick = |n: usize| (rng.next_u64() % n as u64) as usize;
+
+    for run in 0..20 {
+        let mut font_cache = FontCache::new();
+        // a few files of random fonts, some the same, some collections
+        let mut paths = Vec::new();
+        for file in 0..6 {
+            let fonts: Vec<FixtureFont> = (0..1 + pick(3))
+                // with a subfamily, without one a font is the same as any of its family's
+                .map(|_| {
+                    FixtureFont::new(families[pick(families.len())])
+                        .subfamily(["Regular", "Bold"][pick(2)])
+                        .revision(1.0 + pick(3) as f32)
+                })
+                .collect();
+            let (name, data) = if fonts.len() == 1 && pick(2) == 0 {
+                (format!("{}-{}.ttf", run, file), fonts[0].to_file())
+            } else {
+                (
+                    format!("{}-{}.ttc", run, file),
+                    FixtureFont::collection(&fonts),
+                )
+            };
+            paths.push(write(&directory, &name, &data));
+        }
+
+        for step in 0..30 {
+            let path = &paths[pick(paths.len())];
+            let operation = pick(4);
+            match operation {
+                0 => {
+                    for idx in font_cache.load_font_file(path).unwrap() {
+                        assert!(font_cache.get_font(idx).is_some());
+                    }
+                }
+                1 => {
+                    font_cache.reload_font_file(path).unwrap();
+                }
+                2 => {
+                    font_cache.unload_path(path);
+                }
+                _ => {
+                    let keep: Vec<usize> = font_cache
+                        .fonts()
+                        .map(|font| font.cache_index())
+                        .filter(|_| pick(2) == 0)
+                        .collect();
+                    font_cache.retain_only(&keep);
+                }
+            }
+            if let Err(error) = font_cache.check_consistency() {
+                panic!("run {}, step {} ({}): {:?}", run, step, operation, error);
+            }
+            // a font is never cached twice
+            let mut names: Vec<_> = font_cache
+                .fonts()
+                .map(|font| {
+                    (
+                        font.family_name().to_string(),
+                        font.subfamily_name().map(str::to_string),
+                    )
+                })
+                .collect();
+            let count = names.len();
+            names.sort();
+            names.dedup();
+            assert_eq!(names.len(), count, "run {}, step {}", run, step);
+        }
+    }
+}
+
+#[test]
+fn the_fallback_font_is_in_the_binary() {
+    let mut font_cache = FontCache::new();
+    let idx = font_cache.load_fallback_font().unwrap();
+    let font = font_cache.get_font(idx).unwrap();
+    assert_eq!(font.family_name(), "Cantarell");
+    assert!(font.ext_font_ref().charmap().map('A').is_some());
+    // loading it again is the same data
+    assert_eq!(font_cache.load_fallback_font().unwrap(), idx);
+    assert_eq!(font_cache.fonts().count(), 1);
+    font_cache.check_consistency().unwrap();
+}
+
+#[test]
+fn shaped_lines_belong_to_their_cache() {
+    // a different font at cache index 0 of each
+    let mut cantarell = FontCache::new();
+    let idx = cantarell.load_fallback_font().unwrap();
+    let cantarell_font = cantarell.get_font(idx).unwrap();
+    let mut dejavu = FontCache::new();
+    let idxs = dejavu
+        .load_font_bytes("DejaVuSans.ttf", include_bytes!("fonts/DejaVuSans.ttf"))
+        .unwrap();
+    let dejavu_font = dejavu.get_font(idxs[0]).unwrap();
+    assert_eq!(cantarell_font.cache_index(), dejavu_font.cache_index());
+
+    let shape = |font| {
+        layout_line(
+            font,
+            "Sphinx of black quartz",
+            Size::new(24.0),
+            &ShaperSettings::new(),
+            false,
+            4.0,
+        )
+    };
+    let first = shape(&cantarell_font);
+    let second = shape(&dejavu_font);
+    assert_ne!(first.width, second.width);
+    assert!(dejavu.shape_cache_size() > 0);
+    // and each cache finds its own line again
+    assert_eq!(shape(&cantarell_font).width, first.width);
+    assert_eq!(shape(&dejavu_font).width, second.width);
+}
@@ -0,0 +1,236 @@
+#!/usr/bin/env python3
+"""Writes the fixture fonts FixtureCJK.ttf and FixtureEmoji.ttf next to this script.
+
+They cover exactly the characters of the golden test's CJK and emoji cases (see
+tests/text_golden.rs). Their glyphs are made up patterns of strokes, not real ideographs or
+emoji. What the cases test is everything around the glyphs: full width advances, characters
+outside the BMP (a format 12 cmap), emoji modifier clusters and fonts without any Latin.
+
+Run it again after changing a case's text, then UPDATE_GOLDEN=1 the test.
+"""
+
+import os
+import struct
+
+UNITS_PER_EM = 1000
+ASCENT = 880
+DESCENT = -120
+
+CJK_TEXT = "永和九年，岁在癸丑。日本語のテキスト"
+EMOJI_TEXT = "😀 🚀 👍🏽 ❤"
+
+
+def rect(x0, y0, x1, y1):
+    # clockwise, y up
+    return [(x0, y0), (x0, y1), (x1, y1), (x1, y0)]
+
+
+def polygon(points, clockwise=True):
+    return points if clockwise else points[::-1]
+
+
+def octagon(cx, cy, r):
+    k = int(r * 0.41)
+    return [
+        (cx - k, cy - r), (cx - r, cy - k), (cx - r, cy + k), (cx - k, cy + r),
+        (cx + k, cy + r), (cx + r, cy + k), (cx + r, cy - k), (cx + k, cy - r),
+    ]
+
+
+class Random:
+    # a small LCG, so the glyphs don't change between Python versions
+    def __init__(self, seed):
+        self.state = seed * 2654435761 % 2**32
+
+    def next(self, n):
+        self.state = (self.state * 1103515245 + 12345) % 2**31
+        return (self.state >> 8) % n
+
+
+def cjk_glyph(c):
+    random = Random(ord(c))
+    stroke = 70
+    contours = []
+    for _ in range(2 + random.next(3)):
+        y = 40 + random.next(8) * 100
+        x0 = 100 + random.next(3) * 100
+        x1 = 900 - random.next(3) * 100
+        contours.append(rect(x0, y, x1, y + stroke))
+    for _ in range(1 + random.next(3)):
+        x = 130 + random.next(7) * 100
+        y0 = -40 + random.next(3) * 100
+        y1 = 800 - random.next(3) * 100
+        contours.append(rect(x, y0, x + stroke, y1))
+    # a diagonal sweep, the way many characters end
+    if random.next(2):
+        x = 150 + random.next(4) * 100
+        contours.append(polygon([(x, -40), (x + 300, 360), (x + 380, 300), (x + 80, -40)]))
+    return contours
+
+
+def emoji_glyph(c):
+    cx, cy, r = 600, 380, 480
+    if c == " ":
+        return []
+    if c == "\U0001f3fd":
+        # a skin tone modifier on its own is a swatch
+        return [rect(cx - 300, cy - 300, cx + 300, cy + 300)]
+    if c == "❤":
+        return [polygon([(cx, cy - r), (cx - r, cy + 80), (cx - 260, cy + r - 40),
+                         (cx, cy + 200), (cx + 260, cy + r - 40), (cx + r, cy + 80)])]
+    random = Random(ord(c))
+    contours = [octagon(cx, cy, r), polygon(octagon(cx, cy, r - 70), clockwise=False)]
+    for _ in range(2 + random.next(3)):
+        x = cx - 300 + random.next(10) * 60
+        y = cy - 300 + random.next(10) * 60
+        contours.append(rect(x, y, x + 60 + random.next(3) * 40, y + 60 + random.next(3) * 40))
+    return contours
+
+
+def glyf_entry(contours):
+    if not contours:
+        return b""
+    points = [p for contour in contours for p in contour]
+    xs = [p[0] for p in points]
+    ys = [p[1] for p in points]
+    data = struct.pack(">hhhhh", len(contours), min(xs), min(ys), max(xs), max(ys))
+    end = -1
+    for contour in contours:
+        end += len(contour)
+        data += struct.pack(">H", end)
+    data += struct.pack(">H", 0)  # no instructions
+    data += bytes([0x01] * len(points))  # on curve, 16 bit deltas
+    previous = 0
+    for x in xs:
+        data += struct.pack(">h", x - previous)
+        previous = x
+    previous = 0
+    for y in ys:
+        data += struct.pack(">h", y - previous)
+        previous = y
+    if len(data) % 2:
+        data += b"\0"
+    return data
+
+
+def cmap_format4(mapping):
+    segments = [(c, c, g) for c, g in sorted(mapping.items()) if c <= 0xFFFF]
+    segments.append((0xFFFF, 0xFFFF, 0))
+    count = len(segments)
+    search = 2 ** (count.bit_length() - 1)
+    data = struct.pack(">HHH", count * 2, search * 2, search.bit_length() - 1)
+    data += struct.pack(">H", count * 2 - search * 2)
+    data += b"".join(struct.pack(">H", end) for _, end, _ in segments)
+    data += struct.pack(">H", 0)
+    data += b"".join(struct.pack(">H", start) for start, _, _ in segments)
+    data += b"".join(struct.pack(">H", (g - start) % 65536) for start, _, g in segments)
+    data += b"".join(struct.pack(">H", 0) for _ in segments)
+    return struct.pack(">HHH", 4, len(data) + 6, 0) + data
+
+
+def cmap_format12(mapping):
+    groups = [(c, c, g) for c, g in sorted(mapping.items())]
+    data = b"".join(struct.pack(">III", *group) for group in groups)
+    return struct.pack(">HHIII", 12, 0, len(data) + 16, 0, len(groups)) + data
+
+
+def cmap(mapping):
+    subtables = [(3, 1, cmap_format4(mapping))]
+    if any(c > 0xFFFF for c in mapping):
+        subtables.append((3, 10, cmap_format12(mapping)))
+    offset = 4 + 8 * len(subtables)
+    header = struct.pack(">HH", 0, len(subtables))
+    body = b""
+    for platform, encoding, subtable in subtables:
+        header += struct.pack(">HHI", platform, encoding, offset + len(body))
+        body += subtable
+    return header + body
+
+
+def name(family):
+    records = [(1, family), (2, "Regular"), (4, family + " Regular"),
+               (6, family.replace(" ", "") + "-Regular")]
+    strings = b""
+    data = struct.pack(">HHH", 0, len(records), 6 + 12 * len(records))
+    for name_id, text in records:
+        encoded = text.encode("utf-16-be")
+        data += struct.pack(">HHHHHH", 3, 1, 0x409, name_id, len(encoded), len(strings))
+        strings += encoded
+    return data + strings
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(">%dI" % (len(data) // 4), data)) % 2**32
+
+
+def font(family, text, glyph, advance):
+    chars = sorted(set(text), key=ord)
+    # .notdef, an empty box
+    glyphs = [[rect(100, 0, advance - 100, 700), rect(170, 70, advance - 170, 630)[::-1]]]
+    glyphs += [glyph(c) for c in chars]
+    mapping = {ord(c): i + 1 for i, c in enumerate(chars)}
+
+    glyf = b""
+    offsets = [0]
+    for contours in glyphs:
+        glyf += glyf_entry(contours)
+        offsets.append(len(glyf))
+    loca = b"".join(struct.pack(">I", offset) for offset in offsets)
+    points = [p for contours in glyphs for contour in contours for p in contour]
+    x_min = min(p[0] for p in points)
+    y_min = min(p[1] for p in points)
+    x_max = max(p[0] for p in points)
+    y_max = max(p[1] for p in points)
+    max_points = max(sum(len(c) for c in contours) for contours in glyphs)
+    max_contours = max(len(contours) for contours in glyphs)
+
+    tables = {
+        b"OS/2": struct.pack(">HhHHHhhhhhhhhhhh10sIIII4sHHHhhhHHII", 1, advance, 400, 5, 0,
+                             650, 700, 0, 140, 650, 700, 0, 480, 50, 300, 0, bytes(10),
+                             0, 0, 0, 0, b"NONE", 0x40, min(mapping), min(max(mapping), 0xFFFF),
+                             ASCENT, DESCENT, 0, ASCENT, -DESCENT, 0, 0),
+        b"cmap": cmap(mapping),
+        b"glyf": glyf,
+        b"head": struct.pack(">IIIIHHqqhhhhHHhhh", 0x00010000, 0x00010000, 0, 0x5F0F3CF5,
+                             0x000B, UNITS_PER_EM, 0, 0, x_min, y_min, x_max, y_max,
+                             0, 8, 2, 1, 0),
+        b"hhea": struct.pack(">IhhhHhhhhhhhhhhhH", 0x00010000, ASCENT, DESCENT, 0, advance,
+                             0, 0, advance, 1, 0, 0, 0, 0, 0, 0, 0, len(glyphs)),
+        b"hmtx": b"".join(struct.pack(">Hh", advance, 0) for _ in glyphs),
+        b"loca": loca,
+        b"maxp": struct.pack(">IHHHHHHHHHHHHHH", 0x00010000, len(glyphs), max_points,
+                             max_contours, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0),
+        b"name": name(family),
+        b"post": struct.pack(">IIhhIIIII", 0x00030000, 0, -100, 50, 0, 0, 0, 0, 0),
+    }
+
+    count = len(tables)
+    search = 2 ** (count.bit_length() - 1)
+    header = struct.pack(">IHHHH", 0x00010000, count, search * 16, search.bit_length() - 1,
+                         count * 16 - search * 16)
+    offset = 12 + 16 * count
+    directory = b""
+    body = b""
+    head_offset = 0
+    for tag in sorted(tables):
+        data = tables[tag]
+        if tag == b"head":
+            head_offset = offset + len(body)
+        directory += struct.pack(">4sIII", tag, checksum(data), offset + len(body), len(data))
+        body += data + b"\0" * (-len(data) % 4)
+    file = bytearray(header + directory + body)
+    adjustment = (0xB1B0AFBA - checksum(bytes(file))) % 2**32
+    file[head_offset + 8:head_offset + 12] = struct.pack(">I", adjustment)
+    return bytes(file)
+
+
+if __name__ == "__main__":
+    directory = os.path.dirname(os.path.abspath(__file__))
+    for file_name, data in [
+        ("FixtureCJK.ttf", font("Fixture CJK", CJK_TEXT, cjk_glyph, 1000)),
+        ("FixtureEmoji.ttf", font("Fixture Emoji", EMOJI_TEXT, emoji_glyph, 1200)),
+    ]:
+        with open(os.path.join(directory, file_name), "wb") as f:
+            f.write(data)
+        print("wrote", file_name, len(data), "bytes")
@@ -0,0 +1,110 @@
+//! Glyphs are cached per instance of a variable font, so text drawn at one weight must never
+//! pick up the glyphs rasterized for another. These draw the square 'o' of fixture fonts (see
+//! tests/common), one of them with a weight axis.
+
+mod common;
+
+use common::FixtureFont;
+use game::gfx::text::{FontCache, FontRef, GlyphCache, ShaperSettings, TextAntialiasing};
+use skrifa::instance::{Location, NormalizedCoord, Size};
+use skrifa::{GlyphId, MetadataProvider, Tag};
+
+// the cache with just the fixture font in it
+fn font_cache(font: &FixtureFont) -> FontCache {
+    let mut font_cache = FontCache::new();
+    // the cache copies the data, this test's few bytes can stay around
+    let data = font.to_file().leak();
+    font_cache
+        .load_font_bytes("fixture.ttf", data)
+        .expect("the fixture font failed to load");
+    font_cache
+}
+
+fn glyph(font: &FontRef<'_>, character: char) -> GlyphId {
+    font.ext_font_ref()
+        .charmap()
+        .map(character)
+        .expect("the fixture font has no glyph for the character")
+}
+
+// where the glyph ended up in the atlas
+fn atlas_position(
+    glyph_cache: &mut GlyphCache,
+    font: &FontRef<'_>,
+    glyph_id: GlyphId,
+    coords: Location,
+) -> (u32, u32) {
+    let (_, uv_bounds) = glyph_cache
+        .get_glyph_texture_bounds(
+            font,
+            glyph_id,
+            Size::new(32.0),
+            coords,
+            TextAntialiasing::Grayscale,
+        )
+        .expect("the glyph failed to rasterize")
+        .expect("the glyph is empty");
+    (uv_bounds.min.x, uv_bounds.min.y)
+}
+
+#[test]
+fn distinct_weights_get_distinct_atlas_entries() {
+    let font_cache = font_cache(
+        &FixtureFont::new("Fixture Sans")
+            .outlines()
+            .weight_axis(100.0, 400.0, 900.0),
+    );
+    let font = font_cache.fonts().next().unwrap();
+    let glyph_id = glyph(&font, 'o');
+    let axis = font
+        .variation_axes()
+        .iter()
+        .find(|axis| axis.tag() == Tag::new(b"wght"))
+        .expect("the fixture font has no weight axis")
+        .clone();
+    assert_eq!((axis.min_value(), axis.max_value()), (100.0, 900.0));
+    let coords = |value: f32| {
+        ShaperSettings::new()
+            .with_variations([harfrust::Variation {
+                tag: harfrust::Tag::new(b"wght"),
+                value,
+            }])
+            .coords(&font)
+    };
+
+    let mut glyph_cache = GlyphCache::new(1024, 1024);
+    let light = atlas_position(&mut glyph_cache, &font, glyph_id, coords(axis.min_value()));
+    let bold = atlas_position(&mut glyph_cache, &font, glyph_id, coords(axis.max_value()));
+    assert_ne!(light, bold, "{} shares a glyph between weights", font);
+    // and each weight is found again instead of being added twice
+    assert_eq!(
+        atlas_position(&mut glyph_cache, &font, glyph_id, coords(axis.min_value())),
+        light
+    );
+    assert_eq!(
+        atlas_position(&mut glyph_cache, &font, glyph_id, coords(axis.max_value())),
+        bold
+    );
+}
+
+#[test]
+fn default_location_is_cached_once() {
+    let font_cache = font_cache(&FixtureFont::new("Fixture Sans").outlines());
+    let font = font_cache.fonts().next().unwrap();
+    let glyph_id = glyph(&font, 'o');
+
+    let mut glyph_cache = GlyphCache::new(1024, 1024);
+    let default = atlas_position(&mut glyph_cache, &font, glyph_id, Location::default());
+    // the default instance with its coordinates spelled out is the same glyph
+    assert_eq!(
+        atlas_position(&mut glyph_cache, &font, glyph_id, Location::new(2)),
+        default
+    );
+    // anywhere else is another one, even if the font has no axes to move along
+    let mut moved = Location::new(2);
+    moved.coords_mut()[0] = NormalizedCoord::from_f32(0.5);
+    assert_ne!(
+        atlas_position(&mut glyph_cache, &font, glyph_id, moved),
+        default
+    );
+}
@@ -0,0 +1,48 @@
+//! A client and a server talking over localhost. Time is simulated: both get a made up
+//! `now` every step, the datagrams themselves go over real sockets.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use game::engine;
+use game::net::{Client, ConnectionState, Server};
+use game::ship::ShipControls;
+use game::tick_rate::TickRate;
+
+// long enough for the datagrams to arrive on localhost
+const DELIVERY: Duration = Duration::from_millis(1);
+
+#[test]
+fn minimized_client_stays_connected() {
+    let tick_rate = TickRate::default();
+    let interval = engine::update_interval(tick_rate, false, true, true)
+        .expect("a minimized multiplayer game still updates");
+    let mut server = Server::bind("127.0.0.1:0", tick_rate).unwrap();
+    let mut client = Client::connect(server.local_addr().unwrap(), "pilot").unwrap();
+
+    // a good while past the timeouts on both ends
+    let start = Instant::now();
+    let mut now = start;
+    while now - start < Duration::from_secs(12) {
+        client.update(ShipControls::default(), now);
+        thread::sleep(DELIVERY);
+        server.tick(now);
+        thread::sleep(DELIVERY);
+        now += interval;
+    }
+    assert!(
+        matches!(client.state(), ConnectionState::Connected { .. }),
+        "{:?}",
+        client.state()
+    );
+    assert_eq!(server.players(), 1);
+}
+
+#[test]
+fn minimized_window_waits_when_offline() {
+    assert_eq!(
+        engine::update_interval(TickRate::default(), true, true, false),
+        None
+    );
+    assert!(engine::update_interval(TickRate::default(), false, false, false).is_some());
+}
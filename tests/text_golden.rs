@@ -0,0 +1,227 @@
+//! Text is shaped and rasterized on the CPU (the same layout_line and Rasterizer the renderer
+//! uses, without a GPU) and compared against golden images in `tests/golden`, so a change in
+//! shaping, positioning or rasterizing shows up as a failed test.
+//!
+//! The cases use fonts checked in under `tests/fonts`, so they draw the same on every machine:
+//! DejaVu Sans, and for CJK and emoji two fixture fonts with made up glyphs for exactly the
+//! characters of their cases (`make_fixture_fonts.py` writes them). A missing golden image
+//! fails its case; `UPDATE_GOLDEN=1` writes them all (`tests/golden/<case>.png`), after a
+//! change that's meant to look different. A failed case writes what it drew next to the test
+//! binaries, see the failure message.
+
+use std::path::PathBuf;
+
+use game::file_formats::{decode_png, encode_png};
+use game::gfx::text::{
+    FontCache, FontRef, Rasterizer, ShaperSettings, TextAntialiasing, layout_line, line_height,
+};
+use skrifa::MetadataProvider;
+use skrifa::instance::{LocationRef, Size};
+
+const DEJAVU: Font = Font {
+    file_name: "DejaVuSans.ttf",
+    data: include_bytes!("fonts/DejaVuSans.ttf"),
+};
+const CJK: Font = Font {
+    file_name: "FixtureCJK.ttf",
+    data: include_bytes!("fonts/FixtureCJK.ttf"),
+};
+const EMOJI: Font = Font {
+    file_name: "FixtureEmoji.ttf",
+    data: include_bytes!("fonts/FixtureEmoji.ttf"),
+};
+const SIZE: f32 = 24.0;
+// pixels around the text
+const MARGIN: usize = 4;
+// a channel that's off by more than this is a different pixel...
+const CHANNEL_TOLERANCE: u8 = 24;
+// ...and a case fails when more than this share of its pixels is different
+const PIXEL_TOLERANCE: f64 = 0.01;
+
+#[derive(Clone, Copy)]
+struct Font {
+    file_name: &'static str,
+    data: &'static [u8],
+}
+
+struct Case {
+    name: &'static str,
+    font: Font,
+    text: &'static str,
+}
+
+const CASES: &[Case] = &[
+    Case {
+        name: "latin",
+        font: DEJAVU,
+        text: "Sphinx of black quartz, judge my vow! AV To fi",
+    },
+    Case {
+        name: "arabic",
+        font: DEJAVU,
+        text: "نص حكيم له سر قاطع",
+    },
+    Case {
+        name: "combining",
+        font: DEJAVU,
+        text: "a\u{301} e\u{308}\u{323} n\u{303} Z\u{327}\u{30c} q\u{307}\u{323}",
+    },
+    // full width, with Chinese and Japanese punctuation
+    Case {
+        name: "cjk",
+        font: CJK,
+        text: "永和九年，岁在癸丑。日本語のテキスト",
+    },
+    // outside the BMP, and a thumbs up with a skin tone modifier
+    Case {
+        name: "emoji",
+        font: EMOJI,
+        text: "😀 🚀 👍🏽 ❤",
+    },
+];
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(name)
+}
+
+// the text in white on black, RGBA
+fn draw(font: &FontRef<'_>, text: &str) -> (u32, u32, Vec<u8>) {
+    let size = Size::new(SIZE);
+    let layout = layout_line(font, text, size, &ShaperSettings::new(), false, 4.0);
+    let ascent = font
+        .ext_font_ref()
+        .metrics(size, LocationRef::default())
+        .ascent;
+    let width = layout.width.ceil() as usize + 2 * MARGIN;
+    let height = line_height(font, size).ceil() as usize + 2 * MARGIN;
+    let mut coverage = vec![0u8; width * height];
+    // y down, from the top of the image
+    let baseline = MARGIN as f32 + ascent;
+
+    let mut rasterizer = Rasterizer::new();
+    for glyph in &layout.glyphs {
+        let Ok((mask, placement)) = rasterizer.render_owned_mask(
+            font,
+            glyph.glyph_id,
+            size,
+            &Default::default(),
+            TextAntialiasing::Grayscale,
+        ) else {
+            continue;
+        };
+        let left = (MARGIN as f32 + glyph.x) as i32 + placement.left;
+        // the placement is y up, from its bottom edge
+        let top = (baseline - glyph.y) as i32 - (placement.top + placement.height as i32);
+        // every channel has the coverage
+        for (row, pixels) in mask.chunks_exact(placement.width as usize * 4).enumerate() {
+            for (column, pixel) in pixels.chunks_exact(4).enumerate() {
+                let x = left + column as i32;
+                let y = top + row as i32;
+                if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
+                    continue;
+                }
+                let target = &mut coverage[y as usize * width + x as usize];
+                *target = (*target).max(pixel[0]);
+            }
+        }
+    }
+    let rgba = coverage
+        .iter()
+        .flat_map(|&value| [value, value, value, 255])
+        .collect();
+    (width as u32, height as u32, rgba)
+}
+
+// the share of the pixels that are different, None if the sizes are
+fn difference(expected: &(u32, u32, Vec<u8>), actual: &(u32, u32, Vec<u8>)) -> Option<f64> {
+    if (expected.0, expected.1) != (actual.0, actual.1) {
+        return None;
+    }
+    let different = expected
+        .2
+        .chunks_exact(4)
+        .zip(actual.2.chunks_exact(4))
+        .filter(|(a, b)| {
+            a.iter()
+                .zip(*b)
+                .any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    Some(different as f64 / (actual.0 * actual.1).max(1) as f64)
+}
+
+#[test]
+fn text_matches_golden_images() {
+    let mut font_cache = FontCache::new();
+    let mut fonts = Vec::new();
+    for font in [DEJAVU, CJK, EMOJI] {
+        let idx = font_cache
+            .load_font_bytes(font.file_name, font.data)
+            .unwrap()[0];
+        fonts.push((font.file_name, idx));
+    }
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+
+    let mut failures = Vec::new();
+    for case in CASES {
+        let idx = fonts
+            .iter()
+            .find(|(file_name, _)| *file_name == case.font.file_name)
+            .map(|(_, idx)| *idx)
+            .unwrap();
+        let font = font_cache.get_font(idx).unwrap();
+        // the font needs every character, or the case is about its missing glyph boxes
+        let charmap = font.ext_font_ref().charmap();
+        let missing: String = case
+            .text
+            .chars()
+            .filter(|c| !c.is_whitespace() && charmap.map(*c).is_none())
+            .collect();
+        assert!(
+            missing.is_empty(),
+            "{}: no glyphs for {:?}",
+            case.name,
+            missing
+        );
+        let name = format!("{}.png", case.name);
+        let path = golden_path(&name);
+        let actual = draw(&font, case.text);
+
+        if update {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, encode_png(actual.0, actual.1, &actual.2)).unwrap();
+            eprintln!("wrote {}", path.display());
+            continue;
+        }
+        let Ok(expected) = std::fs::read(&path) else {
+            failures.push(format!(
+                "{}: no golden image, UPDATE_GOLDEN=1 writes it",
+                name
+            ));
+            continue;
+        };
+        let expected = decode_png(&expected)
+            .unwrap_or_else(|| panic!("{} isn't a PNG this test wrote", path.display()));
+        let failure = match difference(&expected, &actual) {
+            None => format!(
+                "{}: {}x{} instead of {}x{}",
+                name, actual.0, actual.1, expected.0, expected.1
+            ),
+            Some(share) if share > PIXEL_TOLERANCE => {
+                format!(
+                    "{}: {:.1}% of the pixels are different",
+                    name,
+                    share * 100.0
+                )
+            }
+            Some(_) => continue,
+        };
+        let actual_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(&name);
+        std::fs::write(&actual_path, encode_png(actual.0, actual.1, &actual.2)).unwrap();
+        failures.push(format!("{} (drawn: {})", failure, actual_path.display()));
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}